@@ -0,0 +1,8 @@
+use my_rust_vercel_api::{handlers, telemetry};
+use vercel_runtime::{run, Error};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    telemetry::init();
+    run(handlers::choose).await
+}
@@ -0,0 +1,141 @@
+//! Optional API-key authentication.
+//!
+//! When `API_KEYS` lists one or more keys, every request must present one of
+//! them as `Authorization: Bearer <key>` or `x-api-key: <key>`. With no keys
+//! configured the API stays open.
+
+use crate::config::Config;
+use crate::error::ApiError;
+use http::header::AUTHORIZATION;
+use std::fmt;
+use vercel_runtime::Request;
+
+/// The configured API keys. `Debug` never prints the keys themselves.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ApiKeys(Vec<String>);
+
+impl ApiKeys {
+    /// Parses a comma-separated key list, ignoring blank entries.
+    pub fn parse(raw: &str) -> Self {
+        ApiKeys(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(String::from)
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, candidate: &str) -> bool {
+        // Check every key so the response time doesn't hint at which one matched
+        self.0
+            .iter()
+            .fold(false, |found, key| constant_time_eq(key, candidate) | found)
+    }
+}
+
+impl fmt::Debug for ApiKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ApiKeys({} configured)", self.0.len())
+    }
+}
+
+/// A configured API key the request presented, left in its extensions by
+/// [`authenticate`]. Never present while auth is off.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Authenticated(pub String);
+
+impl fmt::Debug for Authenticated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Authenticated(..)")
+    }
+}
+
+/// Returns the key presented by the request, if any. It may not be valid;
+/// see [`authenticated_key`] for one that is.
+pub fn presented_key(req: &Request) -> Option<&str> {
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+    header("x-api-key")
+        .or_else(|| header(AUTHORIZATION.as_str()).and_then(|value| value.strip_prefix("Bearer ")))
+        .map(str::trim)
+}
+
+/// Rejects the request unless auth is disabled or it presents a valid key.
+pub fn authorize(req: &Request) -> Result<(), ApiError> {
+    let keys = &Config::get().api_keys;
+    if keys.is_empty() {
+        return Ok(());
+    }
+    match presented_key(req) {
+        Some(key) if keys.contains(key) => Ok(()),
+        Some(_) => Err(ApiError::Unauthorized {
+            reason: "the API key is not valid",
+        }),
+        None => Err(ApiError::Unauthorized {
+            reason: "an API key is required",
+        }),
+    }
+}
+
+/// Like [`authorize`], and records the accepted key on the request so
+/// [`authenticated_key`] can name the caller.
+pub fn authenticate(req: &mut Request) -> Result<(), ApiError> {
+    authorize(req)?;
+    if Config::get().api_keys.is_empty() {
+        return Ok(());
+    }
+    if let Some(key) = presented_key(req).map(String::from) {
+        req.extensions_mut().insert(Authenticated(key));
+    }
+    Ok(())
+}
+
+/// The configured key [`authenticate`] accepted for the request, if any.
+pub fn authenticated_key(req: &Request) -> Option<&str> {
+    req.extensions().get::<Authenticated>().map(|key| key.0.as_str())
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a
+            .bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vercel_runtime::Body;
+
+    #[test]
+    fn parses_and_matches_keys() {
+        let keys = ApiKeys::parse(" alpha, ,beta ");
+        assert!(keys.contains("alpha"));
+        assert!(keys.contains("beta"));
+        assert!(!keys.contains("alph"));
+        assert!(!keys.contains(""));
+        assert!(ApiKeys::parse("").is_empty());
+        assert_eq!(format!("{:?}", keys), "ApiKeys(2 configured)");
+    }
+
+    #[test]
+    fn key_from_either_header() {
+        let req = |name: &str, value: &str| {
+            http::Request::builder()
+                .header(name, value)
+                .body(Body::Empty)
+                .unwrap()
+        };
+        assert_eq!(presented_key(&req("x-api-key", "k1")), Some("k1"));
+        assert_eq!(presented_key(&req("authorization", "Bearer k2")), Some("k2"));
+        assert_eq!(presented_key(&req("authorization", "Basic abc")), None);
+        // Only `authenticate` vouches for a key
+        assert_eq!(authenticated_key(&req("x-api-key", "k1")), None);
+    }
+}
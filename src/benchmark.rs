@@ -0,0 +1,125 @@
+//! A fixed workload timed on the running instance.
+//!
+//! The same tasks run on every deployment, so their timings can be compared
+//! between a cold and a warm instance or between two releases. The tasks
+//! compute from scratch, bypassing the memo cache and the build-time table.
+
+use crate::{fib, primes};
+use num_bigint::BigUint;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// One task of the workload.
+#[derive(Debug, Clone, Copy)]
+pub struct Task {
+    pub name: &'static str,
+    pub description: &'static str,
+    // Runs the task and estimates the bytes its result holds
+    work: fn() -> u64,
+}
+
+/// How long a task took and how large its result was.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timing {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub elapsed: Duration,
+    pub result_bytes: u64,
+}
+
+impl Task {
+    /// Runs the task once and times it.
+    pub fn run(&self) -> Timing {
+        let start = Instant::now();
+        let result_bytes = (self.work)();
+        Timing {
+            name: self.name,
+            description: self.description,
+            elapsed: start.elapsed(),
+            result_bytes,
+        }
+    }
+}
+
+// The odd numbers whose primality the last task tests
+const PRIMALITY_START: u64 = 1_000_000_000_000_001;
+const PRIMALITY_COUNT: u64 = 1000;
+
+/// The workload, in the order it runs.
+pub const TASKS: [Task; 5] = [
+    Task {
+        name: "fibonacci-10k",
+        description: "F(10000) by fast doubling",
+        work: || magnitude_bytes(&fib::fast_doubling(10_000)),
+    },
+    Task {
+        name: "fibonacci-100k",
+        description: "F(100000) by fast doubling",
+        work: || magnitude_bytes(&fib::fast_doubling(100_000)),
+    },
+    Task {
+        name: "decimal-100k",
+        description: "F(100000) by fast doubling, written in decimal",
+        work: || fib::fast_doubling(100_000).to_string().len() as u64,
+    },
+    Task {
+        name: "nth-prime",
+        description: "the 100000th prime by a segmented sieve",
+        work: || {
+            std::hint::black_box(primes::nth_prime(100_000));
+            std::mem::size_of::<u64>() as u64
+        },
+    },
+    Task {
+        name: "primality-batch",
+        description: "primality of the 1000 odd numbers from 10^15 + 1",
+        work: || {
+            let found: Vec<u64> = (0..PRIMALITY_COUNT)
+                .map(|i| PRIMALITY_START + 2 * i)
+                .filter(|&x| primes::primality(&BigUint::from(x)).is_prime())
+                .collect();
+            (found.len() * std::mem::size_of::<u64>()) as u64
+        },
+    },
+];
+
+// The bytes of a value's magnitude, rounded up to whole digits of 64 bits
+fn magnitude_bytes(value: &BigUint) -> u64 {
+    value.bits().div_ceil(64) * 8
+}
+
+/// Counts a run of the workload on this instance and returns its ordinal,
+/// starting at 1.
+pub fn begin() -> u64 {
+    static RUNS: AtomicU64 = AtomicU64::new(0);
+    RUNS.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// The memory the process holds, from `/proc/self/status`: the current
+/// resident set and its peak so far. `None` where that file does not exist.
+pub fn resident_bytes() -> Option<(u64, u64)> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let field = |name: &str| {
+        let line = status.lines().find(|line| line.starts_with(name))?;
+        let kib = line[name.len()..].trim().strip_suffix("kB")?.trim().parse::<u64>().ok()?;
+        Some(kib * 1024)
+    };
+    Some((field("VmRSS:")?, field("VmHWM:")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_every_task() {
+        let timings: Vec<Timing> = TASKS.iter().map(Task::run).collect();
+        let names: Vec<&str> = timings.iter().map(|timing| timing.name).collect();
+        assert_eq!(names, ["fibonacci-10k", "fibonacci-100k", "decimal-100k", "nth-prime", "primality-batch"]);
+        // F(10000) has 6942 bits and F(100000) has 20899 decimal digits
+        assert_eq!(timings[0].result_bytes, 109 * 8);
+        assert_eq!(timings[2].result_bytes, 20899);
+        assert!(timings[4].result_bytes > 0);
+        assert!(begin() < begin());
+    }
+}
@@ -0,0 +1,94 @@
+//! Local development server: `cargo run --bin dev-server`.
+//!
+//! Serves every endpoint through the same router as the deployed catch-all
+//! function, so `curl http://localhost:3000/api/42` behaves like the real
+//! deployment without `vercel dev`. Listens on `PORT` (default 3000).
+//!
+//! Unlike the Vercel runtime, it sends [`Streamed`] bodies in pieces as they
+//! are produced, so `curl -N "http://localhost:3000/api/stream?from=0&to=500"`
+//! shows the sequence growing.
+
+use http_body_util::{BodyExt, Either, Full};
+use hyper::body::{Bytes, Frame, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use my_rust_vercel_api::streaming::{self, Streamed};
+use my_rust_vercel_api::{handlers, telemetry};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use vercel_runtime::{Body, Error};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    telemetry::init();
+    let port = std::env::var("PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(3000);
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(%addr, "dev server listening");
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            let service = service_fn(move |req| serve(req, peer));
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::warn!(error = %err, "connection failed");
+            }
+        });
+    }
+}
+
+// A streamed body, sending each chunk once the handler produced it
+struct Chunks(mpsc::Receiver<String>);
+
+impl hyper::body::Body for Chunks {
+    type Data = Bytes;
+    type Error = std::convert::Infallible;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        self.0
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| Ok(Frame::data(Bytes::from(chunk)))))
+    }
+}
+
+// Converts between hyper's types and the runtime's and runs the router
+async fn serve(
+    req: hyper::Request<Incoming>,
+    peer: SocketAddr,
+) -> Result<hyper::Response<Either<Full<Bytes>, Chunks>>, Error> {
+    let (mut parts, body) = req.into_parts();
+    let bytes = body.collect().await?.to_bytes();
+    let body = if bytes.is_empty() {
+        Body::Empty
+    } else {
+        Body::Binary(bytes.to_vec())
+    };
+    // Vercel's proxy adds this; rate limiting keys on it
+    if !parts.headers.contains_key("x-forwarded-for") {
+        parts
+            .headers
+            .insert("x-forwarded-for", peer.ip().to_string().parse()?);
+    }
+
+    let response = streaming::incremental(handlers::router(hyper::Request::from_parts(parts, body))).await?;
+    let (parts, body) = response.into_parts();
+    if let Some(chunks) = parts.extensions.get::<Streamed>().and_then(Streamed::take) {
+        return Ok(hyper::Response::from_parts(parts, Either::Right(Chunks(chunks))));
+    }
+    let bytes = match body {
+        Body::Empty => Bytes::new(),
+        Body::Text(text) => Bytes::from(text),
+        Body::Binary(bytes) => Bytes::from(bytes),
+    };
+    Ok(hyper::Response::from_parts(parts, Either::Left(Full::new(bytes))))
+}
@@ -0,0 +1,142 @@
+//! The per-request computation time budget.
+//!
+//! Raising `MAX_FIB_N` makes it possible to ask for values that take longer
+//! than the platform lets a function run, and the client would only see the
+//! platform's own error. Instead, long computations check a [`Deadline`]
+//! between steps and stop once `COMPUTE_BUDGET_MS` has passed since the
+//! request arrived, so the handler can still answer with a structured `504`.
+
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::middleware;
+use std::fmt;
+use std::time::{Duration, Instant};
+use vercel_runtime::Error;
+
+/// The moment a request's computations should give up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    start: Instant,
+    budget: Option<Duration>,
+}
+
+impl Deadline {
+    /// A deadline that never passes.
+    pub fn none() -> Self {
+        Deadline {
+            start: Instant::now(),
+            budget: None,
+        }
+    }
+
+    /// `budget` from now.
+    pub fn after(budget: Duration) -> Self {
+        Deadline {
+            start: Instant::now(),
+            budget: Some(budget),
+        }
+    }
+
+    /// The configured budget, counted from when the current request entered
+    /// its pipeline. `COMPUTE_BUDGET_MS=0` disables it.
+    pub fn for_request() -> Self {
+        let budget_ms = Config::get().compute_budget_ms;
+        let elapsed = middleware::request_elapsed().unwrap_or_default();
+        Deadline {
+            start: Instant::now().checked_sub(elapsed).unwrap_or_else(Instant::now),
+            budget: (budget_ms > 0).then(|| Duration::from_millis(budget_ms)),
+        }
+    }
+
+    /// Whether the budget is used up.
+    pub fn expired(&self) -> bool {
+        self.budget.is_some_and(|budget| self.start.elapsed() >= budget)
+    }
+
+    /// The error reporting that the computation of F(`n`) was abandoned
+    /// after getting as far as `progress`.
+    pub fn exceeded(&self, n: u64, progress: impl fmt::Display) -> ApiError {
+        // Endpoints that answer questions about F(n) without computing all of it
+        let suggestions = vec![
+            format!("/api/mod?n={}&m=1000000007", n),
+            format!("/api/digits/{}", n),
+            format!("/api/{}?last_digits=100", n),
+            format!("/api/{}?algo=binet", n),
+        ];
+        self.abandoned(format!("F({})", n), progress, suggestions)
+    }
+
+    /// The error reporting that `task` was abandoned after getting as far
+    /// as `progress`, pointing to `suggestions` instead.
+    pub fn abandoned(
+        &self,
+        task: impl fmt::Display,
+        progress: impl fmt::Display,
+        suggestions: Vec<String>,
+    ) -> ApiError {
+        ApiError::DeadlineExceeded {
+            task: task.to_string(),
+            budget_ms: self.budget.unwrap_or_default().as_millis() as u64,
+            elapsed_ms: self.start.elapsed().as_millis() as u64,
+            progress: progress.to_string(),
+            suggestions,
+        }
+    }
+}
+
+/// How far an interrupted computation got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupted {
+    pub done: u64,
+    pub total: u64,
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} doubling steps", self.done, self.total)
+    }
+}
+
+/// Runs `work` on the blocking thread pool, or gives up waiting for it
+/// once `deadline` passes and returns `None`.
+///
+/// For work that cannot check the deadline itself, such as decimal
+/// conversion. An abandoned thread still runs to completion in the
+/// background; only the response stops waiting for it.
+pub async fn run_blocking<T, F>(deadline: Deadline, work: F) -> Result<Option<T>, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let task = tokio::task::spawn_blocking(work);
+    let Some(budget) = deadline.budget else {
+        return Ok(Some(task.await?));
+    };
+    let remaining = budget.saturating_sub(deadline.start.elapsed());
+    match tokio::time::timeout(remaining, task).await {
+        Ok(result) => Ok(Some(result?)),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expires_after_the_budget() {
+        assert!(!Deadline::none().expired());
+        assert!(!Deadline::after(Duration::from_secs(60)).expired());
+        assert!(Deadline::after(Duration::ZERO).expired());
+    }
+
+    #[tokio::test]
+    async fn stops_waiting_for_slow_work() {
+        let slow = run_blocking(Deadline::after(Duration::from_millis(10)), || {
+            std::thread::sleep(Duration::from_millis(200));
+        });
+        assert_eq!(slow.await.unwrap(), None);
+        let quick = run_blocking(Deadline::none(), || 7);
+        assert_eq!(quick.await.unwrap(), Some(7));
+    }
+}
@@ -0,0 +1,32 @@
+//! Build metadata and instance uptime, reported by the health endpoint.
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The crate version from `Cargo.toml`.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The git commit the binary was built from, or `"unknown"`.
+pub const GIT_SHA: &str = env!("BUILD_GIT_SHA");
+
+/// Build time in seconds since the Unix epoch.
+pub const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+
+/// Build time as an RFC 3339 string, if the embedded timestamp is valid.
+pub fn built_at() -> Option<String> {
+    let secs = BUILD_TIMESTAMP.parse::<i64>().ok()?;
+    chrono::DateTime::from_timestamp(secs, 0).map(|time| time.to_rfc3339())
+}
+
+/// Marks the instance as started. Called on every request; only the first
+/// call has an effect, so uptime is measured from the first request the warm
+/// instance served.
+pub fn mark_started() -> Instant {
+    static STARTED: OnceLock<Instant> = OnceLock::new();
+    *STARTED.get_or_init(Instant::now)
+}
+
+/// Time since [`mark_started`] was first called.
+pub fn uptime() -> Duration {
+    mark_started().elapsed()
+}
@@ -0,0 +1,109 @@
+//! HTTP caching metadata for immutable results.
+//!
+//! A given index always yields the same Fibonacci number, so GET responses are
+//! marked publicly cacheable and carry an ETag derived from the endpoint, the
+//! canonical parameters, the output format, the API version, the response
+//! [`Shape`] and the digit grouping locale. The ETag is weak because the body
+//! also embeds a per-request timestamp.
+
+use crate::config::Config;
+use crate::format;
+use crate::negotiation::Format;
+use crate::response::{self, Shape};
+use crate::version::ApiVersion;
+use http::header::{HeaderValue, CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// Builds the ETag for a result identified by `endpoint` and `key`.
+pub fn etag(endpoint: &str, key: &str, format: Format) -> String {
+    let mut identity = format!(
+        "{}|{}|{}|{}",
+        endpoint,
+        key,
+        format.content_type(),
+        ApiVersion::current().as_str()
+    );
+    // Only trimmed, indented or grouped documents get a different tag, so
+    // existing ones stay valid
+    let shape = Shape::current().key();
+    if !shape.is_empty() {
+        identity.push('|');
+        identity.push_str(&shape);
+    }
+    if let Some(locale) = format::current() {
+        identity.push_str("|grouped=");
+        identity.push_str(locale.tag);
+    }
+    format!("W/\"{}-{:016x}\"", endpoint, fnv1a(identity.as_bytes()))
+}
+
+/// Returns true when the request's `If-None-Match` already names `etag`.
+pub fn is_fresh(req: &Request, etag: &str) -> bool {
+    let Some(header) = req.headers().get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || opaque(candidate) == opaque(etag))
+}
+
+/// Attaches `Cache-Control` and `ETag` to a successful response.
+pub fn apply(mut response: Response<Body>, etag: &str) -> Result<Response<Body>, Error> {
+    let headers = response.headers_mut();
+    headers.insert(CACHE_CONTROL, HeaderValue::from_str(&cache_control())?);
+    headers.insert(ETAG, HeaderValue::from_str(etag)?);
+    Ok(response)
+}
+
+/// Builds the `304 Not Modified` reply for a fresh conditional request.
+pub fn not_modified(etag: &str) -> Result<Response<Body>, Error> {
+    apply(response::empty(StatusCode::NOT_MODIFIED)?, etag)
+}
+
+fn cache_control() -> String {
+    format!("public, max-age={}", Config::get().cache_max_age)
+}
+
+// Weak comparison ignores the W/ prefix (RFC 9110 section 8.8.3.2)
+fn opaque(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+// 64-bit FNV-1a: stable across builds and instances, unlike std's hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with(if_none_match: &str) -> Request {
+        http::Request::builder()
+            .header("if-none-match", if_none_match)
+            .body(Body::Empty)
+            .unwrap()
+    }
+
+    #[test]
+    fn etag_is_deterministic_per_key_and_format() {
+        let a = etag("fibonacci", "n=20", Format::Json);
+        assert_eq!(a, etag("fibonacci", "n=20", Format::Json));
+        assert_ne!(a, etag("fibonacci", "n=21", Format::Json));
+        assert_ne!(a, etag("fibonacci", "n=20", Format::Csv));
+        assert_ne!(a, etag("range", "n=20", Format::Json));
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let tag = etag("fibonacci", "n=20", Format::Json);
+        assert!(is_fresh(&request_with(&tag), &tag));
+        assert!(is_fresh(&request_with(tag.trim_start_matches("W/")), &tag));
+        assert!(is_fresh(&request_with(&format!("\"other\", {}", tag)), &tag));
+        assert!(is_fresh(&request_with("*"), &tag));
+        assert!(!is_fresh(&request_with("\"other\""), &tag));
+    }
+}
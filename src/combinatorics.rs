@@ -0,0 +1,60 @@
+//! Factorials and binomial coefficients.
+//!
+//! Both are products of consecutive integers, multiplied as a balanced tree
+//! so most multiplications are between numbers of similar size, which is
+//! much faster than a running product once the values get large.
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+/// Below this many factors a range is multiplied out directly.
+const LEAF: u64 = 16;
+
+/// n!
+pub fn factorial(n: u64) -> BigUint {
+    product(1, n)
+}
+
+/// The binomial coefficient C(n, k), 0 when k > n.
+pub fn choose(n: u64, k: u64) -> BigUint {
+    if k > n {
+        return BigUint::zero();
+    }
+    let k = k.min(n - k);
+    product(n - k + 1, n) / factorial(k)
+}
+
+// lo · (lo + 1) ⋯ hi, 1 for an empty range
+fn product(lo: u64, hi: u64) -> BigUint {
+    if lo > hi {
+        return BigUint::one();
+    }
+    if hi - lo < LEAF {
+        return (lo..=hi).fold(BigUint::one(), |product, i| product * i);
+    }
+    let mid = lo + (hi - lo) / 2;
+    product(lo, mid) * product(mid + 1, hi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factorials() {
+        assert_eq!(factorial(0), BigUint::one());
+        assert_eq!(factorial(1), BigUint::one());
+        assert_eq!(factorial(20).to_string(), "2432902008176640000");
+        assert_eq!(factorial(30).to_string(), "265252859812191058636308480000000");
+        assert_eq!(factorial(1000).to_string().len(), 2568);
+    }
+
+    #[test]
+    fn binomial_coefficients() {
+        assert_eq!(choose(10, 3), BigUint::from(120u32));
+        assert_eq!(choose(10, 0), BigUint::one());
+        assert_eq!(choose(10, 10), BigUint::one());
+        assert_eq!(choose(5, 7), BigUint::zero());
+        assert_eq!(choose(100, 50).to_string(), "100891344545564193334812497256");
+    }
+}
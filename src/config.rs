@@ -0,0 +1,390 @@
+//! Deployment-specific computation limits read from the environment.
+//!
+//! The configuration is read once per instance, on first use, and shared by
+//! every request the warm instance serves afterwards.
+
+use crate::auth::ApiKeys;
+use crate::cors::CorsPolicy;
+use crate::security::SecurityHeaders;
+use crate::signing::Secret;
+use crate::store::StoreUrl;
+use crate::usage::Quotas;
+use std::sync::OnceLock;
+
+/// Computation limits enforced by the handlers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Largest Fibonacci index accepted by any endpoint (`MAX_FIB_N`).
+    pub max_n: u64,
+    /// Largest number of indices in one batch request (`MAX_BATCH_SIZE`).
+    pub max_batch_size: u64,
+    /// Largest number of values in one range request (`MAX_RANGE_SIZE`).
+    pub max_range_size: u64,
+    /// Most values a batch or range request computes at once (`MAX_PARALLELISM`).
+    pub max_parallelism: u64,
+    /// Most values one streaming request sends (`MAX_STREAM_SIZE`).
+    pub max_stream_size: u64,
+    /// Largest index accepted by the async jobs endpoint (`MAX_JOB_N`).
+    pub max_job_n: u64,
+    /// Largest `precision` for the golden ratio endpoint (`MAX_GOLDEN_PRECISION`).
+    pub max_golden_precision: u64,
+    /// Largest `k` accepted by the k-step Fibonacci endpoint (`MAX_KFIB_K`).
+    pub max_kfib_k: u64,
+    /// Largest `last_digits` value (`MAX_LAST_DIGITS`).
+    pub max_last_digits: u64,
+    /// Largest modulus accepted by the Pisano period endpoint (`MAX_PISANO_M`).
+    pub max_pisano_m: u64,
+    /// Most decimal digits in a Collatz starting value (`MAX_COLLATZ_DIGITS`).
+    pub max_collatz_digits: u64,
+    /// Most trajectory values a Collatz response lists (`MAX_COLLATZ_SEQUENCE`).
+    pub max_collatz_sequence: u64,
+    /// Most characters in an expression sent to `/api/eval` (`MAX_EVAL_LENGTH`).
+    pub max_eval_length: u64,
+    /// Deepest nesting in an expression (`MAX_EVAL_DEPTH`).
+    pub max_eval_depth: u64,
+    /// Most decimal digits in any value an expression computes
+    /// (`MAX_EVAL_DIGITS`).
+    pub max_eval_digits: u64,
+    /// Most terms of a continued fraction returned (`MAX_CF_TERMS`).
+    pub max_cf_terms: u64,
+    /// Most decimal digits in a number to expand as a continued fraction
+    /// (`MAX_CF_DIGITS`).
+    pub max_cf_digits: u64,
+    /// Most digits in a value given to the base conversion endpoint
+    /// (`MAX_CONVERT_DIGITS`).
+    pub max_convert_digits: u64,
+    /// Largest `n` for the factorial and binomial endpoints (`MAX_FACTORIAL_N`).
+    pub max_factorial_n: u64,
+    /// Most decimal digits in a value given to the gcd endpoint (`MAX_GCD_DIGITS`).
+    pub max_gcd_digits: u64,
+    /// Largest index accepted by the n-th prime endpoint (`MAX_PRIME_N`).
+    pub max_prime_n: u64,
+    /// Most decimal digits in a number tested for primality (`MAX_PRIME_DIGITS`).
+    pub max_prime_digits: u64,
+    /// Most decimal digits in a number to factorize (`MAX_FACTOR_DIGITS`).
+    pub max_factor_digits: u64,
+    /// Most characters of a Fibonacci word returned (`MAX_FIB_WORD_LEN`).
+    pub max_fib_word_len: u64,
+    /// Largest request body accepted by any endpoint, in bytes (`MAX_BODY_BYTES`).
+    pub max_body_bytes: u64,
+    /// Longest query string accepted by any endpoint, in bytes (`MAX_QUERY_LENGTH`).
+    pub max_query_length: u64,
+    /// Most values one random request may draw (`MAX_RANDOM_COUNT`).
+    pub max_random_count: u64,
+    /// Most bytes in the span of a random range (`MAX_RANDOM_BYTES`).
+    pub max_random_bytes: u64,
+    /// Largest index accepted by the sequences endpoint (`MAX_SEQ_N`).
+    pub max_seq_n: u64,
+    /// Largest `profile_runs` value (`MAX_PROFILE_RUNS`).
+    pub max_profile_runs: u64,
+    /// Largest index that may be profiled (`MAX_PROFILE_N`).
+    pub max_profile_n: u64,
+    /// Most fields in one GraphQL query, nested ones included
+    /// (`MAX_GRAPHQL_FIELDS`).
+    pub max_graphql_fields: u64,
+    /// Milliseconds a request may spend computing, 0 to disable (`COMPUTE_BUDGET_MS`).
+    pub compute_budget_ms: u64,
+    /// `max-age` in seconds for cacheable responses (`CACHE_MAX_AGE`).
+    pub cache_max_age: u64,
+    /// Entries kept in the in-process result cache, 0 to disable (`MEMO_CACHE_SIZE`).
+    pub memo_cache_size: u64,
+    /// Persistent cache connection string (`KV_URL`, or `REDIS_URL`); unset
+    /// keeps results in process memory only.
+    pub kv_url: Option<StoreUrl>,
+    /// Seconds a result stays in the persistent cache (`KV_TTL_SECONDS`).
+    pub kv_ttl_secs: u64,
+    /// Largest serialized value written to the persistent cache (`KV_MAX_VALUE_BYTES`).
+    pub kv_max_value_bytes: u64,
+    /// Seconds a POST response is replayed for a repeated `Idempotency-Key`,
+    /// 0 to disable (`IDEMPOTENCY_TTL_SECONDS`).
+    pub idempotency_ttl_secs: u64,
+    /// Key signing job callbacks (`WEBHOOK_SECRET`); unset disables jobs.
+    pub webhook_secret: Option<Secret>,
+    /// Key signing every response in `X-Signature` (`RESPONSE_SIGNING_SECRET`);
+    /// unset sends no signatures and disables `/api/verify`.
+    pub response_signing_secret: Option<Secret>,
+    /// OTLP/HTTP collector base URL (`OTEL_EXPORTER_OTLP_ENDPOINT`); unset
+    /// disables telemetry export.
+    pub otlp_endpoint: Option<String>,
+    /// Headers sent to the collector as `key=value,...`, usually carrying an
+    /// API key (`OTEL_EXPORTER_OTLP_HEADERS`).
+    pub otlp_headers: Option<Secret>,
+    /// `service.name` reported to the collector (`OTEL_SERVICE_NAME`).
+    pub otel_service_name: String,
+    /// Accept plain `http` callback URLs, for local testing (`WEBHOOK_ALLOW_HTTP`).
+    pub webhook_allow_http: bool,
+    /// Smallest body, in bytes, worth compressing (`COMPRESSION_MIN_BYTES`).
+    pub compression_min_bytes: u64,
+    /// Requests admitted per client per minute, 0 to disable (`RATE_LIMIT_PER_MINUTE`).
+    pub rate_limit_per_minute: u64,
+    /// Requests a client may make in a burst (`RATE_LIMIT_BURST`).
+    pub rate_limit_burst: u64,
+    /// Keys accepted by the API; empty leaves it open (`API_KEYS`, comma-separated).
+    pub api_keys: ApiKeys,
+    /// Per-key limits, 0 for none (`QUOTA_DAILY_REQUESTS`,
+    /// `QUOTA_MONTHLY_REQUESTS`, `QUOTA_DAILY_UNITS`, `QUOTA_MONTHLY_UNITS`).
+    pub quotas: Quotas,
+    /// Cross-origin policy (`CORS_ALLOWED_ORIGINS`, `CORS_ALLOWED_METHODS`,
+    /// `CORS_ALLOWED_HEADERS`, `CORS_ALLOW_CREDENTIALS`).
+    pub cors: CorsPolicy,
+    /// Headers added to every response (`SECURITY_CONTENT_TYPE_OPTIONS`,
+    /// `SECURITY_REFERRER_POLICY`, `SECURITY_HSTS`, `SECURITY_CSP`).
+    pub security_headers: SecurityHeaders,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_n: 1000,
+            max_batch_size: 100,
+            max_range_size: 500,
+            max_parallelism: 4,
+            max_stream_size: 10_000,
+            max_job_n: 5_000_000,
+            max_golden_precision: 10_000,
+            max_kfib_k: 100,
+            max_last_digits: 1000,
+            max_pisano_m: 1_000_000,
+            max_collatz_digits: 1000,
+            max_collatz_sequence: 1000,
+            max_eval_length: 1000,
+            max_eval_depth: 32,
+            max_eval_digits: 100_000,
+            max_cf_terms: 1000,
+            max_cf_digits: 1000,
+            max_convert_digits: 10_000,
+            max_factorial_n: 20_000,
+            max_gcd_digits: 10_000,
+            max_prime_n: 10_000_000,
+            max_prime_digits: 1000,
+            max_factor_digits: 40,
+            max_fib_word_len: 10_000,
+            max_body_bytes: 65_536,
+            max_query_length: 16_384,
+            max_random_count: 1000,
+            max_random_bytes: 1024,
+            max_seq_n: 10_000,
+            max_profile_runs: 100,
+            max_profile_n: 1000,
+            max_graphql_fields: 100,
+            compute_budget_ms: 8000,
+            cache_max_age: 86400,
+            memo_cache_size: 256,
+            kv_url: None,
+            kv_ttl_secs: 7 * 86400,
+            kv_max_value_bytes: 1024 * 1024,
+            idempotency_ttl_secs: 86400,
+            webhook_secret: None,
+            response_signing_secret: None,
+            otlp_endpoint: None,
+            otlp_headers: None,
+            otel_service_name: env!("CARGO_PKG_NAME").to_string(),
+            webhook_allow_http: false,
+            compression_min_bytes: 1024,
+            rate_limit_per_minute: 60,
+            rate_limit_burst: 20,
+            api_keys: ApiKeys::default(),
+            quotas: Quotas::default(),
+            cors: CorsPolicy::default(),
+            security_headers: SecurityHeaders::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Drops `CORS_ALLOW_CREDENTIALS` when every origin is allowed, since
+    /// credentials may only be granted to an explicit origin list.
+    fn checked_cors(mut cors: CorsPolicy) -> CorsPolicy {
+        if cors.allow_credentials && cors.allowed_origins.is_empty() {
+            tracing::warn!(
+                key = "CORS_ALLOW_CREDENTIALS",
+                "ignoring credentials for a wildcard CORS_ALLOWED_ORIGINS"
+            );
+            cors.allow_credentials = false;
+        }
+        cors
+    }
+
+    /// Builds a configuration from the process environment.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Builds a configuration from an arbitrary key lookup, falling back to
+    /// the defaults for missing or unparseable values.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Config::default();
+        let read = |key: &str, default: u64| match lookup(key) {
+            Some(raw) => match raw.trim().parse::<u64>() {
+                Ok(value) => value,
+                Err(_) => {
+                    tracing::warn!(key, value = %raw, default, "ignoring invalid setting");
+                    default
+                }
+            },
+            None => default,
+        };
+        let read_bool = |key: &str, default: bool| match lookup(key) {
+            Some(raw) => match raw.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" => true,
+                "false" | "0" | "no" => false,
+                _ => {
+                    tracing::warn!(key, value = %raw, default, "ignoring invalid setting");
+                    default
+                }
+            },
+            None => default,
+        };
+        let read_string = |key: &str, default: String| {
+            lookup(key)
+                .map(|raw| raw.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or(default)
+        };
+
+        Config {
+            max_n: read("MAX_FIB_N", defaults.max_n),
+            max_batch_size: read("MAX_BATCH_SIZE", defaults.max_batch_size),
+            max_range_size: read("MAX_RANGE_SIZE", defaults.max_range_size),
+            max_parallelism: read("MAX_PARALLELISM", defaults.max_parallelism),
+            max_stream_size: read("MAX_STREAM_SIZE", defaults.max_stream_size),
+            max_job_n: read("MAX_JOB_N", defaults.max_job_n),
+            max_golden_precision: read("MAX_GOLDEN_PRECISION", defaults.max_golden_precision),
+            max_kfib_k: read("MAX_KFIB_K", defaults.max_kfib_k),
+            max_last_digits: read("MAX_LAST_DIGITS", defaults.max_last_digits),
+            max_pisano_m: read("MAX_PISANO_M", defaults.max_pisano_m),
+            max_collatz_digits: read("MAX_COLLATZ_DIGITS", defaults.max_collatz_digits),
+            max_collatz_sequence: read("MAX_COLLATZ_SEQUENCE", defaults.max_collatz_sequence),
+            max_eval_length: read("MAX_EVAL_LENGTH", defaults.max_eval_length),
+            max_eval_depth: read("MAX_EVAL_DEPTH", defaults.max_eval_depth),
+            max_eval_digits: read("MAX_EVAL_DIGITS", defaults.max_eval_digits),
+            max_cf_terms: read("MAX_CF_TERMS", defaults.max_cf_terms),
+            max_cf_digits: read("MAX_CF_DIGITS", defaults.max_cf_digits),
+            max_convert_digits: read("MAX_CONVERT_DIGITS", defaults.max_convert_digits),
+            max_factorial_n: read("MAX_FACTORIAL_N", defaults.max_factorial_n),
+            max_gcd_digits: read("MAX_GCD_DIGITS", defaults.max_gcd_digits),
+            max_prime_n: read("MAX_PRIME_N", defaults.max_prime_n),
+            max_prime_digits: read("MAX_PRIME_DIGITS", defaults.max_prime_digits),
+            max_factor_digits: read("MAX_FACTOR_DIGITS", defaults.max_factor_digits),
+            max_fib_word_len: read("MAX_FIB_WORD_LEN", defaults.max_fib_word_len),
+            max_body_bytes: read("MAX_BODY_BYTES", defaults.max_body_bytes),
+            max_query_length: read("MAX_QUERY_LENGTH", defaults.max_query_length),
+            max_random_count: read("MAX_RANDOM_COUNT", defaults.max_random_count),
+            max_random_bytes: read("MAX_RANDOM_BYTES", defaults.max_random_bytes),
+            max_seq_n: read("MAX_SEQ_N", defaults.max_seq_n),
+            max_profile_runs: read("MAX_PROFILE_RUNS", defaults.max_profile_runs),
+            max_profile_n: read("MAX_PROFILE_N", defaults.max_profile_n),
+            max_graphql_fields: read("MAX_GRAPHQL_FIELDS", defaults.max_graphql_fields),
+            compute_budget_ms: read("COMPUTE_BUDGET_MS", defaults.compute_budget_ms),
+            cache_max_age: read("CACHE_MAX_AGE", defaults.cache_max_age),
+            memo_cache_size: read("MEMO_CACHE_SIZE", defaults.memo_cache_size),
+            kv_url: lookup("KV_URL")
+                .or_else(|| lookup("REDIS_URL"))
+                .map(|raw| raw.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .map(StoreUrl),
+            kv_ttl_secs: read("KV_TTL_SECONDS", defaults.kv_ttl_secs),
+            kv_max_value_bytes: read("KV_MAX_VALUE_BYTES", defaults.kv_max_value_bytes),
+            idempotency_ttl_secs: read("IDEMPOTENCY_TTL_SECONDS", defaults.idempotency_ttl_secs),
+            webhook_secret: lookup("WEBHOOK_SECRET")
+                .filter(|secret| !secret.is_empty())
+                .map(Secret),
+            response_signing_secret: lookup("RESPONSE_SIGNING_SECRET")
+                .filter(|secret| !secret.is_empty())
+                .map(Secret),
+            otlp_endpoint: lookup("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .map(|raw| raw.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+            otlp_headers: lookup("OTEL_EXPORTER_OTLP_HEADERS")
+                .filter(|headers| !headers.is_empty())
+                .map(Secret),
+            otel_service_name: read_string("OTEL_SERVICE_NAME", defaults.otel_service_name),
+            webhook_allow_http: read_bool("WEBHOOK_ALLOW_HTTP", defaults.webhook_allow_http),
+            compression_min_bytes: read("COMPRESSION_MIN_BYTES", defaults.compression_min_bytes),
+            rate_limit_per_minute: read("RATE_LIMIT_PER_MINUTE", defaults.rate_limit_per_minute),
+            rate_limit_burst: read("RATE_LIMIT_BURST", defaults.rate_limit_burst),
+            api_keys: lookup("API_KEYS").map(|raw| ApiKeys::parse(&raw)).unwrap_or_default(),
+            quotas: Quotas {
+                daily_requests: read("QUOTA_DAILY_REQUESTS", defaults.quotas.daily_requests),
+                monthly_requests: read("QUOTA_MONTHLY_REQUESTS", defaults.quotas.monthly_requests),
+                daily_units: read("QUOTA_DAILY_UNITS", defaults.quotas.daily_units),
+                monthly_units: read("QUOTA_MONTHLY_UNITS", defaults.quotas.monthly_units),
+            },
+            cors: Self::checked_cors(CorsPolicy {
+                allowed_origins: lookup("CORS_ALLOWED_ORIGINS")
+                    .map(|raw| CorsPolicy::parse_origins(&raw))
+                    .unwrap_or(defaults.cors.allowed_origins),
+                allowed_methods: read_string("CORS_ALLOWED_METHODS", defaults.cors.allowed_methods),
+                allowed_headers: read_string("CORS_ALLOWED_HEADERS", defaults.cors.allowed_headers),
+                allow_credentials: read_bool(
+                    "CORS_ALLOW_CREDENTIALS",
+                    defaults.cors.allow_credentials,
+                ),
+            }),
+            security_headers: SecurityHeaders {
+                content_type_options: read_string(
+                    "SECURITY_CONTENT_TYPE_OPTIONS",
+                    defaults.security_headers.content_type_options,
+                ),
+                referrer_policy: read_string(
+                    "SECURITY_REFERRER_POLICY",
+                    defaults.security_headers.referrer_policy,
+                ),
+                strict_transport_security: read_string(
+                    "SECURITY_HSTS",
+                    defaults.security_headers.strict_transport_security,
+                ),
+                content_security_policy: read_string(
+                    "SECURITY_CSP",
+                    defaults.security_headers.content_security_policy,
+                ),
+            },
+        }
+    }
+
+    /// Returns the instance-wide configuration, reading it on first use.
+    pub fn get() -> &'static Config {
+        static CONFIG: OnceLock<Config> = OnceLock::new();
+        CONFIG.get_or_init(|| {
+            let config = Config::from_env();
+            tracing::info!(?config, "loaded configuration");
+            config
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_when_unset() {
+        assert_eq!(Config::from_lookup(|_| None), Config::default());
+    }
+
+    #[test]
+    fn overrides_and_invalid_values() {
+        let config = Config::from_lookup(|key| match key {
+            "MAX_FIB_N" => Some("100000".to_string()),
+            "MAX_BATCH_SIZE" => Some("lots".to_string()),
+            "CORS_ALLOWED_ORIGINS" => Some("https://a.test".to_string()),
+            "CORS_ALLOW_CREDENTIALS" => Some("true".to_string()),
+            "REDIS_URL" => Some("redis://localhost:6379".to_string()),
+            "SECURITY_HSTS" => Some("off".to_string()),
+            _ => None,
+        });
+        assert_eq!(config.max_n, 100_000);
+        assert_eq!(config.max_batch_size, Config::default().max_batch_size);
+        assert_eq!(config.cors.allowed_origins, vec!["https://a.test".to_string()]);
+        assert!(config.cors.allow_credentials);
+        assert_eq!(config.kv_url, Some(StoreUrl("redis://localhost:6379".to_string())));
+        assert_eq!(config.cors.allowed_methods, CorsPolicy::default().allowed_methods);
+        assert_eq!(config.security_headers.strict_transport_security, "off");
+
+        let wildcard = Config::from_lookup(|key| match key {
+            "CORS_ALLOW_CREDENTIALS" => Some("true".to_string()),
+            _ => None,
+        });
+        assert!(!wildcard.cors.allow_credentials);
+    }
+}
@@ -0,0 +1,160 @@
+//! Cross-origin resource sharing headers.
+//!
+//! By default any origin may call the API without credentials. Deployments
+//! can restrict origins, methods and headers, and opt into credentialed
+//! requests from the listed origins, through `CORS_*` environment variables.
+
+use http::header::{
+    HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+    ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS,
+    VARY,
+};
+
+/// Response headers browsers may read from cross-origin responses.
+pub const EXPOSED_HEADERS: &str =
+    "ETag, Idempotent-Replayed, Retry-After, X-Api-Version, X-RateLimit-Limit, X-RateLimit-Remaining, X-RateLimit-Reset, \
+     X-Signature, traceparent, tracestate";
+
+/// Which cross-origin requests are allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsPolicy {
+    /// Allowed origins; empty means any origin (`CORS_ALLOWED_ORIGINS=*`).
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: String,
+    pub allowed_headers: String,
+    /// Whether listed origins may send credentials; ignored for any-origin
+    /// policies.
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        CorsPolicy {
+            allowed_origins: Vec::new(),
+            allowed_methods: "GET, HEAD, POST, OPTIONS".to_string(),
+            allowed_headers: "Content-Type, If-None-Match, Authorization, X-Api-Key, Idempotency-Key, traceparent, tracestate"
+                .to_string(),
+            allow_credentials: false,
+        }
+    }
+}
+
+impl CorsPolicy {
+    /// Parses a comma-separated origin list, where `*` allows any origin.
+    pub fn parse_origins(raw: &str) -> Vec<String> {
+        let origins: Vec<String> = raw
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_ascii_lowercase())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        if origins.iter().any(|origin| origin == "*") {
+            Vec::new()
+        } else {
+            origins
+        }
+    }
+
+    /// Whether responses carry `Access-Control-Allow-Credentials`. Only
+    /// policies with an explicit origin list do: echoing any origin with
+    /// credentials would let every site make authenticated calls.
+    pub fn credentialed(&self) -> bool {
+        self.allow_credentials && !self.allowed_origins.is_empty()
+    }
+
+    /// The `Access-Control-Allow-Origin` value for a request from `origin`,
+    /// or `None` if that origin is not allowed.
+    ///
+    /// Any-origin policies answer `*`; listed origins are echoed back.
+    pub fn allow_origin<'a>(&self, origin: Option<&'a str>) -> Option<&'a str> {
+        if self.allowed_origins.is_empty() {
+            return Some("*");
+        }
+        let origin = origin?;
+        let normalized = origin.trim_end_matches('/').to_ascii_lowercase();
+        self.allowed_origins.contains(&normalized).then_some(origin)
+    }
+
+    /// Adds the CORS headers for a request from `origin` to `headers`.
+    pub fn apply(&self, origin: Option<&str>, headers: &mut HeaderMap) {
+        // The answer depends on Origin unless every origin gets `*`
+        if !self.allowed_origins.is_empty() {
+            headers.append(VARY, HeaderValue::from_static("Origin"));
+        }
+
+        let Some(allowed) = self
+            .allow_origin(origin)
+            .and_then(|value| HeaderValue::from_str(value).ok())
+        else {
+            return;
+        };
+
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allowed);
+        if let Ok(methods) = HeaderValue::from_str(&self.allowed_methods) {
+            headers.insert(ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if let Ok(allowed_headers) = HeaderValue::from_str(&self.allowed_headers) {
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+        }
+        headers.insert(
+            ACCESS_CONTROL_EXPOSE_HEADERS,
+            HeaderValue::from_static(EXPOSED_HEADERS),
+        );
+        if self.credentialed() {
+            headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn restricted(credentials: bool) -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: CorsPolicy::parse_origins("https://app.example.com/, https://Other.dev"),
+            allow_credentials: credentials,
+            ..CorsPolicy::default()
+        }
+    }
+
+    #[test]
+    fn default_policy_allows_any_origin() {
+        let policy = CorsPolicy::default();
+        assert_eq!(policy.allow_origin(Some("https://x.test")), Some("*"));
+        assert_eq!(policy.allow_origin(None), Some("*"));
+        assert!(CorsPolicy::parse_origins("https://a.test, *").is_empty());
+    }
+
+    #[test]
+    fn restricted_policy_echoes_matching_origin() {
+        let policy = restricted(false);
+        assert_eq!(
+            policy.allow_origin(Some("https://app.example.com")),
+            Some("https://app.example.com")
+        );
+        assert_eq!(policy.allow_origin(Some("https://other.dev")), Some("https://other.dev"));
+        assert_eq!(policy.allow_origin(Some("https://evil.test")), None);
+        assert_eq!(policy.allow_origin(None), None);
+    }
+
+    #[test]
+    fn credentialed_headers() {
+        let mut headers = HeaderMap::new();
+        restricted(true).apply(Some("https://app.example.com"), &mut headers);
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[VARY], "Origin");
+
+        let mut headers = HeaderMap::new();
+        restricted(true).apply(Some("https://evil.test"), &mut headers);
+        assert!(headers.get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        assert_eq!(headers[VARY], "Origin");
+
+        let wildcard = CorsPolicy { allow_credentials: true, ..CorsPolicy::default() };
+        let mut headers = HeaderMap::new();
+        wildcard.apply(Some("https://evil.test"), &mut headers);
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(headers.get(ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+        assert!(headers.get(VARY).is_none());
+    }
+}
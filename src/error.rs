@@ -0,0 +1,456 @@
+//! Structured API errors returned to clients instead of silent fallbacks.
+//!
+//! Every error is sent as an RFC 7807 `application/problem+json` document:
+//! the standard `type`, `title`, `status`, `detail` and `instance` members,
+//! plus this API's `code` and whatever fields describe the specific error.
+
+use crate::{middleware, otlp, response};
+use http::header::{HeaderValue, ALLOW, RETRY_AFTER, WWW_AUTHENTICATE};
+use serde_json::json;
+use std::borrow::Cow;
+use std::fmt;
+use vercel_runtime::{Body, Error, Response, StatusCode};
+
+/// Media type of every error response.
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Prefix of the problem `type` URIs; the error code completes it.
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:fibonacci-api:problem:";
+
+/// An error that is reported to the client as a problem document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// A required parameter was not supplied (400).
+    MissingParameter { name: &'static str },
+    /// A parameter could not be parsed into the expected type (400).
+    InvalidParameter {
+        name: &'static str,
+        received: String,
+        expected: Cow<'static, str>,
+    },
+    /// The request body is not valid JSON or has the wrong shape (400).
+    MalformedBody { reason: String },
+    /// An expression does not parse or has no value, such as one dividing
+    /// by zero (400). `position` is the character offset of the problem.
+    InvalidExpression { position: usize, reason: String },
+    /// A parameter parsed but lies outside the accepted range (422).
+    OutOfRange {
+        name: &'static str,
+        received: String,
+        max: u64,
+    },
+    /// An integer parameter lies outside the range `min..=max` an endpoint
+    /// accepts for it (422).
+    NotInRange {
+        name: &'static str,
+        received: String,
+        min: i128,
+        max: i128,
+    },
+    /// The request body is larger than `max` bytes (413).
+    PayloadTooLarge { size: u64, max: u64 },
+    /// The query string is longer than `max` bytes (414).
+    UriTooLong { size: u64, max: u64 },
+    /// An `Idempotency-Key` already answered a different request (422).
+    IdempotencyKeyReused { key: String },
+    /// A request with the same `Idempotency-Key` is still being served (409).
+    IdempotencyKeyInFlight { key: String, retry_after_secs: u64 },
+    /// No valid API key was presented while auth is enabled (401).
+    Unauthorized { reason: &'static str },
+    /// The client exhausted its rate limit (429).
+    RateLimited { retry_after_secs: u64 },
+    /// The API key used up its `period` quota of `limit` requests or compute
+    /// units (429).
+    QuotaExceeded {
+        period: &'static str,
+        unit: &'static str,
+        used: u64,
+        limit: u64,
+        retry_after_secs: u64,
+    },
+    /// The endpoint does not support the request method (405). `allow` is
+    /// the value of the `Allow` header sent back.
+    MethodNotAllowed { method: String, allow: String },
+    /// No endpoint serves the requested path (404).
+    NotFound { path: String },
+    /// The endpoint is not configured on this deployment (503).
+    Unavailable { reason: &'static str },
+    /// Computing `task`, such as `F(n)`, outlasted the time budget (504).
+    /// `progress` says how far it got, and `suggestions` are cheaper
+    /// requests that may help instead.
+    DeadlineExceeded {
+        task: String,
+        budget_ms: u64,
+        elapsed_ms: u64,
+        progress: String,
+        suggestions: Vec<String>,
+    },
+    /// A handler or layer failed unexpectedly (500). The cause is logged but
+    /// not sent to the client.
+    Internal,
+}
+
+impl ApiError {
+    /// Every value [`ApiError::code`] can return, in declaration order.
+    pub const CODES: &'static [&'static str] = &[
+        "missing_parameter",
+        "invalid_parameter",
+        "malformed_body",
+        "invalid_expression",
+        "out_of_range",
+        "payload_too_large",
+        "uri_too_long",
+        "idempotency_key_reused",
+        "idempotency_key_in_flight",
+        "unauthorized",
+        "rate_limited",
+        "quota_exceeded",
+        "method_not_allowed",
+        "not_found",
+        "unavailable",
+        "deadline_exceeded",
+        "internal_error",
+    ];
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::MissingParameter { .. }
+            | ApiError::InvalidParameter { .. }
+            | ApiError::MalformedBody { .. }
+            | ApiError::InvalidExpression { .. } => StatusCode::BAD_REQUEST,
+            ApiError::OutOfRange { .. } | ApiError::NotInRange { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::UriTooLong { .. } => StatusCode::URI_TOO_LONG,
+            ApiError::IdempotencyKeyReused { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::IdempotencyKeyInFlight { .. } => StatusCode::CONFLICT,
+            ApiError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            ApiError::RateLimited { .. } | ApiError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::MethodNotAllowed { .. } => StatusCode::METHOD_NOT_ALLOWED,
+            ApiError::NotFound { .. } => StatusCode::NOT_FOUND,
+            ApiError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::DeadlineExceeded { .. } => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine-readable error identifier.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::MissingParameter { .. } => "missing_parameter",
+            ApiError::InvalidParameter { .. } => "invalid_parameter",
+            ApiError::MalformedBody { .. } => "malformed_body",
+            ApiError::InvalidExpression { .. } => "invalid_expression",
+            ApiError::OutOfRange { .. } | ApiError::NotInRange { .. } => "out_of_range",
+            ApiError::PayloadTooLarge { .. } => "payload_too_large",
+            ApiError::UriTooLong { .. } => "uri_too_long",
+            ApiError::IdempotencyKeyReused { .. } => "idempotency_key_reused",
+            ApiError::IdempotencyKeyInFlight { .. } => "idempotency_key_in_flight",
+            ApiError::Unauthorized { .. } => "unauthorized",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::QuotaExceeded { .. } => "quota_exceeded",
+            ApiError::MethodNotAllowed { .. } => "method_not_allowed",
+            ApiError::NotFound { .. } => "not_found",
+            ApiError::Unavailable { .. } => "unavailable",
+            ApiError::DeadlineExceeded { .. } => "deadline_exceeded",
+            ApiError::Internal => "internal_error",
+        }
+    }
+
+    /// Short summary of the problem type, the same for every occurrence.
+    pub fn title(&self) -> &'static str {
+        match self {
+            ApiError::MissingParameter { .. } => "Missing parameter",
+            ApiError::InvalidParameter { .. } => "Invalid parameter",
+            ApiError::MalformedBody { .. } => "Malformed request body",
+            ApiError::InvalidExpression { .. } => "Invalid expression",
+            ApiError::OutOfRange { .. } | ApiError::NotInRange { .. } => "Parameter out of range",
+            ApiError::PayloadTooLarge { .. } => "Payload too large",
+            ApiError::UriTooLong { .. } => "URI too long",
+            ApiError::IdempotencyKeyReused { .. } => "Idempotency key reused",
+            ApiError::IdempotencyKeyInFlight { .. } => "Idempotency key in use",
+            ApiError::Unauthorized { .. } => "Unauthorized",
+            ApiError::RateLimited { .. } => "Rate limit exceeded",
+            ApiError::QuotaExceeded { .. } => "Quota exceeded",
+            ApiError::MethodNotAllowed { .. } => "Method not allowed",
+            ApiError::NotFound { .. } => "Not found",
+            ApiError::Unavailable { .. } => "Unavailable",
+            ApiError::DeadlineExceeded { .. } => "Computation time budget exceeded",
+            ApiError::Internal => "Internal error",
+        }
+    }
+
+    /// The problem document. `instance` is the path of the request being
+    /// served, when there is one.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut body = json!({
+            "type": format!("{}{}", PROBLEM_TYPE_PREFIX, self.code()),
+            "title": self.title(),
+            "status": self.status().as_u16(),
+            "detail": self.to_string(),
+            "code": self.code(),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+        if let Some(path) = middleware::request_path() {
+            body["instance"] = json!(path);
+        }
+        match self {
+            ApiError::MissingParameter { name } => {
+                body["parameter"] = json!(name);
+            }
+            ApiError::InvalidParameter { name, received, expected } => {
+                body["parameter"] = json!(name);
+                body["received"] = json!(received);
+                body["expected"] = json!(expected);
+            }
+            ApiError::MalformedBody { .. }
+            | ApiError::Unauthorized { .. }
+            | ApiError::Unavailable { .. }
+            | ApiError::Internal => {}
+            ApiError::InvalidExpression { position, .. } => {
+                body["position"] = json!(position);
+            }
+            ApiError::OutOfRange { name, received, max } => {
+                body["parameter"] = json!(name);
+                body["received"] = json!(received);
+                body["max"] = json!(max);
+            }
+            ApiError::NotInRange { name, received, min, max } => {
+                body["parameter"] = json!(name);
+                body["received"] = json!(received);
+                body["min"] = json!(min);
+                body["max"] = json!(max);
+            }
+            ApiError::PayloadTooLarge { size, max } | ApiError::UriTooLong { size, max } => {
+                body["size"] = json!(size);
+                body["max"] = json!(max);
+            }
+            ApiError::IdempotencyKeyReused { key } => {
+                body["key"] = json!(key);
+            }
+            ApiError::IdempotencyKeyInFlight { key, retry_after_secs } => {
+                body["key"] = json!(key);
+                body["retry_after"] = json!(retry_after_secs);
+            }
+            ApiError::RateLimited { retry_after_secs } => {
+                body["retry_after"] = json!(retry_after_secs);
+            }
+            ApiError::QuotaExceeded {
+                period,
+                unit,
+                used,
+                limit,
+                retry_after_secs,
+            } => {
+                body["period"] = json!(period);
+                body["unit"] = json!(unit);
+                body["used"] = json!(used);
+                body["limit"] = json!(limit);
+                body["retry_after"] = json!(retry_after_secs);
+            }
+            ApiError::MethodNotAllowed { method, .. } => {
+                body["received"] = json!(method);
+            }
+            ApiError::NotFound { path } => {
+                body["path"] = json!(path);
+            }
+            ApiError::DeadlineExceeded {
+                budget_ms,
+                elapsed_ms,
+                progress,
+                suggestions,
+                ..
+            } => {
+                body["budget_ms"] = json!(budget_ms);
+                body["elapsed_ms"] = json!(elapsed_ms);
+                body["progress"] = json!(progress);
+                body["suggestions"] = json!(suggestions);
+            }
+        }
+        body
+    }
+
+    pub fn into_response(self) -> Result<Response<Body>, Error> {
+        otlp::record_error(self.code(), &self.to_string());
+        let mut response = response::text(self.status(), PROBLEM_CONTENT_TYPE, self.to_json().to_string())?;
+        match &self {
+            ApiError::Unauthorized { .. } => {
+                response
+                    .headers_mut()
+                    .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+            ApiError::MethodNotAllowed { allow, .. } => {
+                response.headers_mut().insert(ALLOW, HeaderValue::from_str(allow)?);
+            }
+            ApiError::QuotaExceeded { retry_after_secs, .. }
+            | ApiError::IdempotencyKeyInFlight { retry_after_secs, .. } => {
+                response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(*retry_after_secs));
+            }
+            _ => {}
+        }
+        Ok(response)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::MissingParameter { name } => {
+                write!(f, "parameter '{}' is required", name)
+            }
+            ApiError::InvalidParameter {
+                name,
+                received,
+                expected,
+            } => write!(f, "parameter '{}' must be {}, got '{}'", name, expected, received),
+            ApiError::MalformedBody { reason } => write!(f, "malformed request body: {}", reason),
+            ApiError::InvalidExpression { position, reason } => {
+                write!(f, "invalid expression at position {}: {}", position, reason)
+            }
+            ApiError::OutOfRange {
+                name,
+                received,
+                max,
+            } => write!(f, "parameter '{}' must be at most {}, got {}", name, max, received),
+            ApiError::NotInRange {
+                name,
+                received,
+                min,
+                max,
+            } => write!(f, "parameter '{}' must be an integer between {} and {}, got {}", name, min, max, received),
+            ApiError::PayloadTooLarge { size, max } => {
+                write!(f, "request body of {} bytes exceeds the limit of {} bytes", size, max)
+            }
+            ApiError::UriTooLong { size, max } => {
+                write!(f, "query string of {} bytes exceeds the limit of {} bytes", size, max)
+            }
+            ApiError::IdempotencyKeyReused { key } => {
+                write!(f, "idempotency key '{}' was already used for a different request", key)
+            }
+            ApiError::IdempotencyKeyInFlight { key, retry_after_secs } => write!(
+                f,
+                "a request with idempotency key '{}' is still running, retry in {} seconds",
+                key, retry_after_secs
+            ),
+            ApiError::Unauthorized { reason } => write!(f, "unauthorized: {}", reason),
+            ApiError::RateLimited { retry_after_secs } => {
+                write!(f, "rate limit exceeded, retry in {} seconds", retry_after_secs)
+            }
+            ApiError::QuotaExceeded {
+                period,
+                unit,
+                limit,
+                retry_after_secs,
+                ..
+            } => write!(
+                f,
+                "{} quota of {} {} used up, resets in {} seconds",
+                period, limit, unit, retry_after_secs
+            ),
+            ApiError::MethodNotAllowed { method, allow } => {
+                write!(f, "method {} is not allowed, use one of: {}", method, allow)
+            }
+            ApiError::NotFound { path } => write!(f, "no endpoint serves '{}'", path),
+            ApiError::Unavailable { reason } => write!(f, "unavailable: {}", reason),
+            ApiError::DeadlineExceeded { task, budget_ms, .. } => write!(
+                f,
+                "computing {} did not finish within the {} ms budget",
+                task, budget_ms
+            ),
+            ApiError::Internal => write!(f, "the server failed to complete the request"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_lists_every_variant() {
+        let samples = [
+            ApiError::MissingParameter { name: "n" },
+            ApiError::InvalidParameter {
+                name: "n",
+                received: "x".to_string(),
+                expected: "a number".into(),
+            },
+            ApiError::MalformedBody {
+                reason: "eof".to_string(),
+            },
+            ApiError::InvalidExpression {
+                position: 3,
+                reason: "division by zero".to_string(),
+            },
+            ApiError::OutOfRange {
+                name: "n",
+                received: "5".to_string(),
+                max: 1,
+            },
+            ApiError::PayloadTooLarge { size: 2, max: 1 },
+            ApiError::UriTooLong { size: 2, max: 1 },
+            ApiError::IdempotencyKeyReused { key: "k".to_string() },
+            ApiError::IdempotencyKeyInFlight {
+                key: "k".to_string(),
+                retry_after_secs: 1,
+            },
+            ApiError::Unauthorized { reason: "no key" },
+            ApiError::RateLimited { retry_after_secs: 1 },
+            ApiError::QuotaExceeded {
+                period: "daily",
+                unit: "requests",
+                used: 1,
+                limit: 1,
+                retry_after_secs: 1,
+            },
+            ApiError::MethodNotAllowed {
+                method: "PUT".to_string(),
+                allow: "GET".to_string(),
+            },
+            ApiError::NotFound {
+                path: "/api/nope".to_string(),
+            },
+            ApiError::Unavailable { reason: "off" },
+            ApiError::DeadlineExceeded {
+                task: "F(1)".to_string(),
+                budget_ms: 1,
+                elapsed_ms: 2,
+                progress: "0 of 1 doubling steps".to_string(),
+                suggestions: Vec::new(),
+            },
+            ApiError::Internal,
+        ];
+        let codes: Vec<_> = samples.iter().map(ApiError::code).collect();
+        assert_eq!(codes, ApiError::CODES);
+    }
+
+    #[test]
+    fn responses_are_problem_documents() {
+        let err = ApiError::OutOfRange {
+            name: "n",
+            received: "5000".to_string(),
+            max: 1000,
+        };
+        let body = err.to_json();
+        assert_eq!(body["type"], "urn:fibonacci-api:problem:out_of_range");
+        assert_eq!(body["title"], "Parameter out of range");
+        assert_eq!(body["status"], 422);
+        assert_eq!(body["detail"], "parameter 'n' must be at most 1000, got 5000");
+        assert_eq!(body["max"], 1000);
+
+        let response = err.into_response().unwrap();
+        assert_eq!(response.headers()["content-type"], PROBLEM_CONTENT_TYPE);
+
+        let err = ApiError::NotInRange {
+            name: "n",
+            received: "-5000".to_string(),
+            min: -1000,
+            max: 1000,
+        };
+        let body = err.to_json();
+        assert_eq!(body["code"], "out_of_range");
+        assert_eq!(body["detail"], "parameter 'n' must be an integer between -1000 and 1000, got -5000");
+        assert_eq!((body["min"].clone(), body["max"].clone()), (json!(-1000), json!(1000)));
+    }
+}
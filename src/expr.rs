@@ -0,0 +1,670 @@
+//! Arithmetic expressions over arbitrary-size integers, such as
+//! `fib(100) * 3 + factorial(20)`.
+//!
+//! The grammar, loosest binding first:
+//!
+//! ```text
+//! expression := term (("+" | "-") term)*
+//! term       := unary (("*" | "/" | "%") unary)*
+//! unary      := ("-" | "+") unary | power
+//! power      := postfix ("^" unary)?
+//! postfix    := primary "!"*
+//! primary    := integer | name "(" expression ("," expression)* ")" | "(" expression ")"
+//! ```
+//!
+//! so `-2^2` is −4 and `2^3^2` is 2^9. `/` and `%` round towards negative
+//! infinity, and `n!` is `factorial(n)`. The functions are listed in
+//! [`FUNCTIONS`]. Parsing is recursive descent with the nesting depth capped,
+//! and evaluation refuses any value with more digits than [`Limits`] allows
+//! before computing it where it can.
+
+use crate::budget::Deadline;
+use crate::combinatorics;
+use crate::fib;
+use crate::numtheory;
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::fmt;
+
+/// log2(10), to turn digit limits into bit limits.
+const BITS_PER_DIGIT: f64 = std::f64::consts::LOG2_10;
+
+/// What an expression may ask for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Deepest nesting of parentheses, calls and prefix operators.
+    pub max_depth: u64,
+    /// Most decimal digits in any value, the intermediate ones included.
+    pub max_digits: u64,
+    /// Largest |n| for `fib` and `lucas`.
+    pub max_fib_n: u64,
+    /// Largest n for `factorial` and `choose`.
+    pub max_factorial_n: u64,
+}
+
+impl Limits {
+    fn max_bits(&self) -> u64 {
+        (self.max_digits as f64 * BITS_PER_DIGIT).ceil() as u64
+    }
+}
+
+/// Why an expression has no value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprError {
+    /// The text is not an expression; `position` is a character offset.
+    Syntax { position: usize, message: String },
+    /// An operation is undefined, such as division by zero.
+    Undefined { position: usize, message: String },
+    /// Something exceeded one of the [`Limits`].
+    TooLarge {
+        name: &'static str,
+        received: String,
+        max: u64,
+    },
+    /// The deadline passed after `operations` operations.
+    Interrupted { operations: u64 },
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::Syntax { position, message } | ExprError::Undefined { position, message } => {
+                write!(f, "{} at position {}", message, position)
+            }
+            ExprError::TooLarge { name, received, max } => {
+                write!(f, "{} must be at most {}, got {}", name, max, received)
+            }
+            ExprError::Interrupted { operations } => write!(f, "interrupted after {} operations", operations),
+        }
+    }
+}
+
+/// A function callable from an expression.
+#[derive(Debug)]
+pub struct Function {
+    pub name: &'static str,
+    /// Shown in errors and documentation, such as `choose(n, k)`.
+    pub signature: &'static str,
+    /// Fewest and most arguments; `None` for any number.
+    arity: (usize, Option<usize>),
+    apply: fn(&[BigInt], &Limits) -> Result<BigInt, Failure>,
+}
+
+// A function's complaint, given a position by the caller
+enum Failure {
+    Undefined(String),
+    TooLarge {
+        name: &'static str,
+        received: String,
+        max: u64,
+    },
+}
+
+/// Every function, by name.
+pub static FUNCTIONS: &[Function] = &[
+    Function {
+        name: "abs",
+        signature: "abs(x)",
+        arity: (1, Some(1)),
+        apply: |args, _| Ok(args[0].abs()),
+    },
+    Function {
+        name: "choose",
+        signature: "choose(n, k)",
+        arity: (2, Some(2)),
+        apply: choose,
+    },
+    Function {
+        name: "factorial",
+        signature: "factorial(n)",
+        arity: (1, Some(1)),
+        apply: |args, limits| factorial(&args[0], limits),
+    },
+    Function {
+        name: "fib",
+        signature: "fib(n)",
+        arity: (1, Some(1)),
+        apply: |args, limits| {
+            let n = index(&args[0], "fib", limits.max_fib_n)?;
+            Ok(fib::negafibonacci(n))
+        },
+    },
+    Function {
+        name: "gcd",
+        signature: "gcd(a, b, ...)",
+        arity: (1, None),
+        apply: |args, _| Ok(numtheory::bezout(args).gcd),
+    },
+    Function {
+        name: "isqrt",
+        signature: "isqrt(x)",
+        arity: (1, Some(1)),
+        apply: |args, _| {
+            if args[0].is_negative() {
+                return Err(Failure::Undefined("isqrt of a negative number".to_string()));
+            }
+            Ok(args[0].sqrt())
+        },
+    },
+    Function {
+        name: "lcm",
+        signature: "lcm(a, b, ...)",
+        arity: (1, None),
+        apply: |args, _| Ok(numtheory::bezout(args).lcm),
+    },
+    Function {
+        name: "lucas",
+        signature: "lucas(n)",
+        arity: (1, Some(1)),
+        apply: |args, limits| {
+            let n = natural(&args[0], "lucas", limits.max_fib_n)?;
+            Ok(BigInt::from(fib::lucas(n)))
+        },
+    },
+    Function {
+        name: "max",
+        signature: "max(a, b, ...)",
+        arity: (1, None),
+        apply: |args, _| Ok(args.iter().max().expect("at least one argument").clone()),
+    },
+    Function {
+        name: "min",
+        signature: "min(a, b, ...)",
+        arity: (1, None),
+        apply: |args, _| Ok(args.iter().min().expect("at least one argument").clone()),
+    },
+];
+
+/// The function called `name`.
+pub fn function(name: &str) -> Option<&'static Function> {
+    FUNCTIONS.iter().find(|function| function.name == name)
+}
+
+// A signed index of at most `max` in magnitude
+fn index(value: &BigInt, name: &'static str, max: u64) -> Result<i64, Failure> {
+    match value.to_i64() {
+        Some(n) if n.unsigned_abs() <= max => Ok(n),
+        _ => Err(Failure::TooLarge {
+            name,
+            received: value.to_string(),
+            max,
+        }),
+    }
+}
+
+// A non-negative index of at most `max`
+fn natural(value: &BigInt, name: &'static str, max: u64) -> Result<u64, Failure> {
+    if value.is_negative() {
+        return Err(Failure::Undefined(format!("{} of a negative number", name)));
+    }
+    match value.to_u64() {
+        Some(n) if n <= max => Ok(n),
+        _ => Err(Failure::TooLarge {
+            name,
+            received: value.to_string(),
+            max,
+        }),
+    }
+}
+
+fn factorial(n: &BigInt, limits: &Limits) -> Result<BigInt, Failure> {
+    let n = natural(n, "factorial", limits.max_factorial_n)?;
+    Ok(BigInt::from(combinatorics::factorial(n)))
+}
+
+fn choose(args: &[BigInt], limits: &Limits) -> Result<BigInt, Failure> {
+    let n = natural(&args[0], "choose", limits.max_factorial_n)?;
+    let k = natural(&args[1], "choose", limits.max_factorial_n)?;
+    Ok(BigInt::from(combinatorics::choose(n, k)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+}
+
+#[derive(Debug)]
+enum Node {
+    Integer(BigInt),
+    Negate(Box<Node>),
+    Binary {
+        operator: Operator,
+        position: usize,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    Factorial {
+        position: usize,
+        operand: Box<Node>,
+    },
+    Call {
+        function: &'static Function,
+        position: usize,
+        args: Vec<Node>,
+    },
+}
+
+/// Parses and evaluates `text`, giving up once `deadline` passes.
+pub fn evaluate(text: &str, limits: &Limits, deadline: &Deadline) -> Result<BigInt, ExprError> {
+    let node = Parser::new(text, limits).parse()?;
+    Evaluator {
+        limits,
+        deadline,
+        operations: 0,
+    }
+    .eval(&node)
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    depth: u64,
+    limits: &'a Limits,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &str, limits: &'a Limits) -> Self {
+        Parser {
+            chars: text.chars().collect(),
+            position: 0,
+            depth: 0,
+            limits,
+        }
+    }
+
+    fn parse(mut self) -> Result<Node, ExprError> {
+        let node = self.expression()?;
+        match self.peek() {
+            None => Ok(node),
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+        }
+    }
+
+    fn error(&self, message: String) -> ExprError {
+        ExprError::Syntax {
+            position: self.position,
+            message,
+        }
+    }
+
+    // The next character that is not whitespace
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.position).is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ExprError> {
+        if self.eat(expected) {
+            return Ok(());
+        }
+        Err(match self.peek() {
+            Some(c) => self.error(format!("expected '{}', found '{}'", expected, c)),
+            None => self.error(format!("expected '{}' before the end", expected)),
+        })
+    }
+
+    // Runs `parse` one level deeper, failing past the depth limit
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, ExprError>) -> Result<T, ExprError> {
+        self.depth += 1;
+        if self.depth > self.limits.max_depth {
+            return Err(ExprError::TooLarge {
+                name: "depth",
+                received: format!("{} levels", self.depth),
+                max: self.limits.max_depth,
+            });
+        }
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn binary(operator: Operator, position: usize, left: Node, right: Node) -> Node {
+        Node::Binary {
+            operator,
+            position,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, ExprError> {
+        self.nested(|parser| {
+            let mut node = parser.term()?;
+            loop {
+                let operator = match parser.peek() {
+                    Some('+') => Operator::Add,
+                    Some('-') => Operator::Subtract,
+                    _ => return Ok(node),
+                };
+                let position = parser.position;
+                parser.position += 1;
+                node = Self::binary(operator, position, node, parser.term()?);
+            }
+        })
+    }
+
+    fn term(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.unary()?;
+        loop {
+            let operator = match self.peek() {
+                Some('*') => Operator::Multiply,
+                Some('/') => Operator::Divide,
+                Some('%') => Operator::Remainder,
+                _ => return Ok(node),
+            };
+            let position = self.position;
+            self.position += 1;
+            node = Self::binary(operator, position, node, self.unary()?);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        if self.eat('-') {
+            return self.nested(|parser| Ok(Node::Negate(Box::new(parser.unary()?))));
+        }
+        if self.eat('+') {
+            return self.nested(Self::unary);
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Node, ExprError> {
+        let base = self.postfix()?;
+        if self.peek() != Some('^') {
+            return Ok(base);
+        }
+        let position = self.position;
+        self.position += 1;
+        let exponent = self.nested(Self::unary)?;
+        Ok(Self::binary(Operator::Power, position, base, exponent))
+    }
+
+    fn postfix(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.primary()?;
+        while self.peek() == Some('!') {
+            node = Node::Factorial {
+                position: self.position,
+                operand: Box::new(node),
+            };
+            self.position += 1;
+        }
+        Ok(node)
+    }
+
+    fn primary(&mut self) -> Result<Node, ExprError> {
+        let start = self.position;
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let node = self.expression()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                Ok(Node::Integer(digits.parse().expect("only digits")))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                let function = function(&name).ok_or_else(|| ExprError::Syntax {
+                    position: start,
+                    message: format!("unknown function '{}'", name),
+                })?;
+                self.expect('(')?;
+                let mut args = vec![self.expression()?];
+                while self.eat(',') {
+                    args.push(self.expression()?);
+                }
+                self.expect(')')?;
+                let (fewest, most) = function.arity;
+                if args.len() < fewest || most.is_some_and(|most| args.len() > most) {
+                    return Err(ExprError::Syntax {
+                        position: start,
+                        message: format!("{} takes the arguments {}", function.name, function.signature),
+                    });
+                }
+                Ok(Node::Call {
+                    function,
+                    position: start,
+                    args,
+                })
+            }
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+            None => Err(self.error("unexpected end of expression".to_string())),
+        }
+    }
+
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> String {
+        let start = self.position;
+        while self.chars.get(self.position).is_some_and(|&c| accept(c)) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+}
+
+struct Evaluator<'a> {
+    limits: &'a Limits,
+    deadline: &'a Deadline,
+    operations: u64,
+}
+
+impl Evaluator<'_> {
+    fn eval(&mut self, node: &Node) -> Result<BigInt, ExprError> {
+        if self.deadline.expired() {
+            return Err(ExprError::Interrupted {
+                operations: self.operations,
+            });
+        }
+        self.operations += 1;
+        let value = match node {
+            Node::Integer(value) => value.clone(),
+            Node::Negate(operand) => -self.eval(operand)?,
+            Node::Binary {
+                operator,
+                position,
+                left,
+                right,
+            } => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                self.apply(*operator, *position, left, right)?
+            }
+            Node::Factorial { position, operand } => {
+                let operand = self.eval(operand)?;
+                factorial(&operand, self.limits).map_err(|failure| locate(failure, *position))?
+            }
+            Node::Call {
+                function,
+                position,
+                args,
+            } => {
+                let args = args.iter().map(|arg| self.eval(arg)).collect::<Result<Vec<_>, _>>()?;
+                (function.apply)(&args, self.limits).map_err(|failure| locate(failure, *position))?
+            }
+        };
+        self.check_size(value.bits() as f64)?;
+        Ok(value)
+    }
+
+    fn apply(&self, operator: Operator, position: usize, left: BigInt, right: BigInt) -> Result<BigInt, ExprError> {
+        let undefined = |message: &str| ExprError::Undefined {
+            position,
+            message: message.to_string(),
+        };
+        Ok(match operator {
+            Operator::Add => left + right,
+            Operator::Subtract => left - right,
+            Operator::Multiply => {
+                // The product has at least this many bits
+                self.check_size((left.bits() + right.bits()).saturating_sub(1) as f64)?;
+                left * right
+            }
+            Operator::Divide | Operator::Remainder if right.is_zero() => return Err(undefined("division by zero")),
+            Operator::Divide => left.div_floor(&right),
+            Operator::Remainder => left.mod_floor(&right),
+            Operator::Power => {
+                if right.sign() == Sign::Minus {
+                    return Err(undefined("negative exponent"));
+                }
+                if left.magnitude() <= &One::one() {
+                    // 0, 1 and −1 stay small whatever the exponent
+                    return Ok(match left.to_i64() {
+                        Some(-1) if right.is_odd() => -BigInt::one(),
+                        Some(-1) => BigInt::one(),
+                        _ if right.is_zero() => BigInt::one(),
+                        _ => left,
+                    });
+                }
+                // The power has at least this many bits, estimated from the
+                // whole exponent however large it is
+                let bits = (left.bits() - 1) as f64 * right.to_f64().unwrap_or(f64::INFINITY);
+                self.check_size(bits)?;
+                left.pow(right.to_u32().ok_or_else(|| self.too_large(bits))?)
+            }
+        })
+    }
+
+    fn check_size(&self, bits: f64) -> Result<(), ExprError> {
+        if bits <= self.limits.max_bits() as f64 {
+            return Ok(());
+        }
+        Err(self.too_large(bits))
+    }
+
+    // A value of `bits` bits; past 10^15 digits only the magnitude is named
+    fn too_large(&self, bits: f64) -> ExprError {
+        let digits = (bits / BITS_PER_DIGIT).floor() + 1.0;
+        let received = if digits < 1e15 {
+            format!("a value of about {} digits", digits as u64)
+        } else if digits.is_finite() {
+            format!("a value of about {:.2e} digits", digits)
+        } else {
+            format!("a value of more than {:e} digits", f64::MAX)
+        };
+        ExprError::TooLarge {
+            name: "digits",
+            received,
+            max: self.limits.max_digits,
+        }
+    }
+}
+
+fn locate(failure: Failure, position: usize) -> ExprError {
+    match failure {
+        Failure::Undefined(message) => ExprError::Undefined { position, message },
+        Failure::TooLarge { name, received, max } => ExprError::TooLarge { name, received, max },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_depth: 16,
+        max_digits: 1000,
+        max_fib_n: 1000,
+        max_factorial_n: 1000,
+    };
+
+    fn eval(text: &str) -> Result<String, ExprError> {
+        evaluate(text, &LIMITS, &Deadline::none()).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn arithmetic_and_precedence() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), "7");
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), "9");
+        assert_eq!(eval("2^3^2").unwrap(), "512");
+        assert_eq!(eval("-2^2").unwrap(), "-4");
+        assert_eq!(eval("(-2)^3").unwrap(), "-8");
+        assert_eq!(eval("-7 / 2").unwrap(), "-4");
+        assert_eq!(eval("-7 % 2").unwrap(), "1");
+        assert_eq!(eval("3!!").unwrap(), "720");
+        assert_eq!(eval("(-1)^1000001 + 0^0").unwrap(), "0");
+        assert_eq!(eval(" 10 - 4 - 3 ").unwrap(), "3");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(
+            eval("fib(100) * 3 + factorial(20)").unwrap(),
+            "1065107446545962385225"
+        );
+        assert_eq!(eval("fib(-6) + lucas(10)").unwrap(), "115");
+        assert_eq!(eval("choose(10, 3) + gcd(12, 18, 30) + lcm(4, 6)").unwrap(), "138");
+        assert_eq!(eval("max(3, -9, 7) - min(3, -9, 7) + abs(-2) + isqrt(99)").unwrap(), "27");
+    }
+
+    #[test]
+    fn syntax_errors_have_positions() {
+        let position = |text| match eval(text) {
+            Err(ExprError::Syntax { position, .. }) => position,
+            other => panic!("{:?} for {}", other, text),
+        };
+        assert_eq!(position("1 +"), 3);
+        assert_eq!(position("2 * (3"), 6);
+        assert_eq!(position("foo(1)"), 0);
+        assert_eq!(position("1 + choose(3)"), 4);
+        assert_eq!(position("4 4"), 2);
+        assert_eq!(position(""), 0);
+    }
+
+    #[test]
+    fn undefined_operations() {
+        assert!(matches!(eval("1 / (2 - 2)"), Err(ExprError::Undefined { position: 2, .. })));
+        assert!(matches!(eval("2 ^ -1"), Err(ExprError::Undefined { .. })));
+        assert!(matches!(eval("(-3)!"), Err(ExprError::Undefined { .. })));
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        let name = |text: &str| match eval(text) {
+            Err(ExprError::TooLarge { name, .. }) => name,
+            other => panic!("{:?} for {}", other, text),
+        };
+        assert_eq!(name("fib(1001)"), "fib");
+        assert_eq!(name("10^1001"), "digits");
+        assert_eq!(name("2^99999999999"), "digits");
+        assert_eq!(name("fib(1000) * fib(1000) * fib(1000) * fib(1000) * fib(1000)"), "digits");
+        assert_eq!(name(&format!("{}1{}", "(".repeat(20), ")".repeat(20))), "depth");
+        assert_eq!(name(&format!("{}1", "-".repeat(20))), "depth");
+        assert_eq!(eval("10^999").unwrap().len(), 1000);
+
+        let received = |text: &str| match eval(text) {
+            Err(ExprError::TooLarge { received, .. }) => received,
+            other => panic!("{:?} for {}", other, text),
+        };
+        assert_eq!(received("10^1001"), "a value of about 1002 digits");
+        // 64·2^64 bits, far more than an exponent cut to u32 would give
+        assert_eq!(received("(2^64)^(2^64)"), "a value of about 3.55e20 digits");
+        assert_eq!(received(&format!("2^{}", "9".repeat(400))), format!("a value of more than {:e} digits", f64::MAX));
+    }
+
+    #[test]
+    fn stops_at_the_deadline() {
+        let expired = Deadline::after(std::time::Duration::ZERO);
+        assert_eq!(
+            evaluate("1 + 1", &LIMITS, &expired),
+            Err(ExprError::Interrupted { operations: 0 })
+        );
+    }
+}
@@ -0,0 +1,737 @@
+//! Fibonacci number computation.
+
+use crate::budget::{Deadline, Interrupted};
+use crate::lucas::LucasParams;
+use num_bigint::{BigInt, BigUint};
+use num_traits::{ToPrimitive, Zero};
+use std::collections::VecDeque;
+
+/// Largest index for which [`binet`] is exact in double precision.
+pub const BINET_EXACT_UP_TO: u64 = 70;
+
+/// Largest index whose [`binet`] error estimate stays below 1; past it the
+/// estimate would not carry a single correct digit.
+pub const BINET_MAX_N: u64 = 4_679_435_742_058_785;
+
+/// An implementation users can pick with `?algo=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Iterative,
+    FastDoubling,
+    Matrix,
+    /// Binet's closed form in floating point, see [`binet`].
+    Binet,
+}
+
+impl Algorithm {
+    /// Parses an `?algo=` value.
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        match name.to_ascii_lowercase().as_str() {
+            "iterative" => Some(Algorithm::Iterative),
+            "fast-doubling" | "fast_doubling" | "doubling" => Some(Algorithm::FastDoubling),
+            "matrix" => Some(Algorithm::Matrix),
+            "binet" => Some(Algorithm::Binet),
+            _ => None,
+        }
+    }
+
+    /// The canonical `?algo=` value.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Iterative => "iterative",
+            Algorithm::FastDoubling => "fast-doubling",
+            Algorithm::Matrix => "matrix",
+            Algorithm::Binet => "binet",
+        }
+    }
+
+    /// Computes F(n) exactly, or `None` for the approximate [`Algorithm::Binet`].
+    pub fn compute(self, n: u64) -> Option<BigUint> {
+        match self {
+            Algorithm::Iterative => Some(iterative(n)),
+            Algorithm::FastDoubling => Some(fast_doubling(n)),
+            Algorithm::Matrix => Some(matrix(n)),
+            Algorithm::Binet => None,
+        }
+    }
+}
+
+/// Computes F(n) with arbitrary precision using the default algorithm.
+pub fn calculate_fibonacci(n: u64) -> BigUint {
+    fast_doubling(n)
+}
+
+/// Computes F(n) in O(log n) big-integer multiplications.
+///
+/// Fibonacci numbers are the Lucas sequence U_n(1, −1), so this is the
+/// generic fast doubling of [`LucasParams::terms`].
+pub fn fast_doubling(n: u64) -> BigUint {
+    fibonacci_pair(n).0
+}
+
+/// Fast doubling that gives up once `deadline` passes, reporting how many
+/// of the doubling steps were done.
+pub fn fast_doubling_within(n: u64, deadline: &Deadline) -> Result<BigUint, Interrupted> {
+    LucasParams::FIBONACCI
+        .terms_within(n, deadline)
+        .map(|(u, _)| into_biguint(u))
+}
+
+/// Returns the pair (F(n), F(n+1)) using fast doubling.
+pub fn fibonacci_pair(n: u64) -> (BigUint, BigUint) {
+    let (u, v) = LucasParams::FIBONACCI.terms(n);
+    // F(n+1) = (F(n) + L(n)) / 2
+    let next = (&u + v) >> 1u32;
+    (into_biguint(u), into_biguint(next))
+}
+
+/// Computes F(n) for a signed index, extending the sequence backwards with
+/// F(−n) = (−1)^(n+1)·F(n): ..., 5, −3, 2, −1, 1, 0, 1, 1, 2, ...
+pub fn negafibonacci(n: i64) -> BigInt {
+    let value = BigInt::from(calculate_fibonacci(n.unsigned_abs()));
+    if negafibonacci_is_negative(n) {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Whether F(n) is negative, which happens exactly for even negative indices.
+pub fn negafibonacci_is_negative(n: i64) -> bool {
+    n < 0 && n % 2 == 0
+}
+
+/// Computes the Lucas number L(n) = F(n−1) + F(n+1).
+pub fn lucas(n: u64) -> BigUint {
+    into_biguint(LucasParams::FIBONACCI.terms(n).1)
+}
+
+/// Sums over F(1), ..., F(n), from closed forms rather than adding terms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sums {
+    /// F(1) + ⋯ + F(n) = F(n+2) − 1.
+    pub sum: BigUint,
+    /// F(1)² + ⋯ + F(n)² = F(n)·F(n+1).
+    pub sum_of_squares: BigUint,
+    /// F(1) − F(2) + ⋯ + (−1)^(n+1)·F(n) = (−1)^(n+1)·F(n−1) + 1.
+    pub alternating_sum: BigInt,
+}
+
+/// The [`Sums`] of the first `n` Fibonacci numbers, all from one
+/// [`fibonacci_pair`].
+pub fn sums(n: u64) -> Sums {
+    let (current, next) = fibonacci_pair(n);
+    // F(n−1) = F(n+1) − F(n), which is 1 for n = 0
+    let previous = BigInt::from(&next - &current);
+    let alternating_sum = if n % 2 == 1 { previous + 1 } else { 1 - previous };
+    Sums {
+        sum: &current + &next - 1u32,
+        sum_of_squares: current * next,
+        alternating_sum,
+    }
+}
+
+/// The first `len` characters of the Fibonacci word S(n), where S(0) = "0",
+/// S(1) = "01" and S(n) = S(n−1)S(n−2). S(n) has F(n+2) characters, F(n+1)
+/// of them zeros.
+pub fn word_prefix(n: u64, len: usize) -> String {
+    if n == 0 {
+        return "0"[..len.min(1)].to_string();
+    }
+    // Each word is a prefix of the next, so growth can stop at `len`
+    let (mut previous, mut current) = (String::from("0"), String::from("01"));
+    let mut index = 1;
+    while index < n && current.len() < len {
+        let next = current.clone() + &previous;
+        previous = std::mem::replace(&mut current, next);
+        index += 1;
+    }
+    current.truncate(len);
+    current
+}
+
+// U_n(1, −1) and V_n(1, −1) are never negative
+fn into_biguint(value: BigInt) -> BigUint {
+    value.into_parts().1
+}
+
+/// Computes F(n) mod m for an index of any size, without materializing F(n).
+///
+/// This is the fast-doubling walk of [`fibonacci_pair`] (equivalently,
+/// squaring the matrix [[1, 1], [1, 0]]) carried out in `u128` modulo `m`, so
+/// it costs O(log n) word operations. `m` must be non-zero.
+pub fn modular(n: &BigUint, m: u64) -> u64 {
+    let m = u128::from(m);
+    let mut a = 0u128; // F(k) mod m
+    let mut b = 1 % m; // F(k+1) mod m
+
+    for bit in (0..n.bits()).rev() {
+        let c = a * ((2 * b + m - a) % m) % m;
+        let d = (a * a % m + b * b % m) % m;
+        if n.bit(bit) {
+            a = d;
+            b = (c + d) % m;
+        } else {
+            a = c;
+            b = d;
+        }
+    }
+
+    a as u64
+}
+
+/// Computes the n-th term of the k-step Fibonacci sequence, where each term
+/// is the sum of the k before it and the sequence starts with k − 1 zeros
+/// followed by a one (k = 3 gives the tribonacci numbers 0, 0, 1, 1, 2, 4, ...).
+///
+/// Keeps a sliding window of the last k terms and their sum, so every step
+/// costs one shift and one subtraction regardless of k.
+pub fn k_step(k: u64, n: u64) -> BigUint {
+    assert!(k >= 2, "k-step Fibonacci needs k >= 2");
+    if n + 1 < k {
+        return BigUint::ZERO;
+    }
+
+    let len = usize::try_from(k).expect("k fits in memory");
+    let mut window: VecDeque<BigUint> = std::iter::repeat_n(BigUint::ZERO, len - 1)
+        .chain(std::iter::once(BigUint::from(1u32)))
+        .collect();
+    let mut sum = BigUint::from(1u32);
+
+    for _ in k..=n {
+        // The sum of the window is the next term; sliding the window past it
+        // adds that term and drops the oldest one
+        let oldest = window.pop_front().unwrap_or_default();
+        let next = std::mem::replace(&mut sum, BigUint::ZERO);
+        sum = (&next << 1u32) - oldest;
+        window.push_back(next);
+    }
+
+    window.pop_back().unwrap_or_default()
+}
+
+/// Computes F(n) mod m for a modulus of any size with the same fast-doubling
+/// walk as [`modular`], in big-integer arithmetic. `m` must be non-zero.
+pub fn modular_big(n: u64, m: &BigUint) -> BigUint {
+    let mut a = BigUint::ZERO; // F(k) mod m
+    let mut b = BigUint::from(1u32) % m; // F(k+1) mod m
+
+    for bit in (0..u64::BITS - n.leading_zeros()).rev() {
+        let c = &a * (((&b << 1u32) + m - &a) % m) % m;
+        let d = (&a * &a + &b * &b) % m;
+        if (n >> bit) & 1 == 0 {
+            a = c;
+            b = d;
+        } else {
+            b = (&c + &d) % m;
+            a = d;
+        }
+    }
+
+    a
+}
+
+/// Returns the last `k` decimal digits of F(n), computed as F(n) mod 10^k,
+/// and whether F(n) has more digits than that. Truncated values keep their
+/// leading zeros so that they are exactly `k` digits long.
+pub fn last_digits(n: u64, k: u32) -> (String, bool) {
+    let modulus = BigUint::from(10u32).pow(k);
+    let digits = modular_big(n, &modulus).to_string();
+
+    // F(n) has more than k digits exactly when n reaches the first index
+    // whose value is at least 10^k
+    let start = estimate_index(&modulus);
+    let first_long = Sequence::starting_at(start)
+        .zip(start..)
+        .find(|(value, _)| *value >= modulus)
+        .map_or(u64::MAX, |(_, index)| index);
+
+    let truncated = n >= first_long;
+    if truncated {
+        (format!("{:0>width$}", digits, width = k as usize), true)
+    } else {
+        (digits, false)
+    }
+}
+
+/// Estimates F(n) as `(mantissa, exponent)` with F(n) ≈ mantissa · 10^exponent
+/// and 1 <= mantissa < 10, without computing F(n).
+///
+/// Values up to F(93) fit in a `u64` and are exact. Beyond that Binet's
+/// formula gives log10 F(n) = n·log10(φ) − log10(√5) up to a negligible
+/// error; the exponent (and so the digit count) is exact until `n` is large
+/// enough for `f64` rounding to matter, around 10^13, and the mantissa
+/// degrades gradually from there.
+pub fn magnitude(n: u64) -> (f64, u64) {
+    if n <= 93 {
+        let (mut a, mut b) = (0u64, 1u64);
+        for _ in 0..n {
+            (a, b) = (b, a.wrapping_add(b));
+        }
+        if a == 0 {
+            return (0.0, 0);
+        }
+        let exponent = a.ilog10();
+        return (a as f64 / 10f64.powi(exponent as i32), u64::from(exponent));
+    }
+
+    let phi = (1.0 + 5f64.sqrt()) / 2.0;
+    let log10 = n as f64 * phi.log10() - 5f64.sqrt().log10();
+    let exponent = log10.floor();
+    (10f64.powf(log10 - exponent), exponent as u64)
+}
+
+/// Number of decimal digits of F(n), see [`magnitude`] for its accuracy.
+pub fn digit_count(n: u64) -> u64 {
+    magnitude(n).1 + 1
+}
+
+/// Statistics over the decimal digits of a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigitStats {
+    /// Number of digits.
+    pub digits: u64,
+    /// How often each digit 0 to 9 occurs.
+    pub histogram: [u64; 10],
+    pub digit_sum: u64,
+    /// The digit sum summed again until one digit is left; 0 only for 0.
+    pub digital_root: u64,
+}
+
+/// The [`DigitStats`] of a decimal string, in one pass over it. Anything but
+/// an ASCII digit is skipped, such as a leading sign.
+pub fn digit_stats(decimal: &str) -> DigitStats {
+    let mut histogram = [0u64; 10];
+    for byte in decimal.bytes().filter(u8::is_ascii_digit) {
+        histogram[usize::from(byte - b'0')] += 1;
+    }
+    let digit_sum = histogram.iter().zip(0..).map(|(count, digit)| count * digit).sum::<u64>();
+    DigitStats {
+        digits: histogram.iter().sum(),
+        histogram,
+        digit_sum,
+        // The digital root is the value mod 9, with 9 in place of 0
+        digital_root: if digit_sum == 0 { 0 } else { 1 + (digit_sum - 1) % 9 },
+    }
+}
+
+/// Returns the Pisano period π(m), the period of F(n) mod m. `m` must be
+/// non-zero.
+///
+/// Steps through the residues until the starting pair (0, 1) comes back,
+/// which takes at most 6m steps.
+pub fn pisano_period(m: u64) -> u64 {
+    if m == 1 {
+        return 1;
+    }
+    let m = u128::from(m);
+    let (mut a, mut b) = (0u128, 1u128);
+    let mut period = 0;
+    loop {
+        (a, b) = (b, (a + b) % m);
+        period += 1;
+        if a == 0 && b == 1 {
+            return period;
+        }
+    }
+}
+
+/// Tests whether `x` is a Fibonacci number: x is one exactly when 5x² + 4 or
+/// 5x² − 4 is a perfect square.
+pub fn is_fibonacci(x: &BigUint) -> bool {
+    let five_x_squared = x * x * 5u32;
+    let is_square = |value: &BigUint| {
+        let root = value.sqrt();
+        &root * &root == *value
+    };
+    let four = BigUint::from(4u32);
+    is_square(&(&five_x_squared + &four))
+        || (five_x_squared >= four && is_square(&(&five_x_squared - &four)))
+}
+
+/// Returns the smallest n with F(n) = x, or `None` if `x` is not a Fibonacci
+/// number. For x = 1 that is n = 1, although F(2) = 1 as well.
+pub fn index_of(x: &BigUint) -> Option<u64> {
+    if !is_fibonacci(x) {
+        return None;
+    }
+
+    let start = estimate_index(x);
+    Sequence::starting_at(start)
+        .zip(start..)
+        .find(|(value, _)| value >= x)
+        .filter(|(value, _)| value == x)
+        .map(|(_, n)| n)
+}
+
+/// Decomposes `x` into its Zeckendorf representation: the unique sum of
+/// non-consecutive Fibonacci numbers F(k) with k >= 2, as `(k, F(k))` pairs
+/// from the largest term down. Zero has the empty representation.
+///
+/// Greedily takes the largest Fibonacci number that still fits, walking the
+/// sequence downwards with F(k−1) = F(k+1) − F(k) so only two terms are ever
+/// held besides the result.
+pub fn zeckendorf(x: &BigUint) -> Vec<(u64, BigUint)> {
+    let mut terms = Vec::new();
+    if x.is_zero() {
+        return terms;
+    }
+
+    // Find k with F(k) <= x < F(k+1)
+    let start = estimate_index(x).max(2);
+    let (mut current, mut next) = fibonacci_pair(start);
+    let mut k = start;
+    while next <= *x {
+        let following = &current + &next;
+        current = std::mem::replace(&mut next, following);
+        k += 1;
+    }
+
+    let mut remainder = x.clone();
+    while k >= 2 && !remainder.is_zero() {
+        if current <= remainder {
+            remainder -= &current;
+            terms.push((k, current.clone()));
+        }
+        let previous = &next - &current;
+        next = std::mem::replace(&mut current, previous);
+        k -= 1;
+    }
+
+    terms
+}
+
+// An index slightly below the n with F(n) closest to x. F(n) is the integer
+// nearest φⁿ/√5, so n ≈ log_φ(x·√5)
+fn estimate_index(x: &BigUint) -> u64 {
+    let log2_phi = ((1.0 + 5f64.sqrt()) / 2.0).log2();
+    let estimate = (log2(x) + 5f64.sqrt().log2()) / log2_phi;
+    (estimate.max(0.0) as u64).saturating_sub(2)
+}
+
+// Approximate base-2 logarithm of a value of any size (-inf for zero)
+fn log2(x: &BigUint) -> f64 {
+    let shift = x.bits().saturating_sub(64);
+    let top = (x >> shift).to_u64().unwrap_or(u64::MAX);
+    (top as f64).log2() + shift as f64
+}
+
+/// Iterator over consecutive Fibonacci numbers F(start), F(start+1), ...
+///
+/// Only the starting pair is computed by fast doubling; every following term
+/// costs a single addition.
+pub struct Sequence {
+    current: BigUint,
+    next: BigUint,
+}
+
+impl Sequence {
+    pub fn starting_at(n: u64) -> Self {
+        let (current, next) = fibonacci_pair(n);
+        Sequence { current, next }
+    }
+}
+
+impl Iterator for Sequence {
+    type Item = BigUint;
+
+    fn next(&mut self) -> Option<BigUint> {
+        let following = &self.current + &self.next;
+        let next = std::mem::replace(&mut self.next, following);
+        Some(std::mem::replace(&mut self.current, next))
+    }
+}
+
+/// Computes F(n) as an entry of [[1, 1], [1, 0]]^n, raised by repeated
+/// squaring in O(log n) matrix products.
+pub fn matrix(n: u64) -> BigUint {
+    // Powers of the Fibonacci matrix are symmetric, so (a, b, c) stands for
+    // [[a, b], [b, c]]
+    fn product(x: &(BigUint, BigUint, BigUint), y: &(BigUint, BigUint, BigUint)) -> (BigUint, BigUint, BigUint) {
+        (
+            &x.0 * &y.0 + &x.1 * &y.1,
+            &x.0 * &y.1 + &x.1 * &y.2,
+            &x.1 * &y.1 + &x.2 * &y.2,
+        )
+    }
+
+    let one = || BigUint::from(1u32);
+    let mut result = (one(), BigUint::zero(), one());
+    let mut power = (one(), one(), BigUint::zero());
+    let mut remaining = n;
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = product(&result, &power);
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            power = product(&power, &power);
+        }
+    }
+    result.1
+}
+
+/// F(n) from Binet's formula φ^n/√5 evaluated in `f64`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinetEstimate {
+    /// The exact value up to [`BINET_EXACT_UP_TO`], scientific notation with
+    /// only the trustworthy digits beyond it.
+    pub value: String,
+    pub exact: bool,
+    /// Estimated bound on the relative error, 0 when exact.
+    pub relative_error: f64,
+}
+
+/// Evaluates Binet's formula in double precision.
+///
+/// Rounding φ^n/√5 gives F(n) exactly up to n = [`BINET_EXACT_UP_TO`]. Past
+/// that the result is computed in log space, so it never overflows, but the
+/// error in φ is amplified n times: the relative error is about
+/// 2·ε·ln F(n) ≈ 2·ε·n·ln φ, so F(10^6) keeps roughly ten significant digits
+/// and nothing is left beyond [`BINET_MAX_N`]. The exponent is the one
+/// [`digit_count`] is based on.
+pub fn binet(n: u64) -> BinetEstimate {
+    let phi = (1.0 + 5f64.sqrt()) / 2.0;
+    if n <= BINET_EXACT_UP_TO {
+        let value = (phi.powi(n as i32) / 5f64.sqrt()).round() as u64;
+        return BinetEstimate {
+            value: value.to_string(),
+            exact: true,
+            relative_error: 0.0,
+        };
+    }
+
+    let (mantissa, exponent) = magnitude(n);
+    let relative_error = (2.0 * f64::EPSILON * n as f64 * phi.ln()).max(f64::EPSILON);
+    let digits = (-relative_error.log10()).floor().clamp(1.0, 15.0) as usize;
+    BinetEstimate {
+        value: format!("{:.*}e{}", digits - 1, mantissa, exponent),
+        exact: false,
+        relative_error,
+    }
+}
+
+/// Computes F(n) by summing successive terms, in O(n) additions.
+pub fn iterative(n: u64) -> BigUint {
+    if n == 0 {
+        return BigUint::from(0u32);
+    }
+    if n == 1 {
+        return BigUint::from(1u32);
+    }
+    
+    let mut a = BigUint::from(0u32);
+    let mut b = BigUint::from(1u32);
+    
+    for _ in 2..=n {
+        let next = &a + &b;
+        a = b;
+        b = next;
+    }
+    
+    b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn small_values() {
+        let expected = [0u32, 1, 1, 2, 3, 5, 8, 13, 21, 34, 55];
+        for (n, value) in expected.iter().enumerate() {
+            assert_eq!(calculate_fibonacci(n as u64), BigUint::from(*value));
+        }
+    }
+
+    #[test]
+    fn beyond_u64() {
+        assert_eq!(
+            calculate_fibonacci(100).to_string(),
+            "354224848179261915075"
+        );
+    }
+
+    #[test]
+    fn fast_doubling_matches_iterative() {
+        for n in 0..=300 {
+            assert_eq!(fast_doubling(n), iterative(n), "mismatch at n={}", n);
+        }
+    }
+
+    #[test]
+    fn doubling_stops_at_the_deadline() {
+        assert_eq!(fast_doubling_within(1000, &Deadline::none()), Ok(fast_doubling(1000)));
+        assert_eq!(
+            fast_doubling_within(1000, &Deadline::after(Duration::ZERO)),
+            Err(Interrupted { done: 0, total: 10 })
+        );
+    }
+
+    #[test]
+    fn algorithms_agree() {
+        for n in 0..=300 {
+            assert_eq!(matrix(n), fast_doubling(n), "mismatch at n={}", n);
+        }
+        for n in 0..=BINET_EXACT_UP_TO {
+            assert_eq!(binet(n).value, fast_doubling(n).to_string(), "mismatch at n={}", n);
+        }
+        let estimate = binet(1000);
+        assert!(!estimate.exact);
+        assert!(estimate.value.starts_with("4.3466557686") && estimate.value.ends_with("e208"));
+        assert!(binet(BINET_MAX_N).relative_error < 1.0);
+        assert!(binet(BINET_MAX_N + 1).relative_error >= 1.0);
+        let estimate = binet(BINET_MAX_N);
+        assert!(estimate.value.ends_with(&format!("e{}", digit_count(BINET_MAX_N) - 1)), "{}", estimate.value);
+        for name in ["iterative", "fast-doubling", "matrix", "binet"] {
+            assert_eq!(Algorithm::from_name(name).map(Algorithm::name), Some(name));
+        }
+    }
+
+    #[test]
+    fn negative_indices() {
+        let values: Vec<String> = (-8..=8).map(|n| negafibonacci(n).to_string()).collect();
+        assert_eq!(
+            values.join(","),
+            "-21,13,-8,5,-3,2,-1,1,0,1,1,2,3,5,8,13,21"
+        );
+    }
+
+    #[test]
+    fn modular_matches_full_computation() {
+        for m in [1u64, 2, 10, 1_000_000_007, u64::MAX] {
+            for n in (0..=300).step_by(7) {
+                let expected = fast_doubling(n) % m;
+                assert_eq!(BigUint::from(modular(&BigUint::from(n), m)), expected, "n={} m={}", n, m);
+            }
+        }
+        // Known value: F(10^18) mod (10^9 + 7)
+        assert_eq!(modular(&BigUint::from(10u64.pow(18)), 1_000_000_007), 209_783_453);
+    }
+
+    #[test]
+    fn k_step_sequences() {
+        let terms = |k| (0..12).map(|n| k_step(k, n).to_string()).collect::<Vec<_>>().join(",");
+        assert_eq!(terms(2), "0,1,1,2,3,5,8,13,21,34,55,89");
+        assert_eq!(terms(3), "0,0,1,1,2,4,7,13,24,44,81,149");
+        assert_eq!(terms(4), "0,0,0,1,1,2,4,8,15,29,56,108");
+        assert_eq!(k_step(2, 300), iterative(300));
+    }
+
+    #[test]
+    fn last_digits_of_large_indices() {
+        assert_eq!(last_digits(10, 3), ("55".to_string(), false));
+        assert_eq!(last_digits(17, 3), ("597".to_string(), true)); // F(17) = 1597
+        assert_eq!(last_digits(16, 3), ("987".to_string(), false));
+        assert_eq!(last_digits(30, 3), ("040".to_string(), true)); // F(30) = 832040
+        for n in [500, 1234] {
+            let full = fast_doubling(n).to_string();
+            let (digits, truncated) = last_digits(n, 25);
+            assert!(truncated);
+            assert_eq!(digits, full[full.len() - 25..]);
+        }
+        let modulus = BigUint::from(1_000_000_007u64);
+        assert_eq!(modular_big(10u64.pow(18), &modulus), BigUint::from(209_783_453u32));
+    }
+
+    #[test]
+    fn digit_counts() {
+        for n in 0..=1500 {
+            assert_eq!(digit_count(n), fast_doubling(n).to_string().len() as u64, "n={}", n);
+        }
+        let (mantissa, exponent) = magnitude(1000);
+        assert_eq!(exponent, 208);
+        assert!((mantissa - 4.346_656).abs() < 1e-6);
+    }
+
+    #[test]
+    fn digit_statistics() {
+        let stats = digit_stats(&calculate_fibonacci(30).to_string());
+        assert_eq!(stats.digits, 6);
+        assert_eq!(stats.histogram, [2, 0, 1, 1, 1, 0, 0, 0, 1, 0]);
+        assert_eq!(stats.digit_sum, 17);
+        assert_eq!(stats.digital_root, 8);
+        assert_eq!(digit_stats("0").digital_root, 0);
+    }
+
+    #[test]
+    fn sums_match_adding_terms() {
+        let (mut sum, mut squares, mut alternating) = (BigUint::ZERO, BigUint::ZERO, BigInt::ZERO);
+        for n in 0..=200u64 {
+            if n > 0 {
+                let term = iterative(n);
+                squares += &term * &term;
+                alternating += if n % 2 == 1 { BigInt::from(term.clone()) } else { -BigInt::from(term.clone()) };
+                sum += term;
+            }
+            let expected = Sums {
+                sum: sum.clone(),
+                sum_of_squares: squares.clone(),
+                alternating_sum: alternating.clone(),
+            };
+            assert_eq!(sums(n), expected, "n={}", n);
+        }
+    }
+
+    #[test]
+    fn fibonacci_words() {
+        let words: Vec<_> = (0..6).map(|n| word_prefix(n, usize::MAX)).collect();
+        assert_eq!(words, ["0", "01", "010", "01001", "01001010", "0100101001001"]);
+        for n in 0..20 {
+            assert_eq!(word_prefix(n, usize::MAX).len() as u64, iterative(n + 2).to_u64().unwrap());
+        }
+        assert_eq!(word_prefix(1000, 10), "0100101001");
+        assert_eq!(word_prefix(3, 0), "");
+    }
+
+    #[test]
+    fn pisano_periods() {
+        let periods: Vec<u64> = (1..=12).map(pisano_period).collect();
+        assert_eq!(periods, vec![1, 3, 8, 6, 20, 24, 16, 12, 24, 60, 10, 24]);
+        assert_eq!(pisano_period(1_000), 1_500);
+    }
+
+    #[test]
+    fn inverse_lookup() {
+        assert_eq!(index_of(&BigUint::ZERO), Some(0));
+        assert_eq!(index_of(&BigUint::from(1u32)), Some(1));
+        assert_eq!(index_of(&BigUint::from(2u32)), Some(3));
+        for n in 3..=400 {
+            assert_eq!(index_of(&fast_doubling(n)), Some(n), "n={}", n);
+            if n >= 5 {
+                assert!(!is_fibonacci(&(fast_doubling(n) + 1u32)), "F({})+1", n);
+            }
+        }
+        assert_eq!(index_of(&BigUint::from(4u32)), None);
+    }
+
+    #[test]
+    fn zeckendorf_representation() {
+        let indices = |x: u32| {
+            zeckendorf(&BigUint::from(x))
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(indices(0), Vec::<u64>::new());
+        assert_eq!(indices(1), vec![2]);
+        assert_eq!(indices(64), vec![10, 6, 2]); // 55 + 8 + 1
+        assert_eq!(indices(100), vec![11, 6, 4]); // 89 + 8 + 3
+
+        for x in 0u32..2000 {
+            let terms = zeckendorf(&BigUint::from(x));
+            let sum: BigUint = terms.iter().map(|(_, value)| value).sum();
+            assert_eq!(sum, BigUint::from(x));
+            assert!(terms.windows(2).all(|pair| pair[0].0 >= pair[1].0 + 2), "x={}", x);
+        }
+    }
+
+    #[test]
+    fn sequence_continues_from_start() {
+        let values: Vec<BigUint> = Sequence::starting_at(50).take(20).collect();
+        for (offset, value) in values.iter().enumerate() {
+            assert_eq!(*value, iterative(50 + offset as u64));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Locale-aware digit grouping, e.g. `1,234,567`, `1.234.567` or `12,34,567`.
+//!
+//! Grouping is opt-in: `?grouped=true` turns it on and an `Accept-Language`
+//! header naming a supported locale both turns it on and picks the
+//! separators, English otherwise. `?grouped=false` turns it off whatever the
+//! header says. JSON and MessagePack results then carry the grouped values
+//! next to the raw ones, see [`response::render`](crate::response::render).
+
+use crate::error::ApiError;
+use crate::params::Params;
+use serde::Deserialize;
+
+/// How one locale writes large integers.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Locale {
+    /// The language tag, e.g. `de` or `de-ch`.
+    pub tag: &'static str,
+    /// Placed between digit groups.
+    pub separator: &'static str,
+    /// Groups of two above the first three digits, as in `12,34,567`.
+    pub indian: bool,
+}
+
+const fn locale(tag: &'static str, separator: &'static str) -> Locale {
+    Locale {
+        tag,
+        separator,
+        indian: false,
+    }
+}
+
+// A NO-BREAK SPACE, so numbers are not wrapped in the middle
+const SPACE: &str = "\u{a0}";
+
+/// Every supported locale; region-specific tags come before their language.
+pub const LOCALES: &[Locale] = &[
+    locale("de-ch", "\u{2019}"),
+    Locale {
+        tag: "en-in",
+        separator: ",",
+        indian: true,
+    },
+    locale("en", ","),
+    locale("ja", ","),
+    locale("ko", ","),
+    locale("zh", ","),
+    locale("de", "."),
+    locale("es", "."),
+    locale("it", "."),
+    locale("nl", "."),
+    locale("pt", "."),
+    locale("da", "."),
+    locale("id", "."),
+    locale("tr", "."),
+    locale("fr", "\u{202f}"),
+    locale("cs", SPACE),
+    locale("fi", SPACE),
+    locale("nb", SPACE),
+    locale("pl", SPACE),
+    locale("ru", SPACE),
+    locale("sv", SPACE),
+    locale("uk", SPACE),
+    Locale {
+        tag: "hi",
+        separator: ",",
+        indian: true,
+    },
+];
+
+tokio::task_local! {
+    static CURRENT: Option<&'static Locale>;
+}
+
+impl Locale {
+    /// The locale for a language tag such as `de-AT`: the exact tag if it is
+    /// supported, its language otherwise.
+    pub fn find(tag: &str) -> Option<&'static Locale> {
+        let tag = tag.trim().to_ascii_lowercase().replace('_', "-");
+        let language = tag.split('-').next().unwrap_or_default();
+        LOCALES
+            .iter()
+            .find(|locale| locale.tag == tag)
+            .or_else(|| LOCALES.iter().find(|locale| locale.tag == language))
+    }
+
+    /// Groups a decimal integer such as `-1234567`, or returns `None` for
+    /// anything else, e.g. values in other bases or fractions.
+    pub fn group(&self, value: &str) -> Option<String> {
+        let (sign, digits) = match value.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", value),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        // Group sizes from the right: 3, 3, 3, ... or 3, 2, 2, ...
+        let mut groups = Vec::new();
+        let mut rest = digits;
+        let mut size = 3;
+        while rest.len() > size {
+            let (head, tail) = rest.split_at(rest.len() - size);
+            groups.push(tail);
+            rest = head;
+            if self.indian {
+                size = 2;
+            }
+        }
+        groups.push(rest);
+        groups.reverse();
+        Some(format!("{}{}", sign, groups.join(self.separator)))
+    }
+}
+
+/// The locale a request asks for, from its query string and
+/// `Accept-Language` header; `None` when grouping is off.
+pub fn from_request(query: &str, accept_language: Option<&str>) -> Result<Option<&'static Locale>, ApiError> {
+    #[derive(Deserialize)]
+    struct Grouping {
+        grouped: Option<bool>,
+    }
+
+    let preferred = accept_language.and_then(preferred);
+    let Grouping { grouped } = Params::from_query(query).parse()?;
+    Ok(match grouped {
+        None => preferred,
+        Some(true) => preferred.or_else(|| Locale::find("en")),
+        Some(false) => None,
+    })
+}
+
+// The supported locale with the highest quality in an `Accept-Language` list
+fn preferred(header: &str) -> Option<&'static Locale> {
+    let mut ranges: Vec<(&str, f32)> = header
+        .split(',')
+        .map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            (tag, quality)
+        })
+        .filter(|(tag, quality)| *tag != "*" && *quality > 0.0)
+        .collect();
+    // Stable, so equal qualities keep the client's order
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges.into_iter().find_map(|(tag, _)| Locale::find(tag))
+}
+
+/// The locale of the request being served, `None` outside a request or when
+/// grouping is off.
+pub fn current() -> Option<&'static Locale> {
+    CURRENT.try_with(|locale| *locale).ok().flatten()
+}
+
+/// Runs `future` with `locale` as the [`current`] one.
+pub async fn scope<F: std::future::Future>(locale: Option<&'static Locale>, future: F) -> F::Output {
+    CURRENT.scope(locale, future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_digits_per_locale() {
+        let group = |tag: &str, value: &str| Locale::find(tag).unwrap().group(value);
+        assert_eq!(group("en", "1234567").as_deref(), Some("1,234,567"));
+        assert_eq!(group("de-AT", "-1234567").as_deref(), Some("-1.234.567"));
+        assert_eq!(group("de-CH", "1234").as_deref(), Some("1\u{2019}234"));
+        assert_eq!(group("hi", "123456789").as_deref(), Some("12,34,56,789"));
+        assert_eq!(group("en", "999").as_deref(), Some("999"));
+        assert_eq!(group("en", "1a6d"), None);
+        assert_eq!(group("en", "-"), None);
+        assert_eq!(Locale::find("xx"), None);
+    }
+
+    #[test]
+    fn opts_in_from_query_and_header() {
+        let tag = |query: &str, header: Option<&str>| from_request(query, header).unwrap().map(|locale| locale.tag);
+        assert_eq!(tag("", None), None);
+        assert_eq!(tag("grouped=true", None), Some("en"));
+        assert_eq!(tag("", Some("xx, fr-CA;q=0.5, de;q=0.8")), Some("de"));
+        assert_eq!(tag("grouped", Some("*, xx")), Some("en"));
+        assert_eq!(tag("grouped=false", Some("de")), None);
+        assert!(from_request("grouped=maybe", None).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Decimal approximations of the golden ratio φ = (1 + √5) / 2.
+
+use crate::fib::Sequence;
+use num_bigint::BigUint;
+
+/// How the approximation is computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// An integer square root of 5·10^(2p), exact to the last digit.
+    Sqrt,
+    /// F(n+1) / F(n) for the first n that is accurate enough.
+    Ratio,
+}
+
+impl Method {
+    pub fn from_name(name: &str) -> Option<Method> {
+        match name.to_ascii_lowercase().as_str() {
+            "sqrt" => Some(Method::Sqrt),
+            "ratio" | "fibonacci" => Some(Method::Ratio),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Sqrt => "sqrt",
+            Method::Ratio => "ratio",
+        }
+    }
+}
+
+/// φ truncated to a number of decimal places.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Approximation {
+    /// The digits, e.g. `1.618`.
+    pub value: String,
+    /// The index n whose ratio F(n+1)/F(n) was used, for [`Method::Ratio`].
+    pub n: Option<u64>,
+    /// The error is below `error_factor`·10^(−precision).
+    pub error_factor: u32,
+}
+
+/// Approximates φ to `precision` decimal places.
+pub fn approximate(precision: u32, method: Method) -> Approximation {
+    let scale = BigUint::from(10u32).pow(precision);
+    match method {
+        Method::Sqrt => {
+            // floor(φ·10^p) = floor((10^p + floor(√5·10^p)) / 2)
+            let root = (&scale * &scale * 5u32).sqrt();
+            Approximation {
+                value: decimal(&((&scale + root) >> 1u32), precision),
+                n: None,
+                error_factor: 1,
+            }
+        }
+        Method::Ratio => {
+            // |φ − F(n+1)/F(n)| < 1 / (F(n)·F(n+1)), so stop once that product
+            // exceeds 10^p; truncating the quotient adds at most 10^−p more
+            let mut terms = Sequence::starting_at(1);
+            let mut current = terms.next().unwrap_or_default();
+            let mut n = 1;
+            loop {
+                let next = terms.next().unwrap_or_default();
+                // The bit lengths rule the product out cheaply most of the time
+                if current.bits() + next.bits() > scale.bits() && &current * &next > scale {
+                    return Approximation {
+                        value: decimal(&(next * &scale / &current), precision),
+                        n: Some(n),
+                        error_factor: 2,
+                    };
+                }
+                current = next;
+                n += 1;
+            }
+        }
+    }
+}
+
+// Formats `scaled` / 10^precision with exactly `precision` decimals
+fn decimal(scaled: &BigUint, precision: u32) -> String {
+    let digits = scaled.to_string();
+    let split = digits.len() - precision as usize;
+    if precision == 0 {
+        digits
+    } else {
+        format!("{}.{}", &digits[..split], &digits[split..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHI_50: &str = "1.61803398874989484820458683436563811772030917980576";
+
+    #[test]
+    fn both_methods_agree_with_known_digits() {
+        let sqrt = approximate(50, Method::Sqrt);
+        assert_eq!(sqrt.value, PHI_50);
+
+        // The ratio is only within its error bound, which may change several
+        // trailing digits (1.6179... vs 1.6180...)
+        let scaled = |value: &str| value.replace('.', "").parse::<i128>().unwrap();
+        for precision in [3, 20, 35] {
+            let exact = approximate(precision, Method::Sqrt);
+            let ratio = approximate(precision, Method::Ratio);
+            assert!(ratio.n.is_some());
+            let difference = (scaled(&exact.value) - scaled(&ratio.value)).abs();
+            assert!(difference < i128::from(ratio.error_factor), "precision={}", precision);
+        }
+
+        assert_eq!(approximate(0, Method::Sqrt).value, "1");
+    }
+}
@@ -0,0 +1,1099 @@
+//! A small GraphQL executor over the numeric API, for `POST /api/graphql`.
+//!
+//! One query can ask for several values with just the fields it needs:
+//!
+//! ```graphql
+//! query ($n: Int!) {
+//!   fib: fibonacci(n: $n) { value digits }
+//!   hex: fibonacci(n: 30) { value(base: 16) }
+//!   range(from: 10, to: 12) { n value }
+//!   isPrime(x: "1000000007")
+//! }
+//! ```
+//!
+//! The schema is [`SCHEMA`], printed in SDL by [`sdl`]. Queries support
+//! aliases, arguments, variables with defaults, `__typename` and several
+//! operations picked by `operationName`; fragments, directives, mutations,
+//! subscriptions and introspection are not. As in GraphQL, a field that
+//! fails is `null` with an entry in `errors`, and the rest of the query is
+//! still answered.
+
+use crate::error::ApiError;
+use crate::fib::{self, Sequence};
+use crate::{memo, primes, radix};
+use num_bigint::BigUint;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::sync::Arc;
+
+// Deepest nesting of selections and input values the parser accepts
+const MAX_DEPTH: usize = 32;
+
+/// What a query may ask for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Largest |n| for `fibonacci` and `lucas`, and largest `to` for `range`.
+    pub max_n: u64,
+    /// Most values one `range` field returns.
+    pub max_range_size: u64,
+    /// Most decimal digits in an `isPrime` argument.
+    pub max_prime_digits: u64,
+    /// Most fields in the operation, nested ones included.
+    pub max_fields: u64,
+}
+
+/// A line and column in the query text, both counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// An entry of the response's `errors`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphqlError {
+    pub message: String,
+    pub locations: Vec<Location>,
+    /// Response keys and list indices leading to the failed field; empty for
+    /// errors in the query itself.
+    pub path: Vec<Value>,
+    /// The API error code, for failed fields.
+    pub code: Option<&'static str>,
+}
+
+impl GraphqlError {
+    fn at(location: Location, message: impl Into<String>) -> Self {
+        GraphqlError {
+            message: message.into(),
+            locations: vec![location],
+            path: Vec::new(),
+            code: None,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut error = json!({ "message": self.message });
+        if !self.locations.is_empty() {
+            error["locations"] = self
+                .locations
+                .iter()
+                .map(|location| json!({ "line": location.line, "column": location.column }))
+                .collect();
+        }
+        if !self.path.is_empty() {
+            error["path"] = Value::Array(self.path.clone());
+        }
+        if let Some(code) = self.code {
+            error["extensions"] = json!({ "code": code });
+        }
+        error
+    }
+}
+
+impl fmt::Display for GraphqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.locations.first() {
+            Some(location) => write!(f, "{} at {}:{}", self.message, location.line, location.column),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// A parsed query document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub name: Option<String>,
+    pub variables: Vec<VariableDefinition>,
+    pub selection: Vec<Field>,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableDefinition {
+    pub name: String,
+    /// The type as written, e.g. `Int!`.
+    pub ty: String,
+    pub default: Option<Input>,
+    pub location: Location,
+}
+
+impl VariableDefinition {
+    fn required(&self) -> bool {
+        self.ty.ends_with('!')
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub alias: Option<String>,
+    pub name: String,
+    pub arguments: Vec<(String, Input)>,
+    pub selection: Vec<Field>,
+    pub location: Location,
+}
+
+impl Field {
+    /// The key of the field in the response.
+    pub fn key(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// An argument or default value, before variables are substituted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    Variable(String, Location),
+    Const(Value),
+    List(Vec<Input>),
+    Object(Vec<(String, Input)>),
+}
+
+/// What a field returns.
+#[derive(Debug)]
+pub enum Kind {
+    Scalar(&'static str),
+    Object(&'static Type),
+    List(&'static Type),
+}
+
+#[derive(Debug)]
+pub struct Argument {
+    pub name: &'static str,
+    /// The type in SDL, e.g. `Int!`; a trailing `!` makes it required.
+    pub ty: &'static str,
+}
+
+#[derive(Debug)]
+pub struct FieldType {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub arguments: &'static [Argument],
+    pub kind: Kind,
+}
+
+#[derive(Debug)]
+pub struct Type {
+    pub name: &'static str,
+    pub fields: &'static [FieldType],
+}
+
+impl Type {
+    fn field(&'static self, name: &str) -> Option<&'static FieldType> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// A value of a sequence, as returned by `fibonacci`, `lucas` and `range`.
+pub static TERM: Type = Type {
+    name: "Term",
+    fields: &[
+        FieldType {
+            name: "n",
+            description: "The index",
+            arguments: &[],
+            kind: Kind::Scalar("Int!"),
+        },
+        FieldType {
+            name: "value",
+            description: "The value in decimal, or in `base` 2 to 62",
+            arguments: &[Argument { name: "base", ty: "Int" }],
+            kind: Kind::Scalar("String!"),
+        },
+        FieldType {
+            name: "digits",
+            description: "Decimal digits in the value",
+            arguments: &[],
+            kind: Kind::Scalar("Int!"),
+        },
+    ],
+};
+
+/// The fields a query can start from.
+pub static SCHEMA: Type = Type {
+    name: "Query",
+    fields: &[
+        FieldType {
+            name: "fibonacci",
+            description: "F(n); negative indices give the negafibonacci numbers",
+            arguments: &[Argument { name: "n", ty: "Int!" }],
+            kind: Kind::Object(&TERM),
+        },
+        FieldType {
+            name: "lucas",
+            description: "The Lucas number L(n)",
+            arguments: &[Argument { name: "n", ty: "Int!" }],
+            kind: Kind::Object(&TERM),
+        },
+        FieldType {
+            name: "range",
+            description: "F(from) through F(to)",
+            arguments: &[Argument { name: "from", ty: "Int!" }, Argument { name: "to", ty: "Int!" }],
+            kind: Kind::List(&TERM),
+        },
+        FieldType {
+            name: "isPrime",
+            description: "Whether x is (probably) prime",
+            arguments: &[Argument { name: "x", ty: "BigInt!" }],
+            kind: Kind::Scalar("Boolean"),
+        },
+    ],
+};
+
+/// The schema in GraphQL SDL.
+pub fn sdl() -> String {
+    let mut out = String::from(
+        "\"An integer of any size, as a decimal string (integers are accepted as input too)\"\nscalar BigInt\n",
+    );
+    for ty in [&SCHEMA, &TERM] {
+        out.push_str(&format!("\ntype {} {{\n", ty.name));
+        for field in ty.fields {
+            let arguments: Vec<String> = field
+                .arguments
+                .iter()
+                .map(|argument| format!("{}: {}", argument.name, argument.ty))
+                .collect();
+            let arguments = if arguments.is_empty() {
+                String::new()
+            } else {
+                format!("({})", arguments.join(", "))
+            };
+            let ty = match &field.kind {
+                Kind::Scalar(name) => name.to_string(),
+                Kind::Object(ty) => ty.name.to_string(),
+                Kind::List(ty) => format!("[{}!]", ty.name),
+            };
+            out.push_str(&format!("  \"{}\"\n  {}{}: {}\n", field.description, field.name, arguments, ty));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// Parses a query document.
+pub fn parse(source: &str) -> Result<Document, GraphqlError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+    };
+    let mut operations = Vec::new();
+    while parser.peek() != &Token::End {
+        operations.push(parser.operation()?);
+    }
+    if operations.is_empty() {
+        return Err(GraphqlError::at(parser.location(), "the document has no operation"));
+    }
+    Ok(Document { operations })
+}
+
+/// The outcome of running a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    /// `None` when the query was rejected before running.
+    pub data: Option<Value>,
+    pub errors: Vec<GraphqlError>,
+    /// The Fibonacci and Lucas indices computed.
+    pub indices: Vec<u64>,
+}
+
+impl Execution {
+    fn rejected(errors: Vec<GraphqlError>) -> Self {
+        Execution {
+            data: None,
+            errors,
+            indices: Vec::new(),
+        }
+    }
+
+    /// The response document: `data`, and `errors` when there are any.
+    pub fn to_json(&self) -> Value {
+        let mut body = Map::new();
+        if let Some(data) = &self.data {
+            body.insert("data".to_string(), data.clone());
+        }
+        if !self.errors.is_empty() {
+            body.insert("errors".to_string(), self.errors.iter().map(GraphqlError::to_json).collect());
+        }
+        Value::Object(body)
+    }
+}
+
+/// Runs the operation named `operation_name` (or the only one) of `document`.
+pub fn execute(
+    document: &Document,
+    operation_name: Option<&str>,
+    variables: &Map<String, Value>,
+    limits: &Limits,
+) -> Execution {
+    let operation = match (operation_name, document.operations.as_slice()) {
+        (None, [operation]) => operation,
+        (None, _) => {
+            return Execution::rejected(vec![GraphqlError {
+                message: "the document has several operations, so operationName is required".to_string(),
+                locations: Vec::new(),
+                path: Vec::new(),
+                code: None,
+            }])
+        }
+        (Some(name), operations) => match operations.iter().find(|operation| operation.name.as_deref() == Some(name)) {
+            Some(operation) => operation,
+            None => {
+                return Execution::rejected(vec![GraphqlError {
+                    message: format!("unknown operation '{}'", name),
+                    locations: Vec::new(),
+                    path: Vec::new(),
+                    code: None,
+                }])
+            }
+        },
+    };
+
+    let mut errors = Vec::new();
+    let mut bound = Map::new();
+    for definition in &operation.variables {
+        let value = match (variables.get(&definition.name), &definition.default) {
+            (Some(value), _) => value.clone(),
+            (None, Some(default)) => substitute(default, &Map::new()).unwrap_or(Value::Null),
+            (None, None) => Value::Null,
+        };
+        if value.is_null() && definition.required() {
+            errors.push(GraphqlError::at(
+                definition.location,
+                format!("variable '${}' of type '{}' was not provided", definition.name, definition.ty),
+            ));
+        }
+        bound.insert(definition.name.clone(), value);
+    }
+    let mut fields = 0;
+    validate(&operation.selection, &SCHEMA, &bound, &mut fields, &mut errors);
+    if fields > limits.max_fields {
+        errors.push(GraphqlError::at(
+            operation.location,
+            format!("the operation has {} fields, more than the limit of {}", fields, limits.max_fields),
+        ));
+    }
+    if !errors.is_empty() {
+        return Execution::rejected(errors);
+    }
+
+    let mut executor = Executor {
+        variables: &bound,
+        limits,
+        errors: Vec::new(),
+        indices: Vec::new(),
+    };
+    let mut data = Map::new();
+    for field in &operation.selection {
+        let path = vec![json!(field.key())];
+        let value = executor.root(field, &path);
+        data.insert(field.key().to_string(), value);
+    }
+    Execution {
+        data: Some(Value::Object(data)),
+        errors: executor.errors,
+        indices: executor.indices,
+    }
+}
+
+// Checks `selection` against `ty`, counting its fields
+fn validate(
+    selection: &[Field],
+    ty: &'static Type,
+    variables: &Map<String, Value>,
+    fields: &mut u64,
+    errors: &mut Vec<GraphqlError>,
+) {
+    for field in selection {
+        *fields += 1;
+        if field.name == "__typename" {
+            if !field.selection.is_empty() {
+                errors.push(GraphqlError::at(field.location, "'__typename' has no fields to select"));
+            }
+            continue;
+        }
+        let Some(definition) = ty.field(&field.name) else {
+            errors.push(GraphqlError::at(
+                field.location,
+                format!("cannot query field '{}' on type '{}'", field.name, ty.name),
+            ));
+            continue;
+        };
+        for (name, input) in &field.arguments {
+            if !definition.arguments.iter().any(|argument| argument.name == name) {
+                errors.push(GraphqlError::at(
+                    field.location,
+                    format!("unknown argument '{}' on field '{}.{}'", name, ty.name, field.name),
+                ));
+            }
+            if let Err(err) = substitute(input, variables) {
+                errors.push(err);
+            }
+        }
+        for argument in definition.arguments.iter().filter(|argument| argument.ty.ends_with('!')) {
+            if !field.arguments.iter().any(|(name, _)| name == argument.name) {
+                errors.push(GraphqlError::at(
+                    field.location,
+                    format!(
+                        "field '{}' needs argument '{}' of type '{}'",
+                        field.name, argument.name, argument.ty
+                    ),
+                ));
+            }
+        }
+        match (&definition.kind, field.selection.is_empty()) {
+            (Kind::Scalar(_), false) => errors.push(GraphqlError::at(
+                field.location,
+                format!("field '{}' is a scalar and has no fields to select", field.name),
+            )),
+            (Kind::Object(ty) | Kind::List(ty), true) => errors.push(GraphqlError::at(
+                field.location,
+                format!("field '{}' of type '{}' needs a selection of its fields", field.name, ty.name),
+            )),
+            (Kind::Object(ty) | Kind::List(ty), false) => validate(&field.selection, ty, variables, fields, errors),
+            (Kind::Scalar(_), true) => {}
+        }
+    }
+}
+
+// `input` with its variables replaced by their values
+fn substitute(input: &Input, variables: &Map<String, Value>) -> Result<Value, GraphqlError> {
+    match input {
+        Input::Variable(name, location) => variables
+            .get(name)
+            .cloned()
+            .ok_or_else(|| GraphqlError::at(*location, format!("variable '${}' is not defined", name))),
+        Input::Const(value) => Ok(value.clone()),
+        Input::List(items) => items.iter().map(|item| substitute(item, variables)).collect(),
+        Input::Object(entries) => entries
+            .iter()
+            .map(|(name, input)| Ok((name.clone(), substitute(input, variables)?)))
+            .collect::<Result<Map<_, _>, _>>()
+            .map(Value::Object),
+    }
+}
+
+// A resolved `Term`
+struct Term {
+    n: i64,
+    negative: bool,
+    magnitude: Arc<BigUint>,
+}
+
+struct Executor<'a> {
+    variables: &'a Map<String, Value>,
+    limits: &'a Limits,
+    errors: Vec<GraphqlError>,
+    indices: Vec<u64>,
+}
+
+impl Executor<'_> {
+    fn root(&mut self, field: &Field, path: &[Value]) -> Value {
+        let resolved = match field.name.as_str() {
+            "__typename" => return json!(SCHEMA.name),
+            "fibonacci" => self.fibonacci(field).map(|term| self.term(field, term, path)),
+            "lucas" => self.lucas(field).map(|term| self.term(field, term, path)),
+            "range" => self.range(field).map(|terms| {
+                let values: Vec<Value> = terms
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, term)| {
+                        let mut path = path.to_vec();
+                        path.push(json!(i));
+                        self.term(field, term, &path)
+                    })
+                    .collect();
+                // A `Term!` that failed makes the whole list null
+                if values.iter().any(Value::is_null) {
+                    Value::Null
+                } else {
+                    Value::Array(values)
+                }
+            }),
+            "isPrime" => self.is_prime(field),
+            other => unreachable!("validated field {}", other),
+        };
+        resolved.unwrap_or_else(|err| {
+            self.fail(field, path, err);
+            Value::Null
+        })
+    }
+
+    // The selected fields of `term`, or null if a non-null one failed
+    fn term(&mut self, parent: &Field, term: Term, path: &[Value]) -> Value {
+        let mut object = Map::new();
+        for field in &parent.selection {
+            let mut path = path.to_vec();
+            path.push(json!(field.key()));
+            let value = match field.name.as_str() {
+                "__typename" => Ok(json!(TERM.name)),
+                "n" => Ok(json!(term.n)),
+                "digits" => Ok(json!(term.magnitude.to_string().len())),
+                "value" => self.optional_int(field, "base").and_then(|base| {
+                    let digits = match base {
+                        Some(base) => radix::encode(&term.magnitude, radix::validate(base)?),
+                        None => term.magnitude.to_string(),
+                    };
+                    Ok(json!(if term.negative { format!("-{}", digits) } else { digits }))
+                }),
+                other => unreachable!("validated field {}", other),
+            };
+            match value {
+                Ok(value) => {
+                    object.insert(field.key().to_string(), value);
+                }
+                Err(err) => {
+                    self.fail(field, &path, err);
+                    return Value::Null;
+                }
+            }
+        }
+        Value::Object(object)
+    }
+
+    fn fibonacci(&mut self, field: &Field) -> Result<Term, ApiError> {
+        let n = self.int(field, "n")?;
+        if n.unsigned_abs() > self.limits.max_n {
+            return Err(ApiError::OutOfRange {
+                name: "n",
+                received: n.to_string(),
+                max: self.limits.max_n,
+            });
+        }
+        self.indices.push(n.unsigned_abs());
+        Ok(Term {
+            n,
+            negative: fib::negafibonacci_is_negative(n),
+            magnitude: memo::fibonacci(n.unsigned_abs()).0,
+        })
+    }
+
+    fn lucas(&mut self, field: &Field) -> Result<Term, ApiError> {
+        let n = self.natural(field, "n", self.limits.max_n)?;
+        self.indices.push(n);
+        Ok(Term {
+            n: n as i64,
+            negative: false,
+            magnitude: Arc::new(fib::lucas(n)),
+        })
+    }
+
+    fn range(&mut self, field: &Field) -> Result<Vec<Term>, ApiError> {
+        let to = self.natural(field, "to", self.limits.max_n)?;
+        let from = self.natural(field, "from", to)?;
+        let count = to - from + 1;
+        if count > self.limits.max_range_size {
+            return Err(ApiError::OutOfRange {
+                name: "to",
+                received: format!("a range of {} values", count),
+                max: self.limits.max_range_size,
+            });
+        }
+        Ok((from..=to)
+            .zip(Sequence::starting_at(from))
+            .map(|(n, value)| Term {
+                n: n as i64,
+                negative: false,
+                magnitude: Arc::new(value),
+            })
+            .collect())
+    }
+
+    fn is_prime(&mut self, field: &Field) -> Result<Value, ApiError> {
+        let x = match self.argument(field, "x")? {
+            Value::String(text) => text,
+            Value::Number(number) if number.is_u64() => number.to_string(),
+            other => return Err(invalid("x", &other, "a non-negative integer, or one as a string")),
+        };
+        if x.is_empty() || !x.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid("x", &json!(x), "a non-negative decimal integer"));
+        }
+        if x.len() as u64 > self.limits.max_prime_digits {
+            return Err(ApiError::OutOfRange {
+                name: "x",
+                received: format!("{} digits", x.len()),
+                max: self.limits.max_prime_digits,
+            });
+        }
+        let x: BigUint = x.parse().expect("checked to be decimal");
+        Ok(json!(primes::primality(&x).is_prime()))
+    }
+
+    fn argument(&self, field: &Field, name: &str) -> Result<Value, ApiError> {
+        let input = field.arguments.iter().find(|(argument, _)| argument == name).map(|(_, input)| input);
+        // Variables were checked to be defined while validating
+        Ok(input
+            .map(|input| substitute(input, self.variables).unwrap_or(Value::Null))
+            .unwrap_or(Value::Null))
+    }
+
+    fn optional_int(&self, field: &Field, name: &'static str) -> Result<Option<u64>, ApiError> {
+        match self.argument(field, name)? {
+            Value::Null => Ok(None),
+            value => value.as_u64().map(Some).ok_or_else(|| invalid(name, &value, "a non-negative integer")),
+        }
+    }
+
+    fn int(&self, field: &Field, name: &'static str) -> Result<i64, ApiError> {
+        match self.argument(field, name)? {
+            Value::Null => Err(ApiError::MissingParameter { name }),
+            value => value.as_i64().ok_or_else(|| invalid(name, &value, "an integer")),
+        }
+    }
+
+    // A non-negative integer argument of at most `max`
+    fn natural(&self, field: &Field, name: &'static str, max: u64) -> Result<u64, ApiError> {
+        let value = self.int(field, name)?;
+        let n = u64::try_from(value).map_err(|_| invalid(name, &json!(value), "a non-negative integer"))?;
+        if n > max {
+            return Err(ApiError::OutOfRange {
+                name,
+                received: n.to_string(),
+                max,
+            });
+        }
+        Ok(n)
+    }
+
+    fn fail(&mut self, field: &Field, path: &[Value], err: ApiError) {
+        self.errors.push(GraphqlError {
+            message: err.to_string(),
+            locations: vec![field.location],
+            path: path.to_vec(),
+            code: Some(err.code()),
+        });
+    }
+}
+
+fn invalid(name: &'static str, value: &Value, expected: &'static str) -> ApiError {
+    ApiError::InvalidParameter {
+        name,
+        received: value.to_string(),
+        expected: expected.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Punctuator(char),
+    Spread,
+    Name(String),
+    Int(i64),
+    Float(f64),
+    String(String),
+    End,
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Location)>, GraphqlError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut line_start) = (0, 1, 0);
+    loop {
+        // Whitespace, commas and comments carry no meaning
+        while let Some(&c) = chars.get(i) {
+            match c {
+                '\n' => {
+                    line += 1;
+                    line_start = i + 1;
+                }
+                ' ' | '\t' | '\r' | ',' | '\u{feff}' => {}
+                '#' => {
+                    while chars.get(i + 1).is_some_and(|&c| c != '\n') {
+                        i += 1;
+                    }
+                }
+                _ => break,
+            }
+            i += 1;
+        }
+        let location = Location {
+            line,
+            column: i - line_start + 1,
+        };
+        let Some(&c) = chars.get(i) else {
+            tokens.push((Token::End, location));
+            return Ok(tokens);
+        };
+        let token = match c {
+            '!' | '$' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '|' | '}' | '&' => {
+                i += 1;
+                Token::Punctuator(c)
+            }
+            '.' if chars.get(i..i + 3) == Some(&['.', '.', '.']) => {
+                i += 3;
+                Token::Spread
+            }
+            'a'..='z' | 'A'..='Z' | '_' => {
+                let start = i;
+                while chars.get(i).is_some_and(|&c| c.is_ascii_alphanumeric() || c == '_') {
+                    i += 1;
+                }
+                Token::Name(chars[start..i].iter().collect())
+            }
+            '-' | '0'..='9' => {
+                let start = i;
+                i += 1;
+                while chars
+                    .get(i)
+                    .is_some_and(|&c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+                {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                if text.contains(['.', 'e', 'E']) {
+                    Token::Float(text.parse().map_err(|_| GraphqlError::at(location, format!("invalid number '{}'", text)))?)
+                } else {
+                    Token::Int(text.parse().map_err(|_| {
+                        GraphqlError::at(location, format!("invalid integer '{}'; pass large numbers as strings", text))
+                    })?)
+                }
+            }
+            '"' => {
+                if chars.get(i..i + 3) == Some(&['"', '"', '"']) {
+                    return Err(GraphqlError::at(location, "block strings are not supported"));
+                }
+                i += 1;
+                let mut text = String::new();
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err(GraphqlError::at(location, "unterminated string")),
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped = match chars.get(i + 1) {
+                                Some('"') => '"',
+                                Some('\\') => '\\',
+                                Some('/') => '/',
+                                Some('b') => '\u{8}',
+                                Some('f') => '\u{c}',
+                                Some('n') => '\n',
+                                Some('r') => '\r',
+                                Some('t') => '\t',
+                                Some('u') => {
+                                    let hex: String = chars.get(i + 2..i + 6).unwrap_or_default().iter().collect();
+                                    let code = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                                    i += 4;
+                                    code.ok_or_else(|| GraphqlError::at(location, "invalid unicode escape"))?
+                                }
+                                _ => return Err(GraphqlError::at(location, "invalid escape sequence")),
+                            };
+                            text.push(escaped);
+                            i += 2;
+                        }
+                        Some(&c) => {
+                            text.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                Token::String(text)
+            }
+            other => return Err(GraphqlError::at(location, format!("unexpected character '{}'", other))),
+        };
+        tokens.push((token, location));
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, Location)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn location(&self) -> Location {
+        self.tokens[self.position].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self, expected: &str) -> GraphqlError {
+        let found = match self.peek() {
+            Token::Punctuator(c) => format!("'{}'", c),
+            Token::Spread => "'...'".to_string(),
+            Token::Name(name) => format!("'{}'", name),
+            Token::Int(n) => n.to_string(),
+            Token::Float(x) => x.to_string(),
+            Token::String(text) => format!("\"{}\"", text),
+            Token::End => "the end of the document".to_string(),
+        };
+        GraphqlError::at(self.location(), format!("expected {}, found {}", expected, found))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == &Token::Punctuator(c) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), GraphqlError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", c)))
+        }
+    }
+
+    fn name(&mut self) -> Result<String, GraphqlError> {
+        match self.peek() {
+            Token::Name(_) => match self.advance() {
+                Token::Name(name) => Ok(name),
+                _ => unreachable!(),
+            },
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    fn operation(&mut self) -> Result<Operation, GraphqlError> {
+        let location = self.location();
+        if self.peek() == &Token::Punctuator('{') {
+            return Ok(Operation {
+                name: None,
+                variables: Vec::new(),
+                selection: self.selection_set(0)?,
+                location,
+            });
+        }
+        match self.name()?.as_str() {
+            "query" => {}
+            "mutation" | "subscription" => {
+                return Err(GraphqlError::at(location, "only queries are supported"));
+            }
+            "fragment" => return Err(GraphqlError::at(location, "fragments are not supported")),
+            other => return Err(GraphqlError::at(location, format!("expected an operation, found '{}'", other))),
+        }
+        let name = match self.peek() {
+            Token::Name(_) => Some(self.name()?),
+            _ => None,
+        };
+        let mut variables = Vec::new();
+        if self.eat('(') {
+            while !self.eat(')') {
+                variables.push(self.variable_definition()?);
+            }
+        }
+        self.no_directives()?;
+        Ok(Operation {
+            name,
+            variables,
+            selection: self.selection_set(0)?,
+            location,
+        })
+    }
+
+    fn variable_definition(&mut self) -> Result<VariableDefinition, GraphqlError> {
+        let location = self.location();
+        self.expect('$')?;
+        let name = self.name()?;
+        self.expect(':')?;
+        let ty = self.type_reference(0)?;
+        let default = if self.eat('=') { Some(self.value(0)?) } else { None };
+        Ok(VariableDefinition {
+            name,
+            ty,
+            default,
+            location,
+        })
+    }
+
+    fn type_reference(&mut self, depth: usize) -> Result<String, GraphqlError> {
+        if depth > MAX_DEPTH {
+            return Err(GraphqlError::at(self.location(), "the type is nested too deeply"));
+        }
+        let mut ty = if self.eat('[') {
+            let inner = self.type_reference(depth + 1)?;
+            self.expect(']')?;
+            format!("[{}]", inner)
+        } else {
+            self.name()?
+        };
+        if self.eat('!') {
+            ty.push('!');
+        }
+        Ok(ty)
+    }
+
+    fn selection_set(&mut self, depth: usize) -> Result<Vec<Field>, GraphqlError> {
+        if depth > MAX_DEPTH {
+            return Err(GraphqlError::at(self.location(), "the selection is nested too deeply"));
+        }
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        while !self.eat('}') {
+            if self.peek() == &Token::Spread {
+                return Err(GraphqlError::at(self.location(), "fragments are not supported"));
+            }
+            fields.push(self.field(depth)?);
+        }
+        if fields.is_empty() {
+            return Err(GraphqlError::at(self.location(), "a selection needs at least one field"));
+        }
+        Ok(fields)
+    }
+
+    fn field(&mut self, depth: usize) -> Result<Field, GraphqlError> {
+        let location = self.location();
+        let mut name = self.name()?;
+        let mut alias = None;
+        if self.eat(':') {
+            alias = Some(name);
+            name = self.name()?;
+        }
+        let mut arguments = Vec::new();
+        if self.eat('(') {
+            while !self.eat(')') {
+                let argument = self.name()?;
+                self.expect(':')?;
+                arguments.push((argument, self.value(0)?));
+            }
+        }
+        self.no_directives()?;
+        let selection = if self.peek() == &Token::Punctuator('{') {
+            self.selection_set(depth + 1)?
+        } else {
+            Vec::new()
+        };
+        Ok(Field {
+            alias,
+            name,
+            arguments,
+            selection,
+            location,
+        })
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Input, GraphqlError> {
+        if depth > MAX_DEPTH {
+            return Err(GraphqlError::at(self.location(), "the value is nested too deeply"));
+        }
+        let location = self.location();
+        match self.advance() {
+            Token::Punctuator('$') => Ok(Input::Variable(self.name()?, location)),
+            Token::Int(n) => Ok(Input::Const(json!(n))),
+            Token::Float(x) => Ok(Input::Const(json!(x))),
+            Token::String(text) => Ok(Input::Const(Value::String(text))),
+            Token::Name(name) => Ok(Input::Const(match name.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => Value::String(name),
+            })),
+            Token::Punctuator('[') => {
+                let mut items = Vec::new();
+                while !self.eat(']') {
+                    items.push(self.value(depth + 1)?);
+                }
+                Ok(Input::List(items))
+            }
+            Token::Punctuator('{') => {
+                let mut entries = Vec::new();
+                while !self.eat('}') {
+                    let name = self.name()?;
+                    self.expect(':')?;
+                    entries.push((name, self.value(depth + 1)?));
+                }
+                Ok(Input::Object(entries))
+            }
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected("a value"))
+            }
+        }
+    }
+
+    fn no_directives(&self) -> Result<(), GraphqlError> {
+        if self.peek() == &Token::Punctuator('@') {
+            return Err(GraphqlError::at(self.location(), "directives are not supported"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_n: 1000,
+        max_range_size: 500,
+        max_prime_digits: 1000,
+        max_fields: 100,
+    };
+
+    fn run(query: &str, variables: Value) -> Value {
+        let document = parse(query).unwrap();
+        let variables = variables.as_object().cloned().unwrap_or_default();
+        execute(&document, None, &variables, &LIMITS).to_json()
+    }
+
+    #[test]
+    fn answers_several_fields_in_one_query() {
+        let body = run(
+            r#"query Values($n: Int! = 5) {
+                fib: fibonacci(n: $n) { value digits }
+                hex: fibonacci(n: 30) { value(base: 16) }
+                negative: fibonacci(n: -2) { n value }
+                range(from: 10, to: 12) { value }
+                isPrime(x: "1000000007")
+                __typename
+            }"#,
+            json!({ "n": 20 }),
+        );
+        assert_eq!(body["data"]["fib"], json!({ "value": "6765", "digits": 4 }));
+        assert_eq!(body["data"]["hex"]["value"], "cb228");
+        assert_eq!(body["data"]["negative"], json!({ "n": -2, "value": "-1" }));
+        assert_eq!(body["data"]["range"], json!([{ "value": "55" }, { "value": "89" }, { "value": "144" }]));
+        assert_eq!(body["data"]["isPrime"], true);
+        assert_eq!(body["data"]["__typename"], "Query");
+        assert!(body.get("errors").is_none());
+    }
+
+    #[test]
+    fn failed_fields_are_null_with_an_error() {
+        let body = run("{ big: fibonacci(n: 5000) { value } small: lucas(n: 3) { value } }", json!({}));
+        assert_eq!(body["data"]["big"], Value::Null);
+        assert_eq!(body["data"]["small"]["value"], "4");
+        let error = &body["errors"][0];
+        assert_eq!(error["path"], json!(["big"]));
+        assert_eq!(error["extensions"]["code"], "out_of_range");
+        assert_eq!(error["locations"], json!([{ "line": 1, "column": 3 }]));
+    }
+
+    #[test]
+    fn rejects_invalid_queries_before_running() {
+        for (query, message) in [
+            ("{ fibonacci { value } }", "needs argument 'n'"),
+            ("{ fibonacci(n: 1) }", "needs a selection"),
+            ("{ nope }", "cannot query field 'nope'"),
+            ("{ fibonacci(n: $n) { value } }", "variable '$n' is not defined"),
+            ("mutation { x }", "only queries are supported"),
+            ("{ ...Values }", "fragments are not supported"),
+            ("{ fibonacci(n: 1) { value ", "expected a name, found the end"),
+        ] {
+            let result = parse(query).map(|document| execute(&document, None, &Map::new(), &LIMITS));
+            let error = match result {
+                Ok(execution) => {
+                    assert_eq!(execution.data, None, "{}", query);
+                    execution.errors[0].message.clone()
+                }
+                Err(err) => err.message,
+            };
+            assert!(error.contains(message), "{}: {}", query, error);
+        }
+    }
+}
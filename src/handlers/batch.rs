@@ -0,0 +1,165 @@
+//! The batch endpoint computing several Fibonacci numbers in one request.
+
+use super::{check_limit, guarded};
+use crate::budget::Deadline;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::memo::{self, CacheStatus};
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use crate::radix;
+use crate::parallel;
+use crate::usage::{self, Work};
+use crate::routing::parse_json_object;
+use http::Method;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `POST /api/batch`: computes F(n) for every index in `{"n": [10, 20, 500]}`.
+///
+/// Each value is looked up in the in-process cache or computed on its own
+/// task, at most `MAX_PARALLELISM` at a time, and the whole batch shares one
+/// `COMPUTE_BUDGET_MS` budget. The batch size is checked against the
+/// configured limit; an entry that is not a valid index, or that was not
+/// done in time, gets an `error` in its result instead of failing the
+/// batch. A `"base"` field (or `?base=`) writes the values in another radix.
+pub async fn batch(req: Request) -> Result<Response<Body>, Error> {
+    guarded("batch", &[Method::POST], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, read_body(&req)?)));
+    let (format, (entries, base)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "batch rejected");
+            return err.into_response();
+        }
+    };
+    tracing::debug!(count = entries.len(), "batch requested");
+
+    let deadline = Deadline::for_request();
+    let indices = entries.iter().map(|(_, index)| index.clone());
+    let outcomes = parallel::map(indices, move |index| async move {
+        let n = index?;
+        match memo::fibonacci_persisted(n, deadline).await {
+            Ok(Ok((value, cache_status))) => {
+                let value = match base {
+                    Some(base) => radix::encode(&value, base),
+                    None => value.to_string(),
+                };
+                Ok((value, cache_status))
+            }
+            Ok(Err(interrupted)) => Err(deadline.exceeded(n, interrupted)),
+            Err(err) => {
+                tracing::error!(error = %err, n, "batch entry failed");
+                Err(ApiError::Internal)
+            }
+        }
+    })
+    .await?;
+
+    let mut rows = Vec::with_capacity(outcomes.len());
+    let mut statuses = Vec::with_capacity(outcomes.len());
+    let mut results = Vec::with_capacity(outcomes.len());
+    let mut computed = Vec::with_capacity(outcomes.len());
+    for ((entry, index), outcome) in entries.iter().zip(outcomes) {
+        match outcome {
+            Ok((value, cache_status)) => {
+                computed.extend(index.as_ref().ok().copied());
+                results.push(json!({ "n": entry, "fibonacci": value, "cache": cache_status.as_str() }));
+                rows.push((entry.to_string(), value));
+                statuses.push(cache_status);
+            }
+            Err(err) => results.push(json!({
+                "n": entry,
+                "error": { "code": err.code(), "status": err.status().as_u16(), "detail": err.to_string() },
+            })),
+        }
+    }
+    usage::charge(Work::indices(computed));
+    let failed = results.len() - rows.len();
+    if failed > 0 {
+        tracing::info!(failed, count = results.len(), "batch entries failed");
+    }
+
+    let mut response_body = json!({
+        "results": results,
+        "count": entries.len(),
+        "failed": failed,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+    if let Some(base) = base {
+        response_body["base"] = json!(base);
+    }
+
+    let payload = Payload {
+        json: response_body,
+        rows,
+        label: "fibonacci",
+        meta: Meta::new("fast-doubling").with_cache(CacheStatus::summarize(&statuses)),
+    };
+    response::render(StatusCode::OK, format, payload)
+}
+
+// An entry of the batch as sent, with the index it names or why it is not
+// a valid one
+type Entry = (Value, Result<u64, ApiError>);
+
+// Reads the entries and the optional output base, from the body or `?base=`
+fn read_body(req: &Request) -> Result<(Vec<Entry>, Option<u32>), ApiError> {
+    if req.body().is_empty() {
+        return Err(ApiError::MalformedBody {
+            reason: "expected a JSON object such as {\"n\": [10, 20, 500]}".to_string(),
+        });
+    }
+    let config = Config::get();
+    let mut body = parse_json_object(req.body())?;
+    let entries = match body.remove("n") {
+        None | Some(Value::Null) => return Err(ApiError::MissingParameter { name: "n" }),
+        Some(Value::Array(entries)) => entries,
+        Some(other) => {
+            return Err(ApiError::InvalidParameter {
+                name: "n",
+                received: other.to_string(),
+                expected: "an array of non-negative integers".into(),
+            })
+        }
+    };
+
+    if entries.len() as u64 > config.max_batch_size {
+        return Err(ApiError::OutOfRange {
+            name: "n",
+            received: format!("{} indices", entries.len()),
+            max: config.max_batch_size,
+        });
+    }
+
+    #[derive(Deserialize)]
+    struct Output {
+        base: Option<u32>,
+    }
+    // A base in the body takes precedence over `?base=`
+    let Output { base } = Params::from_body(req.body())?
+        .merge(Params::from_query(req.uri().query().unwrap_or("")))
+        .bound("base", radix::BASES)
+        .parse()?;
+    let entries = entries
+        .into_iter()
+        .map(|entry| {
+            let index = match entry.as_u64() {
+                Some(n) => check_limit(n, "n", config.max_n),
+                None => Err(ApiError::InvalidParameter {
+                    name: "n",
+                    received: entry.to_string(),
+                    expected: "a non-negative integer".into(),
+                }),
+            };
+            (entry, index)
+        })
+        .collect();
+    Ok((entries, base))
+}
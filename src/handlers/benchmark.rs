@@ -0,0 +1,78 @@
+//! The self-benchmark endpoint.
+
+use super::authorized;
+use crate::benchmark::{self, Timing, TASKS};
+use crate::budget::{self, Deadline};
+use crate::build_info;
+use crate::response;
+use http::header::{HeaderValue, CACHE_CONTROL};
+use http::Method;
+use serde_json::{json, Value};
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/benchmark`: runs the fixed workload in [`crate::benchmark`] on
+/// this instance and reports how long each task took and how large its
+/// result was, next to the memory the process holds.
+///
+/// `instance.run` counts the runs on this instance, so the first one shows
+/// how a cold instance performs. Requires an API key when `API_KEYS` is set
+/// and is rate limited, but not counted against the quotas. The tasks share
+/// the `COMPUTE_BUDGET_MS` budget and are never cached.
+pub async fn benchmark(req: Request) -> Result<Response<Body>, Error> {
+    authorized("benchmark", &[Method::GET], req, handle).await
+}
+
+async fn handle(_req: Request) -> Result<Response<Body>, Error> {
+    let run = benchmark::begin();
+    let uptime = build_info::uptime();
+    let memory_before = benchmark::resident_bytes();
+    tracing::debug!(run, "benchmark started");
+
+    let deadline = Deadline::for_request();
+    let mut timings = Vec::with_capacity(TASKS.len());
+    for task in TASKS {
+        let Some(timing) = budget::run_blocking(deadline, move || task.run()).await? else {
+            let progress = format!("{} of {} tasks finished, {} still running", timings.len(), TASKS.len(), task.name);
+            let err = deadline.abandoned("the benchmark", progress, Vec::new());
+            tracing::warn!(error = %err, "benchmark abandoned");
+            return err.into_response();
+        };
+        tracing::debug!(task = timing.name, elapsed_ns = timing.elapsed.as_nanos() as u64, "benchmark task finished");
+        timings.push(timing);
+    }
+    let memory_after = benchmark::resident_bytes();
+
+    let total_ns: u128 = timings.iter().map(|timing| timing.elapsed.as_nanos()).sum();
+    let body = json!({
+        "tasks": timings.iter().map(task_json).collect::<Vec<_>>(),
+        "total_ns": total_ns as u64,
+        "memory": {
+            "resident_bytes_before": memory_before.map(|(resident, _)| resident),
+            "resident_bytes_after": memory_after.map(|(resident, _)| resident),
+            "peak_resident_bytes": memory_after.map(|(_, peak)| peak),
+        },
+        "instance": {
+            "run": run,
+            "uptime_seconds": uptime.as_secs_f64(),
+            "version": build_info::VERSION,
+            "git_sha": build_info::GIT_SHA,
+        },
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    let mut response = response::json(StatusCode::OK, &body)?;
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Ok(response)
+}
+
+fn task_json(timing: &Timing) -> Value {
+    json!({
+        "name": timing.name,
+        "description": timing.description,
+        "elapsed_ns": timing.elapsed.as_nanos() as u64,
+        "result_bytes": timing.result_bytes,
+    })
+}
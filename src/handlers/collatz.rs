@@ -0,0 +1,150 @@
+//! The Collatz trajectory endpoint.
+
+use super::guarded;
+use crate::budget::Deadline;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::negotiation::Format;
+use crate::numtheory::{self, Trajectory};
+use crate::params::{Natural, Params};
+use crate::response::{self, Meta, Payload};
+use http::Method;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/collatz/[n]`: how many Collatz steps take n to 1, and the
+/// largest value on the way.
+///
+/// `n` is a positive decimal of at most `MAX_COLLATZ_DIGITS` digits, read
+/// from the path or `?n=`. `?include_sequence=true` adds the trajectory
+/// itself, cut off after `?max_length=` values (at most, and by default,
+/// `MAX_COLLATZ_SEQUENCE`). A trajectory still running at
+/// `COMPUTE_BUDGET_MS` is abandoned with a `504`. Responses are cacheable.
+pub async fn collatz(req: Request) -> Result<Response<Body>, Error> {
+    guarded("collatz", &[Method::GET], req, handle).await
+}
+
+/// Validated parameters of a Collatz request.
+struct CollatzParams {
+    n: BigUint,
+    /// Values of the trajectory to include, 0 without `include_sequence`.
+    keep: u64,
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, params) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let key = format!("n={}&keep={}", params.n, params.keep);
+    let etag = caching::etag("collatz", &key, format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        let deadline = Deadline::for_request();
+        let task = {
+            let (n, keep) = (params.n.clone(), params.keep as usize);
+            tokio::task::spawn_blocking(move || numtheory::collatz(&n, keep, &deadline))
+        };
+        let trajectory = match task.await? {
+            Ok(trajectory) => trajectory,
+            Err(steps) => {
+                let progress = format!("{} steps without reaching 1", steps);
+                let err = deadline.abandoned(format!("the Collatz trajectory of {}", params.n), progress, Vec::new());
+                tracing::warn!(error = %err, "trajectory abandoned");
+                return err.into_response();
+            }
+        };
+        response::render(StatusCode::OK, format, collatz_body(&params, trajectory))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<CollatzParams, ApiError> {
+    #[derive(Deserialize)]
+    struct Start {
+        n: Natural,
+        #[serde(default)]
+        include_sequence: bool,
+        max_length: Option<u64>,
+    }
+
+    let config = Config::get();
+    let Start {
+        n: Natural(n),
+        include_sequence,
+        max_length,
+    } = Params::from_query(req.uri().query().unwrap_or(""))
+        .with_path("n", req.uri().path())
+        .bound("max_length", 0..=i128::from(config.max_collatz_sequence))
+        .parse()?;
+    if n.is_zero() {
+        return Err(ApiError::InvalidParameter {
+            name: "n",
+            received: "0".to_string(),
+            expected: "a positive integer".into(),
+        });
+    }
+    let digits = n.to_string().len() as u64;
+    if digits > config.max_collatz_digits {
+        return Err(ApiError::OutOfRange {
+            name: "n",
+            received: format!("{} digits", digits),
+            max: config.max_collatz_digits,
+        });
+    }
+    let keep = if include_sequence {
+        max_length.unwrap_or(config.max_collatz_sequence)
+    } else {
+        0
+    };
+    tracing::debug!(digits, keep, "collatz trajectory requested");
+    Ok(CollatzParams { n, keep })
+}
+
+fn collatz_body(params: &CollatzParams, trajectory: Trajectory) -> Payload {
+    let Trajectory { steps, max, values } = trajectory;
+    let n = params.n.to_string();
+
+    let mut body = json!({
+        "n": n,
+        "stopping_time": steps,
+        "max_value": max.to_string(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+    // Text and CSV list the trajectory when there is one, else the step count
+    let rows = if params.keep > 0 {
+        let sequence: Vec<_> = values.iter().map(BigUint::to_string).collect();
+        body["sequence_truncated"] = json!((sequence.len() as u64) < steps + 1);
+        body["sequence"] = json!(sequence);
+        sequence
+            .into_iter()
+            .enumerate()
+            .map(|(step, value)| (step.to_string(), value))
+            .collect()
+    } else {
+        vec![(n, steps.to_string())]
+    };
+
+    Payload {
+        json: body,
+        rows,
+        label: if params.keep > 0 { "value" } else { "stopping_time" },
+        meta: Meta::new("iterative"),
+    }
+}
@@ -0,0 +1,139 @@
+//! The factorial and binomial coefficient endpoints.
+
+use super::{bounded_index, guarded};
+use crate::caching;
+use crate::combinatorics;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use crate::telemetry;
+use crate::usage::{self, Work};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/factorial/[n]`: computes n!.
+///
+/// The value is read from the path or `?n=` and is limited by
+/// `MAX_FACTORIAL_N`. Responses are cacheable.
+pub async fn factorial(req: Request) -> Result<Response<Body>, Error> {
+    guarded("factorial", &[Method::GET], req, handle_factorial).await
+}
+
+/// `GET /api/choose?n=10&k=3`: computes the binomial coefficient C(n, k).
+///
+/// `n` is limited by `MAX_FACTORIAL_N`; a `k` above `n` gives 0. Responses
+/// are cacheable.
+pub async fn choose(req: Request) -> Result<Response<Body>, Error> {
+    guarded("choose", &[Method::GET], req, handle_choose).await
+}
+
+async fn handle_factorial(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve_factorial(&req)?)));
+    let (format, n) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("factorial", &format!("n={}", n), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, factorial_body(n))?
+    };
+    caching::apply(response, &etag)
+}
+
+async fn handle_choose(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve_choose(&req)?)));
+    let (format, (n, k)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("choose", &format!("n={}&k={}", n, k), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, choose_body(n, k))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve_factorial(req: &Request) -> Result<u64, ApiError> {
+    let range = 0..=i128::from(Config::get().max_factorial_n);
+    let n = bounded_index(req.uri().path(), req.uri().query().unwrap_or(""), range)?;
+    usage::charge(Work::index(n));
+    Ok(n.unsigned_abs())
+}
+
+fn resolve_choose(req: &Request) -> Result<(u64, u64), ApiError> {
+    #[derive(Deserialize)]
+    struct Choice {
+        n: u64,
+        k: u64,
+    }
+
+    let Choice { n, k } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("n", 0..=i128::from(Config::get().max_factorial_n))
+        .parse()?;
+    telemetry::record_n(n);
+    usage::charge(Work::index(n));
+    Ok((n, k))
+}
+
+fn factorial_body(n: u64) -> Payload {
+    let value = combinatorics::factorial(n).to_string();
+
+    let body = json!({
+        "n": n,
+        "factorial": value,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), value)],
+        label: "factorial",
+        meta: Meta::new("binary-splitting"),
+    }
+}
+
+fn choose_body(n: u64, k: u64) -> Payload {
+    let value = combinatorics::choose(n, k).to_string();
+
+    let body = json!({
+        "n": n,
+        "k": k,
+        "binomial": value,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), format!("{},{}", k, value))],
+        label: "k,binomial",
+        meta: Meta::new("binary-splitting"),
+    }
+}
@@ -0,0 +1,135 @@
+//! The continued fraction endpoint.
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::negotiation::Format;
+use crate::numtheory::{self, Expansion, Real};
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// Terms returned when `terms` is not given.
+const DEFAULT_TERMS: u64 = 20;
+
+/// `GET /api/continued-fraction?x=sqrt(2)&terms=50`: the continued fraction
+/// of x and its convergents.
+///
+/// `x` is an integer, a fraction `p/q` or a square root `sqrt(n)` or
+/// `sqrt(p/q)`, each part at most `MAX_CF_DIGITS` digits. Up to `terms`
+/// terms are returned (20 by default, at most `MAX_CF_TERMS`); a rational's
+/// expansion may end sooner. Responses are cacheable.
+pub async fn continued_fraction(req: Request) -> Result<Response<Body>, Error> {
+    guarded("continued-fraction", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, (x, terms)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("continued-fraction", &format!("x={}&terms={}", x, terms), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        let expansion = numtheory::continued_fraction(&x, terms as usize);
+        response::render(StatusCode::OK, format, expansion_body(&x, expansion))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<(Real, u64), ApiError> {
+    #[derive(Deserialize)]
+    struct Expand {
+        x: String,
+        terms: Option<u64>,
+    }
+
+    let config = Config::get();
+    let Expand { x: text, terms } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("terms", 0..=i128::from(config.max_cf_terms))
+        .parse()?;
+    let x = Real::parse(&text).ok_or_else(|| ApiError::InvalidParameter {
+        name: "x",
+        received: text.clone(),
+        expected: "an integer, a fraction p/q, or sqrt(n) or sqrt(p/q) of a non-negative one".into(),
+    })?;
+    let digits = x.digits();
+    if digits > config.max_cf_digits {
+        return Err(ApiError::OutOfRange {
+            name: "x",
+            received: format!("{} digits", digits),
+            max: config.max_cf_digits,
+        });
+    }
+    let terms = terms.unwrap_or(DEFAULT_TERMS.min(config.max_cf_terms));
+    tracing::debug!(%x, terms, "continued fraction requested");
+    Ok((x, terms))
+}
+
+fn expansion_body(x: &Real, expansion: Expansion) -> Payload {
+    let Expansion {
+        terms,
+        convergents,
+        complete,
+        period,
+    } = expansion;
+    let terms: Vec<_> = terms.iter().map(ToString::to_string).collect();
+    let convergents: Vec<_> = convergents.iter().map(|(h, k)| format!("{}/{}", h, k)).collect();
+
+    // [a0; a1, a2, ...]
+    let mut notation = String::from("[");
+    if let Some((first, rest)) = terms.split_first() {
+        notation.push_str(first);
+        if !rest.is_empty() {
+            notation.push_str("; ");
+            notation.push_str(&rest.join(", "));
+        }
+    }
+    if !complete {
+        notation.push_str(", ...");
+    }
+    notation.push(']');
+
+    let mut body = json!({
+        "x": x.to_string(),
+        "terms": terms,
+        "convergents": convergents,
+        "notation": notation,
+        "complete": complete,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+    if let Some((start, length)) = period {
+        body["period"] = json!({ "start": start, "length": length });
+    }
+
+    Payload {
+        json: body,
+        rows: terms
+            .iter()
+            .zip(&convergents)
+            .enumerate()
+            .map(|(index, (term, convergent))| (index.to_string(), format!("{},{}", term, convergent)))
+            .collect(),
+        label: "term,convergent",
+        meta: Meta::new(match x {
+            Real::Rational(..) => "euclidean",
+            Real::Sqrt(..) => "quadratic-surd",
+        }),
+    }
+}
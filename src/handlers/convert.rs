@@ -0,0 +1,142 @@
+//! The base conversion endpoint.
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::radix;
+use crate::response::{self, Meta, Payload};
+use http::Method;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/convert?value=1a6d&from=16&to=2`: rewrites an integer of any
+/// size from one base to another.
+///
+/// Bases run from 2 to 62 with the alphabets of `?base=` (see
+/// [`crate::radix`]); `from` defaults to 10. `value` may start with `-` and
+/// has at most `MAX_CONVERT_DIGITS` digits, each valid in `from`. Responses
+/// are cacheable.
+pub async fn convert(req: Request) -> Result<Response<Body>, Error> {
+    guarded("convert", &[Method::GET], req, handle).await
+}
+
+struct Conversion {
+    negative: bool,
+    value: BigUint,
+    from: u32,
+    to: u32,
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, conversion) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    // Keyed by the value itself rather than its spelling, so `FF` and `ff` share an ETag
+    let key = format!(
+        "value={}{:x}&from={}&to={}",
+        if conversion.negative { "-" } else { "" },
+        conversion.value,
+        conversion.from,
+        conversion.to
+    );
+    let etag = caching::etag("convert", &key, format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, convert_body(&conversion))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<Conversion, ApiError> {
+    #[derive(Deserialize)]
+    struct Received {
+        value: String,
+        from: Option<u32>,
+        to: u32,
+    }
+
+    let Received { value: text, from, to } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("from", radix::BASES)
+        .bound("to", radix::BASES)
+        .parse()?;
+    let from = from.unwrap_or(10);
+
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let max = Config::get().max_convert_digits;
+    let length = digits.chars().count() as u64;
+    if length > max {
+        return Err(ApiError::OutOfRange {
+            name: "value",
+            received: format!("{} digits", length),
+            max,
+        });
+    }
+    if digits.is_empty() {
+        return Err(ApiError::InvalidParameter {
+            name: "value",
+            received: text.to_string(),
+            expected: "written only with digits of base `from`".into(),
+        });
+    }
+    // The offending digit is what the client needs to see
+    let value = radix::decode(digits, from).map_err(|invalid| {
+        tracing::debug!(position = invalid.position + usize::from(negative), from, "digit outside the base");
+        ApiError::InvalidParameter {
+            name: "value",
+            received: invalid.digit.to_string(),
+            expected: "written only with digits of base `from`".into(),
+        }
+    })?;
+    tracing::debug!(from, to, bits = value.bits(), "conversion requested");
+    Ok(Conversion {
+        negative: negative && value.bits() > 0,
+        value,
+        from,
+        to,
+    })
+}
+
+fn convert_body(conversion: &Conversion) -> Payload {
+    let sign = if conversion.negative { "-" } else { "" };
+    let digits = radix::encode(&conversion.value, conversion.to);
+    let result = format!("{}{}", sign, digits);
+    let value = format!("{}{}", sign, radix::encode(&conversion.value, conversion.from));
+
+    let body = json!({
+        "value": value,
+        "from": conversion.from,
+        "to": conversion.to,
+        "result": result,
+        "digits": digits.len(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(value, result)],
+        label: "result",
+        meta: Meta::new("radix").with_digits(digits.len() as u64),
+    }
+}
@@ -0,0 +1,76 @@
+//! The digit-count endpoint, answered without computing F(n).
+
+use super::{bounded_index, guarded};
+use crate::caching;
+use crate::error::ApiError;
+use crate::fib;
+use crate::negotiation::Format;
+use crate::response::{self, Meta, Payload};
+use crate::usage::{self, Work};
+use http::Method;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// Largest index whose value is computed exactly rather than estimated.
+const EXACT_UP_TO: u64 = 93;
+
+/// `GET /api/digits/[n]`: the number of decimal digits of F(n) and an
+/// order-of-magnitude estimate, from Binet's formula.
+///
+/// Nothing is computed beyond a logarithm, so `n` is not subject to
+/// `MAX_FIB_N` and clients can use this to decide whether a full request is
+/// worth making. Negative indices report the digits of |F(n)|. Responses are
+/// cacheable.
+pub async fn digits(req: Request) -> Result<Response<Body>, Error> {
+    guarded("digits", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, n) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("digits", &format!("n={}", n), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    caching::apply(response::render(StatusCode::OK, format, digits_body(n))?, &etag)
+}
+
+fn resolve(req: &Request) -> Result<i64, ApiError> {
+    // Any index is cheap here, but still has to fit the index type
+    let range = i128::from(i64::MIN)..=i128::from(i64::MAX);
+    let n = bounded_index(req.uri().path(), req.uri().query().unwrap_or(""), range)?;
+    usage::charge(Work::Flat);
+    Ok(n)
+}
+
+fn digits_body(n: i64) -> Payload {
+    let (mantissa, exponent) = fib::magnitude(n.unsigned_abs());
+    let digits = exponent + 1;
+
+    let body = json!({
+        "n": n,
+        "digits": digits,
+        "magnitude": format!("{:.6}e{}", mantissa, exponent),
+        "mantissa": mantissa,
+        "exponent": exponent,
+        "approximate": n.unsigned_abs() > EXACT_UP_TO,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), digits.to_string())],
+        label: "digits",
+        meta: Meta::new(if n.unsigned_abs() <= EXACT_UP_TO { "iterative" } else { "binet" }).with_digits(digits),
+    }
+}
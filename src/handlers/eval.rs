@@ -0,0 +1,118 @@
+//! The expression evaluator endpoint.
+
+use super::guarded;
+use crate::budget::Deadline;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::expr::{self, ExprError, Limits};
+use crate::negotiation::Format;
+use crate::response::{self, Meta, Payload};
+use crate::routing::{json_str, parse_json_object};
+use http::Method;
+use num_bigint::BigInt;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `POST /api/eval`: evaluates `{"expression": "fib(100) * 3 + factorial(20)"}`
+/// over arbitrary-size integers.
+///
+/// The grammar and functions are described in [`crate::expr`]. The text is
+/// limited to `MAX_EVAL_LENGTH` characters nested at most `MAX_EVAL_DEPTH`
+/// deep, every value to `MAX_EVAL_DIGITS` digits, and `fib`, `factorial`
+/// and `choose` to the limits of their own endpoints. Evaluation shares the
+/// `COMPUTE_BUDGET_MS` budget.
+pub async fn eval(req: Request) -> Result<Response<Body>, Error> {
+    guarded("eval", &[Method::POST], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, read_body(&req)?)));
+    let (format, expression) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "expression rejected");
+            return err.into_response();
+        }
+    };
+
+    let config = Config::get();
+    let limits = Limits {
+        max_depth: config.max_eval_depth,
+        max_digits: config.max_eval_digits,
+        max_fib_n: config.max_n,
+        max_factorial_n: config.max_factorial_n,
+    };
+    let deadline = Deadline::for_request();
+    let task = {
+        let expression = expression.clone();
+        tokio::task::spawn_blocking(move || expr::evaluate(&expression, &limits, &deadline))
+    };
+    let value = match task.await? {
+        Ok(value) => value,
+        Err(ExprError::Interrupted { operations }) => {
+            let progress = format!("{} operations", operations);
+            let err = deadline.abandoned("the expression", progress, Vec::new());
+            tracing::warn!(error = %err, "evaluation abandoned");
+            return err.into_response();
+        }
+        Err(err) => {
+            tracing::info!(error = %err, "expression has no value");
+            return into_api_error(err).into_response();
+        }
+    };
+    response::render(StatusCode::OK, format, eval_body(&expression, value))
+}
+
+fn read_body(req: &Request) -> Result<String, ApiError> {
+    if req.body().is_empty() {
+        return Err(ApiError::MalformedBody {
+            reason: "expected a JSON object such as {\"expression\": \"fib(100) * 3\"}".to_string(),
+        });
+    }
+    let body = parse_json_object(req.body())?;
+    let expression = json_str(&body, "expression")?.ok_or(ApiError::MissingParameter { name: "expression" })?;
+    let length = expression.chars().count() as u64;
+    let max = Config::get().max_eval_length;
+    if length > max {
+        return Err(ApiError::OutOfRange {
+            name: "expression",
+            received: format!("{} characters", length),
+            max,
+        });
+    }
+    tracing::debug!(expression, "evaluation requested");
+    Ok(expression.to_string())
+}
+
+fn into_api_error(err: ExprError) -> ApiError {
+    match err {
+        ExprError::Syntax { position, message } | ExprError::Undefined { position, message } => {
+            ApiError::InvalidExpression {
+                position,
+                reason: message,
+            }
+        }
+        ExprError::TooLarge { name, received, max } => ApiError::OutOfRange { name, received, max },
+        ExprError::Interrupted { .. } => unreachable!("handled with the deadline"),
+    }
+}
+
+fn eval_body(expression: &str, value: BigInt) -> Payload {
+    let result = value.to_string();
+    let digits = value.magnitude().to_string().len() as u64;
+
+    let body = json!({
+        "expression": expression,
+        "result": result,
+        "digits": digits,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![("1".to_string(), result)],
+        label: "result",
+        meta: Meta::new("recursive-descent").with_digits(digits),
+    }
+}
@@ -0,0 +1,131 @@
+//! The digit statistics endpoint.
+
+use super::{bounded_index, guarded};
+use crate::budget::{self, Deadline};
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib::{self, DigitStats};
+use crate::memo;
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use crate::table;
+use crate::usage::{self, Work};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/fib-digits/[n]`: the decimal digits of F(n), or with
+/// `?stats=true` only their frequency histogram, digit sum and digital root.
+///
+/// The statistics are gathered in one pass over the decimal representation,
+/// so a client interested in them does not have to download every digit.
+/// Indices up to `MAX_FIB_N` are accepted; negative ones describe the digits
+/// of |F(n)|. Responses are cacheable.
+pub async fn fib_digits(req: Request) -> Result<Response<Body>, Error> {
+    guarded("fib-digits", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, (n, stats)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("fib-digits", &format!("n={}&stats={}", n, stats), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let index = n.unsigned_abs();
+    let deadline = Deadline::for_request();
+    let (decimal, cache_status) = match table::get(index) {
+        Some(decimal) => (decimal.to_string(), None),
+        None => {
+            let (value, cache_status) = match memo::fibonacci_persisted(index, deadline).await? {
+                Ok(found) => found,
+                Err(interrupted) => {
+                    let err = deadline.exceeded(index, interrupted);
+                    tracing::warn!(error = %err, "computation abandoned");
+                    return err.into_response();
+                }
+            };
+            // Converting a large value to text can take longer than computing it
+            let Some(decimal) = budget::run_blocking(deadline, move || value.to_string()).await? else {
+                let err = deadline.exceeded(index, "computed, but not yet converted to text");
+                tracing::warn!(error = %err, "conversion abandoned");
+                return err.into_response();
+            };
+            (decimal, Some(cache_status))
+        }
+    };
+
+    let mut payload = if stats {
+        stats_body(n, &fib::digit_stats(&decimal))
+    } else {
+        digits_body(n, decimal)
+    };
+    if let Some(cache_status) = cache_status {
+        payload.meta = payload.meta.with_cache(cache_status.as_str());
+    }
+    caching::apply(response::render(StatusCode::OK, format, payload)?, &etag)
+}
+
+fn resolve(req: &Request) -> Result<(i64, bool), ApiError> {
+    #[derive(Deserialize)]
+    struct Options {
+        #[serde(default)]
+        stats: bool,
+    }
+
+    let query = req.uri().query().unwrap_or("");
+    let max = i128::from(Config::get().max_n);
+    let n = bounded_index(req.uri().path(), query, -max..=max)?;
+    usage::charge(Work::index(n));
+    let Options { stats } = Params::from_query(query).parse()?;
+    Ok((n, stats))
+}
+
+fn stats_body(n: i64, stats: &DigitStats) -> Payload {
+    let body = json!({
+        "n": n,
+        "digits": stats.digits,
+        "histogram": stats.histogram,
+        "digit_sum": stats.digit_sum,
+        "digital_root": stats.digital_root,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: (0..).zip(stats.histogram).map(|(digit, count)| (digit.to_string(), count.to_string())).collect(),
+        label: "count",
+        meta: Meta::new("fast-doubling").with_digits(stats.digits),
+    }
+}
+
+fn digits_body(n: i64, decimal: String) -> Payload {
+    let digits = decimal.len() as u64;
+    let body = json!({
+        "n": n,
+        "digits": digits,
+        "value": decimal,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), decimal)],
+        label: "value",
+        meta: Meta::new("fast-doubling").with_digits(digits),
+    }
+}
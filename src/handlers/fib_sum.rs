@@ -0,0 +1,86 @@
+//! The Fibonacci sums endpoint.
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib::{self, Sums};
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use crate::telemetry;
+use crate::usage::{self, Work};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/fib-sum?n=10`: the sum, the sum of squares and the alternating
+/// sum of F(1), ..., F(n).
+///
+/// Each comes from a closed form over F(n) and F(n+1), so the cost is that
+/// of one fast-doubling computation whatever `n` is. `n` is limited by
+/// `MAX_FIB_N`. Responses are cacheable.
+pub async fn fib_sum(req: Request) -> Result<Response<Body>, Error> {
+    guarded("fib-sum", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, n) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("fib-sum", &format!("n={}", n), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, sums_body(n, fib::sums(n)))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<u64, ApiError> {
+    #[derive(Deserialize)]
+    struct Count {
+        n: u64,
+    }
+
+    let Count { n } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("n", 0..=i128::from(Config::get().max_n))
+        .parse()?;
+    telemetry::record_n(n);
+    usage::charge(Work::index(n));
+    Ok(n)
+}
+
+fn sums_body(n: u64, sums: Sums) -> Payload {
+    let sum = sums.sum.to_string();
+    let sum_of_squares = sums.sum_of_squares.to_string();
+    let alternating_sum = sums.alternating_sum.to_string();
+
+    let body = json!({
+        "n": n,
+        "sum": sum,
+        "sum_of_squares": sum_of_squares,
+        "alternating_sum": alternating_sum,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), format!("{},{},{}", sum, sum_of_squares, alternating_sum))],
+        label: "sum,sum_of_squares,alternating_sum",
+        meta: Meta::new("fast-doubling").with_digits(sum.len() as u64),
+    }
+}
@@ -0,0 +1,94 @@
+//! The Fibonacci word endpoint.
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib;
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use crate::telemetry;
+use crate::usage::{self, Work};
+use http::Method;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/fib-word?n=5`: the Fibonacci word S(n), built like F(n) but by
+/// concatenating strings: S(0) = "0", S(1) = "01", S(n) = S(n−1)S(n−2).
+///
+/// S(n) has F(n+2) characters, so the word is cut to `max_len` characters
+/// (at most and by default `MAX_FIB_WORD_LEN`); `length` is always the full
+/// length. `n` is limited by `MAX_FIB_N`. Responses are cacheable.
+pub async fn fib_word(req: Request) -> Result<Response<Body>, Error> {
+    guarded("fib-word", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, (n, max_len)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("fib-word", &format!("n={}&max_len={}", n, max_len), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, word_body(n, max_len))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<(u64, u64), ApiError> {
+    #[derive(Deserialize)]
+    struct Word {
+        n: u64,
+        max_len: Option<u64>,
+    }
+
+    let config = Config::get();
+    let Word { n, max_len } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("n", 0..=i128::from(config.max_n))
+        .bound("max_len", 0..=i128::from(config.max_fib_word_len))
+        .parse()?;
+    telemetry::record_n(n);
+    usage::charge(Work::Flat);
+    Ok((n, max_len.unwrap_or(config.max_fib_word_len)))
+}
+
+fn word_body(n: u64, max_len: u64) -> Payload {
+    let word = fib::word_prefix(n, max_len as usize);
+    // F(n+1) zeros and F(n) ones, F(n+2) in all
+    let (zeros, length) = fib::fibonacci_pair(n + 1);
+    let ones = &length - &zeros;
+    let truncated = length > BigUint::from(word.len());
+
+    let body = json!({
+        "n": n,
+        "word": word,
+        "length": length.to_string(),
+        "zeros": zeros.to_string(),
+        "ones": ones.to_string(),
+        "truncated": truncated,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), word)],
+        label: "word",
+        meta: Meta::new("concatenation"),
+    }
+}
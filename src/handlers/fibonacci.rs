@@ -0,0 +1,581 @@
+//! The single-value Fibonacci endpoint.
+
+use super::guarded;
+use crate::budget::{self, Deadline};
+use crate::fib::{self, Algorithm, BinetEstimate};
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::memo::{self, CacheStatus};
+use crate::negotiation::Format;
+use crate::profile::profile_fibonacci;
+use crate::query::Query;
+use crate::radix;
+use crate::response::{self, Meta, Payload};
+use crate::params::Params;
+use crate::table;
+use crate::telemetry;
+use crate::usage::{self, Work};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use num_bigint::BigUint;
+use std::time::Instant;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// Index used when the request does not name one (outside strict mode).
+pub const DEFAULT_N: i64 = 10;
+
+/// `GET /api/[n]` and `POST /api`: computes a single Fibonacci number.
+///
+/// A POST request with a non-empty body reads its parameters from a JSON
+/// object such as `{"n": 250, "strict": true}`; otherwise they come from the
+/// path and query string. By default a missing index falls back to
+/// [`DEFAULT_N`]; with `?strict=true` it is reported as an error instead.
+/// Unparseable input and indices above the configured limit are always errors.
+///
+/// With `?last_digits=k` only F(n) mod 10^k is computed, which makes indices
+/// far beyond `MAX_FIB_N` affordable; the response then carries `truncated`
+/// and the `modulus` used.
+///
+/// `?base=16` (2 to 62) writes the value in another radix and adds `base` to
+/// the response. It cannot be combined with `last_digits`.
+///
+/// `?algo=iterative|fast-doubling|matrix|binet` runs that implementation
+/// instead of the cached default and reports it as `algorithm`, along with
+/// `elapsed_ns`. `binet` is a floating-point approximation: exact up to n = 70,
+/// beyond that it returns scientific notation with `approximate: true` and a
+/// `relative_error` estimate, and it is not bound by `MAX_FIB_N` but by
+/// [`fib::BINET_MAX_N`], where that estimate reaches 1.
+///
+/// A computation still running `COMPUTE_BUDGET_MS` after the request arrived
+/// is abandoned with a `504` that says how far it got; see [`budget`].
+///
+/// Several indices, as `?n=5,10,200` or `?n=5&n=10`, answer with a `results`
+/// map from each index to its value instead; only `base` combines with them.
+///
+/// Negative indices follow the negafibonacci identity F(−n) = (−1)^(n+1)·F(n),
+/// and the response's `sign` field says whether the value is negative.
+///
+/// GET responses are cacheable and answer a matching `If-None-Match` with
+/// `304 Not Modified` before anything is computed. HEAD requests get the same
+/// headers without computing the value at all.
+pub async fn fibonacci(req: Request) -> Result<Response<Body>, Error> {
+    guarded("fibonacci", &[Method::GET, Method::POST], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| match resolve_many(&req)? {
+        Some(many) => Ok((format, Err(many))),
+        None => Ok((format, Ok(resolve(&req)?))),
+    });
+    let (format, params) = match parsed {
+        Ok((format, Ok(params))) => (format, params),
+        Ok((format, Err(many))) => return handle_many(&req, format, many).await,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    // Profiling timings differ on every run, so those responses are never cached
+    let cacheable = matches!(*req.method(), Method::GET | Method::HEAD);
+    let etag = (cacheable && params.profile_runs.is_none())
+        .then(|| {
+            let key = format!(
+                "n={}&debug={}&last_digits={:?}&base={:?}&algo={:?}",
+                params.n,
+                params.debug,
+                params.last_digits,
+                params.base,
+                params.algorithm.map(Algorithm::name)
+            );
+            caching::etag("fibonacci", &key, format)
+        });
+
+    if let Some(etag) = &etag {
+        if caching::is_fresh(&req, etag) {
+            tracing::debug!(%etag, "etag still fresh, skipping computation");
+            return caching::not_modified(etag);
+        }
+    }
+
+    let response = if req.method() == Method::HEAD {
+        // Only the headers are sent, so skip computing the value
+        response::head(StatusCode::OK, format)?
+    } else {
+        let computed = match compute(&params, Deadline::for_request()).await? {
+            Ok(computed) => computed,
+            Err(err) => {
+                tracing::warn!(error = %err, "computation abandoned");
+                return err.into_response();
+            }
+        };
+        response::render(StatusCode::OK, format, fibonacci_body(&req, &params, computed))?
+    };
+    match etag {
+        Some(etag) => caching::apply(response, &etag),
+        None => Ok(response),
+    }
+}
+
+// Several indices in one GET: F(n) for each, keyed by index
+async fn handle_many(req: &Request, format: Format, many: ManyParams) -> Result<Response<Body>, Error> {
+    let ManyParams { indices, base } = many;
+    let cacheable = matches!(*req.method(), Method::GET | Method::HEAD);
+    let etag = cacheable.then(|| {
+        let key = format!("n={:?}&base={:?}", indices, base);
+        caching::etag("fibonacci", &key, format)
+    });
+    if let Some(etag) = &etag {
+        if caching::is_fresh(req, etag) {
+            return caching::not_modified(etag);
+        }
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        let deadline = Deadline::for_request();
+        let tasks: Vec<_> = indices
+            .iter()
+            .map(|&n| tokio::spawn(memo::fibonacci_persisted(n.unsigned_abs(), deadline)))
+            .collect();
+        let mut rows = Vec::with_capacity(tasks.len());
+        let mut statuses = Vec::with_capacity(tasks.len());
+        for (&n, task) in indices.iter().zip(tasks) {
+            let (value, cache_status) = match task.await?? {
+                Ok(found) => found,
+                Err(interrupted) => {
+                    let progress = format!("{} of {} values, then {}", rows.len(), indices.len(), interrupted);
+                    let err = deadline.exceeded(n.unsigned_abs(), progress);
+                    tracing::warn!(error = %err, "computation abandoned");
+                    return err.into_response();
+                }
+            };
+            let digits = encode(&value, base);
+            let value = if fib::negafibonacci_is_negative(n) {
+                format!("-{}", digits)
+            } else {
+                digits
+            };
+            rows.push((n.to_string(), value));
+            statuses.push(cache_status);
+        }
+        // A repeated index appears once in the map but keeps its rows
+        let results: serde_json::Map<_, _> = rows
+            .iter()
+            .map(|(n, value)| (n.clone(), json!(value)))
+            .collect();
+        let mut response_body = json!({
+            "results": results,
+            "count": results.len(),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "status": "success",
+        });
+        if let Some(base) = base {
+            response_body["base"] = json!(base);
+        }
+        let payload = Payload {
+            json: response_body,
+            rows,
+            label: "fibonacci",
+            meta: Meta::new("fast-doubling").with_cache(CacheStatus::summarize(&statuses)),
+        };
+        response::render(StatusCode::OK, format, payload)?
+    };
+    match etag {
+        Some(etag) => caching::apply(response, &etag),
+        None => Ok(response),
+    }
+}
+
+/// Validated parameters of a request for several indices.
+struct ManyParams {
+    indices: Vec<i64>,
+    base: Option<u32>,
+}
+
+// `None` unless the query string names more than one index
+fn resolve_many(req: &Request) -> Result<Option<ManyParams>, ApiError> {
+    if req.method() == Method::POST && !req.body().is_empty() {
+        return Ok(None);
+    }
+    let query = req.uri().query().unwrap_or("");
+    // A single index keeps its usual parsing and errors
+    if Query::parse(query).get_list("n").nth(1).is_none() {
+        return Ok(None);
+    }
+    #[derive(Deserialize)]
+    struct Many {
+        n: Vec<i64>,
+        base: Option<u32>,
+        last_digits: Option<String>,
+        algo: Option<String>,
+        profile_runs: Option<String>,
+    }
+    let config = Config::get();
+    let max = i128::from(config.max_n);
+    let Many {
+        n: indices,
+        base,
+        last_digits,
+        algo,
+        profile_runs,
+    } = Params::from_query(query)
+        .bound("n", -max..=max)
+        .bound("base", radix::BASES)
+        .parse()?;
+    let unsupported = [("last_digits", last_digits), ("algo", algo), ("profile_runs", profile_runs)];
+    if let Some((name, Some(value))) = unsupported.into_iter().find(|(_, value)| value.is_some()) {
+        return Err(ApiError::InvalidParameter {
+            name,
+            received: value,
+            expected: "omitted when n lists several indices".into(),
+        });
+    }
+    if indices.len() as u64 > config.max_batch_size {
+        return Err(ApiError::OutOfRange {
+            name: "n",
+            received: format!("{} indices", indices.len()),
+            max: config.max_batch_size,
+        });
+    }
+    tracing::debug!(?indices, "several indices requested");
+    usage::charge(Work::indices(indices.iter().copied()));
+    Ok(Some(ManyParams { indices, base }))
+}
+
+/// Validated parameters of a Fibonacci request, wherever they were read from.
+struct FibonacciParams {
+    n: i64,
+    strict: bool,
+    profile_runs: Option<u64>,
+    last_digits: Option<u64>,
+    base: Option<u32>,
+    algorithm: Option<Algorithm>,
+    debug: bool,
+    extraction_method: &'static str,
+}
+
+// The parameters that decide how `n` is limited
+#[derive(Deserialize)]
+struct Mode {
+    last_digits: Option<u64>,
+    algo: Option<String>,
+}
+
+// The parameters as sent, before they are checked against each other
+#[derive(Deserialize)]
+struct Received {
+    n: Option<i64>,
+    #[serde(default)]
+    strict: bool,
+    profile_runs: Option<u64>,
+    base: Option<u32>,
+    #[serde(default)]
+    debug: bool,
+}
+
+fn resolve(req: &Request) -> Result<FibonacciParams, ApiError> {
+    // Parse the request to get the Fibonacci number
+    let path = req.uri().path();
+    let query = req.uri().query().unwrap_or("");
+
+    let params = Params::from_query(query).with_path("n", path);
+    let (params, extraction_method) = if req.method() == Method::POST && !req.body().is_empty() {
+        // Body members take precedence over the query string and path
+        (Params::from_body(req.body())?.merge(params), "json_body")
+    } else {
+        (params, "path_analysis")
+    };
+    let config = Config::get();
+    let params = params.bound("last_digits", 1..=i128::from(config.max_last_digits));
+    let Mode { last_digits, algo } = params.parse()?;
+    let algo = algo.as_deref().map(parse_algorithm).transpose()?;
+    // Truncated values and approximations are cheap at any index, so only
+    // full ones are limited
+    let params = if last_digits.is_some() {
+        params
+    } else if algo == Some(Algorithm::Binet) {
+        // Beyond this the estimate has no correct digit left
+        let max = i128::from(fib::BINET_MAX_N);
+        params.bound("n", -max..=max)
+    } else {
+        let max = i128::from(config.max_n);
+        params.bound("n", -max..=max)
+    };
+    let Received {
+        n,
+        strict,
+        profile_runs,
+        base,
+        debug,
+    } = params
+        .bound("base", radix::BASES)
+        .bound("profile_runs", 0..=i128::from(config.max_profile_runs))
+        .parse()?;
+    tracing::debug!(?n, extraction_method, "parsed parameters");
+
+    let n = match n {
+        Some(n) => n,
+        None if strict => return Err(ApiError::MissingParameter { name: "n" }),
+        None => {
+            tracing::debug!(default = DEFAULT_N, "no number found, using default");
+            DEFAULT_N
+        }
+    };
+
+    telemetry::record_n(n);
+    // Residues and estimates cost about the same at any index
+    let cheap = last_digits.is_some() || algo == Some(Algorithm::Binet);
+    usage::charge(if cheap { Work::Flat } else { Work::index(n) });
+
+    if let (Some(_), Some(base)) = (last_digits, base) {
+        return Err(ApiError::InvalidParameter {
+            name: "base",
+            received: base.to_string(),
+            expected: "omitted when last_digits is set".into(),
+        });
+    }
+    if let Some(algorithm) = algo.filter(|_| last_digits.is_some()) {
+        return Err(ApiError::InvalidParameter {
+            name: "algo",
+            received: algorithm.name().to_string(),
+            expected: "omitted when last_digits is set".into(),
+        });
+    }
+    if let (Some(Algorithm::Binet), Some(base)) = (algo, base) {
+        return Err(ApiError::InvalidParameter {
+            name: "base",
+            received: base.to_string(),
+            expected: "omitted when algo is binet".into(),
+        });
+    }
+    if profile_runs.is_some() {
+        check_magnitude(n, config.max_profile_n)?;
+    }
+    let profile_runs = profile_runs.map(|runs| runs.max(1));
+
+    Ok(FibonacciParams {
+        n,
+        strict,
+        profile_runs,
+        last_digits,
+        base,
+        algorithm: algo,
+        debug,
+        extraction_method,
+    })
+}
+
+fn parse_algorithm(name: &str) -> Result<Algorithm, ApiError> {
+    Algorithm::from_name(name).ok_or_else(|| ApiError::InvalidParameter {
+        name: "algo",
+        received: name.to_string(),
+        expected: "one of iterative, fast-doubling, matrix or binet".into(),
+    })
+}
+
+// Like `check_limit`, for a signed index whose magnitude is limited
+fn check_magnitude(n: i64, max: u64) -> Result<(), ApiError> {
+    if n.unsigned_abs() <= max {
+        Ok(())
+    } else {
+        let received = if n < 0 {
+            format!("{} (magnitude {})", n, n.unsigned_abs())
+        } else {
+            n.to_string()
+        };
+        Err(ApiError::OutOfRange {
+            name: "n",
+            received,
+            max,
+        })
+    }
+}
+
+/// F(|n|) as text in the requested base, and how it was obtained.
+struct Computed {
+    digits: String,
+    cache_status: Option<CacheStatus>,
+    truncated: bool,
+    binet: Option<BinetEstimate>,
+    elapsed_ns: Option<u64>,
+    /// Read from the embedded table rather than computed.
+    from_table: bool,
+}
+
+// Produces the digits for `params`, giving up with a 504 error once
+// `deadline` passes. The cache holds F(|n|); negative indices only differ
+// in sign
+async fn compute(params: &FibonacciParams, deadline: Deadline) -> Result<Result<Computed, ApiError>, Error> {
+    let n = params.n.unsigned_abs();
+    let base = params.base;
+    // Small values in decimal are embedded in the binary, see `table`
+    if let (None, None, None, Some(digits)) = (params.last_digits, params.algorithm, base, table::get(n)) {
+        return Ok(Ok(Computed {
+            digits: digits.to_string(),
+            cache_status: None,
+            truncated: false,
+            binet: None,
+            elapsed_ns: None,
+            from_table: true,
+        }));
+    }
+    let computed = match (params.last_digits, params.algorithm) {
+        // Works on numbers of at most k digits, so it always fits the budget
+        (Some(k), _) => {
+            let (digits, truncated) = fib::last_digits(n, k as u32);
+            Computed {
+                digits,
+                cache_status: None,
+                truncated,
+                binet: None,
+                elapsed_ns: None,
+                from_table: false,
+            }
+        }
+        // An explicit algorithm is timed, so it always runs instead of using the cache
+        (None, Some(algorithm)) => {
+            let timed = budget::run_blocking(deadline, move || {
+                let start = Instant::now();
+                let (digits, binet) = match algorithm.compute(n) {
+                    Some(value) => (encode(&value, base), None),
+                    None => {
+                        let estimate = fib::binet(n);
+                        (estimate.value.clone(), Some(estimate))
+                    }
+                };
+                (digits, binet, start.elapsed().as_nanos() as u64)
+            });
+            let Some((digits, binet, elapsed_ns)) = timed.await? else {
+                return Ok(Err(deadline.exceeded(n, format!("{} was still running", algorithm.name()))));
+            };
+            Computed {
+                digits,
+                cache_status: None,
+                truncated: false,
+                binet,
+                elapsed_ns: Some(elapsed_ns),
+                from_table: false,
+            }
+        }
+        // Plain values go through both caches
+        (None, None) => {
+            let (value, cache_status) = match memo::fibonacci_persisted(n, deadline).await? {
+                Ok(found) => found,
+                Err(interrupted) => return Ok(Err(deadline.exceeded(n, interrupted))),
+            };
+            // Converting a large value to text can take longer than computing it
+            let digits = budget::run_blocking(deadline, move || encode(&value, base)).await?;
+            let Some(digits) = digits else {
+                return Ok(Err(deadline.exceeded(n, "computed, but not yet converted to text")));
+            };
+            Computed {
+                digits,
+                cache_status: Some(cache_status),
+                truncated: false,
+                binet: None,
+                elapsed_ns: None,
+                from_table: false,
+            }
+        }
+    };
+    Ok(Ok(computed))
+}
+
+fn encode(value: &BigUint, base: Option<u32>) -> String {
+    match base {
+        Some(base) => radix::encode(value, base),
+        None => value.to_string(),
+    }
+}
+
+fn fibonacci_body(req: &Request, params: &FibonacciParams, computed: Computed) -> Payload {
+    let n = params.n;
+    let negative = fib::negafibonacci_is_negative(n);
+    let Computed {
+        digits,
+        cache_status,
+        truncated,
+        binet,
+        elapsed_ns,
+        from_table,
+    } = computed;
+    let fibonacci_result = if negative {
+        format!("-{}", digits)
+    } else {
+        digits
+    };
+    let sign = if negative {
+        "negative"
+    } else if n == 0 {
+        "zero"
+    } else {
+        "positive"
+    };
+
+    let mut response_body = json!({
+        "fibonacci": fibonacci_result,
+        "n": n,
+        "sign": sign,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+        "usage": "To calculate Fibonacci of a different number, use: /api/20 (replace 20 with your desired number(integer))"
+    });
+    if let Some(cache_status) = cache_status {
+        response_body["cache"] = json!(cache_status.as_str());
+    }
+    if let Some(base) = params.base {
+        response_body["base"] = json!(base);
+    }
+    if let Some(algorithm) = params.algorithm {
+        response_body["algorithm"] = json!(algorithm.name());
+        response_body["elapsed_ns"] = json!(elapsed_ns);
+    }
+    if let Some(estimate) = &binet {
+        response_body["approximate"] = json!(!estimate.exact);
+        response_body["relative_error"] = json!(estimate.relative_error);
+    }
+    if let Some(k) = params.last_digits {
+        response_body["last_digits"] = json!(k);
+        response_body["modulus"] = json!(format!("1{}", "0".repeat(k as usize)));
+        response_body["truncated"] = json!(truncated);
+    }
+
+    // Request details for troubleshooting, only on ?debug=true
+    if params.debug {
+        response_body["debug"] = json!({
+            "path": req.uri().path(),
+            "query": req.uri().query().unwrap_or(""),
+            "full_uri": req.uri().to_string(),
+            "method": req.method().as_str(),
+            "extraction_method": params.extraction_method,
+            "strict": params.strict
+        });
+    }
+
+    // Optional profiling mode: ?profile_runs=k repeats the computation k times
+    if let Some(runs) = params.profile_runs {
+        let stats = profile_fibonacci(n.unsigned_abs(), runs as usize);
+        tracing::debug!(runs = stats.runs, n, "profiled computation");
+        response_body["profile"] = stats.to_json();
+    }
+
+    let algorithm = params.algorithm.unwrap_or(Algorithm::FastDoubling);
+    let mut meta = Meta::new(algorithm.name()).with_source(if from_table { "table" } else { "computed" });
+    if let Some(cache_status) = cache_status {
+        meta = meta.with_cache(cache_status.as_str());
+    }
+    // The scientific notation of an approximation says little about its size
+    if binet.as_ref().is_some_and(|estimate| !estimate.exact) {
+        meta = meta.with_digits(fib::digit_count(n.unsigned_abs()));
+    }
+    Payload {
+        json: response_body,
+        rows: vec![(n.to_string(), fibonacci_result)],
+        label: "fibonacci",
+        meta,
+    }
+}
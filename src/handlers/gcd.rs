@@ -0,0 +1,118 @@
+//! The gcd / lcm endpoint.
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::negotiation::Format;
+use crate::numtheory::{self, Bezout};
+use crate::params::{Integer, Params};
+use crate::response::{self, Meta, Payload};
+use http::Method;
+use num_bigint::BigInt;
+use num_traits::One;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/gcd?a=240&b=46`: the gcd and lcm of a and b, and Bézout
+/// coefficients x, y with a·x + b·y = gcd.
+///
+/// Values are signed decimal strings of at most `MAX_GCD_DIGITS` digits.
+/// More than two may be given, as `?a=12&b=18&b=30` or `?a=12,18,30`; at
+/// most `MAX_BATCH_SIZE` in all. Responses are cacheable.
+pub async fn gcd(req: Request) -> Result<Response<Body>, Error> {
+    guarded("gcd", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, values) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let key = values.iter().map(BigInt::to_string).collect::<Vec<_>>().join(",");
+    let etag = caching::etag("gcd", &key, format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        let result = numtheory::bezout(&values);
+        response::render(StatusCode::OK, format, gcd_body(&values, result))?
+    };
+    caching::apply(response, &etag)
+}
+
+// Every value of `a`, then every value of `b`
+fn resolve(req: &Request) -> Result<Vec<BigInt>, ApiError> {
+    #[derive(Deserialize)]
+    struct Operands {
+        a: Vec<Integer>,
+        #[serde(default)]
+        b: Vec<Integer>,
+    }
+
+    let Operands { a, b } = Params::from_query(req.uri().query().unwrap_or("")).parse()?;
+    let values: Vec<BigInt> = a.into_iter().chain(b).map(|Integer(value)| value).collect();
+    if values.len() < 2 {
+        return Err(ApiError::MissingParameter { name: "b" });
+    }
+
+    let config = Config::get();
+    if values.len() as u64 > config.max_batch_size {
+        return Err(ApiError::OutOfRange {
+            name: "b",
+            received: format!("{} values", values.len()),
+            max: config.max_batch_size,
+        });
+    }
+    if let Some(digits) = values
+        .iter()
+        .map(|value| value.magnitude().to_string().len() as u64)
+        .find(|&digits| digits > config.max_gcd_digits)
+    {
+        return Err(ApiError::OutOfRange {
+            name: "a",
+            received: format!("{} digits", digits),
+            max: config.max_gcd_digits,
+        });
+    }
+    tracing::debug!(count = values.len(), "gcd requested");
+    Ok(values)
+}
+
+fn gcd_body(values: &[BigInt], result: Bezout) -> Payload {
+    let Bezout {
+        gcd,
+        lcm,
+        coefficients,
+    } = result;
+    let coprime = gcd.is_one();
+    let (gcd, lcm) = (gcd.to_string(), lcm.to_string());
+
+    let body = json!({
+        "values": values.iter().map(BigInt::to_string).collect::<Vec<_>>(),
+        "gcd": gcd,
+        "lcm": lcm,
+        "bezout": coefficients.iter().map(BigInt::to_string).collect::<Vec<_>>(),
+        "coprime": coprime,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    let key = values.iter().map(BigInt::to_string).collect::<Vec<_>>().join(" ");
+    Payload {
+        json: body,
+        rows: vec![(key, format!("{},{}", gcd, lcm))],
+        label: "gcd,lcm",
+        meta: Meta::new("extended-euclid"),
+    }
+}
@@ -0,0 +1,103 @@
+//! The golden ratio endpoint.
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::golden::{self, Method as Algorithm};
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// Decimal places returned when the request does not ask for a precision.
+pub const DEFAULT_PRECISION: u32 = 50;
+
+/// `GET /api/golden-ratio?precision=200`: φ truncated to `precision` decimal
+/// places.
+///
+/// `?method=sqrt` (the default) takes an exact integer square root;
+/// `?method=ratio` uses F(n+1)/F(n) for the first n accurate enough and
+/// reports that n. Either way the response states its error bound.
+/// `precision` is limited by `MAX_GOLDEN_PRECISION`. Responses are cacheable.
+pub async fn golden_ratio(req: Request) -> Result<Response<Body>, Error> {
+    guarded("golden-ratio", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, (precision, algorithm)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let key = format!("precision={}&method={}", precision, algorithm.name());
+    let etag = caching::etag("golden-ratio", &key, format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        let approximation =
+            tokio::task::spawn_blocking(move || golden::approximate(precision, algorithm)).await?;
+        response::render(
+            StatusCode::OK,
+            format,
+            golden_body(precision, algorithm, approximation),
+        )?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<(u32, Algorithm), ApiError> {
+    #[derive(Deserialize)]
+    struct Expansion {
+        precision: Option<u32>,
+        method: Option<String>,
+    }
+
+    // A limit beyond u32::MAX is narrowed to it by the field's type
+    let Expansion { precision, method } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("precision", 0..=i128::from(Config::get().max_golden_precision))
+        .parse()?;
+    let precision = precision.unwrap_or(DEFAULT_PRECISION);
+    let algorithm = match method {
+        None => Algorithm::Sqrt,
+        Some(name) => Algorithm::from_name(&name).ok_or_else(|| ApiError::InvalidParameter {
+            name: "method",
+            received: name.to_string(),
+            expected: "sqrt or ratio".into(),
+        })?,
+    };
+    Ok((precision, algorithm))
+}
+
+fn golden_body(precision: u32, algorithm: Algorithm, approximation: golden::Approximation) -> Payload {
+    let mut body = json!({
+        "phi": approximation.value,
+        "precision": precision,
+        "method": algorithm.name(),
+        "error_bound": format!("{}e-{}", approximation.error_factor, precision),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+    if let Some(n) = approximation.n {
+        body["n"] = json!(n);
+    }
+
+    Payload {
+        json: body,
+        rows: vec![(precision.to_string(), approximation.value)],
+        label: "phi",
+        meta: Meta::new(algorithm.name()),
+    }
+}
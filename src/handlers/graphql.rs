@@ -0,0 +1,109 @@
+//! The GraphQL endpoint over the numeric API.
+
+use super::guarded;
+use crate::budget::{self, Deadline};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::graphql::{self, Limits};
+use crate::routing::{json_str, parse_json_object};
+use crate::response;
+use crate::usage::{self, Work};
+use http::Method;
+use serde_json::{Map, Value};
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `POST /api/graphql`: runs `{"query": "...", "variables": {...},
+/// "operationName": "..."}` against the schema in [`crate::graphql`], so one
+/// request can fetch `fibonacci`, `lucas`, `range` and `isPrime` values with
+/// just the fields it needs. `GET` answers the schema in SDL.
+///
+/// The answer is `{"data": ..., "errors": [...]}`: a field beyond a limit is
+/// `null` with an error naming it, and the other fields are still answered
+/// with `200`. A query that does not parse or does not fit the schema is
+/// rejected with `400` and no `data`. Queries are limited to
+/// `MAX_GRAPHQL_FIELDS` fields, each field to the limits of its own endpoint,
+/// and the whole query shares the `COMPUTE_BUDGET_MS` budget.
+pub async fn graphql(req: Request) -> Result<Response<Body>, Error> {
+    guarded("graphql", &[Method::GET, Method::POST], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    if req.method() == Method::GET {
+        return response::text(StatusCode::OK, "text/plain; charset=utf-8", graphql::sdl());
+    }
+    let query = match read_body(&req) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "query rejected");
+            return err.into_response();
+        }
+    };
+    let document = match graphql::parse(&query.text) {
+        Ok(document) => document,
+        Err(err) => {
+            tracing::info!(error = %err, "query does not parse");
+            let body = serde_json::json!({ "errors": [err.to_json()] });
+            return response::json(StatusCode::BAD_REQUEST, &body);
+        }
+    };
+
+    let config = Config::get();
+    let limits = Limits {
+        max_n: config.max_n,
+        max_range_size: config.max_range_size,
+        max_prime_digits: config.max_prime_digits,
+        max_fields: config.max_graphql_fields,
+    };
+    let deadline = Deadline::for_request();
+    let work = move || graphql::execute(&document, query.operation_name.as_deref(), &query.variables, &limits);
+    let Some(execution) = budget::run_blocking(deadline, work).await? else {
+        let err = deadline.abandoned("the query", "still running", Vec::new());
+        tracing::warn!(error = %err, "query abandoned");
+        return err.into_response();
+    };
+
+    usage::charge(Work::indices(execution.indices.iter().copied()));
+    if execution.data.is_none() {
+        tracing::info!(errors = execution.errors.len(), "query does not fit the schema");
+        return response::json(StatusCode::BAD_REQUEST, &execution.to_json());
+    }
+    if !execution.errors.is_empty() {
+        tracing::info!(errors = execution.errors.len(), "query fields failed");
+    }
+    response::json(StatusCode::OK, &execution.to_json())
+}
+
+// A GraphQL request: the document, its variables and the operation to run
+struct Query {
+    text: String,
+    variables: Map<String, Value>,
+    operation_name: Option<String>,
+}
+
+fn read_body(req: &Request) -> Result<Query, ApiError> {
+    if req.body().is_empty() {
+        return Err(ApiError::MalformedBody {
+            reason: "expected a JSON object such as {\"query\": \"{ fibonacci(n: 10) { value } }\"}".to_string(),
+        });
+    }
+    let body = parse_json_object(req.body())?;
+    let text = json_str(&body, "query")?.ok_or(ApiError::MissingParameter { name: "query" })?;
+    let variables = match body.get("variables") {
+        None | Some(Value::Null) => Map::new(),
+        Some(Value::Object(variables)) => variables.clone(),
+        Some(other) => {
+            return Err(ApiError::InvalidParameter {
+                name: "variables",
+                received: other.to_string(),
+                expected: "an object".into(),
+            })
+        }
+    };
+    let operation_name = json_str(&body, "operationName")?.map(String::from);
+    tracing::debug!(length = text.len(), "query requested");
+    Ok(Query {
+        text: text.to_string(),
+        variables,
+        operation_name,
+    })
+}
@@ -0,0 +1,77 @@
+//! The health and build-info endpoint.
+
+use super::observed;
+use crate::build_info;
+use crate::config::Config;
+use crate::response;
+use http::Method;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/health`: uptime, build metadata and configured limits.
+///
+/// Never requires an API key and is not rate limited, so uptime monitors can
+/// poll it freely.
+pub async fn health(req: Request) -> Result<Response<Body>, Error> {
+    observed("health", &[Method::GET], req, handle).await
+}
+
+async fn handle(_req: Request) -> Result<Response<Body>, Error> {
+    let config = Config::get();
+    let uptime = build_info::uptime();
+
+    let mut body = json!({
+        "status": "ok",
+        "version": build_info::VERSION,
+        "git_sha": build_info::GIT_SHA,
+        "built_at": build_info::built_at(),
+        "uptime_seconds": uptime.as_secs_f64(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "limits": {
+            "max_n": config.max_n,
+            "max_batch_size": config.max_batch_size,
+            "max_range_size": config.max_range_size,
+            "max_parallelism": config.max_parallelism,
+            "max_job_n": config.max_job_n,
+            "max_golden_precision": config.max_golden_precision,
+            "max_kfib_k": config.max_kfib_k,
+            "max_last_digits": config.max_last_digits,
+            "max_pisano_m": config.max_pisano_m,
+            "max_collatz_digits": config.max_collatz_digits,
+            "max_collatz_sequence": config.max_collatz_sequence,
+            "max_eval_length": config.max_eval_length,
+            "max_eval_depth": config.max_eval_depth,
+            "max_eval_digits": config.max_eval_digits,
+            "max_cf_terms": config.max_cf_terms,
+            "max_cf_digits": config.max_cf_digits,
+            "max_convert_digits": config.max_convert_digits,
+            "max_factorial_n": config.max_factorial_n,
+            "max_gcd_digits": config.max_gcd_digits,
+            "max_prime_n": config.max_prime_n,
+            "max_prime_digits": config.max_prime_digits,
+            "max_factor_digits": config.max_factor_digits,
+            "max_fib_word_len": config.max_fib_word_len,
+            "max_body_bytes": config.max_body_bytes,
+            "max_query_length": config.max_query_length,
+            "max_random_count": config.max_random_count,
+            "max_random_bytes": config.max_random_bytes,
+            "max_seq_n": config.max_seq_n,
+            "max_profile_runs": config.max_profile_runs,
+            "max_profile_n": config.max_profile_n,
+            "compute_budget_ms": config.compute_budget_ms,
+            "memo_cache_size": config.memo_cache_size,
+            "rate_limit_per_minute": config.rate_limit_per_minute,
+            "rate_limit_burst": config.rate_limit_burst,
+        },
+        "auth_required": !config.api_keys.is_empty(),
+    });
+    // Set apart to stay within the `json!` recursion limit; keys are sorted anyway
+    body["limits"]["max_graphql_fields"] = json!(config.max_graphql_fields);
+    body["limits"]["max_stream_size"] = json!(config.max_stream_size);
+
+    let mut response = response::json(StatusCode::OK, &body)?;
+    response
+        .headers_mut()
+        .insert("Cache-Control", http::HeaderValue::from_static("no-store"));
+    Ok(response)
+}
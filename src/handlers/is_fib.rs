@@ -0,0 +1,88 @@
+//! The inverse lookup endpoint: is a value a Fibonacci number, and which one?
+
+use super::guarded;
+use crate::caching;
+use crate::error::ApiError;
+use crate::fib;
+use crate::negotiation::Format;
+use crate::params::{Natural, Params};
+use crate::response::{self, Meta, Payload};
+use crate::telemetry;
+use crate::usage::{self, Work};
+use http::Method;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/is-fib?x=354224848179261915075`: tests whether x = F(n) for
+/// some n and reports that n.
+///
+/// `x` is a decimal string of any length. For x = 1 the smaller index, 1, is
+/// reported. Responses are cacheable.
+pub async fn is_fib(req: Request) -> Result<Response<Body>, Error> {
+    guarded("is-fib", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, x) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("is-fib", &format!("x={}", x), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, is_fib_body(x))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<BigUint, ApiError> {
+    #[derive(Deserialize)]
+    struct Candidate {
+        x: Natural,
+    }
+
+    let Candidate { x: Natural(x) } = Params::from_query(req.uri().query().unwrap_or("")).parse()?;
+    tracing::debug!(bits = x.bits(), "inverse lookup requested");
+    Ok(x)
+}
+
+fn is_fib_body(x: BigUint) -> Payload {
+    let index = fib::index_of(&x);
+    let x = x.to_string();
+
+    let mut body = json!({
+        "x": x,
+        "is_fibonacci": index.is_some(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+    if let Some(n) = index {
+        telemetry::record_n(n);
+        usage::charge(Work::index(n));
+        body["n"] = json!(n);
+    }
+
+    Payload {
+        json: body,
+        rows: vec![(
+            index.map(|n| n.to_string()).unwrap_or_default(),
+            index.is_some().to_string(),
+        )],
+        label: "is_fibonacci",
+        // The answer is a boolean, so report the size of the number examined
+        meta: Meta::new("inverse-binet").with_digits(x.len() as u64),
+    }
+}
@@ -0,0 +1,138 @@
+//! The async jobs endpoint, for results too slow to wait for over HTTP.
+
+use super::{check_limit, guarded};
+use crate::budget::{self, Deadline};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib;
+use crate::response;
+use crate::routing::{json_str, json_u64, parse_json_object};
+use crate::signing::{self, Secret};
+use crate::usage::{self, Work};
+use crate::{telemetry, webhook};
+use http::Method;
+use reqwest::Url;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::time::Instant;
+use tracing::Instrument;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `POST /api/jobs`: accepts `{"n": 5000000, "callback_url": "https://..."}`
+/// and answers `202 Accepted` with a job ID straight away.
+///
+/// F(n) is then computed in the background and posted to `callback_url`,
+/// signed with `WEBHOOK_SECRET` (see [`webhook::deliver`]). The index is
+/// checked against `MAX_JOB_N` rather than `MAX_FIB_N`, and charged to the
+/// API key like F(n) itself. The computation shares the request's
+/// `COMPUTE_BUDGET_MS` budget; a job that runs out of it is delivered with
+/// `"status": "timed_out"`. Without a secret configured the endpoint answers
+/// 503.
+pub async fn jobs(req: Request) -> Result<Response<Body>, Error> {
+    guarded("jobs", &[Method::POST], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let (secret, n, callback) = match read_body(&req) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "job rejected");
+            return err.into_response();
+        }
+    };
+    if let Err(err) = webhook::check_callback(&callback).await {
+        tracing::info!(error = %err, "job rejected");
+        return err.into_response();
+    }
+    usage::charge(Work::index(n));
+
+    let job_id = job_id(&req);
+    tracing::info!(job_id, n, callback = %callback, "job accepted");
+    let body = json!({
+        "job_id": job_id,
+        "n": n,
+        "callback_url": callback.as_str(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "accepted",
+    });
+    let deadline = Deadline::for_request();
+    tokio::spawn(run(job_id, n, callback, secret.clone(), deadline).in_current_span());
+    response::json(StatusCode::ACCEPTED, &body)
+}
+
+// Computes F(n) within `deadline` and delivers it, or the failure, to the
+// callback
+async fn run(job_id: String, n: u64, callback: Url, secret: Secret, deadline: Deadline) {
+    let start = Instant::now();
+    // Decimal conversion dominates at this size, so it runs off the runtime too
+    let computed = budget::run_blocking(deadline, move || {
+        fib::fast_doubling_within(n, &deadline).map(|value| value.to_string())
+    })
+    .await;
+    let timed_out = |progress: String| {
+        let err = deadline.exceeded(n, &progress);
+        tracing::warn!(job_id, error = %err, "job timed out");
+        json!({
+            "job_id": job_id,
+            "n": n,
+            "error": err.to_string(),
+            "progress": progress,
+            "elapsed_ms": start.elapsed().as_millis() as u64,
+            "completed_at": chrono::Utc::now().to_rfc3339(),
+            "status": "timed_out",
+        })
+    };
+    let body = match computed {
+        Ok(Some(Ok(value))) => json!({
+            "job_id": job_id,
+            "n": n,
+            "digits": value.len(),
+            "fibonacci": value,
+            "elapsed_ms": start.elapsed().as_millis() as u64,
+            "completed_at": chrono::Utc::now().to_rfc3339(),
+            "status": "completed",
+        }),
+        Ok(Some(Err(interrupted))) => timed_out(interrupted.to_string()),
+        Ok(None) => timed_out("computed, but not yet converted to text".to_string()),
+        Err(err) => {
+            tracing::error!(job_id, error = %err, "job computation failed");
+            json!({
+                "job_id": job_id,
+                "n": n,
+                "error": "computation failed",
+                "completed_at": chrono::Utc::now().to_rfc3339(),
+                "status": "failed",
+            })
+        }
+    };
+    webhook::deliver(&callback, &job_id, body.to_string().into_bytes(), &secret).await;
+}
+
+// Parses the body, after making sure callbacks can be signed at all
+fn read_body(req: &Request) -> Result<(&'static Secret, u64, Url), ApiError> {
+    let config = Config::get();
+    let secret = config.webhook_secret.as_ref().ok_or(ApiError::Unavailable {
+        reason: "jobs need WEBHOOK_SECRET to sign their callbacks",
+    })?;
+    if req.body().is_empty() {
+        return Err(ApiError::MalformedBody {
+            reason: "expected a JSON object such as {\"n\": 5000000, \"callback_url\": \"https://...\"}"
+                .to_string(),
+        });
+    }
+    let body = parse_json_object(req.body())?;
+    let n = json_u64(&body, "n")?.ok_or(ApiError::MissingParameter { name: "n" })?;
+    let n = check_limit(n, "n", config.max_job_n)?;
+    let callback = json_str(&body, "callback_url")?
+        .ok_or(ApiError::MissingParameter { name: "callback_url" })?;
+    Ok((secret, n, webhook::parse_callback(callback)?))
+}
+
+// An opaque ID that doesn't echo the request ID back; the time keeps two
+// jobs from one retried request apart
+fn job_id(req: &Request) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(telemetry::request_id(req).as_bytes());
+    hasher.update(chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0).to_le_bytes());
+    format!("job_{}", &signing::hex(&hasher.finalize())[..24])
+}
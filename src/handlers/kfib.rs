@@ -0,0 +1,101 @@
+//! The k-step Fibonacci endpoint (tribonacci, tetranacci, ...).
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib;
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use crate::telemetry;
+use crate::usage::{self, Work};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+// Conventional names for the smaller values of k, starting at k = 2
+const NAMES: [&str; 9] = [
+    "fibonacci",
+    "tribonacci",
+    "tetranacci",
+    "pentanacci",
+    "hexanacci",
+    "heptanacci",
+    "octanacci",
+    "nonanacci",
+    "decanacci",
+];
+
+/// `GET /api/kfib?k=3&n=100`: the n-th term of the k-step Fibonacci sequence.
+///
+/// Each term is the sum of the k before it, starting from k − 1 zeros and a
+/// one, so `k=2` is the ordinary Fibonacci sequence. `k` must lie between 2
+/// and `MAX_KFIB_K`. Responses are cacheable.
+pub async fn kfib(req: Request) -> Result<Response<Body>, Error> {
+    guarded("kfib", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, (k, n)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("kfib", &format!("k={}&n={}", k, n), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, kfib_body(k, n))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<(u64, u64), ApiError> {
+    #[derive(Deserialize)]
+    struct Order {
+        k: u64,
+        n: u64,
+    }
+
+    let config = Config::get();
+    let Order { k, n } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("k", 2..=i128::from(config.max_kfib_k))
+        .bound("n", 0..=i128::from(config.max_n))
+        .parse()?;
+    telemetry::record_n(n);
+    usage::charge(Work::index(n));
+    Ok((k, n))
+}
+
+fn kfib_body(k: u64, n: u64) -> Payload {
+    let value = fib::k_step(k, n).to_string();
+
+    let mut body = json!({
+        "k": k,
+        "n": n,
+        "value": value,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+    if let Some(name) = NAMES.get(k as usize - 2) {
+        body["sequence"] = json!(name);
+    }
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), value)],
+        label: "value",
+        meta: Meta::new("iterative"),
+    }
+}
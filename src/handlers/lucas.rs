@@ -0,0 +1,150 @@
+//! Lucas numbers and general Lucas sequences.
+
+use super::{bounded_index, guarded};
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib;
+use crate::lucas::LucasParams;
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use crate::telemetry;
+use crate::usage::{self, Work};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/lucas/[n]`: computes the Lucas number L(n).
+///
+/// The index is read like the Fibonacci endpoint's, from the path or `?n=`,
+/// but is always required. Responses are cacheable.
+pub async fn lucas(req: Request) -> Result<Response<Body>, Error> {
+    guarded("lucas", &[Method::GET], req, handle_lucas).await
+}
+
+/// `GET /api/lucas-seq?p=2&q=-1&n=50`: computes U_n(P, Q) and V_n(P, Q).
+///
+/// `p` and `q` default to 1 and −1, for which U is the Fibonacci sequence
+/// and V the Lucas numbers. Responses are cacheable.
+pub async fn lucas_sequence(req: Request) -> Result<Response<Body>, Error> {
+    guarded("lucas-seq", &[Method::GET], req, handle_sequence).await
+}
+
+async fn handle_lucas(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve_lucas(&req)?)));
+    let (format, n) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("lucas", &format!("n={}", n), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, lucas_body(n))?
+    };
+    caching::apply(response, &etag)
+}
+
+async fn handle_sequence(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve_sequence(&req)?)));
+    let (format, (params, n)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let key = format!("p={}&q={}&n={}", params.p, params.q, n);
+    let etag = caching::etag("lucas-seq", &key, format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, sequence_body(params, n))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve_lucas(req: &Request) -> Result<u64, ApiError> {
+    let range = 0..=i128::from(Config::get().max_n);
+    let n = bounded_index(req.uri().path(), req.uri().query().unwrap_or(""), range)?;
+    usage::charge(Work::index(n));
+    Ok(n.unsigned_abs())
+}
+
+fn resolve_sequence(req: &Request) -> Result<(LucasParams, u64), ApiError> {
+    #[derive(Deserialize)]
+    struct Sequence {
+        n: u64,
+        p: Option<i64>,
+        q: Option<i64>,
+    }
+
+    let Sequence { n, p, q } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("n", 0..=i128::from(Config::get().max_n))
+        .parse()?;
+    let params = LucasParams {
+        p: p.unwrap_or(LucasParams::FIBONACCI.p),
+        q: q.unwrap_or(LucasParams::FIBONACCI.q),
+    };
+    telemetry::record_n(n);
+    usage::charge(Work::index(n));
+    tracing::debug!(p = params.p, q = params.q, n, "lucas sequence requested");
+    Ok((params, n))
+}
+
+fn lucas_body(n: u64) -> Payload {
+    let value = fib::lucas(n).to_string();
+
+    let body = json!({
+        "lucas": value,
+        "n": n,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), value)],
+        label: "lucas",
+        meta: Meta::new("fast-doubling"),
+    }
+}
+
+fn sequence_body(params: LucasParams, n: u64) -> Payload {
+    let (u, v) = params.terms(n);
+    let (u, v) = (u.to_string(), v.to_string());
+
+    let body = json!({
+        "p": params.p,
+        "q": params.q,
+        "n": n,
+        "u": u,
+        "v": v,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), format!("{},{}", u, v))],
+        label: "u,v",
+        meta: Meta::new("fast-doubling"),
+    }
+}
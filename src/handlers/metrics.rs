@@ -0,0 +1,19 @@
+//! The Prometheus scrape endpoint.
+
+use super::guarded;
+use crate::metrics::render;
+use crate::response;
+use http::Method;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/metrics`: this instance's metrics in Prometheus text format.
+///
+/// Subject to the same API-key and rate-limit checks as the computation
+/// endpoints, so a scraper of a protected deployment must send a key.
+pub async fn metrics(req: Request) -> Result<Response<Body>, Error> {
+    guarded("metrics", &[Method::GET], req, handle).await
+}
+
+async fn handle(_req: Request) -> Result<Response<Body>, Error> {
+    response::text(StatusCode::OK, "text/plain; version=0.0.4; charset=utf-8", render())
+}
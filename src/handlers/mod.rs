@@ -0,0 +1,183 @@
+//! Endpoint handlers. The binaries under `api/` only wire these into the runtime.
+
+mod batch;
+mod benchmark;
+mod collatz;
+mod combinatorics;
+mod continued_fraction;
+mod convert;
+mod digits;
+mod eval;
+mod fib_digits;
+mod fib_sum;
+mod fib_word;
+mod fibonacci;
+mod gcd;
+mod golden_ratio;
+mod graphql;
+mod health;
+mod is_fib;
+mod jobs;
+mod kfib;
+mod lucas;
+mod metrics;
+mod modular;
+mod openapi;
+mod pisano;
+mod primes;
+mod random;
+mod range;
+mod router;
+mod sequences;
+mod stream;
+mod usage;
+mod verify;
+mod zeckendorf;
+
+pub use batch::batch;
+pub use benchmark::benchmark;
+pub use collatz::collatz;
+pub use combinatorics::{choose, factorial};
+pub use continued_fraction::continued_fraction;
+pub use convert::convert;
+pub use digits::digits;
+pub use eval::eval;
+pub use fib_digits::fib_digits;
+pub use fib_sum::fib_sum;
+pub use fib_word::fib_word;
+pub use fibonacci::fibonacci;
+pub use gcd::gcd;
+pub use golden_ratio::golden_ratio;
+pub use graphql::graphql;
+pub use health::health;
+pub use is_fib::is_fib;
+pub use jobs::jobs;
+pub use kfib::kfib;
+pub use lucas::{lucas, lucas_sequence};
+pub use metrics::metrics;
+pub use modular::modular;
+pub use openapi::openapi;
+pub use pisano::pisano;
+pub use primes::{factorize, is_prime, prime};
+pub use random::random;
+pub use range::range;
+pub use router::{not_found, paths, router};
+pub use sequences::{sequence, sequence_list};
+pub use stream::stream;
+pub use usage::usage;
+pub use verify::verify;
+pub use zeckendorf::zeckendorf;
+
+use crate::error::ApiError;
+use crate::middleware::{
+    Auth, Compression, Cors, Exported, Idempotency, Limits, Localized, Metered, Methods, Metrics, Pipeline,
+    RateLimit, Security, Shaped, Signed, Trace, Versioned,
+};
+use crate::params::Params;
+use crate::telemetry;
+use http::Method;
+use serde::Deserialize;
+use std::future::Future;
+use std::ops::RangeInclusive;
+use vercel_runtime::{Body, Error, Request, Response};
+
+// The layers every endpoint runs behind, outermost first: a per-request span
+// and its OTLP export, the API version, compression, CORS and security
+// headers, body signatures, metrics under `endpoint`, size limits on the query
+// and body, the response shape, method filtering (see `Methods`) and digit
+// grouping
+fn observing(endpoint: &'static str, methods: &'static [Method]) -> Pipeline {
+    Pipeline::new()
+        .layer(Trace { endpoint })
+        .layer(Exported { endpoint })
+        .layer(Versioned)
+        .layer(Compression)
+        .layer(Cors)
+        .layer(Security)
+        .layer(Signed)
+        .layer(Metrics { endpoint })
+        .layer(Limits)
+        .layer(Shaped)
+        .layer(Methods(methods))
+        .layer(Localized)
+}
+
+// Runs `handler` behind the `observing` layers
+async fn observed<F, Fut>(
+    endpoint: &'static str,
+    methods: &'static [Method],
+    req: Request,
+    handler: F,
+) -> Result<Response<Body>, Error>
+where
+    F: FnOnce(Request) -> Fut + Send,
+    Fut: Future<Output = Result<Response<Body>, Error>> + Send,
+{
+    observing(endpoint, methods).run(req, handler).await
+}
+
+// The `observing` layers, then the API key and rate limit checks, reporting
+// the limit state in the headers either way
+fn authorizing(endpoint: &'static str, methods: &'static [Method]) -> Pipeline {
+    observing(endpoint, methods).layer(Auth).layer(RateLimit)
+}
+
+// Like `observed`, but only runs `handler` if the client is authorized and
+// within its rate limit
+async fn authorized<F, Fut>(
+    endpoint: &'static str,
+    methods: &'static [Method],
+    req: Request,
+    handler: F,
+) -> Result<Response<Body>, Error>
+where
+    F: FnOnce(Request) -> Fut + Send,
+    Fut: Future<Output = Result<Response<Body>, Error>> + Send,
+{
+    authorizing(endpoint, methods).run(req, handler).await
+}
+
+// Like `authorized`, but also replays POST responses for a repeated
+// `Idempotency-Key` and counts the request against the key's quotas
+async fn guarded<F, Fut>(
+    endpoint: &'static str,
+    methods: &'static [Method],
+    req: Request,
+    handler: F,
+) -> Result<Response<Body>, Error>
+where
+    F: FnOnce(Request) -> Fut + Send,
+    Fut: Future<Output = Result<Response<Body>, Error>> + Send,
+{
+    authorizing(endpoint, methods)
+        .layer(Idempotency)
+        .layer(Metered)
+        .run(req, handler)
+        .await
+}
+
+// Reads the index `n` from the query or the last segment of `path`,
+// accepting integers in `range` only
+fn bounded_index(path: &str, query: &str, range: RangeInclusive<i128>) -> Result<i64, ApiError> {
+    #[derive(Deserialize)]
+    struct Index {
+        n: i64,
+    }
+
+    let Index { n } = Params::from_query(query).with_path("n", path).bound("n", range).parse()?;
+    telemetry::record_n(n);
+    Ok(n)
+}
+
+// Rejects `value` when it exceeds the configured `max`
+fn check_limit(value: u64, name: &'static str, max: u64) -> Result<u64, ApiError> {
+    if value <= max {
+        Ok(value)
+    } else {
+        Err(ApiError::OutOfRange {
+            name,
+            received: value.to_string(),
+            max,
+        })
+    }
+}
@@ -0,0 +1,84 @@
+//! The modular endpoint for indices far beyond what can be materialized.
+
+use super::guarded;
+use crate::caching;
+use crate::error::ApiError;
+use crate::fib;
+use crate::negotiation::Format;
+use crate::params::{Natural, Params};
+use crate::response::{self, Meta, Payload};
+use crate::usage::{self, Work};
+use http::Method;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/mod?n=1000000000000000000&m=1000000007`: computes F(n) mod m.
+///
+/// `n` is a decimal string of any length and is not subject to `MAX_FIB_N`:
+/// the computation works on residues only, so its cost grows with the number
+/// of bits in `n` rather than with F(n). Responses are cacheable.
+pub async fn modular(req: Request) -> Result<Response<Body>, Error> {
+    guarded("mod", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, (n, m)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("mod", &format!("n={}&m={}", n, m), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, modular_body(n, m))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<(BigUint, u64), ApiError> {
+    #[derive(Deserialize)]
+    struct Residue {
+        n: Natural,
+        m: u64,
+    }
+
+    let Residue { n: Natural(n), m } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("m", 1..=i128::from(u64::MAX))
+        .parse()?;
+
+    tracing::debug!(bits = n.bits(), m, "modular fibonacci requested");
+    usage::charge(Work::Flat);
+    Ok((n, m))
+}
+
+fn modular_body(n: BigUint, m: u64) -> Payload {
+    let value = fib::modular(&n, m);
+    let n = n.to_string();
+
+    let body = json!({
+        "n": n,
+        "m": m,
+        "fibonacci_mod": value,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n, value.to_string())],
+        label: "fibonacci_mod",
+        meta: Meta::new("fast-doubling"),
+    }
+}
@@ -0,0 +1,47 @@
+//! The OpenAPI document and its Swagger UI page.
+
+use super::observed;
+use crate::config::Config;
+use crate::openapi::{spec, swagger_ui};
+use crate::params::Params;
+use crate::response;
+use http::header::{HeaderValue, CONTENT_SECURITY_POLICY};
+use http::Method;
+use serde::Deserialize;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/openapi.json`: the OpenAPI document for this deployment.
+///
+/// `GET /api/docs` (or `?ui=true`) returns a Swagger UI page rendering it.
+/// Neither requires an API key, so clients can discover how to authenticate.
+/// The page loads Swagger UI from unpkg, so it carries its own, looser
+/// `Content-Security-Policy` than the API's default.
+pub async fn openapi(req: Request) -> Result<Response<Body>, Error> {
+    observed("openapi", &[Method::GET], req, handle).await
+}
+
+const DOCS_POLICY: &str = "default-src 'none'; script-src https://unpkg.com 'unsafe-inline'; \
+    style-src https://unpkg.com 'unsafe-inline'; img-src 'self' data: https://unpkg.com; connect-src 'self'; \
+    frame-ancestors 'none'";
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    #[derive(Deserialize)]
+    struct View {
+        #[serde(default)]
+        ui: bool,
+    }
+
+    let View { ui } = match Params::from_query(req.uri().query().unwrap_or("")).parse() {
+        Ok(view) => view,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+    if req.uri().path().ends_with("/docs") || ui {
+        let mut response = response::html(StatusCode::OK, swagger_ui("/api/openapi.json"))?;
+        response.headers_mut().insert(CONTENT_SECURITY_POLICY, HeaderValue::from_static(DOCS_POLICY));
+        return Ok(response);
+    }
+    response::json(StatusCode::OK, &spec(Config::get()))
+}
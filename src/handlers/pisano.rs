@@ -0,0 +1,76 @@
+//! The Pisano period endpoint.
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib;
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::response::{self, Meta, Payload};
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/pisano?m=10`: the period of the Fibonacci numbers modulo m.
+///
+/// Together with `/api/mod` this answers F(n) mod m for any n by reducing n
+/// modulo the period. `m` must lie between 1 and `MAX_PISANO_M`, since the
+/// cycle search takes up to 6m steps. Responses are cacheable.
+pub async fn pisano(req: Request) -> Result<Response<Body>, Error> {
+    guarded("pisano", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, m) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("pisano", &format!("m={}", m), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        let period = tokio::task::spawn_blocking(move || fib::pisano_period(m)).await?;
+        response::render(StatusCode::OK, format, pisano_body(m, period))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<u64, ApiError> {
+    #[derive(Deserialize)]
+    struct Modulus {
+        m: u64,
+    }
+
+    let Modulus { m } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("m", 1..=i128::from(Config::get().max_pisano_m))
+        .parse()?;
+    Ok(m)
+}
+
+fn pisano_body(m: u64, period: u64) -> Payload {
+    let body = json!({
+        "m": m,
+        "period": period,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(m.to_string(), period.to_string())],
+        label: "period",
+        meta: Meta::new("iterative"),
+    }
+}
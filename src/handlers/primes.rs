@@ -0,0 +1,254 @@
+//! The prime number endpoints.
+
+use super::{bounded_index, guarded};
+use crate::budget::Deadline;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::negotiation::Format;
+use crate::params::{Natural, Params};
+use crate::primes;
+use crate::response::{self, Meta, Payload};
+use crate::usage::{self, Work};
+use http::Method;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/prime/[n]`: the n-th prime, counting 2 as the first.
+///
+/// The index is read from the path or `?n=` and is limited by
+/// `MAX_PRIME_N`. Responses are cacheable.
+pub async fn prime(req: Request) -> Result<Response<Body>, Error> {
+    guarded("prime", &[Method::GET], req, handle_prime).await
+}
+
+/// `GET /api/is-prime?x=1000000007`: tests x for primality.
+///
+/// `x` is a decimal string of at most `MAX_PRIME_DIGITS` digits. The answer
+/// is exact below [`primes::DETERMINISTIC_BELOW`]; larger primes are
+/// reported as `probable_prime`, and 0 and 1 as `neither`. Responses are
+/// cacheable.
+pub async fn is_prime(req: Request) -> Result<Response<Body>, Error> {
+    guarded("is-prime", &[Method::GET], req, handle_is_prime).await
+}
+
+/// `GET /api/factorize?x=360`: the prime factorization of x.
+///
+/// `x` is a positive decimal string of at most `MAX_FACTOR_DIGITS` digits.
+/// Small factors are found by trial division and the rest by Pollard's rho,
+/// which gives up with a `504` if `COMPUTE_BUDGET_MS` runs out first, as it
+/// will for a product of two large primes. Responses are cacheable.
+pub async fn factorize(req: Request) -> Result<Response<Body>, Error> {
+    guarded("factorize", &[Method::GET], req, handle_factorize).await
+}
+
+async fn handle_prime(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve_prime(&req)?)));
+    let (format, n) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("prime", &format!("n={}", n), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, prime_body(n))?
+    };
+    caching::apply(response, &etag)
+}
+
+async fn handle_is_prime(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve_is_prime(&req)?)));
+    let (format, x) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("is-prime", &format!("x={}", x), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, is_prime_body(x))?
+    };
+    caching::apply(response, &etag)
+}
+
+async fn handle_factorize(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve_factorize(&req)?)));
+    let (format, x) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("factorize", &format!("x={}", x), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        let deadline = Deadline::for_request();
+        let task = {
+            let x = x.clone();
+            tokio::task::spawn_blocking(move || primes::factorize(&x, &deadline))
+        };
+        let factors = match task.await? {
+            Ok(factors) => factors,
+            Err(unfactored) => {
+                let suggestions = vec![format!("/api/is-prime?x={}", unfactored.remaining)];
+                let err = deadline.abandoned(format!("the factorization of {}", x), &unfactored, suggestions);
+                tracing::warn!(error = %err, "factorization abandoned");
+                return err.into_response();
+            }
+        };
+        response::render(StatusCode::OK, format, factorize_body(x, factors))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve_prime(req: &Request) -> Result<u64, ApiError> {
+    let range = 1..=i128::from(Config::get().max_prime_n);
+    let n = bounded_index(req.uri().path(), req.uri().query().unwrap_or(""), range)?;
+    usage::charge(Work::index(n));
+    Ok(n.unsigned_abs())
+}
+
+// The number examined by `is-prime` and `factorize`
+#[derive(Deserialize)]
+struct Candidate {
+    x: Natural,
+}
+
+fn resolve_is_prime(req: &Request) -> Result<BigUint, ApiError> {
+    let Candidate { x: Natural(x) } = Params::from_query(req.uri().query().unwrap_or("")).parse()?;
+    let digits = x.to_string().len() as u64;
+    let max = Config::get().max_prime_digits;
+    if digits > max {
+        return Err(ApiError::OutOfRange {
+            name: "x",
+            received: format!("{} digits", digits),
+            max,
+        });
+    }
+    tracing::debug!(bits = x.bits(), "primality test requested");
+    Ok(x)
+}
+
+fn resolve_factorize(req: &Request) -> Result<BigUint, ApiError> {
+    let Candidate { x: Natural(x) } = Params::from_query(req.uri().query().unwrap_or("")).parse()?;
+    if x.is_zero() {
+        return Err(ApiError::InvalidParameter {
+            name: "x",
+            received: "0".to_string(),
+            expected: "a positive integer".into(),
+        });
+    }
+    let digits = x.to_string().len() as u64;
+    let max = Config::get().max_factor_digits;
+    if digits > max {
+        return Err(ApiError::OutOfRange {
+            name: "x",
+            received: format!("{} digits", digits),
+            max,
+        });
+    }
+    tracing::debug!(bits = x.bits(), "factorization requested");
+    Ok(x)
+}
+
+fn prime_body(n: u64) -> Payload {
+    let prime = primes::nth_prime(n).expect("n is positive").to_string();
+
+    let body = json!({
+        "n": n,
+        "prime": prime,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), prime)],
+        label: "prime",
+        meta: Meta::new("segmented-sieve"),
+    }
+}
+
+fn is_prime_body(x: BigUint) -> Payload {
+    let primality = primes::primality(&x);
+    let x = x.to_string();
+
+    let body = json!({
+        "x": x,
+        "is_prime": primality.is_prime(),
+        "primality": primality.as_str(),
+        "deterministic": primality != primes::Primality::ProbablePrime,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(x.clone(), primality.as_str().to_string())],
+        label: "primality",
+        meta: Meta::new("miller-rabin").with_digits(x.len() as u64),
+    }
+}
+
+fn factorize_body(x: BigUint, factors: Vec<primes::Factor>) -> Payload {
+    let x = x.to_string();
+    let written = if factors.is_empty() {
+        "1".to_string()
+    } else {
+        primes::format_factors(&factors)
+    };
+    let is_prime = matches!(factors.as_slice(), [factor] if factor.exponent == 1);
+
+    let body = json!({
+        "x": x,
+        "factors": factors
+            .iter()
+            .map(|factor| json!({ "prime": factor.prime.to_string(), "exponent": factor.exponent }))
+            .collect::<Vec<_>>(),
+        "factorization": written,
+        "is_prime": is_prime,
+        "deterministic": factors.iter().all(|factor| factor.certain),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: factors
+            .iter()
+            .map(|factor| (factor.prime.to_string(), factor.exponent.to_string()))
+            .collect(),
+        label: "exponent",
+        meta: Meta::new("pollard-rho").with_digits(x.len() as u64),
+    }
+}
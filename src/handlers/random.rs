@@ -0,0 +1,117 @@
+//! The secure random number endpoint.
+
+use super::guarded;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::negotiation::Format;
+use crate::params::{Integer, Params};
+use crate::random;
+use crate::response::{self, Meta, Payload};
+use http::header::{HeaderValue, CACHE_CONTROL};
+use http::Method;
+use num_bigint::BigInt;
+use num_traits::One;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/random?min=1&max=6&count=10`: uniform random integers in
+/// `min..=max` from the operating system's CSPRNG.
+///
+/// `min` defaults to 0 and both bounds may be any size, as long as the range
+/// spans at most `MAX_RANDOM_BYTES` bytes. `count` defaults to 1 and is
+/// limited by `MAX_RANDOM_COUNT`. Responses are never cacheable.
+pub async fn random(req: Request) -> Result<Response<Body>, Error> {
+    guarded("random", &[Method::GET], req, handle).await
+}
+
+struct Draw {
+    min: BigInt,
+    max: BigInt,
+    count: u64,
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, draw) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let mut response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        let values = match (0..draw.count).map(|_| random::between(&draw.min, &draw.max)).collect() {
+            Ok(values) => values,
+            Err(err) => {
+                tracing::error!(error = %err, "operating system random source failed");
+                return ApiError::Internal.into_response();
+            }
+        };
+        response::render(StatusCode::OK, format, random_body(&draw, values))?
+    };
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Ok(response)
+}
+
+fn resolve(req: &Request) -> Result<Draw, ApiError> {
+    #[derive(Deserialize)]
+    struct Bounds {
+        min: Option<Integer>,
+        max: Integer,
+        count: Option<u64>,
+    }
+
+    let config = Config::get();
+    let Bounds { min, max: Integer(max), count } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("count", 0..=i128::from(config.max_random_count))
+        .parse()?;
+    let min = min.map(|Integer(min)| min).unwrap_or_default();
+    if max < min {
+        return Err(ApiError::InvalidParameter {
+            name: "max",
+            received: max.to_string(),
+            expected: "at least min".into(),
+        });
+    }
+    let bytes = (&max - &min + BigInt::one()).bits().div_ceil(8);
+    if bytes > config.max_random_bytes {
+        return Err(ApiError::OutOfRange {
+            name: "max",
+            received: format!("a range of {} bytes", bytes),
+            max: config.max_random_bytes,
+        });
+    }
+    let count = count.unwrap_or(1);
+    tracing::debug!(bytes, count, "random values requested");
+    Ok(Draw { min, max, count })
+}
+
+fn random_body(draw: &Draw, values: Vec<BigInt>) -> Payload {
+    let values: Vec<_> = values.iter().map(BigInt::to_string).collect();
+
+    let body = json!({
+        "values": values,
+        "min": draw.min.to_string(),
+        "max": draw.max.to_string(),
+        "count": draw.count,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| (index.to_string(), value))
+            .collect(),
+        label: "value",
+        meta: Meta::new("os-csprng"),
+    }
+}
@@ -0,0 +1,177 @@
+//! The range endpoint returning a contiguous slice of the sequence.
+
+use super::guarded;
+use crate::budget::{self, Deadline};
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib::Sequence;
+use crate::negotiation::Format;
+use crate::parallel;
+use crate::params::Params;
+use crate::radix;
+use crate::response::{self, Meta, Payload};
+use crate::streaming;
+use crate::usage::{self, Work};
+use http::Method;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+// Shorter runs cost more in direct computations than they save
+const MIN_RUN: u64 = 64;
+
+/// `GET /api/range?from=10&to=40`: returns F(from)..=F(to) as an array.
+///
+/// The range is split into up to `MAX_PARALLELISM` runs computed at once;
+/// only the first two values of each run are computed directly, the rest are
+/// built by successive additions. Responses are cacheable like the
+/// single-value endpoint.
+///
+/// In NDJSON each value is serialized as soon as it is generated and handed
+/// to [`streaming::respond`], without building the JSON document or keeping
+/// the sequence around, so a transport that sends bodies in pieces writes
+/// the range incrementally.
+pub async fn range(req: Request) -> Result<Response<Body>, Error> {
+    guarded("range", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, (from, to, base)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "range rejected");
+            return err.into_response();
+        }
+    };
+
+    let key = format!("from={}&to={}&base={:?}", from, to, base);
+    let etag = caching::etag("range", &key, format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let deadline = Deadline::for_request();
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else if format == Format::Ndjson {
+        let lines = (from..=to)
+            .zip(Sequence::starting_at(from))
+            .map(move |(n, value)| response::ndjson([(n.to_string(), encode(&value, base))], "fibonacci"));
+        let expired = move |done: u64| {
+            deadline.exceeded(to, format!("{} of {} values of F({})..F({}) sent", done, to - from + 1, from, to))
+        };
+        let response = streaming::respond(format.content_type(), lines, deadline, expired).await?;
+        if response.status() != StatusCode::OK {
+            return Ok(response);
+        }
+        response
+    } else {
+        let Some(sequence) = sequence(from, to, base, deadline).await? else {
+            let err = deadline.exceeded(to, format!("F({})..F({}) was still being computed", from, to));
+            tracing::warn!(error = %err, "range abandoned");
+            return err.into_response();
+        };
+        response::render(StatusCode::OK, format, range_body(from, to, base, sequence))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<(u64, u64, Option<u32>), ApiError> {
+    #[derive(Deserialize)]
+    struct Bounds {
+        from: u64,
+        to: u64,
+        base: Option<u32>,
+    }
+
+    let config = Config::get();
+    let indices = 0..=i128::from(config.max_n);
+    let Bounds { from, to, base } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("from", indices.clone())
+        .bound("to", indices)
+        .bound("base", radix::BASES)
+        .parse()?;
+
+    tracing::debug!(from, to, "range requested");
+
+    if from > to {
+        return Err(ApiError::InvalidParameter {
+            name: "from",
+            received: from.to_string(),
+            expected: "an integer not greater than 'to'".into(),
+        });
+    }
+    let count = to - from + 1;
+    if count > config.max_range_size {
+        return Err(ApiError::OutOfRange {
+            name: "to",
+            received: format!("a range of {} values", count),
+            max: config.max_range_size,
+        });
+    }
+
+    usage::charge(Work::indices(from..=to));
+    Ok((from, to, base))
+}
+
+// F(from)..=F(to) as text, computed in runs side by side; `None` when the
+// budget ran out first
+async fn sequence(from: u64, to: u64, base: Option<u32>, deadline: Deadline) -> Result<Option<Vec<String>>, Error> {
+    let count = to - from + 1;
+    let run = count.div_ceil(parallel::limit() as u64).max(MIN_RUN);
+    let starts = (from..=to).step_by(run as usize);
+    let runs = parallel::map(starts, move |start| {
+        let len = run.min(to - start + 1) as usize;
+        budget::run_blocking(deadline, move || {
+            Sequence::starting_at(start)
+                .take(len)
+                .map(|value| encode(&value, base))
+                .collect::<Vec<_>>()
+        })
+    })
+    .await?;
+
+    let mut sequence = Vec::with_capacity(count as usize);
+    for run in runs {
+        match run? {
+            Some(values) => sequence.extend(values),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(sequence))
+}
+
+fn range_body(from: u64, to: u64, base: Option<u32>, sequence: Vec<String>) -> Payload {
+    let count = to - from + 1;
+
+    let mut body = json!({
+        "from": from,
+        "to": to,
+        "count": count,
+        "sequence": sequence,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    if let Some(base) = base {
+        body["base"] = json!(base);
+    }
+
+    Payload {
+        json: body,
+        rows: (from..=to).map(|n| n.to_string()).zip(sequence).collect(),
+        label: "fibonacci",
+        meta: Meta::new("iterative"),
+    }
+}
+
+fn encode(value: &BigUint, base: Option<u32>) -> String {
+    match base {
+        Some(base) => radix::encode(value, base),
+        None => value.to_string(),
+    }
+}
@@ -0,0 +1,99 @@
+//! The catch-all endpoint dispatching to every other handler.
+
+use super::observed;
+use crate::error::ApiError;
+use crate::router::{self, Router};
+use crate::version;
+use http::Method;
+use std::sync::OnceLock;
+use vercel_runtime::{Body, Error, Request, Response};
+
+// Every method a path could be requested with; unknown paths answer 404 to all
+const ANY: &[Method] = &[
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+];
+
+/// Serves every endpoint from one function, e.g. `/api/fib/20`,
+/// `/api/range?from=1&to=5` or `/api/health`.
+///
+/// Unknown paths get a `404` JSON error instead of falling back to F(10).
+/// Requests rewritten to `/api/router?route=<path>` are dispatched on
+/// `<path>`, and a `/api/v1/` or `/api/v2/` prefix selects the response
+/// version before routing on the rest of the path.
+pub async fn router(mut req: Request) -> Result<Response<Body>, Error> {
+    router::restore_path(&mut req);
+    version::select(&mut req);
+    routes().handle(req).await
+}
+
+/// Answers `404 Not Found` for a path no endpoint serves.
+pub async fn not_found(req: Request) -> Result<Response<Body>, Error> {
+    observed("not_found", ANY, req, |req| async move {
+        let err = ApiError::NotFound {
+            path: req.uri().path().to_string(),
+        };
+        tracing::info!(error = %err, "request rejected");
+        err.into_response()
+    })
+    .await
+}
+
+/// The path patterns [`router`] serves, such as `/api/lucas/{n}`, in the
+/// order they are matched.
+pub fn paths() -> Vec<&'static str> {
+    routes().patterns().collect()
+}
+
+fn routes() -> &'static Router {
+    static ROUTER: OnceLock<Router> = OnceLock::new();
+    ROUTER.get_or_init(|| {
+        Router::new(not_found)
+            .route("/api", super::fibonacci)
+            .route("/api/batch", super::batch)
+            .route("/api/benchmark", super::benchmark)
+            .route("/api/choose", super::choose)
+            .route("/api/collatz/{n}", super::collatz)
+            .route("/api/continued-fraction", super::continued_fraction)
+            .route("/api/convert", super::convert)
+            .route("/api/digits/{n}", super::digits)
+            .route("/api/docs", super::openapi)
+            .route("/api/eval", super::eval)
+            .route("/api/factorial/{n}", super::factorial)
+            .route("/api/factorize", super::factorize)
+            .route("/api/fib/{n}", super::fibonacci)
+            .route("/api/fib-digits/{n}", super::fib_digits)
+            .route("/api/fib-sum", super::fib_sum)
+            .route("/api/fib-word", super::fib_word)
+            .route("/api/gcd", super::gcd)
+            .route("/api/golden-ratio", super::golden_ratio)
+            .route("/api/graphql", super::graphql)
+            .route("/api/health", super::health)
+            .route("/api/is-fib", super::is_fib)
+            .route("/api/is-prime", super::is_prime)
+            .route("/api/jobs", super::jobs)
+            .route("/api/kfib", super::kfib)
+            .route("/api/lucas/{n}", super::lucas)
+            .route("/api/lucas-seq", super::lucas_sequence)
+            .route("/api/metrics", super::metrics)
+            .route("/api/mod", super::modular)
+            .route("/api/openapi", super::openapi)
+            .route("/api/openapi.json", super::openapi)
+            .route("/api/pisano", super::pisano)
+            .route("/api/prime/{n}", super::prime)
+            .route("/api/random", super::random)
+            .route("/api/range", super::range)
+            .route("/api/seq", super::sequence_list)
+            .route("/api/seq/{name}", super::sequence)
+            .route("/api/seq/{name}/{n}", super::sequence)
+            .route("/api/stream", super::stream)
+            .route("/api/usage", super::usage)
+            .route("/api/verify", super::verify)
+            .route("/api/zeckendorf", super::zeckendorf)
+            // Last, so that it does not shadow the single-segment routes above
+            .route("/api/{n}", super::fibonacci)
+    })
+}
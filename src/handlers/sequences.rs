@@ -0,0 +1,141 @@
+//! The classic sequences endpoint, driven by [`crate::sequences::SEQUENCES`].
+
+use super::{bounded_index, guarded};
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::negotiation::Format;
+use crate::response::{self, Meta, Payload};
+use crate::sequences::{self, Sequence, SEQUENCES};
+use crate::usage::{self, Work};
+use http::Method;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/seq/{name}/[n]`: the n-th term of a named sequence, such as
+/// `/api/seq/catalan/10`.
+///
+/// `n` is limited by `MAX_SEQ_N`. An unknown name answers 404;
+/// `GET /api/seq` lists the names served. Responses are cacheable.
+pub async fn sequence(req: Request) -> Result<Response<Body>, Error> {
+    guarded("seq", &[Method::GET], req, handle_term).await
+}
+
+/// `GET /api/seq`: every sequence served, with its definition and OEIS
+/// number.
+pub async fn sequence_list(req: Request) -> Result<Response<Body>, Error> {
+    guarded("seq", &[Method::GET], req, handle_list).await
+}
+
+async fn handle_term(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, (sequence, n)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("seq", &format!("name={}&n={}", sequence.name, n), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, term_body(sequence, n))?
+    };
+    caching::apply(response, &etag)
+}
+
+async fn handle_list(req: Request) -> Result<Response<Body>, Error> {
+    let format = match Format::from_request(&req) {
+        Ok(format) => format,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("seq", "list", format);
+    if caching::is_fresh(&req, &etag) {
+        return caching::not_modified(&etag);
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, list_body())?
+    };
+    caching::apply(response, &etag)
+}
+
+// `/api/seq/<name>/<n>`, or `/api/seq/<name>?n=`
+fn resolve(req: &Request) -> Result<(&'static Sequence, u64), ApiError> {
+    let path = req.uri().path();
+    let not_found = || ApiError::NotFound {
+        path: path.to_string(),
+    };
+    let rest = path.strip_prefix("/api/seq/").ok_or_else(not_found)?;
+    let (name, index) = rest.split_once('/').unwrap_or((rest, ""));
+    let sequence = sequences::find(name).ok_or_else(not_found)?;
+
+    let range = 0..=i128::from(Config::get().max_seq_n);
+    let n = bounded_index(index, req.uri().query().unwrap_or(""), range)?;
+    usage::charge(Work::index(n));
+    Ok((sequence, n.unsigned_abs()))
+}
+
+fn term_body(sequence: &Sequence, n: u64) -> Payload {
+    let value = (sequence.term)(n).to_string();
+
+    let body = json!({
+        "sequence": sequence.name,
+        "n": n,
+        "value": value,
+        "oeis": sequence.oeis,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: vec![(n.to_string(), value)],
+        label: sequence.name,
+        meta: Meta::new(sequence.algorithm),
+    }
+}
+
+fn list_body() -> Payload {
+    let entries: Vec<_> = SEQUENCES
+        .iter()
+        .map(|sequence| {
+            json!({
+                "name": sequence.name,
+                "description": sequence.description,
+                "oeis": sequence.oeis,
+                "url": format!("/api/seq/{}/{{n}}", sequence.name),
+            })
+        })
+        .collect();
+
+    let body = json!({
+        "sequences": entries,
+        "count": SEQUENCES.len(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows: SEQUENCES
+            .iter()
+            .map(|sequence| (sequence.name.to_string(), sequence.oeis.to_string()))
+            .collect(),
+        label: "oeis",
+        meta: Meta::new("registry"),
+    }
+}
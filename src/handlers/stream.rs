@@ -0,0 +1,146 @@
+//! The streaming endpoint sending the sequence one element at a time.
+
+use super::guarded;
+use crate::budget::Deadline;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib::Sequence;
+use crate::params::Params;
+use crate::radix;
+use crate::response;
+use crate::streaming;
+use crate::usage::{self, Work};
+use http::header::ACCEPT;
+use http::Method;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// How the elements are framed on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// One JSON object per line, see [`response::ndjson`].
+    Ndjson,
+    /// Server-sent events, one `fibonacci` event per element and a final `end`.
+    Events,
+}
+
+impl Framing {
+    fn content_type(self) -> &'static str {
+        match self {
+            Framing::Ndjson => "application/x-ndjson",
+            Framing::Events => streaming::EVENT_STREAM,
+        }
+    }
+}
+
+/// `GET /api/stream?from=0&to=1000`: sends F(from)..=F(to) one element at a
+/// time, as NDJSON lines or, with `?format=sse` or `Accept:
+/// text/event-stream`, as server-sent events.
+///
+/// Each element is written as soon as it is generated, one addition after
+/// the previous one. The dev server flushes every element to the client
+/// right away; on Vercel the runtime sends the response once it is complete.
+/// A stream has at most `MAX_STREAM_SIZE` elements and `?base=` writes them
+/// in another radix. The elements share the `COMPUTE_BUDGET_MS` budget: on
+/// Vercel running out of it answers 504, while one already being sent ends
+/// with an `error` event or line.
+pub async fn stream(req: Request) -> Result<Response<Body>, Error> {
+    guarded("stream", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let (framing, from, to, base) = match resolve(&req) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "stream rejected");
+            return err.into_response();
+        }
+    };
+    if req.method() == Method::HEAD {
+        return response::text(StatusCode::OK, framing.content_type(), String::new());
+    }
+
+    let elements = (from..=to).zip(Sequence::starting_at(from)).map(move |(n, value)| {
+        let value = match base {
+            Some(base) => radix::encode(&value, base),
+            None => value.to_string(),
+        };
+        match framing {
+            Framing::Ndjson => response::ndjson([(n.to_string(), value)], "fibonacci"),
+            Framing::Events => {
+                let data = json!({ "n": n, "fibonacci": value }).to_string();
+                streaming::event(Some(n), "fibonacci", &data)
+            }
+        }
+    });
+    let end = (framing == Framing::Events).then(|| {
+        let data = json!({ "from": from, "to": to, "count": to - from + 1 }).to_string();
+        streaming::event(None, "end", &data)
+    });
+    let deadline = Deadline::for_request();
+    let count = to - from + 1;
+    let expired = move |done: u64| {
+        let progress = format!("{} of {} elements sent", done.min(count), count);
+        deadline.abandoned(format!("the stream of F({})..F({})", from, to), progress, Vec::new())
+    };
+    streaming::respond(framing.content_type(), elements.chain(end), deadline, expired).await
+}
+
+fn resolve(req: &Request) -> Result<(Framing, u64, u64, Option<u32>), ApiError> {
+    #[derive(Deserialize)]
+    struct Bounds {
+        from: u64,
+        to: u64,
+        base: Option<u32>,
+        format: Option<String>,
+    }
+
+    let config = Config::get();
+    let indices = 0..=i128::from(config.max_n);
+    let Bounds { from, to, base, format } = Params::from_query(req.uri().query().unwrap_or(""))
+        .bound("from", indices.clone())
+        .bound("to", indices)
+        .bound("base", radix::BASES)
+        .parse()?;
+
+    tracing::debug!(from, to, "stream requested");
+
+    if from > to {
+        return Err(ApiError::InvalidParameter {
+            name: "from",
+            received: from.to_string(),
+            expected: "an integer not greater than 'to'".into(),
+        });
+    }
+    let count = to - from + 1;
+    if count > config.max_stream_size {
+        return Err(ApiError::OutOfRange {
+            name: "to",
+            received: format!("a stream of {} values", count),
+            max: config.max_stream_size,
+        });
+    }
+
+    let framing = match format.as_deref() {
+        Some("ndjson" | "jsonl") => Framing::Ndjson,
+        Some("sse" | "event-stream") => Framing::Events,
+        Some(other) => {
+            return Err(ApiError::InvalidParameter {
+                name: "format",
+                received: other.to_string(),
+                expected: "ndjson or sse".into(),
+            })
+        }
+        None => {
+            let accept = req.headers().get(ACCEPT).and_then(|value| value.to_str().ok()).unwrap_or("");
+            if accept.contains("text/event-stream") {
+                Framing::Events
+            } else {
+                Framing::Ndjson
+            }
+        }
+    };
+    usage::charge(Work::indices(from..=to));
+    Ok((framing, from, to, base))
+}
@@ -0,0 +1,56 @@
+//! The per-key usage endpoint.
+
+use super::authorized;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::response;
+use crate::usage::{self, Period, N_PER_UNIT};
+use http::Method;
+use serde_json::{json, Map, Value};
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/usage`: the requests and compute units the presented API key
+/// used today and this month (UTC), next to its quotas.
+///
+/// Not itself counted or subject to the quotas, so a key that used up its
+/// share can still see when it resets. Usage is only tracked with `API_KEYS`
+/// set; otherwise the endpoint answers 503.
+pub async fn usage(req: Request) -> Result<Response<Body>, Error> {
+    authorized("usage", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let Some(account) = usage::account(&req) else {
+        let err = ApiError::Unavailable {
+            reason: "usage is only tracked when API_KEYS is set",
+        };
+        tracing::info!(error = %err, "request rejected");
+        return err.into_response();
+    };
+
+    let quotas = Config::get().quotas;
+    let now = chrono::Utc::now();
+    let limit = |limit: u64| (limit > 0).then_some(limit);
+    let mut body = Map::new();
+    for period in Period::ALL {
+        let used = usage::read(&account, period, now).await;
+        let limits = quotas.limits(period);
+        body.insert(
+            period.name().to_string(),
+            json!({
+                "period": period.label(now),
+                "requests": used.requests,
+                "units": used.units,
+                "limits": {
+                    "requests": limit(limits.requests),
+                    "units": limit(limits.units),
+                },
+                "resets_at": period.resets_at(now).to_rfc3339(),
+            }),
+        );
+    }
+    body.insert("n_per_unit".to_string(), json!(N_PER_UNIT));
+    body.insert("timestamp".to_string(), json!(now.to_rfc3339()));
+    body.insert("status".to_string(), json!("success"));
+    response::json(StatusCode::OK, &Value::Object(body))
+}
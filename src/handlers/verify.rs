@@ -0,0 +1,68 @@
+//! The response signature verification endpoint.
+
+use super::guarded;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::response;
+use crate::routing::{json_str, parse_json_object};
+use crate::signing::{self, Secret};
+use http::Method;
+use serde_json::{json, Value};
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `POST /api/verify`: checks `{"body": ..., "signature": "t=...,v1=..."}`
+/// against `RESPONSE_SIGNING_SECRET`, for services that cached a response
+/// and its `X-Signature` header and want to know neither was tampered with.
+///
+/// `body` is the response body as a string, or the JSON document itself;
+/// either way JSON is compared in its [`signing::canonical`] form. Without a
+/// secret configured the endpoint answers 503.
+pub async fn verify(req: Request) -> Result<Response<Body>, Error> {
+    guarded("verify", &[Method::POST], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let (secret, body, signature) = match read_body(&req) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "verification rejected");
+            return err.into_response();
+        }
+    };
+
+    let valid = signing::verify(secret, &signature, &signing::canonical(&body));
+    let signed_at = signing::timestamp(&signature);
+    tracing::info!(valid, "signature checked");
+    let body = json!({
+        "valid": valid,
+        "signed_at": signed_at
+            .and_then(|at| chrono::DateTime::from_timestamp(at, 0))
+            .map(|at| at.to_rfc3339()),
+        "age_secs": signed_at.map(|at| chrono::Utc::now().timestamp() - at),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+    response::json(StatusCode::OK, &body)
+}
+
+// The secret, the signed bytes and the signature header value
+fn read_body(req: &Request) -> Result<(&'static Secret, Vec<u8>, String), ApiError> {
+    let secret = Config::get().response_signing_secret.as_ref().ok_or(ApiError::Unavailable {
+        reason: "verification needs RESPONSE_SIGNING_SECRET",
+    })?;
+    if req.body().is_empty() {
+        return Err(ApiError::MalformedBody {
+            reason: "expected a JSON object such as {\"body\": \"...\", \"signature\": \"t=...,v1=...\"}".to_string(),
+        });
+    }
+    let mut object = parse_json_object(req.body())?;
+    let signature = json_str(&object, "signature")?
+        .ok_or(ApiError::MissingParameter { name: "signature" })?
+        .to_string();
+    let body = match object.remove("body") {
+        Some(Value::String(text)) => text.into_bytes(),
+        Some(value) => value.to_string().into_bytes(),
+        None => return Err(ApiError::MissingParameter { name: "body" }),
+    };
+    Ok((secret, body, signature))
+}
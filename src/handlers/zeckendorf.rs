@@ -0,0 +1,100 @@
+//! The Zeckendorf representation endpoint.
+
+use super::guarded;
+use crate::caching;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::fib;
+use crate::negotiation::Format;
+use crate::params::{Natural, Params};
+use crate::response::{self, Meta, Payload};
+use http::Method;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use serde_json::json;
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// `GET /api/zeckendorf?x=100`: writes x as a sum of non-consecutive
+/// Fibonacci numbers, e.g. 100 = F(11) + F(6) + F(4) = 89 + 8 + 3.
+///
+/// The largest term may not exceed F(`MAX_FIB_N`). Responses are cacheable.
+pub async fn zeckendorf(req: Request) -> Result<Response<Body>, Error> {
+    guarded("zeckendorf", &[Method::GET], req, handle).await
+}
+
+async fn handle(req: Request) -> Result<Response<Body>, Error> {
+    let parsed = Format::from_request(&req).and_then(|format| Ok((format, resolve(&req)?)));
+    let (format, x) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            tracing::info!(error = %err, "request rejected");
+            return err.into_response();
+        }
+    };
+
+    let etag = caching::etag("zeckendorf", &format!("x={}", x), format);
+    if caching::is_fresh(&req, &etag) {
+        tracing::debug!(%etag, "etag still fresh, skipping computation");
+        return caching::not_modified(&etag);
+    }
+
+    let terms = fib::zeckendorf(&x);
+    let max_n = Config::get().max_n;
+    if let Some(&(largest, _)) = terms.first().filter(|(k, _)| *k > max_n) {
+        let err = ApiError::OutOfRange {
+            name: "x",
+            received: format!("a value needing F({})", largest),
+            max: max_n,
+        };
+        tracing::info!(error = %err, "request rejected");
+        return err.into_response();
+    }
+
+    let response = if req.method() == Method::HEAD {
+        response::head(StatusCode::OK, format)?
+    } else {
+        response::render(StatusCode::OK, format, zeckendorf_body(&x, terms))?
+    };
+    caching::apply(response, &etag)
+}
+
+fn resolve(req: &Request) -> Result<BigUint, ApiError> {
+    #[derive(Deserialize)]
+    struct Target {
+        x: Natural,
+    }
+
+    let Target { x: Natural(x) } = Params::from_query(req.uri().query().unwrap_or("")).parse()?;
+    tracing::debug!(bits = x.bits(), "zeckendorf representation requested");
+    Ok(x)
+}
+
+fn zeckendorf_body(x: &BigUint, terms: Vec<(u64, BigUint)>) -> Payload {
+    let terms: Vec<(u64, String)> = terms
+        .into_iter()
+        .map(|(k, value)| (k, value.to_string()))
+        .collect();
+    let json_terms: Vec<_> = terms
+        .iter()
+        .map(|(k, value)| json!({ "n": k, "fibonacci": value }))
+        .collect();
+    let rows: Vec<_> = terms
+        .into_iter()
+        .map(|(k, value)| (k.to_string(), value))
+        .collect();
+
+    let body = json!({
+        "x": x.to_string(),
+        "terms": json_terms,
+        "count": rows.len(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "status": "success",
+    });
+
+    Payload {
+        json: body,
+        rows,
+        label: "fibonacci",
+        meta: Meta::new("greedy"),
+    }
+}
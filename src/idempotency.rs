@@ -0,0 +1,469 @@
+//! Replaying POST responses for a repeated `Idempotency-Key`.
+//!
+//! A client that retries a POST after a timeout cannot tell whether the first
+//! attempt ran. Sending the same `Idempotency-Key` header on every attempt
+//! makes the API answer the retries with the first response instead of
+//! computing again (or, for jobs, starting a second one). Responses are kept
+//! for `IDEMPOTENCY_TTL_SECONDS` in process memory and, when one is
+//! configured, in the persistent [`store`], so a retry landing on another
+//! instance is deduplicated too.
+//!
+//! Keys are scoped to the client and the path. Reusing a key for a different
+//! request answers `422`; server errors are not kept, so those can be retried.
+//! The slot is reserved before the request runs, in process memory and with
+//! `SET NX` in the store, so a retry arriving while the first attempt is
+//! still running answers `409` instead of running alongside it.
+
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::version::ApiVersion;
+use crate::{middleware, ratelimit, signing, store};
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// The request header naming the operation.
+pub const HEADER: &str = "idempotency-key";
+
+/// Response header marking a replayed response.
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest key accepted.
+pub const MAX_KEY_LEN: usize = 255;
+
+// Responses kept in process memory
+const CACHE_CAPACITY: usize = 1024;
+
+// How long a reservation outlives an instance that died holding it; longer
+// than any request may run
+const PENDING_TTL_SECS: u64 = 60;
+
+/// The `Retry-After` sent while a request with the same key is running.
+pub const IN_FLIGHT_RETRY_SECS: u64 = 1;
+
+// What the store holds under a reserved slot until the response is recorded;
+// no encoded response is this short
+const PENDING_MARKER: &[u8] = b"";
+
+// Headers that describe one connection rather than the response (RFC 9110
+// §7.6.1), so they are not replayed
+const HOP_BY_HOP: [HeaderName; 8] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// A response kept for replay, with the digest of the request that produced
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recorded {
+    pub fingerprint: [u8; 32],
+    pub status: u16,
+    /// Every header of the response but the hop-by-hop ones.
+    pub headers: HeaderMap,
+    pub body: Body,
+}
+
+impl Recorded {
+    /// Keeps `response` for replay, or `None` for server errors.
+    pub fn of(fingerprint: [u8; 32], response: &Response<Body>) -> Option<Recorded> {
+        if response.status().is_server_error() {
+            return None;
+        }
+        // So are the headers the Connection header names
+        let named: Vec<HeaderName> = response
+            .headers()
+            .get_all(header::CONNECTION)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+            .collect();
+        let mut headers = HeaderMap::new();
+        for (name, value) in response.headers() {
+            if !HOP_BY_HOP.contains(name) && !named.contains(name) && name != REPLAYED_HEADER {
+                headers.append(name, value.clone());
+            }
+        }
+        Some(Recorded {
+            fingerprint,
+            status: response.status().as_u16(),
+            headers,
+            body: response.body().clone(),
+        })
+    }
+
+    /// Serializes for the persistent store: the fingerprint, the status, the
+    /// body kind, the number of headers, each header's name and value behind
+    /// their lengths, then the body.
+    ///
+    /// The lengths take two bytes, so a header longer than that (or any past
+    /// the 65535th) is left out of the stored copy rather than written in a
+    /// form that could not be read back.
+    pub fn encode(&self) -> Vec<u8> {
+        let (kind, body): (u8, &[u8]) = match &self.body {
+            Body::Empty => (0, &[]),
+            Body::Text(text) => (1, text.as_bytes()),
+            Body::Binary(bytes) => (2, bytes),
+        };
+        let fits = |part: &[u8]| u16::try_from(part.len()).is_ok();
+        let headers: Vec<(&[u8], &[u8])> = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str().as_bytes(), value.as_bytes()))
+            .filter(|&(name, value)| fits(name) && fits(value))
+            .take(usize::from(u16::MAX))
+            .collect();
+        if headers.len() < self.headers.len() {
+            tracing::warn!(
+                skipped = self.headers.len() - headers.len(),
+                "headers too long to store for replay"
+            );
+        }
+        let mut out = Vec::with_capacity(37 + 64 * headers.len() + body.len());
+        out.extend_from_slice(&self.fingerprint);
+        out.extend_from_slice(&self.status.to_be_bytes());
+        out.push(kind);
+        out.extend_from_slice(&(headers.len() as u16).to_be_bytes());
+        for (name, value) in headers {
+            for part in [name, value] {
+                out.extend_from_slice(&(part.len() as u16).to_be_bytes());
+                out.extend_from_slice(part);
+            }
+        }
+        out.extend_from_slice(body);
+        out
+    }
+
+    /// Reads back what [`encode`](Self::encode) wrote, `None` if it is not that.
+    pub fn decode(bytes: &[u8]) -> Option<Recorded> {
+        let (fingerprint, rest) = bytes.split_first_chunk::<32>()?;
+        let (status, rest) = rest.split_first_chunk::<2>()?;
+        let (&kind, rest) = rest.split_first()?;
+        let (count, mut rest) = rest.split_first_chunk::<2>()?;
+        let mut headers = HeaderMap::new();
+        for _ in 0..u16::from_be_bytes(*count) {
+            let (name, after) = length_prefixed(rest)?;
+            let (value, after) = length_prefixed(after)?;
+            headers.append(HeaderName::from_bytes(name).ok()?, HeaderValue::from_bytes(value).ok()?);
+            rest = after;
+        }
+        let body = match kind {
+            0 => Body::Empty,
+            1 => Body::Text(String::from_utf8(rest.to_vec()).ok()?),
+            2 => Body::Binary(rest.to_vec()),
+            _ => return None,
+        };
+        Some(Recorded {
+            fingerprint: *fingerprint,
+            status: u16::from_be_bytes(*status),
+            headers,
+            body,
+        })
+    }
+
+    /// The response to send again, marked with [`REPLAYED_HEADER`].
+    pub fn replay(self) -> Result<Response<Body>, Error> {
+        let mut response = Response::builder()
+            .status(StatusCode::from_u16(self.status)?)
+            .body(self.body)?;
+        *response.headers_mut() = self.headers;
+        response
+            .headers_mut()
+            .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+        Ok(response)
+    }
+}
+
+// Splits a part written behind its 2-byte length off the front of `bytes`
+fn length_prefixed(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (length, rest) = bytes.split_first_chunk::<2>()?;
+    let length = usize::from(u16::from_be_bytes(*length));
+    Some((rest.get(..length)?, &rest[length..]))
+}
+
+/// The request's `Idempotency-Key`, `None` without one.
+pub fn key(req: &Request) -> Result<Option<&str>, ApiError> {
+    let Some(value) = req.headers().get(HEADER) else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => Ok(Some(key)),
+        _ => Err(ApiError::InvalidParameter {
+            name: "Idempotency-Key",
+            received: String::from_utf8_lossy(value.as_bytes()).chars().take(MAX_KEY_LEN).collect(),
+            expected: "1 to 255 visible ASCII characters".into(),
+        }),
+    }
+}
+
+/// Where the response to `key` is kept: the key scoped to the client and the
+/// path, hashed so the store never sees either.
+pub fn slot(req: &Request, key: &str) -> String {
+    let path = middleware::request_path().unwrap_or_else(|| req.uri().path().to_string());
+    let scope = [ratelimit::client_key(req).as_str(), path.as_str(), key].join("\n");
+    format!("idem:{}", signing::hex(&Sha256::digest(scope.as_bytes())))
+}
+
+/// Digest of what the request asks for: its method, API version, path,
+/// query, `Content-Type` and body.
+pub fn fingerprint(req: &Request) -> [u8; 32] {
+    let path = middleware::request_path().unwrap_or_else(|| req.uri().path().to_string());
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    for part in [
+        req.method().as_str(),
+        ApiVersion::of(req).as_str(),
+        path.trim_end_matches('/'),
+        req.uri().query().unwrap_or(""),
+        &content_type,
+    ] {
+        hasher.update(part);
+        hasher.update(b"\n");
+    }
+    hasher.update(req.body().as_ref());
+    hasher.finalize().into()
+}
+
+/// What to do with a request whose key maps to a slot, see [`begin`].
+#[derive(Debug)]
+pub enum Begin {
+    /// A response was recorded for the key; answer with it.
+    Replay(Recorded),
+    /// A request with the key is running; answer `409`.
+    InFlight,
+    /// The slot is now held for this request, until it is finished.
+    Reserved(Reservation),
+}
+
+/// Claims `slot` for the request about to run, unless a response is already
+/// recorded under it or another request holds it. `None` when replay is
+/// disabled with `IDEMPOTENCY_TTL_SECONDS=0`.
+pub async fn begin(slot: &str) -> Option<Begin> {
+    ttl()?;
+    {
+        let mut slots = lock(slots());
+        if let Some((expires, recorded)) = slots.recorded.get(slot) {
+            if Instant::now() < *expires {
+                return Some(Begin::Replay(recorded.clone()));
+            }
+        }
+        if !slots.pending.insert(slot.to_string()) {
+            return Some(Begin::InFlight);
+        }
+    }
+    let reservation = Reservation {
+        slot: slot.to_string(),
+        held: true,
+    };
+    // Another instance may hold the slot or have recorded it already
+    if store::put_if_absent(slot, PENDING_MARKER, PENDING_TTL_SECS).await != Some(false) {
+        return Some(Begin::Reserved(reservation));
+    }
+    drop(reservation);
+    let Some((bytes, remaining)) = store::get_with_ttl(slot).await else {
+        return Some(Begin::InFlight);
+    };
+    match Recorded::decode(&bytes) {
+        Some(recorded) => {
+            // Kept only as long as the stored copy, so every hit doesn't restart the TTL
+            let expires = Instant::now() + remaining;
+            lock(slots()).recorded.put(slot.to_string(), (expires, recorded.clone()));
+            Some(Begin::Replay(recorded))
+        }
+        None => Some(Begin::InFlight),
+    }
+}
+
+/// A slot held by the request [`begin`] let through. Dropping it without
+/// [`finish`](Self::finish)ing releases the slot in process memory; the
+/// store's reservation then expires on its own.
+#[derive(Debug)]
+pub struct Reservation {
+    slot: String,
+    held: bool,
+}
+
+impl Reservation {
+    /// Keeps `recorded` under the slot for the configured TTL, or releases
+    /// the slot when there is nothing to keep, so the key can be retried.
+    pub async fn finish(mut self, recorded: Option<Recorded>) {
+        self.held = false;
+        let ttl = ttl().unwrap_or_default();
+        let encoded = recorded.as_ref().map(Recorded::encode);
+        {
+            let mut slots = lock(slots());
+            slots.pending.remove(&self.slot);
+            if let Some(recorded) = recorded {
+                slots.recorded.put(self.slot.clone(), (Instant::now() + ttl, recorded));
+            }
+        }
+        // A response too large for the store must not leave the reservation behind
+        match encoded.filter(|encoded| encoded.len() as u64 <= Config::get().kv_max_value_bytes) {
+            Some(encoded) => store::put_for(&self.slot, &encoded, ttl.as_secs()).await,
+            None => store::delete(&self.slot).await,
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.held {
+            lock(slots()).pending.remove(&self.slot);
+        }
+    }
+}
+
+// None when replay is disabled with IDEMPOTENCY_TTL_SECONDS=0
+fn ttl() -> Option<Duration> {
+    let secs = Config::get().idempotency_ttl_secs;
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+// The responses kept in process memory, each with when it expires, and the
+// slots held by requests still running
+struct Slots {
+    recorded: LruCache<String, (Instant, Recorded)>,
+    pending: HashSet<String>,
+}
+
+fn slots() -> &'static Mutex<Slots> {
+    static SLOTS: OnceLock<Mutex<Slots>> = OnceLock::new();
+    SLOTS.get_or_init(|| {
+        Mutex::new(Slots {
+            recorded: LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap_or(NonZeroUsize::MIN)),
+            pending: HashSet::new(),
+        })
+    })
+}
+
+// A panic while holding the lock cannot leave the map half-updated, so a
+// poisoned lock is still safe to use
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_responses_round_trip() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.append(header::VARY, HeaderValue::from_static("Accept"));
+        headers.append(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+        for body in [Body::Empty, Body::Text("{\"n\":1}".to_string()), Body::Binary(vec![0x81, 0xa1])] {
+            let recorded = Recorded {
+                fingerprint: [7; 32],
+                status: 200,
+                headers: headers.clone(),
+                body,
+            };
+            assert_eq!(Recorded::decode(&recorded.encode()), Some(recorded));
+        }
+        assert_eq!(Recorded::decode(b"short"), None);
+    }
+
+    #[test]
+    fn headers_too_long_to_store_are_left_out() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        headers.insert("x-huge", HeaderValue::from_bytes(&vec![b'a'; 70_000]).unwrap());
+        let recorded = Recorded {
+            fingerprint: [1; 32],
+            status: 200,
+            headers,
+            body: Body::Text("ok".to_string()),
+        };
+        let decoded = Recorded::decode(&recorded.encode()).unwrap();
+        assert_eq!(decoded.headers.len(), 1);
+        assert_eq!(decoded.headers[header::CONTENT_TYPE], "text/plain");
+        assert_eq!(decoded.body, recorded.body);
+    }
+
+    #[test]
+    fn fingerprints_cover_the_path_version_and_content_type() {
+        let req = |uri: &str, content_type: &str| {
+            let mut req = http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Body::Text("{}".to_string()))
+                .unwrap();
+            crate::version::select(&mut req);
+            req
+        };
+        let json = fingerprint(&req("/api/batch", "application/json"));
+        assert_eq!(json, fingerprint(&req("/api/batch/", "Application/JSON")));
+        assert_ne!(json, fingerprint(&req("/api/batch", "text/plain")));
+        assert_ne!(json, fingerprint(&req("/api/v2/batch", "application/json")));
+        assert_ne!(json, fingerprint(&req("/api/eval", "application/json")));
+    }
+
+    #[tokio::test]
+    async fn slots_are_held_while_the_request_runs() {
+        let slot = "idem:test-held";
+        let Some(Begin::Reserved(first)) = begin(slot).await else {
+            panic!("expected a reservation");
+        };
+        assert!(matches!(begin(slot).await, Some(Begin::InFlight)));
+
+        // Nothing to keep, so the key can be tried again
+        first.finish(None).await;
+        let Some(Begin::Reserved(second)) = begin(slot).await else {
+            panic!("expected a reservation");
+        };
+        let recorded = Recorded {
+            fingerprint: [2; 32],
+            status: 202,
+            headers: HeaderMap::new(),
+            body: Body::Empty,
+        };
+        second.finish(Some(recorded.clone())).await;
+        assert!(matches!(begin(slot).await, Some(Begin::Replay(replayed)) if replayed == recorded));
+
+        // A request that never finishes doesn't hold the slot forever
+        drop(begin("idem:test-dropped").await);
+        assert!(matches!(begin("idem:test-dropped").await, Some(Begin::Reserved(_))));
+    }
+
+    #[test]
+    fn replays_every_end_to_end_header() {
+        let response = Response::builder()
+            .status(StatusCode::CREATED)
+            .header(header::CONTENT_TYPE, "text/csv")
+            .header(header::LOCATION, "/api/jobs/1")
+            .header(header::ETAG, "\"abc\"")
+            .header(header::CONNECTION, "close, x-trace")
+            .header("x-trace", "1")
+            .header(header::TRANSFER_ENCODING, "chunked")
+            .body(Body::Text("n,fibonacci".to_string()))
+            .unwrap();
+        let replayed = Recorded::of([0; 32], &response).unwrap().replay().unwrap();
+        assert_eq!(replayed.status(), StatusCode::CREATED);
+        let headers = replayed.headers();
+        assert_eq!(headers[header::CONTENT_TYPE], "text/csv");
+        assert_eq!(headers[header::LOCATION], "/api/jobs/1");
+        assert_eq!(headers[header::ETAG], "\"abc\"");
+        assert_eq!(headers[REPLAYED_HEADER], "true");
+        for name in ["connection", "x-trace", "transfer-encoding"] {
+            assert!(!headers.contains_key(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Shared computation and HTTP plumbing for the Fibonacci API functions.
+//!
+//! Each file under `api/` is a thin Vercel entry point that delegates to a
+//! handler defined in [`handlers`].
+
+pub mod auth;
+pub mod benchmark;
+pub mod budget;
+pub mod build_info;
+pub mod caching;
+pub mod combinatorics;
+pub mod compression;
+pub mod config;
+pub mod cors;
+pub mod error;
+pub mod expr;
+pub mod fib;
+pub mod format;
+pub mod golden;
+pub mod graphql;
+pub mod handlers;
+pub mod idempotency;
+pub mod lucas;
+pub mod memo;
+pub mod metrics;
+pub mod middleware;
+pub mod negotiation;
+pub mod numtheory;
+pub mod openapi;
+pub mod otlp;
+pub mod parallel;
+pub mod params;
+pub mod primes;
+pub mod profile;
+pub mod query;
+pub mod radix;
+pub mod random;
+pub mod ratelimit;
+pub mod response;
+pub mod router;
+pub mod routing;
+pub mod security;
+pub mod sequences;
+pub mod signing;
+pub mod store;
+pub mod streaming;
+pub mod table;
+pub mod telemetry;
+pub mod usage;
+pub mod version;
+pub mod webhook;
@@ -0,0 +1,105 @@
+//! Lucas sequences U_n(P, Q) and V_n(P, Q).
+//!
+//! Both satisfy x(n+2) = P·x(n+1) − Q·x(n), with U starting 0, 1 and V
+//! starting 2, P. Fibonacci numbers are U_n(1, −1), Lucas numbers are
+//! V_n(1, −1), and Pell numbers are U_n(2, −1).
+
+use crate::budget::{Deadline, Interrupted};
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+
+/// The parameters (P, Q) of a Lucas sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LucasParams {
+    pub p: i64,
+    pub q: i64,
+}
+
+impl LucasParams {
+    /// P = 1, Q = −1: U is the Fibonacci sequence and V the Lucas numbers.
+    pub const FIBONACCI: LucasParams = LucasParams { p: 1, q: -1 };
+
+    /// Returns (U_n, V_n) in O(log n) big-integer multiplications.
+    ///
+    /// Walks the bits of `n` from the most significant end, maintaining
+    /// (U_k, V_k, Q^k) and applying the doubling identities
+    /// U_2k = U_k·V_k, V_2k = V_k² − 2Q^k, and the step
+    /// U_k+1 = (P·U_k + V_k) / 2, V_k+1 = (D·U_k + P·V_k) / 2 with D = P² − 4Q.
+    pub fn terms(self, n: u64) -> (BigInt, BigInt) {
+        match self.terms_within(n, &Deadline::none()) {
+            Ok(terms) => terms,
+            Err(_) => unreachable!("an unbounded deadline never expires"),
+        }
+    }
+
+    /// Like [`terms`](Self::terms), but checks `deadline` before every
+    /// doubling step and stops once it has passed.
+    pub fn terms_within(self, n: u64, deadline: &Deadline) -> Result<(BigInt, BigInt), Interrupted> {
+        let p = BigInt::from(self.p);
+        let q = BigInt::from(self.q);
+        let d = &p * &p - 4 * &q;
+
+        let mut u = BigInt::zero(); // U_k
+        let mut v = BigInt::from(2); // V_k
+        let mut q_k = BigInt::one(); // Q^k
+
+        let steps = u64::BITS - n.leading_zeros();
+        for bit in (0..steps).rev() {
+            if deadline.expired() {
+                return Err(Interrupted {
+                    done: u64::from(steps - 1 - bit),
+                    total: u64::from(steps),
+                });
+            }
+            u = &u * &v;
+            v = &v * &v - (&q_k << 1u32);
+            q_k = &q_k * &q_k;
+            if (n >> bit) & 1 == 1 {
+                // Both numerators are always even, so the shifts are exact
+                let next_u = (&p * &u + &v) >> 1u32;
+                v = (&d * &u + &p * &v) >> 1u32;
+                u = next_u;
+                q_k *= &q;
+            }
+        }
+
+        Ok((u, v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference terms straight from the recurrence
+    fn by_recurrence(params: LucasParams, n: u64) -> (BigInt, BigInt) {
+        let (p, q) = (BigInt::from(params.p), BigInt::from(params.q));
+        let (mut u0, mut u1) = (BigInt::zero(), BigInt::one());
+        let (mut v0, mut v1) = (BigInt::from(2), p.clone());
+        for _ in 0..n {
+            let u2 = &p * &u1 - &q * &u0;
+            let v2 = &p * &v1 - &q * &v0;
+            u0 = std::mem::replace(&mut u1, u2);
+            v0 = std::mem::replace(&mut v1, v2);
+        }
+        (u0, v0)
+    }
+
+    #[test]
+    fn matches_the_recurrence() {
+        let cases = [(1, -1), (2, -1), (3, 2), (-2, 5), (0, 3), (7, 0)];
+        for (p, q) in cases {
+            let params = LucasParams { p, q };
+            for n in 0..=60 {
+                assert_eq!(params.terms(n), by_recurrence(params, n), "P={} Q={} n={}", p, q, n);
+            }
+        }
+    }
+
+    #[test]
+    fn lucas_numbers() {
+        let lucas: Vec<_> = (0..10).map(|n| LucasParams::FIBONACCI.terms(n).1).collect();
+        let expected: Vec<BigInt> = [2, 1, 3, 4, 7, 11, 18, 29, 47, 76].into_iter().map(BigInt::from).collect();
+        assert_eq!(lucas, expected);
+    }
+}
@@ -0,0 +1,146 @@
+//! In-process memoization of computed Fibonacci numbers.
+//!
+//! The cache lives for as long as the warm instance does, so repeated requests
+//! for the same index within its lifetime skip the computation entirely.
+//! [`fibonacci_persisted`] also consults the optional [`store`] shared by every
+//! instance.
+
+use crate::budget::{Deadline, Interrupted};
+use crate::config::Config;
+use crate::fib::{self, calculate_fibonacci};
+use crate::{metrics, otlp, store};
+use lru::LruCache;
+use num_bigint::BigUint;
+use serde_json::json;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use vercel_runtime::Error;
+
+/// Whether a value was served from the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    Hit,
+    /// Not in process memory, but read back from the persistent store.
+    Stored,
+    Miss,
+}
+
+impl CacheStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Stored => "store",
+            CacheStatus::Miss => "miss",
+        }
+    }
+
+    /// One status for several lookups: `hit` when every value was cached
+    /// somewhere, `miss` when none was, otherwise `partial`.
+    pub fn summarize(statuses: &[CacheStatus]) -> &'static str {
+        let cached = statuses.iter().filter(|&&status| status != CacheStatus::Miss).count();
+        match cached {
+            0 => "miss",
+            cached if cached == statuses.len() => "hit",
+            _ => "partial",
+        }
+    }
+}
+
+/// Returns F(n), computing and remembering it on a miss.
+///
+/// The lock is not held while computing, so concurrent misses for the same
+/// index may both compute it; the result is identical either way.
+pub fn fibonacci(n: u64) -> (Arc<BigUint>, CacheStatus) {
+    let Some(cache) = cache() else {
+        metrics::record_cache(false);
+        return (Arc::new(calculate_fibonacci(n)), CacheStatus::Miss);
+    };
+
+    if let Some(value) = lock(cache).get(&n) {
+        metrics::record_cache(true);
+        return (Arc::clone(value), CacheStatus::Hit);
+    }
+
+    metrics::record_cache(false);
+    let value = Arc::new(calculate_fibonacci(n));
+    lock(cache).put(n, Arc::clone(&value));
+    (value, CacheStatus::Miss)
+}
+
+/// Like [`fibonacci`], but checks the persistent store between the
+/// in-process cache and the computation, and writes fresh results to it.
+///
+/// The computation runs on the blocking thread pool and stops at
+/// `deadline`; cached values are returned however late it is.
+pub async fn fibonacci_persisted(
+    n: u64,
+    deadline: Deadline,
+) -> Result<Result<(Arc<BigUint>, CacheStatus), Interrupted>, Error> {
+    let key = format!("fib:{}", n);
+    if let Some(value) = cache().and_then(|cache| lock(cache).get(&n).cloned()) {
+        metrics::record_cache(true);
+        return Ok(Ok((value, CacheStatus::Hit)));
+    }
+    metrics::record_cache(false);
+
+    if let Some(bytes) = store::get(&key).await {
+        let value = Arc::new(BigUint::from_bytes_le(&bytes));
+        remember(n, &value);
+        return Ok(Ok((value, CacheStatus::Stored)));
+    }
+
+    let start = SystemTime::now();
+    let computed = tokio::task::spawn_blocking(move || fib::fast_doubling_within(n, &deadline)).await?;
+    otlp::child_span(
+        format!("F({})", n),
+        start,
+        vec![("fib.n", json!(n)), ("fib.algorithm", json!("fast-doubling"))],
+    );
+    let value = match computed {
+        Ok(value) => Arc::new(value),
+        Err(interrupted) => return Ok(Err(interrupted)),
+    };
+    remember(n, &value);
+    store::put(&key, &value.to_bytes_le()).await;
+    Ok(Ok((value, CacheStatus::Miss)))
+}
+
+fn remember(n: u64, value: &Arc<BigUint>) {
+    if let Some(cache) = cache() {
+        lock(cache).put(n, Arc::clone(value));
+    }
+}
+
+type Cache = Mutex<LruCache<u64, Arc<BigUint>>>;
+
+// None when the cache is disabled with MEMO_CACHE_SIZE=0
+fn cache() -> Option<&'static Cache> {
+    static CACHE: OnceLock<Option<Cache>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| {
+            let capacity = usize::try_from(Config::get().memo_cache_size).unwrap_or(usize::MAX);
+            NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity)))
+        })
+        .as_ref()
+}
+
+// A panic while holding the lock cannot leave the map half-updated, so a
+// poisoned lock is still safe to use
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_lookup_is_a_hit() {
+        let (first, _) = fibonacci(321);
+        let (second, status) = fibonacci(321);
+        assert_eq!(status, CacheStatus::Hit);
+        assert_eq!(first, second);
+        assert_eq!(*second, calculate_fibonacci(321));
+    }
+}
@@ -0,0 +1,235 @@
+//! Process-level request metrics in the Prometheus text exposition format.
+//!
+//! Counters are plain atomics that live as long as the warm instance, so a
+//! scrape reports everything that instance served since its cold start.
+
+use crate::build_info;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use vercel_runtime::{Body, Error, Response};
+
+/// Upper bounds (in seconds) of the latency histogram buckets.
+pub const LATENCY_BUCKETS: [f64; 10] = [0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// Labels of the status classes counted per endpoint.
+pub const STATUS_CLASSES: [&str; 5] = ["1xx", "2xx", "3xx", "4xx", "5xx"];
+
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static STORE_HITS: AtomicU64 = AtomicU64::new(0);
+static STORE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Counters for a single endpoint.
+#[derive(Default)]
+pub struct EndpointMetrics {
+    by_status_class: [AtomicU64; 5],
+    // Non-cumulative counts per bucket; the last slot is +Inf
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    latency_micros_sum: AtomicU64,
+}
+
+impl EndpointMetrics {
+    fn observe(&self, status: u16, elapsed: Duration) {
+        let class = usize::from(status / 100).clamp(1, 5) - 1;
+        self.by_status_class[class].fetch_add(1, Ordering::Relaxed);
+
+        let secs = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| secs <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency_micros_sum
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn requests(&self) -> u64 {
+        self.by_status_class
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+// Endpoint entries are created on first use and never removed, so leaking
+// them gives every caller a plain `&'static` to update without locking
+fn endpoints() -> &'static Mutex<BTreeMap<&'static str, &'static EndpointMetrics>> {
+    static ENDPOINTS: OnceLock<Mutex<BTreeMap<&'static str, &'static EndpointMetrics>>> =
+        OnceLock::new();
+    ENDPOINTS.get_or_init(Default::default)
+}
+
+fn endpoint(name: &'static str) -> &'static EndpointMetrics {
+    let mut endpoints = endpoints().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    endpoints
+        .entry(name)
+        .or_insert_with(|| Box::leak(Box::default()))
+}
+
+/// One endpoint's counters at a point in time, for exporters such as
+/// [`otlp`](crate::otlp).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointSnapshot {
+    pub name: &'static str,
+    /// Requests per entry of [`STATUS_CLASSES`].
+    pub by_status_class: [u64; 5],
+    /// Non-cumulative counts per [`LATENCY_BUCKETS`] bound, then above the last.
+    pub latency_buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    pub latency_sum_secs: f64,
+}
+
+impl EndpointSnapshot {
+    pub fn requests(&self) -> u64 {
+        self.by_status_class.iter().sum()
+    }
+}
+
+/// Reads every endpoint's counters.
+pub fn snapshot() -> Vec<EndpointSnapshot> {
+    let endpoints = endpoints().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+    endpoints
+        .iter()
+        .map(|(name, metrics)| EndpointSnapshot {
+            name,
+            by_status_class: metrics.by_status_class.each_ref().map(load),
+            latency_buckets: metrics.latency_buckets.each_ref().map(load),
+            latency_sum_secs: load(&metrics.latency_micros_sum) as f64 / 1e6,
+        })
+        .collect()
+}
+
+/// Records one request to `name` that finished with `status`.
+pub fn record(name: &'static str, status: u16, elapsed: Duration) {
+    endpoint(name).observe(status, elapsed);
+}
+
+/// Records a lookup in the in-process result cache.
+pub fn record_cache(hit: bool) {
+    let counter = if hit { &CACHE_HITS } else { &CACHE_MISSES };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Records a read from the persistent cache.
+pub fn record_store(hit: bool) {
+    let counter = if hit { &STORE_HITS } else { &STORE_MISSES };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Awaits `handler` and records its status and latency under `name`.
+/// A handler error counts as a 500, which is what the runtime will report.
+pub async fn track<Fut>(name: &'static str, handler: Fut) -> Result<Response<Body>, Error>
+where
+    Fut: Future<Output = Result<Response<Body>, Error>>,
+{
+    let start = Instant::now();
+    let result = handler.await;
+    let status = match &result {
+        Ok(response) => response.status().as_u16(),
+        Err(_) => 500,
+    };
+    record(name, status, start.elapsed());
+    result
+}
+
+/// Renders every metric in the Prometheus text format (version 0.0.4).
+pub fn render() -> String {
+    let endpoints = endpoints().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut out = String::new();
+
+    out.push_str("# HELP fib_requests_total Requests handled, by endpoint and status class.\n");
+    out.push_str("# TYPE fib_requests_total counter\n");
+    for (name, metrics) in endpoints.iter() {
+        for (class, count) in STATUS_CLASSES.iter().zip(&metrics.by_status_class) {
+            let count = count.load(Ordering::Relaxed);
+            if count > 0 {
+                let _ = writeln!(
+                    out,
+                    "fib_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}",
+                    name, class, count
+                );
+            }
+        }
+    }
+
+    out.push_str("# HELP fib_errors_total Requests that finished with a 4xx or 5xx status.\n");
+    out.push_str("# TYPE fib_errors_total counter\n");
+    for (name, metrics) in endpoints.iter() {
+        let errors: u64 = metrics.by_status_class[3..]
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum();
+        let _ = writeln!(out, "fib_errors_total{{endpoint=\"{}\"}} {}", name, errors);
+    }
+
+    out.push_str("# HELP fib_request_duration_seconds Request latency.\n");
+    out.push_str("# TYPE fib_request_duration_seconds histogram\n");
+    for (name, metrics) in endpoints.iter() {
+        let mut cumulative = 0;
+        for (i, count) in metrics.latency_buckets.iter().enumerate() {
+            cumulative += count.load(Ordering::Relaxed);
+            let bound = LATENCY_BUCKETS
+                .get(i)
+                .map(|bound| bound.to_string())
+                .unwrap_or_else(|| "+Inf".to_string());
+            let _ = writeln!(
+                out,
+                "fib_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}",
+                name, bound, cumulative
+            );
+        }
+        let sum = metrics.latency_micros_sum.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "fib_request_duration_seconds_sum{{endpoint=\"{}\"}} {}", name, sum);
+        let _ = writeln!(
+            out,
+            "fib_request_duration_seconds_count{{endpoint=\"{}\"}} {}",
+            name,
+            metrics.requests()
+        );
+    }
+
+    out.push_str("# HELP fib_cache_hits_total In-process result cache hits.\n");
+    out.push_str("# TYPE fib_cache_hits_total counter\n");
+    let _ = writeln!(out, "fib_cache_hits_total {}", CACHE_HITS.load(Ordering::Relaxed));
+    out.push_str("# HELP fib_cache_misses_total In-process result cache misses.\n");
+    out.push_str("# TYPE fib_cache_misses_total counter\n");
+    let _ = writeln!(out, "fib_cache_misses_total {}", CACHE_MISSES.load(Ordering::Relaxed));
+
+    out.push_str("# HELP fib_store_hits_total Persistent cache hits.\n");
+    out.push_str("# TYPE fib_store_hits_total counter\n");
+    let _ = writeln!(out, "fib_store_hits_total {}", STORE_HITS.load(Ordering::Relaxed));
+    out.push_str("# HELP fib_store_misses_total Persistent cache misses, including errors.\n");
+    out.push_str("# TYPE fib_store_misses_total counter\n");
+    let _ = writeln!(out, "fib_store_misses_total {}", STORE_MISSES.load(Ordering::Relaxed));
+
+    out.push_str("# HELP fib_uptime_seconds Time since this instance served its first request.\n");
+    out.push_str("# TYPE fib_uptime_seconds gauge\n");
+    let _ = writeln!(out, "fib_uptime_seconds {}", build_info::uptime().as_secs_f64());
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_and_histogram() {
+        record("metrics_test", 200, Duration::from_micros(200));
+        record("metrics_test", 422, Duration::from_millis(20));
+        record("metrics_test", 200, Duration::from_secs(10));
+
+        let text = render();
+        assert!(text.contains("fib_requests_total{endpoint=\"metrics_test\",status=\"2xx\"} 2"));
+        assert!(text.contains("fib_requests_total{endpoint=\"metrics_test\",status=\"4xx\"} 1"));
+        assert!(text.contains("fib_errors_total{endpoint=\"metrics_test\"} 1"));
+        assert!(text.contains("fib_request_duration_seconds_bucket{endpoint=\"metrics_test\",le=\"0.0005\"} 1"));
+        assert!(text.contains("fib_request_duration_seconds_bucket{endpoint=\"metrics_test\",le=\"0.025\"} 2"));
+        assert!(text.contains("fib_request_duration_seconds_bucket{endpoint=\"metrics_test\",le=\"+Inf\"} 3"));
+        assert!(text.contains("fib_request_duration_seconds_count{endpoint=\"metrics_test\"} 3"));
+    }
+}
@@ -0,0 +1,604 @@
+//! Cross-cutting request handling as composable layers.
+//!
+//! A [`Pipeline`] wraps an endpoint handler in a list of [`Layer`]s, outermost
+//! first. Each layer receives the request and a [`Next`] that runs the rest of
+//! the pipeline, so it can answer on its own, adjust the request, or post-process
+//! the response, in the same spirit as tower's `Layer`/`Service` without the
+//! dependency.
+
+use crate::build_info;
+use crate::compression;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::response::Shape;
+use crate::version::{self, ApiVersion};
+use crate::telemetry::TraceContext;
+use crate::{auth, format, idempotency, metrics, otlp, ratelimit, signing, telemetry, usage};
+use http::header::{ACCEPT_ENCODING, ACCEPT_LANGUAGE, CONTENT_LENGTH, ORIGIN};
+use http::Method;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{field, Instrument};
+use vercel_runtime::{Body, Error, Request, Response, StatusCode};
+
+/// The future a layer or handler resolves to.
+pub type BoxFuture<'a> = Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send + 'a>>;
+
+// What the layers and handlers may want to know about the request being served
+struct RequestContext {
+    start: Instant,
+    path: String,
+    trace: TraceContext,
+}
+
+tokio::task_local! {
+    static REQUEST: RequestContext;
+}
+
+/// Time since the current request entered its pipeline, or `None` outside one.
+pub fn request_elapsed() -> Option<Duration> {
+    REQUEST.try_with(|request| request.start.elapsed()).ok()
+}
+
+/// Path of the request being served, or `None` outside a pipeline.
+pub fn request_path() -> Option<String> {
+    REQUEST.try_with(|request| request.path.clone()).ok()
+}
+
+/// Trace context of the request being served, or `None` outside a pipeline.
+pub fn trace_context() -> Option<TraceContext> {
+    REQUEST.try_with(|request| request.trace.clone()).ok()
+}
+
+type BoxHandler<'a> = Box<dyn FnOnce(Request) -> BoxFuture<'a> + Send + 'a>;
+
+/// One cross-cutting concern wrapped around a handler.
+pub trait Layer: Send + Sync {
+    /// Handles `req`, usually by awaiting `next.run(req)` somewhere inside.
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a>;
+}
+
+/// The remainder of a pipeline: the inner layers and finally the handler.
+pub struct Next<'a> {
+    layers: &'a [Arc<dyn Layer>],
+    handler: BoxHandler<'a>,
+}
+
+impl<'a> Next<'a> {
+    /// Passes `req` to the next layer, or to the handler after the last one.
+    pub fn run(self, req: Request) -> BoxFuture<'a> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => layer.call(
+                req,
+                Next {
+                    layers,
+                    handler: self.handler,
+                },
+            ),
+            None => (self.handler)(req),
+        }
+    }
+}
+
+/// An ordered stack of layers, outermost first.
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    layers: Vec<Arc<dyn Layer>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `layer` inside every layer added so far.
+    pub fn layer(mut self, layer: impl Layer + 'static) -> Self {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    /// Runs `req` through every layer and then `handler`.
+    ///
+    /// An error from the handler is answered as an [`ApiError::Internal`]
+    /// problem that still passes back through the layers; one from a layer
+    /// itself is answered the same way, just without them.
+    pub async fn run<'a, F, Fut>(&'a self, req: Request, handler: F) -> Result<Response<Body>, Error>
+    where
+        F: FnOnce(Request) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<Response<Body>, Error>> + Send + 'a,
+    {
+        let next = Next {
+            layers: &self.layers,
+            handler: Box::new(move |req| Box::pin(recover(handler(req)))),
+        };
+        recover(next.run(req)).await
+    }
+}
+
+// Replaces an opaque runtime error with a problem response, logging the
+// actual error since the client only gets a generic one
+async fn recover<Fut>(response: Fut) -> Result<Response<Body>, Error>
+where
+    Fut: Future<Output = Result<Response<Body>, Error>>,
+{
+    match response.await {
+        Ok(response) => Ok(response),
+        Err(err) => {
+            tracing::error!(error = %err, "request failed");
+            ApiError::Internal.into_response()
+        }
+    }
+}
+
+/// Runs the request inside a span carrying the request ID, `endpoint` and the
+/// W3C trace and span IDs, and logs its status and duration. Handlers add
+/// fields such as `n` with `Span::current().record(..)`.
+///
+/// The response's `traceparent` names this request's span, so the caller can
+/// link it to its own; see [`TraceContext`].
+pub struct Trace {
+    pub endpoint: &'static str,
+}
+
+impl Layer for Trace {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        let trace = TraceContext::from_headers(req.headers());
+        let span = tracing::info_span!(
+            "request",
+            request_id = %telemetry::request_id(&req),
+            trace_id = %trace.trace_id,
+            span_id = %trace.span_id,
+            parent_span_id = trace.parent_id.as_deref(),
+            endpoint = self.endpoint,
+            method = %req.method(),
+            path = req.uri().path(),
+            n = field::Empty,
+            status = field::Empty,
+            duration_ms = field::Empty,
+        );
+        let start = Instant::now();
+        let context = RequestContext {
+            start,
+            path: req.uri().path().to_string(),
+            trace: trace.clone(),
+        };
+        Box::pin(REQUEST.scope(
+            context,
+            async move {
+                let mut result = next.run(req).await;
+                let span = tracing::Span::current();
+                span.record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+                match &mut result {
+                    Ok(response) => {
+                        trace.apply(response.headers_mut());
+                        span.record("status", response.status().as_u16());
+                        tracing::info!("request completed");
+                    }
+                    Err(err) => {
+                        span.record("status", 500);
+                        tracing::error!(error = %err, "request failed");
+                    }
+                }
+                result
+            }
+            .instrument(span),
+        ))
+    }
+}
+
+/// Exports the request's spans and the metrics over OTLP before the response
+/// is returned, when `OTEL_EXPORTER_OTLP_ENDPOINT` is set; see [`otlp`].
+pub struct Exported {
+    pub endpoint: &'static str,
+}
+
+impl Layer for Exported {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        let method = req.method().to_string();
+        let path = req.uri().path().to_string();
+        Box::pin(otlp::collect(self.endpoint, method, path, next.run(req)))
+    }
+}
+
+/// Serves the request as the [`ApiVersion`] it was routed as, and names that
+/// version in the response's `X-Api-Version` header.
+pub struct Versioned;
+
+impl Layer for Versioned {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        let version = ApiVersion::of(&req);
+        Box::pin(version.scope(async move {
+            let mut response = next.run(req).await?;
+            response
+                .headers_mut()
+                .insert(version::HEADER, http::HeaderValue::from_static(version.as_str()));
+            Ok(response)
+        }))
+    }
+}
+
+/// Rejects a query string longer than `MAX_QUERY_LENGTH` with 414 and a body
+/// larger than `MAX_BODY_BYTES` with 413, before anything parses them. A
+/// `Content-Length` over the limit is rejected even if the body is shorter.
+pub struct Limits;
+
+impl Layer for Limits {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(async move {
+            let config = Config::get();
+            let query = req.uri().query().map_or(0, str::len) as u64;
+            let declared = header(&req, CONTENT_LENGTH).and_then(|length| length.trim().parse::<u64>().ok());
+            let body = declared.unwrap_or(0).max(req.body().len() as u64);
+            let err = if query > config.max_query_length {
+                ApiError::UriTooLong {
+                    size: query,
+                    max: config.max_query_length,
+                }
+            } else if body > config.max_body_bytes {
+                ApiError::PayloadTooLarge {
+                    size: body,
+                    max: config.max_body_bytes,
+                }
+            } else {
+                return next.run(req).await;
+            };
+            tracing::info!(error = %err, "request rejected");
+            err.into_response()
+        })
+    }
+}
+
+/// Serves the request with the [`Shape`] its `fields` and `pretty` query
+/// parameters ask for.
+pub struct Shaped;
+
+impl Layer for Shaped {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        let shape = Shape::from_query(req.uri().query().unwrap_or(""));
+        Box::pin(async move {
+            match shape {
+                Ok(shape) => shape.scope(next.run(req)).await,
+                Err(err) => {
+                    tracing::info!(error = %err, "request rejected");
+                    err.into_response()
+                }
+            }
+        })
+    }
+}
+
+/// Serves the request with the digit grouping its `grouped` parameter and
+/// `Accept-Language` header ask for, see [`format`].
+pub struct Localized;
+
+impl Layer for Localized {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        let accept_language = header(&req, ACCEPT_LANGUAGE);
+        let locale = format::from_request(req.uri().query().unwrap_or(""), accept_language.as_deref());
+        Box::pin(async move {
+            match locale {
+                Ok(locale) => format::scope(locale, next.run(req)).await,
+                Err(err) => {
+                    tracing::info!(error = %err, "request rejected");
+                    err.into_response()
+                }
+            }
+        })
+    }
+}
+
+/// Records the status and latency under `endpoint` in the metrics.
+pub struct Metrics {
+    pub endpoint: &'static str,
+}
+
+impl Layer for Metrics {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(metrics::track(self.endpoint, next.run(req)))
+    }
+}
+
+/// Adds the configured CORS headers for the request's `Origin`.
+pub struct Cors;
+
+impl Layer for Cors {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        let origin = header(&req, ORIGIN);
+        Box::pin(async move {
+            let mut response = next.run(req).await?;
+            Config::get().cors.apply(origin.as_deref(), response.headers_mut());
+            Ok(response)
+        })
+    }
+}
+
+/// Adds the configured security headers, see [`SecurityHeaders`](crate::security::SecurityHeaders).
+pub struct Security;
+
+impl Layer for Security {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(async move {
+            let mut response = next.run(req).await?;
+            Config::get().security_headers.apply(response.headers_mut());
+            Ok(response)
+        })
+    }
+}
+
+/// Signs the uncompressed body in `X-Signature` when
+/// `RESPONSE_SIGNING_SECRET` is set, see [`signing::canonical`].
+pub struct Signed;
+
+impl Layer for Signed {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(async move {
+            let mut response = next.run(req).await?;
+            if let Some(secret) = &Config::get().response_signing_secret {
+                if !response.body().is_empty() {
+                    let body = signing::canonical(response.body());
+                    let value = signing::header(secret, chrono::Utc::now().timestamp(), &body);
+                    response
+                        .headers_mut()
+                        .insert(signing::RESPONSE_HEADER, http::HeaderValue::from_str(&value)?);
+                }
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Compresses the body according to `Accept-Encoding`.
+pub struct Compression;
+
+impl Layer for Compression {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        let accept_encoding = header(&req, ACCEPT_ENCODING);
+        Box::pin(async move {
+            let mut response = next.run(req).await?;
+            compression::apply(
+                accept_encoding.as_deref(),
+                Config::get().compression_min_bytes,
+                &mut response,
+            );
+            Ok(response)
+        })
+    }
+}
+
+/// Lets only `methods` through: OPTIONS is answered as a CORS preflight, HEAD
+/// is served like GET without the body, and anything else gets 405.
+pub struct Methods(pub &'static [Method]);
+
+impl Layer for Methods {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(async move {
+            let methods = self.0;
+            let method = req.method().clone();
+            let supported = methods.contains(&method)
+                || (method == Method::HEAD && methods.contains(&Method::GET));
+
+            if method == Method::OPTIONS {
+                // Preflights carry no credentials, so layers after this one never see them
+                return Ok(Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Body::Empty)?);
+            }
+            if !supported {
+                let err = ApiError::MethodNotAllowed {
+                    method: method.to_string(),
+                    allow: allow_header(methods),
+                };
+                tracing::info!(error = %err, "request rejected");
+                return err.into_response();
+            }
+
+            let mut response = next.run(req).await?;
+            if method == Method::HEAD {
+                *response.body_mut() = Body::Empty;
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Rejects requests without a valid API key while auth is enabled, and
+/// records the accepted key for the layers after it. The first request to get
+/// this far also starts the uptime clock.
+pub struct Auth;
+
+impl Layer for Auth {
+    fn call<'a>(&'a self, mut req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(async move {
+            build_info::mark_started();
+            if let Err(err) = auth::authenticate(&mut req) {
+                tracing::info!(error = %err, "request rejected");
+                return err.into_response();
+            }
+            next.run(req).await
+        })
+    }
+}
+
+/// Applies the per-client rate limit and reports its state in the headers.
+pub struct RateLimit;
+
+impl Layer for RateLimit {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(async move {
+            let Some(decision) = ratelimit::check(&req) else {
+                return next.run(req).await;
+            };
+
+            let mut response = if decision.allowed {
+                next.run(req).await?
+            } else {
+                tracing::warn!(client = %ratelimit::client_key(&req), "rate limit exceeded");
+                ApiError::RateLimited {
+                    retry_after_secs: decision.retry_after_secs,
+                }
+                .into_response()?
+            };
+            decision.apply(response.headers_mut());
+            Ok(response)
+        })
+    }
+}
+
+/// Counts the request against the API key's usage and turns it away once a
+/// quota is used up, see [`usage`]; requests without a key pass through.
+pub struct Metered;
+
+impl Layer for Metered {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(async move {
+            let Some(account) = usage::account(&req) else {
+                return next.run(req).await;
+            };
+            if let Err(err) = usage::check(&account, chrono::Utc::now()).await {
+                tracing::warn!(error = %err, "quota exceeded");
+                return err.into_response();
+            }
+            usage::metered(&account, next.run(req)).await
+        })
+    }
+}
+
+/// Answers a POST carrying an `Idempotency-Key` seen before with the response
+/// it got then, or with `409` while that request is still running, see
+/// [`idempotency`]; other requests pass through.
+pub struct Idempotency;
+
+impl Layer for Idempotency {
+    fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+        Box::pin(async move {
+            if req.method() != Method::POST {
+                return next.run(req).await;
+            }
+            let key = match idempotency::key(&req) {
+                Ok(Some(key)) => key.to_string(),
+                Ok(None) => return next.run(req).await,
+                Err(err) => {
+                    tracing::info!(error = %err, "request rejected");
+                    return err.into_response();
+                }
+            };
+            let slot = idempotency::slot(&req, &key);
+            let fingerprint = idempotency::fingerprint(&req);
+            match idempotency::begin(&slot).await {
+                None => next.run(req).await,
+                Some(idempotency::Begin::Replay(recorded)) => {
+                    if recorded.fingerprint != fingerprint {
+                        let err = ApiError::IdempotencyKeyReused { key };
+                        tracing::info!(error = %err, "request rejected");
+                        return err.into_response();
+                    }
+                    tracing::info!("replaying response for idempotency key");
+                    recorded.replay()
+                }
+                Some(idempotency::Begin::InFlight) => {
+                    let err = ApiError::IdempotencyKeyInFlight {
+                        key,
+                        retry_after_secs: idempotency::IN_FLIGHT_RETRY_SECS,
+                    };
+                    tracing::info!(error = %err, "request rejected");
+                    err.into_response()
+                }
+                Some(idempotency::Begin::Reserved(reservation)) => {
+                    let response = next.run(req).await;
+                    let recorded = response
+                        .as_ref()
+                        .ok()
+                        .and_then(|response| idempotency::Recorded::of(fingerprint, response));
+                    reservation.finish(recorded).await;
+                    response
+                }
+            }
+        })
+    }
+}
+
+fn header(req: &Request, name: http::header::HeaderName) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+// The `Allow` value for an endpoint serving `methods`
+fn allow_header(methods: &[Method]) -> String {
+    let mut allowed: Vec<&str> = methods.iter().map(Method::as_str).collect();
+    if methods.contains(&Method::GET) {
+        allowed.push("HEAD");
+    }
+    allowed.push("OPTIONS");
+    allowed.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allow_header_adds_head_and_options() {
+        assert_eq!(allow_header(&[Method::GET, Method::POST]), "GET, POST, HEAD, OPTIONS");
+        assert_eq!(allow_header(&[Method::POST]), "POST, OPTIONS");
+    }
+
+    // Records the order layers run in by appending to a response header
+    struct Tag(&'static str);
+
+    impl Layer for Tag {
+        fn call<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a> {
+            Box::pin(async move {
+                let mut response = next.run(req).await?;
+                let seen = response
+                    .headers()
+                    .get("x-order")
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| format!("{},{}", value, self.0))
+                    .unwrap_or_else(|| self.0.to_string());
+                response.headers_mut().insert("x-order", seen.parse()?);
+                Ok(response)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn layers_wrap_outermost_first() {
+        let pipeline = Pipeline::new()
+            .layer(Tag("outer"))
+            .layer(Methods(&[Method::GET]))
+            .layer(Tag("inner"));
+        let handler = |_| async { Ok(Response::builder().body(Body::Empty)?) };
+
+        let get = http::Request::builder().body(Body::Empty).unwrap();
+        let response = pipeline.run(get, handler).await.unwrap();
+        assert_eq!(response.headers()["x-order"], "inner,outer");
+
+        let post = http::Request::builder().method("POST").body(Body::Empty).unwrap();
+        let response = pipeline.run(post, handler).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()["x-order"], "outer");
+    }
+
+    #[tokio::test]
+    async fn handler_errors_become_problems() {
+        let pipeline = Pipeline::new().layer(Trace { endpoint: "test" }).layer(Tag("inner"));
+        let failing = |_| async { Err::<Response<Body>, Error>("disk on fire".into()) };
+
+        let req = http::Request::builder().uri("/api/x").body(Body::Empty).unwrap();
+        let response = pipeline.run(req, failing).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        // The layers still see the response
+        assert_eq!(response.headers()["x-order"], "inner");
+        let Body::Text(body) = response.body() else {
+            panic!("expected a text body");
+        };
+        let problem: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(problem["code"], "internal_error");
+        assert_eq!(problem["instance"], "/api/x");
+        assert!(!body.contains("disk on fire"));
+    }
+}
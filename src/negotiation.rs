@@ -0,0 +1,166 @@
+//! Output format selection from the `Accept` header and `?format=` override.
+
+use crate::error::ApiError;
+use crate::params::Params;
+use serde::{de, Deserialize, Deserializer};
+use vercel_runtime::Request;
+
+/// A representation the API can render a successful result in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    /// Just the number(s), one per line.
+    Text,
+    /// `n,<value>` rows, mainly useful for ranges and batches.
+    Csv,
+    MessagePack,
+    /// One JSON object per line, see [`crate::response::ndjson`].
+    Ndjson,
+}
+
+impl Format {
+    /// Every supported format, JSON (the default) first.
+    pub const ALL: [Format; 5] = [
+        Format::Json,
+        Format::Text,
+        Format::Csv,
+        Format::MessagePack,
+        Format::Ndjson,
+    ];
+
+    /// Picks the output format for a request.
+    ///
+    /// An explicit `?format=` wins and must name a known format. Otherwise the
+    /// most preferred supported type in `Accept` is used, falling back to JSON.
+    pub fn from_request(req: &Request) -> Result<Format, ApiError> {
+        #[derive(Deserialize)]
+        struct Requested {
+            format: Option<Format>,
+        }
+
+        let Requested { format } = Params::from_query(req.uri().query().unwrap_or("")).parse()?;
+        if let Some(format) = format {
+            return Ok(format);
+        }
+
+        let accept = req
+            .headers()
+            .get("accept")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        Ok(Format::from_accept(accept))
+    }
+
+    /// Parses a `?format=` value.
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "text" | "txt" | "plain" => Some(Format::Text),
+            "csv" => Some(Format::Csv),
+            "msgpack" | "messagepack" => Some(Format::MessagePack),
+            "ndjson" | "jsonl" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// Chooses the supported media type with the highest `q` value.
+    pub fn from_accept(accept: &str) -> Format {
+        let mut best: Option<(Format, f32)> = None;
+
+        for entry in accept.split(',') {
+            let mut parts = entry.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or("").to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            let format = match media_type.as_str() {
+                "application/json" | "*/*" | "application/*" => Format::Json,
+                "text/plain" => Format::Text,
+                "text/csv" => Format::Csv,
+                "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                    Format::MessagePack
+                }
+                "application/x-ndjson" | "application/jsonl" => Format::Ndjson,
+                _ => continue,
+            };
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((format, quality));
+            }
+        }
+
+        best.map(|(format, _)| format).unwrap_or(Format::Json)
+    }
+
+    /// The canonical `?format=` values, in the order of [`Format::ALL`].
+    pub const NAMES: [&'static str; 5] = ["json", "text", "csv", "msgpack", "ndjson"];
+
+    /// The canonical `?format=` value.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Text => "text",
+            Format::Csv => "csv",
+            Format::MessagePack => "msgpack",
+            Format::Ndjson => "ndjson",
+        }
+    }
+
+    /// The media type without parameters, as listed in `Accept`.
+    pub fn media_type(&self) -> &'static str {
+        self.content_type().split(';').next().unwrap_or_default()
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Text => "text/plain; charset=utf-8",
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::MessagePack => "application/msgpack",
+            Format::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+// Accepts every name `from_name` does; others are reported as one of `NAMES`
+impl<'de> Deserialize<'de> for Format {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Format, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Format::from_name(&name).ok_or_else(|| de::Error::unknown_variant(&name, &Format::NAMES))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_header_preferences() {
+        assert_eq!(Format::from_accept(""), Format::Json);
+        assert_eq!(Format::from_accept("text/html"), Format::Json);
+        assert_eq!(Format::from_accept("text/plain"), Format::Text);
+        assert_eq!(
+            Format::from_accept("application/json;q=0.5, text/csv"),
+            Format::Csv
+        );
+        assert_eq!(
+            Format::from_accept("text/csv;q=0.2, application/msgpack;q=0.9"),
+            Format::MessagePack
+        );
+        assert_eq!(Format::from_accept("text/plain;q=0, */*"), Format::Json);
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(Format::from_name("CSV"), Some(Format::Csv));
+        assert_eq!(Format::from_name("msgpack"), Some(Format::MessagePack));
+        assert_eq!(Format::from_name("jsonl"), Some(Format::Ndjson));
+        assert_eq!(Format::from_name("xml"), None);
+        for (format, name) in Format::ALL.into_iter().zip(Format::NAMES) {
+            assert_eq!(format.name(), name);
+            assert_eq!(Format::from_name(format.name()), Some(format));
+            assert_eq!(Format::from_accept(format.media_type()), format);
+        }
+    }
+}
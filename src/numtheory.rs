@@ -0,0 +1,346 @@
+//! Greatest common divisors, least common multiples and Bézout coefficients
+//! of arbitrary-size integers, Collatz trajectories, and continued fractions
+//! of rationals and square roots.
+
+use crate::budget::Deadline;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Collatz steps between deadline checks.
+const COLLATZ_CHECK_EVERY: u64 = 1024;
+
+/// The gcd and lcm of a list of integers, with Bézout coefficients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bezout {
+    /// Never negative; 0 only when every value is 0.
+    pub gcd: BigInt,
+    /// Never negative; 0 when any value is 0.
+    pub lcm: BigInt,
+    /// One coefficient per value, with Σ coefficient · value = gcd.
+    pub coefficients: Vec<BigInt>,
+}
+
+/// gcd(a, b) with x and y such that a·x + b·y = gcd(a, b), by the extended
+/// Euclidean algorithm.
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+    while !r.is_zero() {
+        let quotient = &old_r / &r;
+        let next_r = &old_r - &quotient * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_x = &old_x - &quotient * &x;
+        old_x = std::mem::replace(&mut x, next_x);
+        let next_y = &old_y - &quotient * &y;
+        old_y = std::mem::replace(&mut y, next_y);
+    }
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// Folds [`extended_gcd`] over `values`: gcd(a, b, c) = gcd(gcd(a, b), c),
+/// scaling the earlier coefficients at every step. Empty input gives gcd 0
+/// and lcm 1.
+pub fn bezout(values: &[BigInt]) -> Bezout {
+    let mut gcd = BigInt::zero();
+    let mut lcm = BigInt::one();
+    let mut coefficients: Vec<BigInt> = Vec::with_capacity(values.len());
+    for value in values {
+        let (next, x, y) = extended_gcd(&gcd, value);
+        for coefficient in &mut coefficients {
+            *coefficient *= &x;
+        }
+        coefficients.push(y);
+        gcd = next;
+        lcm = lcm.lcm(&value.abs());
+    }
+    Bezout { gcd, lcm, coefficients }
+}
+
+/// The Collatz trajectory of a starting value down to 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trajectory {
+    /// Steps taken to reach 1 (the total stopping time).
+    pub steps: u64,
+    /// The largest value reached, the start included.
+    pub max: BigUint,
+    /// The first values of the trajectory, the start and 1 included, if
+    /// they were asked for.
+    pub values: Vec<BigUint>,
+}
+
+/// Follows n → n/2 (n even) and n → 3n + 1 (n odd) from `start`, which
+/// must be positive, down to 1, keeping the first `keep` values. Gives up
+/// once `deadline` passes and returns the number of steps taken.
+pub fn collatz(start: &BigUint, keep: usize, deadline: &Deadline) -> Result<Trajectory, u64> {
+    let mut value = start.clone();
+    let mut max = start.clone();
+    let mut values = Vec::new();
+    let mut steps = 0;
+    loop {
+        if values.len() < keep {
+            values.push(value.clone());
+        }
+        if value.is_one() {
+            return Ok(Trajectory { steps, max, values });
+        }
+        if steps % COLLATZ_CHECK_EVERY == 0 && deadline.expired() {
+            return Err(steps);
+        }
+        if value.is_even() {
+            value >>= 1;
+        } else {
+            value = value * 3u32 + 1u32;
+            if value > max {
+                max = value.clone();
+            }
+        }
+        steps += 1;
+    }
+}
+
+/// A number whose continued fraction can be computed exactly, in lowest
+/// terms with a positive denominator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Real {
+    /// numerator / denominator.
+    Rational(BigInt, BigInt),
+    /// √(numerator / denominator), never itself rational.
+    Sqrt(BigInt, BigInt),
+}
+
+impl Real {
+    /// Parses `7`, `-3/7`, `sqrt(2)` or `sqrt(2/3)`. `None` if the text is
+    /// none of these, a denominator is 0 or a square root is of a negative
+    /// number.
+    pub fn parse(text: &str) -> Option<Real> {
+        let text = text.trim();
+        let root = text
+            .strip_prefix("sqrt(")
+            .and_then(|rest| rest.strip_suffix(')'));
+        match root {
+            Some(inner) => {
+                let (p, q) = parse_ratio(inner)?;
+                (!p.is_negative()).then(|| Real::sqrt(p, q))
+            }
+            None => parse_ratio(text).map(|(p, q)| Real::Rational(p, q)),
+        }
+    }
+
+    // √(p/q) = √(pq)/q, which is rational when pq is a square
+    fn sqrt(p: BigInt, q: BigInt) -> Real {
+        let root = (&p * &q).sqrt();
+        if &root * &root == &p * &q {
+            let divisor = root.gcd(&q);
+            Real::Rational(root / &divisor, q / divisor)
+        } else {
+            Real::Sqrt(p, q)
+        }
+    }
+
+    /// Decimal digits in the longer of the two integers.
+    pub fn digits(&self) -> u64 {
+        let (Real::Rational(p, q) | Real::Sqrt(p, q)) = self;
+        p.magnitude().to_string().len().max(q.to_string().len()) as u64
+    }
+}
+
+impl fmt::Display for Real {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ratio = |f: &mut fmt::Formatter<'_>, p: &BigInt, q: &BigInt| {
+            if q.is_one() {
+                write!(f, "{}", p)
+            } else {
+                write!(f, "{}/{}", p, q)
+            }
+        };
+        match self {
+            Real::Rational(p, q) => ratio(f, p, q),
+            Real::Sqrt(p, q) => {
+                write!(f, "sqrt(")?;
+                ratio(f, p, q)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+// `p` or `p/q` in lowest terms with q > 0
+fn parse_ratio(text: &str) -> Option<(BigInt, BigInt)> {
+    let (p, q) = text.split_once('/').unwrap_or((text, "1"));
+    let (p, q): (BigInt, BigInt) = (p.trim().parse().ok()?, q.trim().parse().ok()?);
+    if q.is_zero() {
+        return None;
+    }
+    let divisor = p.gcd(&q);
+    let sign = if q.sign() == Sign::Minus { -BigInt::one() } else { BigInt::one() };
+    Some((sign.clone() * p / &divisor, sign * q / divisor))
+}
+
+/// The first terms of a continued fraction a0 + 1/(a1 + 1/(a2 + ⋯)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    pub terms: Vec<BigInt>,
+    /// The convergent after each term, as (numerator, denominator).
+    pub convergents: Vec<(BigInt, BigInt)>,
+    /// Whether `terms` is the whole expansion, which happens only for
+    /// rationals.
+    pub complete: bool,
+    /// For a square root, the index where the repeating block starts and its
+    /// length, once the terms computed show it.
+    pub period: Option<(usize, usize)>,
+}
+
+/// The continued fraction of `x` up to `max_terms` terms.
+///
+/// A rational's terms are the quotients of the Euclidean algorithm. A
+/// square root √(p/q) is written (m + √D)/d with D = pq, m = 0 and d = q;
+/// each step takes a = ⌊(m + √D)/d⌋ and moves to m' = ad − m and
+/// d' = (D − m'²)/d, a division that is always exact. The pair (m, d)
+/// eventually repeats, which is where the expansion becomes periodic.
+pub fn continued_fraction(x: &Real, max_terms: usize) -> Expansion {
+    let mut terms = Vec::new();
+    let mut complete = false;
+    let mut period = None;
+    match x {
+        Real::Rational(p, q) => {
+            let (mut p, mut q) = (p.clone(), q.clone());
+            while terms.len() < max_terms && !q.is_zero() {
+                let a = p.div_floor(&q);
+                let rest = &p - &a * &q;
+                p = std::mem::replace(&mut q, rest);
+                terms.push(a);
+            }
+            complete = q.is_zero();
+        }
+        Real::Sqrt(p, q) => {
+            let radicand = p * q;
+            let root = radicand.sqrt();
+            let (mut m, mut d) = (BigInt::zero(), q.clone());
+            let mut seen = HashMap::new();
+            while terms.len() < max_terms {
+                if period.is_none() {
+                    if let Some(&start) = seen.get(&(m.clone(), d.clone())) {
+                        period = Some((start, terms.len() - start));
+                    } else {
+                        seen.insert((m.clone(), d.clone()), terms.len());
+                    }
+                }
+                let a = (&m + &root).div_floor(&d);
+                m = &a * &d - &m;
+                d = (&radicand - &m * &m) / &d;
+                terms.push(a);
+            }
+        }
+    }
+    Expansion {
+        convergents: convergents(&terms),
+        terms,
+        complete,
+        period,
+    }
+}
+
+// h(n) = a(n)·h(n−1) + h(n−2), and likewise for k, from h = 1, 0 and k = 0, 1
+fn convergents(terms: &[BigInt]) -> Vec<(BigInt, BigInt)> {
+    let (mut h, mut previous_h) = (BigInt::one(), BigInt::zero());
+    let (mut k, mut previous_k) = (BigInt::zero(), BigInt::one());
+    terms
+        .iter()
+        .map(|a| {
+            let next_h = a * &h + &previous_h;
+            previous_h = std::mem::replace(&mut h, next_h);
+            let next_k = a * &k + &previous_k;
+            previous_k = std::mem::replace(&mut k, next_k);
+            (h.clone(), k.clone())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ints(values: &[i64]) -> Vec<BigInt> {
+        values.iter().map(|&v| BigInt::from(v)).collect()
+    }
+
+    fn check(values: &[i64], gcd: i64, lcm: i64) {
+        let values = ints(values);
+        let result = bezout(&values);
+        assert_eq!(result.gcd, BigInt::from(gcd));
+        assert_eq!(result.lcm, BigInt::from(lcm));
+        let combination: BigInt = values.iter().zip(&result.coefficients).map(|(v, c)| v * c).sum();
+        assert_eq!(combination, result.gcd);
+    }
+
+    #[test]
+    fn gcd_lcm_and_coefficients() {
+        assert_eq!(
+            extended_gcd(&BigInt::from(240), &BigInt::from(46)),
+            (BigInt::from(2), BigInt::from(-9), BigInt::from(47))
+        );
+        check(&[12, 18], 6, 36);
+        check(&[12, 18, 30], 6, 180);
+        check(&[-4, 6], 2, 12);
+        check(&[0, 5], 5, 0);
+        check(&[0, 0], 0, 0);
+        check(&[17, 31], 1, 527);
+    }
+
+    #[test]
+    fn collatz_trajectories() {
+        let run = |n: u64, keep| collatz(&BigUint::from(n), keep, &Deadline::none()).unwrap();
+        let six = run(6, 100);
+        assert_eq!(six.steps, 8);
+        assert_eq!(six.max, BigUint::from(16u32));
+        let values: Vec<_> = six.values.iter().map(BigUint::to_string).collect();
+        assert_eq!(values, ["6", "3", "10", "5", "16", "8", "4", "2", "1"]);
+
+        let long = run(27, 3);
+        assert_eq!((long.steps, long.max, long.values.len()), (111, BigUint::from(9232u32), 3));
+        assert_eq!(run(1, 10).steps, 0);
+
+        let expired = Deadline::after(std::time::Duration::ZERO);
+        assert_eq!(collatz(&BigUint::from(27u32), 0, &expired), Err(0));
+    }
+
+    #[test]
+    fn parses_rationals_and_square_roots() {
+        let parse = |text| Real::parse(text).map(|x| x.to_string());
+        assert_eq!(parse("7").as_deref(), Some("7"));
+        assert_eq!(parse("6/-4").as_deref(), Some("-3/2"));
+        assert_eq!(parse("sqrt(2)").as_deref(), Some("sqrt(2)"));
+        assert_eq!(parse("sqrt(8/18)").as_deref(), Some("2/3"));
+        assert_eq!(Real::parse("sqrt(9)"), Some(Real::Rational(BigInt::from(3), BigInt::one())));
+        assert_eq!(parse("1/0"), None);
+        assert_eq!(parse("sqrt(-2)"), None);
+        assert_eq!(parse("pi"), None);
+    }
+
+    #[test]
+    fn continued_fractions() {
+        let expand = |text, max| {
+            let expansion = continued_fraction(&Real::parse(text).unwrap(), max);
+            let terms: Vec<_> = expansion.terms.iter().map(BigInt::to_string).collect();
+            (terms.join(","), expansion.complete, expansion.period)
+        };
+        assert_eq!(expand("415/93", 10), ("4,2,6,7".to_string(), true, None));
+        assert_eq!(expand("-415/93", 10), ("-5,1,1,6,7".to_string(), true, None));
+        assert_eq!(expand("415/93", 2), ("4,2".to_string(), false, None));
+        assert_eq!(expand("sqrt(2)", 5), ("1,2,2,2,2".to_string(), false, Some((1, 1))));
+        assert_eq!(expand("sqrt(14)", 9), ("3,1,2,1,6,1,2,1,6".to_string(), false, Some((1, 4))));
+        assert_eq!(expand("sqrt(1/2)", 4), ("0,1,2,2".to_string(), false, Some((2, 1))));
+
+        let root_two = continued_fraction(&Real::parse("sqrt(2)").unwrap(), 4);
+        let convergents: Vec<_> = root_two.convergents.iter().map(|(h, k)| format!("{}/{}", h, k)).collect();
+        assert_eq!(convergents, ["1/1", "3/2", "7/5", "17/12"]);
+    }
+}
@@ -0,0 +1,1799 @@
+//! OpenAPI 3 description of the API.
+//!
+//! The document is assembled at request time from the same definitions the
+//! handlers use: limits come from [`Config`], output formats from [`Format`]
+//! and error codes from [`ApiError`], so a deployment's spec always matches
+//! the limits it actually enforces.
+
+use crate::config::Config;
+use crate::error::{ApiError, PROBLEM_CONTENT_TYPE, PROBLEM_TYPE_PREFIX};
+use crate::expr::FUNCTIONS;
+use crate::idempotency;
+use crate::negotiation::Format;
+use crate::radix;
+use crate::sequences::SEQUENCES;
+use crate::usage;
+use serde_json::{json, Map, Value};
+
+/// Builds the OpenAPI document for a deployment configured with `config`.
+pub fn spec(config: &Config) -> Value {
+    let index = json!({ "type": "integer", "minimum": 0, "maximum": config.max_n });
+    // The single-value endpoint also accepts negative (negafibonacci) indices
+    let signed_index = json!({ "type": "integer", "minimum": -(config.max_n as i128), "maximum": config.max_n });
+
+    let mut spec = json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Fibonacci Calculator API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Arbitrary-precision Fibonacci numbers as a serverless API. Every path may be prefixed with a version, e.g. `/api/v2/20`: `v1` (the default) keeps the original result shape, `v2` drops `status`, `usage` and `debug` and moves `timestamp` into `meta`. The `X-Api-Version` response header names the version served.",
+        },
+        "paths": {
+            "/api/{n}": {
+                "get": {
+                    "summary": "Compute F(n)",
+                    "parameters": [
+                        {
+                            "name": "n",
+                            "in": "path",
+                            "required": true,
+                            "schema": signed_index,
+                        },
+                        query_parameter(
+                            "strict",
+                            "Reject a missing `n` instead of defaulting to 10.",
+                            json!({ "type": "boolean" }),
+                        ),
+                        query_parameter(
+                            "debug",
+                            "Include request details in a `debug` object.",
+                            json!({ "type": "boolean" }),
+                        ),
+                        query_parameter(
+                            "profile_runs",
+                            "Repeat the computation and report timing percentiles.",
+                            json!({ "type": "integer", "minimum": 1, "maximum": config.max_profile_runs }),
+                        ),
+                        query_parameter(
+                            "last_digits",
+                            "Return only the last k digits, computed as F(n) mod 10^k; lifts the index limit.",
+                            json!({ "type": "integer", "minimum": 1, "maximum": config.max_last_digits }),
+                        ),
+                        query_parameter(
+                            "algo",
+                            "Implementation to run and time; `binet` is approximate beyond n = 70.",
+                            algo_schema(),
+                        ),
+                        base_parameter(),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Fibonacci", true),
+                }
+            },
+            "/api": {
+                "post": {
+                    "summary": "Compute F(n) from a JSON body",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "n": signed_index,
+                                        "strict": { "type": "boolean" },
+                                        "debug": { "type": "boolean" },
+                                        "profile_runs": {
+                                            "type": "integer",
+                                            "minimum": 1,
+                                            "maximum": config.max_profile_runs,
+                                        },
+                                        "last_digits": {
+                                            "type": "integer",
+                                            "minimum": 1,
+                                            "maximum": config.max_last_digits,
+                                        },
+                                        "base": base_schema(),
+                                        "algo": algo_schema(),
+                                    },
+                                }
+                            }
+                        }
+                    },
+                    "responses": responses("Fibonacci", false),
+                }
+            },
+            "/api/batch": {
+                "post": {
+                    "summary": "Compute several Fibonacci numbers at once",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["n"],
+                                    "properties": {
+                                        "n": {
+                                            "type": "array",
+                                            "items": index,
+                                            "maxItems": config.max_batch_size,
+                                        },
+                                        "base": base_schema(),
+                                    },
+                                }
+                            }
+                        }
+                    },
+                    "responses": responses("Batch", false),
+                }
+            },
+            "/api/jobs": {
+                "post": {
+                    "summary": "Compute F(n) in the background and post it to a callback",
+                    "description": "The callback receives the result signed with `X-Fib-Signature: t=<unix>,v1=<hex HMAC-SHA256 of \"t.body\">`.",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["n", "callback_url"],
+                                    "properties": {
+                                        "n": { "type": "integer", "minimum": 0, "maximum": config.max_job_n },
+                                        "callback_url": { "type": "string", "format": "uri" },
+                                    },
+                                }
+                            }
+                        }
+                    },
+                    "responses": job_responses(),
+                }
+            },
+            "/api/range": {
+                "get": {
+                    "summary": "Compute the run F(from)..=F(to)",
+                    "description": format!("At most {} values per request.", config.max_range_size),
+                    "parameters": [
+                        required(query_parameter("from", "First index of the run.", index.clone())),
+                        required(query_parameter("to", "Last index (inclusive).", index.clone())),
+                        base_parameter(),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Range", true),
+                }
+            },
+            "/api/mod": {
+                "get": {
+                    "summary": "Compute F(n) mod m for an arbitrarily large n",
+                    "parameters": [
+                        required(query_parameter(
+                            "n",
+                            "Index as a decimal string of any length; not limited by the maximum index.",
+                            json!({ "type": "string", "pattern": "^[0-9]+$" }),
+                        )),
+                        required(query_parameter(
+                            "m",
+                            "Modulus.",
+                            json!({ "type": "integer", "minimum": 1 }),
+                        )),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Modular", true),
+                }
+            },
+            "/api/lucas/{n}": {
+                "get": {
+                    "summary": "Compute the Lucas number L(n)",
+                    "parameters": [
+                        {
+                            "name": "n",
+                            "in": "path",
+                            "required": true,
+                            "schema": index,
+                        },
+                        format_parameter(),
+                    ],
+                    "responses": responses("Lucas", true),
+                }
+            },
+            "/api/lucas-seq": {
+                "get": {
+                    "summary": "Compute U_n(P, Q) and V_n(P, Q) of a Lucas sequence",
+                    "parameters": [
+                        required(query_parameter("n", "Index.", index.clone())),
+                        query_parameter("p", "P parameter, default 1.", json!({ "type": "integer", "format": "int64" })),
+                        query_parameter("q", "Q parameter, default -1.", json!({ "type": "integer", "format": "int64" })),
+                        format_parameter(),
+                    ],
+                    "responses": responses("LucasSequence", true),
+                }
+            },
+            "/api/kfib": {
+                "get": {
+                    "summary": "Compute the n-th k-step Fibonacci number",
+                    "parameters": [
+                        required(query_parameter(
+                            "k",
+                            "Number of preceding terms summed; 3 is tribonacci.",
+                            json!({ "type": "integer", "minimum": 2, "maximum": config.max_kfib_k }),
+                        )),
+                        required(query_parameter("n", "Index.", index.clone())),
+                        format_parameter(),
+                    ],
+                    "responses": responses("KFib", true),
+                }
+            },
+            "/api/is-fib": {
+                "get": {
+                    "summary": "Test whether x is a Fibonacci number and find its index",
+                    "parameters": [
+                        required(query_parameter(
+                            "x",
+                            "Value to test, as a decimal string of any length.",
+                            json!({ "type": "string", "pattern": "^[0-9]+$" }),
+                        )),
+                        format_parameter(),
+                    ],
+                    "responses": responses("IsFib", true),
+                }
+            },
+            "/api/zeckendorf": {
+                "get": {
+                    "summary": "Write x as a sum of non-consecutive Fibonacci numbers",
+                    "parameters": [
+                        required(query_parameter(
+                            "x",
+                            "Value to decompose, as a decimal string.",
+                            json!({ "type": "string", "pattern": "^[0-9]+$" }),
+                        )),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Zeckendorf", true),
+                }
+            },
+            "/api/pisano": {
+                "get": {
+                    "summary": "Compute the Pisano period of m",
+                    "parameters": [
+                        required(query_parameter(
+                            "m",
+                            "Modulus.",
+                            json!({ "type": "integer", "minimum": 1, "maximum": config.max_pisano_m }),
+                        )),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Pisano", true),
+                }
+            },
+            "/api/digits/{n}": {
+                "get": {
+                    "summary": "Estimate the size of F(n) without computing it",
+                    "parameters": [
+                        {
+                            "name": "n",
+                            "in": "path",
+                            "required": true,
+                            "description": "Index; not limited by the maximum index.",
+                            "schema": { "type": "integer", "format": "int64" },
+                        },
+                        format_parameter(),
+                    ],
+                    "responses": responses("Digits", true),
+                }
+            },
+            "/api/golden-ratio": {
+                "get": {
+                    "summary": "Approximate the golden ratio to a number of decimal places",
+                    "parameters": [
+                        query_parameter(
+                            "precision",
+                            "Decimal places, default 50.",
+                            json!({ "type": "integer", "minimum": 0, "maximum": config.max_golden_precision }),
+                        ),
+                        query_parameter(
+                            "method",
+                            "`sqrt` (exact integer square root) or `ratio` (F(n+1)/F(n)).",
+                            json!({ "type": "string", "enum": ["sqrt", "ratio"] }),
+                        ),
+                        format_parameter(),
+                    ],
+                    "responses": responses("GoldenRatio", true),
+                }
+            },
+            "/api/prime/{n}": {
+                "get": {
+                    "summary": "Find the n-th prime",
+                    "parameters": [
+                        {
+                            "name": "n",
+                            "in": "path",
+                            "required": true,
+                            "description": "Index, counting 2 as the first prime.",
+                            "schema": { "type": "integer", "minimum": 1, "maximum": config.max_prime_n },
+                        },
+                        format_parameter(),
+                    ],
+                    "responses": responses("Prime", true),
+                }
+            },
+            "/api/is-prime": {
+                "get": {
+                    "summary": "Test x for primality",
+                    "parameters": [
+                        required(query_parameter(
+                            "x",
+                            "Value to test, as a decimal string.",
+                            json!({ "type": "string", "pattern": "^[0-9]+$", "maxLength": config.max_prime_digits }),
+                        )),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Primality", true),
+                }
+            },
+            "/api/factorize": {
+                "get": {
+                    "summary": "Factorize x into primes",
+                    "parameters": [
+                        required(query_parameter(
+                            "x",
+                            "Positive value to factorize, as a decimal string.",
+                            json!({ "type": "string", "pattern": "^[0-9]+$", "maxLength": config.max_factor_digits }),
+                        )),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Factorization", true),
+                }
+            },
+            "/api/factorial/{n}": {
+                "get": {
+                    "summary": "Compute n!",
+                    "parameters": [
+                        {
+                            "name": "n",
+                            "in": "path",
+                            "required": true,
+                            "schema": { "type": "integer", "minimum": 0, "maximum": config.max_factorial_n },
+                        },
+                        format_parameter(),
+                    ],
+                    "responses": responses("Factorial", true),
+                }
+            },
+            "/api/choose": {
+                "get": {
+                    "summary": "Compute the binomial coefficient C(n, k)",
+                    "parameters": [
+                        required(query_parameter(
+                            "n",
+                            "Size of the set.",
+                            json!({ "type": "integer", "minimum": 0, "maximum": config.max_factorial_n }),
+                        )),
+                        required(query_parameter(
+                            "k",
+                            "Elements chosen; above n the coefficient is 0.",
+                            json!({ "type": "integer", "minimum": 0 }),
+                        )),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Binomial", true),
+                }
+            },
+            "/api/gcd": {
+                "get": {
+                    "summary": "gcd, lcm and Bézout coefficients of two or more integers",
+                    "parameters": [
+                        bigint_list_parameter("a", "First value; more may follow, comma-separated.", config, true),
+                        bigint_list_parameter("b", "Second value; repeat for more.", config, false),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Gcd", true),
+                }
+            },
+            "/api/collatz/{n}": {
+                "get": {
+                    "summary": "Follow the Collatz trajectory of n down to 1",
+                    "parameters": [
+                        {
+                            "name": "n",
+                            "in": "path",
+                            "required": true,
+                            "description": "Positive starting value, as a decimal string.",
+                            "schema": { "type": "string", "pattern": "^[0-9]+$", "maxLength": config.max_collatz_digits },
+                        },
+                        query_parameter(
+                            "include_sequence",
+                            "Include the trajectory itself.",
+                            json!({ "type": "boolean" }),
+                        ),
+                        query_parameter(
+                            "max_length",
+                            "Most trajectory values to include.",
+                            json!({ "type": "integer", "minimum": 0, "maximum": config.max_collatz_sequence }),
+                        ),
+                        format_parameter(),
+                    ],
+                    "responses": responses("Collatz", true),
+                }
+            },
+            "/api/health": {
+                "get": {
+                    "summary": "Uptime, build metadata and configured limits",
+                    "responses": { "200": { "description": "Instance health" } },
+                }
+            },
+            "/api/metrics": {
+                "get": {
+                    "summary": "Instance metrics in Prometheus text format",
+                    "responses": {
+                        "200": {
+                            "description": "Prometheus exposition format 0.0.4",
+                            "content": { "text/plain": { "schema": { "type": "string" } } },
+                        }
+                    },
+                }
+            },
+            "/api/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "parameters": [
+                        query_parameter("ui", "Return the page of `/api/docs` instead.", json!({ "type": "boolean" })),
+                    ],
+                    "responses": { "200": { "description": "OpenAPI 3 document" } },
+                }
+            },
+        },
+        "components": {
+            "schemas": {
+                "Fibonacci": {
+                    "type": "object",
+                    "properties": {
+                        "fibonacci": { "type": "string", "description": "F(n) in decimal" },
+                        "n": signed_index,
+                        "sign": { "type": "string", "enum": ["positive", "negative", "zero"] },
+                        "base": { "type": "integer", "description": "Radix of `fibonacci`, when not 10" },
+                        "cache": { "type": "string", "enum": ["hit", "store", "miss"] },
+                        "last_digits": { "type": "integer" },
+                        "modulus": { "type": "string", "description": "10^last_digits" },
+                        "truncated": { "type": "boolean", "description": "Whether F(n) has more than last_digits digits" },
+                        "algorithm": algo_schema(),
+                        "elapsed_ns": { "type": "integer", "description": "Time spent in the selected algorithm" },
+                        "approximate": { "type": "boolean", "description": "Whether a binet result is rounded" },
+                        "relative_error": { "type": "number", "description": "Estimated relative error of a binet result" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                        "profile": { "$ref": "#/components/schemas/Profile" },
+                        "debug": { "type": "object" },
+                        "usage": { "type": "string" },
+                    },
+                },
+                "Profile": {
+                    "type": "object",
+                    "properties": {
+                        "n": { "type": "integer" },
+                        "runs": { "type": "integer" },
+                        "min_ns": { "type": "integer" },
+                        "median_ns": { "type": "integer" },
+                        "p95_ns": { "type": "integer" },
+                        "max_ns": { "type": "integer" },
+                    },
+                },
+                "Batch": {
+                    "type": "object",
+                    "properties": {
+                        "results": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "n": index,
+                                    "fibonacci": { "type": "string" },
+                                    "cache": { "type": "string", "enum": ["hit", "store", "miss"] },
+                                },
+                            },
+                        },
+                        "count": { "type": "integer" },
+                        "base": { "type": "integer" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "FibonacciMap": fibonacci_map_schema(),
+                "Range": {
+                    "type": "object",
+                    "properties": {
+                        "from": index,
+                        "to": index,
+                        "count": { "type": "integer", "maximum": config.max_range_size },
+                        "sequence": { "type": "array", "items": { "type": "string" } },
+                        "base": { "type": "integer" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Modular": {
+                    "type": "object",
+                    "properties": {
+                        "n": { "type": "string" },
+                        "m": { "type": "integer" },
+                        "fibonacci_mod": { "type": "integer" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Lucas": {
+                    "type": "object",
+                    "properties": {
+                        "lucas": { "type": "string", "description": "L(n) in decimal" },
+                        "n": index,
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "LucasSequence": {
+                    "type": "object",
+                    "properties": {
+                        "p": { "type": "integer" },
+                        "q": { "type": "integer" },
+                        "n": index,
+                        "u": { "type": "string", "description": "U_n(P, Q) in decimal" },
+                        "v": { "type": "string", "description": "V_n(P, Q) in decimal" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "KFib": {
+                    "type": "object",
+                    "properties": {
+                        "k": { "type": "integer" },
+                        "n": index,
+                        "value": { "type": "string", "description": "The term in decimal" },
+                        "sequence": { "type": "string", "description": "Conventional name, for k <= 10" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "IsFib": {
+                    "type": "object",
+                    "properties": {
+                        "x": { "type": "string" },
+                        "is_fibonacci": { "type": "boolean" },
+                        "n": { "type": "integer", "description": "Smallest n with F(n) = x, when there is one" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Zeckendorf": {
+                    "type": "object",
+                    "properties": {
+                        "x": { "type": "string" },
+                        "terms": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "n": index,
+                                    "fibonacci": { "type": "string" },
+                                },
+                            },
+                        },
+                        "count": { "type": "integer" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Pisano": {
+                    "type": "object",
+                    "properties": {
+                        "m": { "type": "integer" },
+                        "period": { "type": "integer" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Digits": {
+                    "type": "object",
+                    "properties": {
+                        "n": { "type": "integer" },
+                        "digits": { "type": "integer", "description": "Decimal digits of |F(n)|" },
+                        "magnitude": { "type": "string", "description": "F(n) in scientific notation, e.g. 4.346656e208" },
+                        "mantissa": { "type": "number" },
+                        "exponent": { "type": "integer" },
+                        "approximate": { "type": "boolean", "description": "False when the value was small enough to compute exactly" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "GoldenRatio": {
+                    "type": "object",
+                    "properties": {
+                        "phi": { "type": "string" },
+                        "precision": { "type": "integer" },
+                        "method": { "type": "string", "enum": ["sqrt", "ratio"] },
+                        "n": { "type": "integer", "description": "Index used by the ratio method" },
+                        "error_bound": { "type": "string", "description": "Upper bound on |φ − phi|, e.g. 1e-50" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Prime": {
+                    "type": "object",
+                    "properties": {
+                        "n": { "type": "integer" },
+                        "prime": { "type": "string", "description": "The n-th prime in decimal" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Primality": {
+                    "type": "object",
+                    "properties": {
+                        "x": { "type": "string" },
+                        "is_prime": { "type": "boolean" },
+                        "primality": { "type": "string", "enum": ["prime", "probable_prime", "composite", "neither"] },
+                        "deterministic": { "type": "boolean", "description": "False when x is only a probable prime" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Factorization": {
+                    "type": "object",
+                    "properties": {
+                        "x": { "type": "string" },
+                        "factors": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "prime": { "type": "string" },
+                                    "exponent": { "type": "integer" },
+                                },
+                            },
+                        },
+                        "factorization": { "type": "string", "description": "e.g. 2^3 × 3^2 × 5" },
+                        "is_prime": { "type": "boolean" },
+                        "deterministic": { "type": "boolean", "description": "False when a factor is only a probable prime" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Factorial": {
+                    "type": "object",
+                    "properties": {
+                        "n": { "type": "integer" },
+                        "factorial": { "type": "string", "description": "n! in decimal" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Binomial": {
+                    "type": "object",
+                    "properties": {
+                        "n": { "type": "integer" },
+                        "k": { "type": "integer" },
+                        "binomial": { "type": "string", "description": "C(n, k) in decimal" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Gcd": {
+                    "type": "object",
+                    "properties": {
+                        "values": { "type": "array", "items": { "type": "string" } },
+                        "gcd": { "type": "string" },
+                        "lcm": { "type": "string" },
+                        "bezout": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "One coefficient per value; their weighted sum is the gcd",
+                        },
+                        "coprime": { "type": "boolean" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Collatz": {
+                    "type": "object",
+                    "properties": {
+                        "n": { "type": "string" },
+                        "stopping_time": { "type": "integer", "description": "Steps to reach 1" },
+                        "max_value": { "type": "string", "description": "Largest value on the trajectory" },
+                        "sequence": { "type": "array", "items": { "type": "string" } },
+                        "sequence_truncated": { "type": "boolean" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["success"] },
+                    },
+                },
+                "Job": {
+                    "type": "object",
+                    "properties": {
+                        "job_id": { "type": "string" },
+                        "n": { "type": "integer" },
+                        "callback_url": { "type": "string" },
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "status": { "type": "string", "enum": ["accepted"] },
+                    },
+                },
+                "Error": error_schema(),
+            },
+        },
+    });
+
+    // Added here rather than above to stay within the `json!` recursion limit
+    spec["components"]["schemas"]["Benchmark"] = benchmark_schema();
+    spec["components"]["schemas"]["ContinuedFraction"] = continued_fraction_schema();
+    spec["components"]["schemas"]["Conversion"] = conversion_schema();
+    spec["components"]["schemas"]["Evaluation"] = evaluation_schema();
+    spec["components"]["schemas"]["DigitStats"] = digit_stats_schema();
+    spec["components"]["schemas"]["FibonacciSums"] = fibonacci_sums_schema();
+    spec["components"]["schemas"]["FibonacciWord"] = fibonacci_word_schema();
+    spec["components"]["schemas"]["GraphqlResponse"] = graphql_response_schema();
+    spec["components"]["schemas"]["Random"] = random_schema();
+    spec["components"]["schemas"]["Sequence"] = sequence_schema();
+    spec["components"]["schemas"]["SequenceList"] = sequence_list_schema();
+    spec["components"]["schemas"]["Usage"] = usage_schema();
+    spec["components"]["schemas"]["Verification"] = verification_schema();
+    let batch = &mut spec["components"]["schemas"]["Batch"]["properties"];
+    batch["failed"] = json!({ "type": "integer", "description": "Entries answered with an error" });
+    batch["results"]["items"]["properties"]["error"] = json!({
+        "type": "object",
+        "description": "Why this entry has no value; the rest of the batch is unaffected",
+        "properties": {
+            "code": { "type": "string", "enum": ApiError::CODES },
+            "status": { "type": "integer" },
+            "detail": { "type": "string" },
+        },
+    });
+
+    // Every computed result carries the same `meta` object, see `response::Meta`
+    if let Some(schemas) = spec["components"]["schemas"].as_object_mut() {
+        for (name, schema) in schemas.iter_mut() {
+            if !matches!(name.as_str(), "Benchmark" | "Error" | "GraphqlResponse" | "Job" | "Profile" | "Usage" | "Verification") {
+                schema["properties"]["meta"] = json!({ "$ref": "#/components/schemas/Meta" });
+                schema["properties"]["formatted"] = json!({
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Grouped values by index, only when digit grouping was requested",
+                });
+                schema["properties"]["locale"] = json!({ "type": "string" });
+            }
+        }
+        schemas.insert(
+            "Meta".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "elapsed_us": { "type": "integer", "description": "Time spent on the request so far" },
+                    "digits": { "type": "integer", "description": "Digits in the result" },
+                    "algorithm": { "type": "string" },
+                    "cache": { "type": "string", "enum": ["hit", "store", "miss", "partial"], "nullable": true },
+                    "source": {
+                        "type": "string",
+                        "enum": ["table", "computed"],
+                        "description": "Single-value endpoint only: whether F(n) came from the embedded table",
+                    },
+                },
+            }),
+        );
+    }
+
+    // The router serves the single-value endpoint and this document under
+    // two paths each
+    spec["paths"]["/api/fib/{n}"] = spec["paths"]["/api/{n}"].clone();
+    spec["paths"]["/api/openapi"] = spec["paths"]["/api/openapi.json"].clone();
+    spec["paths"]["/api/docs"] = documentation_page();
+    spec["paths"]["/api"]["get"] = several_indices(config);
+    spec["paths"]["/api/benchmark"] = benchmark_report();
+    spec["paths"]["/api/continued-fraction"] = continued_fraction(config);
+    spec["paths"]["/api/convert"] = conversion(config);
+    spec["paths"]["/api/eval"] = evaluation(config);
+    spec["paths"]["/api/fib-digits/{n}"] = digit_stats(config);
+    spec["paths"]["/api/fib-sum"] = fibonacci_sums(config);
+    spec["paths"]["/api/fib-word"] = fibonacci_word(config);
+    spec["paths"]["/api/graphql"] = graphql_query(config);
+    spec["paths"]["/api/random"] = random_values(config);
+    spec["paths"]["/api/seq"] = sequence_list();
+    spec["paths"]["/api/seq/{name}"] = sequence_term(config, "query");
+    spec["paths"]["/api/seq/{name}/{n}"] = sequence_term(config, "path");
+    spec["paths"]["/api/usage"] = usage_report();
+    spec["paths"]["/api/verify"] = verification();
+
+    // Every operation choosing a format can also shape its JSON and group its
+    // digits, see `response::Shape` and `format`; those taking a body may get
+    // 413 and can be deduplicated with an `Idempotency-Key`
+    if let Some(paths) = spec["paths"].as_object_mut() {
+        for operation in paths.values_mut().filter_map(Value::as_object_mut).flat_map(|path| path.values_mut()) {
+            if operation.get("requestBody").is_some() {
+                operation["responses"]["413"] = operation["responses"]["400"].clone();
+                operation["responses"]["409"] = operation["responses"]["400"].clone();
+                let parameters = &mut operation["parameters"];
+                if parameters.is_null() {
+                    *parameters = json!([]);
+                }
+                if let Some(parameters) = parameters.as_array_mut() {
+                    parameters.push(idempotency_parameter());
+                }
+            }
+            if let Some(parameters) = operation["parameters"].as_array_mut() {
+                if parameters.iter().any(|parameter| parameter["name"] == "format") {
+                    parameters.extend(shape_parameters());
+                }
+            }
+        }
+    }
+
+    // Set after the loop above: streamed elements are neither shaped nor grouped
+    spec["paths"]["/api/stream"] = sequence_stream(config);
+
+    if !config.api_keys.is_empty() {
+        spec["components"]["securitySchemes"] = json!({
+            "bearer": { "type": "http", "scheme": "bearer" },
+            "apiKey": { "type": "apiKey", "in": "header", "name": "x-api-key" },
+        });
+        spec["security"] = json!([{ "bearer": [] }, { "apiKey": [] }]);
+    }
+
+    spec
+}
+
+/// A Swagger UI page that renders the document served at `spec_url`.
+pub fn swagger_ui(spec_url: &str) -> String {
+    format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Fibonacci Calculator API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>SwaggerUIBundle({{ url: "{}", dom_id: "#swagger-ui" }});</script>
+</body>
+</html>
+"##,
+        spec_url
+    )
+}
+
+fn required(mut parameter: Value) -> Value {
+    parameter["required"] = json!(true);
+    parameter
+}
+
+fn query_parameter(name: &str, description: &str, schema: Value) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": false,
+        "description": description,
+        "schema": schema,
+    })
+}
+
+fn bigint_list_parameter(name: &str, description: &str, config: &Config, required: bool) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": required,
+        "description": description,
+        "style": "form",
+        "explode": true,
+        "schema": {
+            "type": "array",
+            "items": { "type": "string", "pattern": "^-?[0-9]+$", "maxLength": config.max_gcd_digits + 1 },
+            "maxItems": config.max_batch_size,
+        },
+    })
+}
+
+fn format_parameter() -> Value {
+    let names: Vec<_> = Format::ALL.iter().map(Format::name).collect();
+    query_parameter(
+        "format",
+        "Output format; overrides the Accept header.",
+        json!({ "type": "string", "enum": names }),
+    )
+}
+
+// `POST /api/verify`, answered in JSON only like jobs
+fn verification() -> Value {
+    let error = json!({
+        "description": "Error",
+        "content": { PROBLEM_CONTENT_TYPE: { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+    json!({
+        "post": {
+            "summary": "Check a response body against its X-Signature header",
+            "requestBody": {
+                "required": true,
+                "content": {
+                    "application/json": {
+                        "schema": {
+                            "type": "object",
+                            "required": ["body", "signature"],
+                            "properties": {
+                                "body": { "description": "The response body as a string, or the JSON document itself" },
+                                "signature": { "type": "string", "example": "t=1700000000,v1=d36d6ce71da26cb3..." },
+                            },
+                        }
+                    }
+                }
+            },
+            "responses": {
+                "200": {
+                    "description": "Whether the signature matches",
+                    "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Verification" } } },
+                },
+                "400": error,
+                "401": error,
+                "405": error,
+                "422": error,
+                "429": error,
+                "503": error,
+            },
+        }
+    })
+}
+
+fn verification_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "valid": { "type": "boolean" },
+            "signed_at": { "type": "string", "format": "date-time", "nullable": true },
+            "age_secs": { "type": "integer", "nullable": true },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+// `GET /api/usage`, answered in JSON only
+fn graphql_query(config: &Config) -> Value {
+    let error = json!({
+        "description": "Error",
+        "content": { PROBLEM_CONTENT_TYPE: { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+    let answer = |description: &str| {
+        json!({
+            "description": description,
+            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/GraphqlResponse" } } },
+        })
+    };
+    json!({
+        "get": {
+            "summary": "The GraphQL schema in SDL",
+            "responses": {
+                "200": { "description": "The schema", "content": { "text/plain": { "schema": { "type": "string" } } } },
+                "405": error,
+            },
+        },
+        "post": {
+            "summary": "Run a GraphQL query over fibonacci, lucas, range and isPrime",
+            "description": format!(
+                "Fields failing a limit are null with an entry in `errors`; the rest of the query is still \
+                 answered. Fragments, directives, mutations and introspection are not supported. At most {} \
+                 fields per query.",
+                config.max_graphql_fields
+            ),
+            "requestBody": {
+                "required": true,
+                "content": {
+                    "application/json": {
+                        "schema": {
+                            "type": "object",
+                            "required": ["query"],
+                            "properties": {
+                                "query": {
+                                    "type": "string",
+                                    "example": "{ fibonacci(n: 100) { value digits } isPrime(x: \"97\") }",
+                                },
+                                "variables": { "type": "object", "additionalProperties": true },
+                                "operationName": { "type": "string" },
+                            },
+                        }
+                    }
+                }
+            },
+            "responses": {
+                "200": answer("The data, with an error for every field that failed"),
+                "400": answer("The query does not parse or does not fit the schema"),
+                "401": error,
+                "405": error,
+                "429": error,
+                "504": error,
+            },
+        }
+    })
+}
+
+fn graphql_response_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "data": { "type": "object", "additionalProperties": true, "description": "Absent when the query was rejected" },
+            "errors": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "message": { "type": "string" },
+                        "locations": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": { "line": { "type": "integer" }, "column": { "type": "integer" } },
+                            },
+                        },
+                        "path": { "type": "array", "items": {} },
+                        "extensions": {
+                            "type": "object",
+                            "properties": { "code": { "type": "string", "enum": ApiError::CODES } },
+                        },
+                    },
+                },
+            },
+        },
+    })
+}
+
+fn sequence_stream(config: &Config) -> Value {
+    let index = json!({ "type": "integer", "minimum": 0, "maximum": config.max_n });
+    let error = json!({
+        "description": "Error",
+        "content": { PROBLEM_CONTENT_TYPE: { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+    json!({
+        "get": {
+            "summary": "Stream F(from)..=F(to) one element at a time",
+            "description": format!(
+                "NDJSON lines, or server-sent events (one `fibonacci` event per element, then `end`) with \
+                 `format=sse` or `Accept: text/event-stream`. Elements are written as they are generated; \
+                 deployed functions send the stream once complete. At most {} elements per request.",
+                config.max_stream_size
+            ),
+            "parameters": [
+                required(query_parameter("from", "First index of the run.", index.clone())),
+                required(query_parameter("to", "Last index (inclusive).", index)),
+                base_parameter(),
+                query_parameter(
+                    "format",
+                    "Framing; overrides the Accept header.",
+                    json!({ "type": "string", "enum": ["ndjson", "sse"] }),
+                ),
+            ],
+            "responses": {
+                "200": {
+                    "description": "The elements in order",
+                    "content": {
+                        "application/x-ndjson": { "schema": { "type": "string" } },
+                        "text/event-stream": { "schema": { "type": "string" } },
+                    },
+                },
+                "400": error,
+                "401": error,
+                "405": error,
+                "422": error,
+                "429": error,
+            },
+        }
+    })
+}
+
+fn usage_report() -> Value {
+    let error = json!({
+        "description": "Error",
+        "content": { PROBLEM_CONTENT_TYPE: { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+    json!({
+        "get": {
+            "summary": "Requests and compute units the API key used today and this month",
+            "description": format!(
+                "Each request costs one compute unit plus one per {} of the indices it computes in full; \
+                 residues, last digits and estimates cost one more unit at any index. \
+                 Not counted itself, and answered even once a quota is used up.",
+                usage::N_PER_UNIT
+            ),
+            "responses": {
+                "200": {
+                    "description": "Usage in the current UTC day and month",
+                    "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Usage" } } },
+                },
+                "401": error,
+                "405": error,
+                "429": error,
+                "503": error,
+            },
+        }
+    })
+}
+
+fn usage_schema() -> Value {
+    let limit = json!({ "type": "integer", "nullable": true, "description": "Absent quotas are null" });
+    let period = json!({
+        "type": "object",
+        "properties": {
+            "period": { "type": "string", "example": "2026-10-14" },
+            "requests": { "type": "integer" },
+            "units": { "type": "integer" },
+            "limits": {
+                "type": "object",
+                "properties": { "requests": limit, "units": limit },
+            },
+            "resets_at": { "type": "string", "format": "date-time" },
+        },
+    });
+    json!({
+        "type": "object",
+        "properties": {
+            "daily": period,
+            "monthly": period,
+            "n_per_unit": { "type": "integer" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn benchmark_report() -> Value {
+    let error = json!({
+        "description": "Error",
+        "content": { PROBLEM_CONTENT_TYPE: { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+    json!({
+        "get": {
+            "summary": "Runs a fixed workload on this instance and times each task",
+            "description": "F(10000) and F(100000) by fast doubling, F(100000) in decimal, the 100000th prime \
+                            and a batch of primality tests, always computed from scratch. Not counted against \
+                            the quotas; the tasks share the compute budget.",
+            "responses": {
+                "200": {
+                    "description": "Timings and memory estimates",
+                    "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Benchmark" } } },
+                },
+                "401": error,
+                "405": error,
+                "429": error,
+                "504": error,
+            },
+        }
+    })
+}
+
+fn benchmark_schema() -> Value {
+    let bytes = json!({ "type": "integer", "nullable": true, "description": "Null where the platform does not report it" });
+    json!({
+        "type": "object",
+        "properties": {
+            "tasks": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string", "example": "fibonacci-100k" },
+                        "description": { "type": "string" },
+                        "elapsed_ns": { "type": "integer" },
+                        "result_bytes": { "type": "integer", "description": "Estimated size of the task's result" },
+                    },
+                },
+            },
+            "total_ns": { "type": "integer" },
+            "memory": {
+                "type": "object",
+                "properties": {
+                    "resident_bytes_before": bytes,
+                    "resident_bytes_after": bytes,
+                    "peak_resident_bytes": bytes,
+                },
+            },
+            "instance": {
+                "type": "object",
+                "properties": {
+                    "run": { "type": "integer", "description": "Benchmarks run on this instance; 1 on a cold one" },
+                    "uptime_seconds": { "type": "number" },
+                    "version": { "type": "string" },
+                    "git_sha": { "type": "string" },
+                },
+            },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn idempotency_parameter() -> Value {
+    json!({
+        "name": "Idempotency-Key",
+        "in": "header",
+        "required": false,
+        "description": "Repeating a request with the same key replays the first response instead of running it again.",
+        "schema": { "type": "string", "minLength": 1, "maxLength": idempotency::MAX_KEY_LEN },
+    })
+}
+
+fn shape_parameters() -> [Value; 3] {
+    [
+        query_parameter(
+            "fields",
+            "Comma-separated top-level keys to keep in JSON and MessagePack documents.",
+            json!({ "type": "string" }),
+        ),
+        query_parameter(
+            "pretty",
+            "Indent the JSON document.",
+            json!({ "type": "boolean" }),
+        ),
+        query_parameter(
+            "grouped",
+            "Add `formatted` values with digit grouping for the Accept-Language locale (English by default); false turns off grouping requested by that header.",
+            json!({ "type": "boolean" }),
+        ),
+    ]
+}
+
+fn base_schema() -> Value {
+    json!({ "type": "integer", "minimum": radix::MIN_BASE, "maximum": radix::MAX_BASE })
+}
+
+fn algo_schema() -> Value {
+    json!({ "type": "string", "enum": ["iterative", "fast-doubling", "matrix", "binet"] })
+}
+
+fn base_parameter() -> Value {
+    query_parameter(
+        "base",
+        "Radix of the returned values; bases above 36 use the alphabet 0-9A-Za-z.",
+        base_schema(),
+    )
+}
+
+// Success in every negotiable format, plus the shared error responses
+// The problem document every error is sent as, see `ApiError::to_json`
+fn error_schema() -> Value {
+    json!({
+        "type": "object",
+        "description": "An RFC 7807 problem document.",
+        "required": ["type", "title", "status", "detail", "code"],
+        "properties": {
+            "type": { "type": "string", "format": "uri", "example": format!("{}out_of_range", PROBLEM_TYPE_PREFIX) },
+            "title": { "type": "string" },
+            "status": { "type": "integer", "description": "The HTTP status code" },
+            "detail": { "type": "string" },
+            "instance": { "type": "string", "description": "Path of the failed request" },
+            "code": { "type": "string", "enum": ApiError::CODES },
+            "parameter": { "type": "string" },
+            "received": { "type": "string" },
+            "expected": { "type": "string", "description": "What an invalid parameter accepts, such as its range" },
+            "min": { "type": "integer", "description": "Lower end of the range an out-of-range parameter accepts" },
+            "max": { "type": "integer" },
+            "position": { "type": "integer", "description": "Character offset of an invalid expression's problem" },
+            "size": { "type": "integer", "description": "Bytes in a body or query string over its limit" },
+            "key": { "type": "string", "description": "An idempotency key already used for another request, or still in use" },
+            "retry_after": { "type": "integer" },
+            "path": { "type": "string" },
+            "budget_ms": { "type": "integer" },
+            "elapsed_ms": { "type": "integer" },
+            "progress": { "type": "string" },
+            "suggestions": { "type": "array", "items": { "type": "string" } },
+            "timestamp": { "type": "string", "format": "date-time" },
+        },
+    })
+}
+
+// A job is accepted rather than computed, so it answers 202 in JSON only
+// `GET /api?n=5,10,200`
+fn several_indices(config: &Config) -> Value {
+    json!({
+        "summary": "Compute F(n) for several indices",
+        "parameters": [
+            {
+                "name": "n",
+                "in": "query",
+                "required": true,
+                "description": "Indices, comma-separated (`n=5,10,200`) or repeated (`n=5&n=10`).",
+                "style": "form",
+                "explode": true,
+                "schema": {
+                    "type": "array",
+                    "items": { "type": "integer", "minimum": -(config.max_n as i128), "maximum": config.max_n },
+                    "maxItems": config.max_batch_size,
+                },
+            },
+            base_parameter(),
+            format_parameter(),
+        ],
+        "responses": responses("FibonacciMap", true),
+    })
+}
+
+fn fibonacci_map_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "results": {
+                "type": "object",
+                "description": "F(n) keyed by the index n",
+                "additionalProperties": { "type": "string" },
+            },
+            "count": { "type": "integer" },
+            "base": { "type": "integer" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn continued_fraction(config: &Config) -> Value {
+    json!({
+        "get": {
+            "summary": "Continued fraction expansion and convergents of a rational or square root",
+            "parameters": [
+                required(query_parameter(
+                    "x",
+                    "An integer, a fraction `p/q`, or `sqrt(n)` / `sqrt(p/q)`.",
+                    json!({ "type": "string", "example": "sqrt(2)" }),
+                )),
+                query_parameter(
+                    "terms",
+                    "Most terms to compute, 20 by default.",
+                    json!({ "type": "integer", "minimum": 0, "maximum": config.max_cf_terms }),
+                ),
+                format_parameter(),
+            ],
+            "responses": responses("ContinuedFraction", true),
+        }
+    })
+}
+
+fn continued_fraction_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "x": { "type": "string", "description": "x in lowest terms" },
+            "terms": { "type": "array", "items": { "type": "string" } },
+            "convergents": { "type": "array", "items": { "type": "string" }, "description": "p/q after each term" },
+            "notation": { "type": "string", "example": "[1; 2, 2, 2, ...]" },
+            "complete": { "type": "boolean", "description": "Whether the terms are the whole expansion" },
+            "period": {
+                "type": "object",
+                "description": "The repeating block of a square root, once the terms show it",
+                "properties": { "start": { "type": "integer" }, "length": { "type": "integer" } },
+            },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn conversion(config: &Config) -> Value {
+    let base = |name: &str, description: &str| {
+        query_parameter(name, description, json!({ "type": "integer", "minimum": radix::MIN_BASE, "maximum": radix::MAX_BASE }))
+    };
+    json!({
+        "get": {
+            "summary": "Convert an integer of any size between bases 2 to 62",
+            "parameters": [
+                required(query_parameter(
+                    "value",
+                    "The integer in base `from`, optionally starting with `-`.",
+                    json!({ "type": "string", "maxLength": config.max_convert_digits, "example": "1a6d" }),
+                )),
+                base("from", "Base of `value`, 10 by default."),
+                required(base("to", "Base of the result.")),
+                format_parameter(),
+            ],
+            "responses": responses("Conversion", true),
+        }
+    })
+}
+
+fn conversion_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "value": { "type": "string", "description": "The value as read, in base `from`" },
+            "from": { "type": "integer" },
+            "to": { "type": "integer" },
+            "result": { "type": "string" },
+            "digits": { "type": "integer", "description": "Digits in the result" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+// `POST /api/eval`, listing the functions from the registry
+fn evaluation(config: &Config) -> Value {
+    let functions: Vec<_> = FUNCTIONS.iter().map(|function| format!("`{}`", function.signature)).collect();
+    json!({
+        "post": {
+            "summary": "Evaluate an arithmetic expression over arbitrary-size integers",
+            "description": format!(
+                "Operators `+ - * / % ^ !` with `/` and `%` rounding down, parentheses, and the functions {}.",
+                functions.join(", ")
+            ),
+            "requestBody": {
+                "required": true,
+                "content": {
+                    "application/json": {
+                        "schema": {
+                            "type": "object",
+                            "required": ["expression"],
+                            "properties": {
+                                "expression": {
+                                    "type": "string",
+                                    "maxLength": config.max_eval_length,
+                                    "example": "fib(100) * 3 + factorial(20)",
+                                },
+                            },
+                        }
+                    }
+                }
+            },
+            "responses": responses("Evaluation", false),
+        }
+    })
+}
+
+fn evaluation_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "expression": { "type": "string" },
+            "result": { "type": "string" },
+            "digits": { "type": "integer" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn digit_stats(config: &Config) -> Value {
+    json!({
+        "get": {
+            "summary": "The decimal digits of F(n), or statistics over them",
+            "description": "Negative indices describe the digits of |F(n)|.",
+            "parameters": [
+                {
+                    "name": "n",
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "integer", "minimum": -(config.max_n as i128), "maximum": config.max_n },
+                },
+                query_parameter(
+                    "stats",
+                    "Answer the histogram, digit sum and digital root instead of the digits.",
+                    json!({ "type": "boolean" }),
+                ),
+                format_parameter(),
+            ],
+            "responses": responses("DigitStats", true),
+        }
+    })
+}
+
+fn digit_stats_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "n": { "type": "integer" },
+            "digits": { "type": "integer" },
+            "value": { "type": "string", "description": "Without `stats` only" },
+            "histogram": {
+                "type": "array",
+                "items": { "type": "integer" },
+                "minItems": 10,
+                "maxItems": 10,
+                "description": "With `stats` only: occurrences of each digit 0 to 9",
+            },
+            "digit_sum": { "type": "integer", "description": "With `stats` only" },
+            "digital_root": { "type": "integer", "description": "With `stats` only" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn fibonacci_sums(config: &Config) -> Value {
+    json!({
+        "get": {
+            "summary": "Sums of F(1), ..., F(n) from closed-form identities",
+            "parameters": [
+                required(query_parameter(
+                    "n",
+                    "Number of terms summed.",
+                    json!({ "type": "integer", "minimum": 0, "maximum": config.max_n }),
+                )),
+                format_parameter(),
+            ],
+            "responses": responses("FibonacciSums", true),
+        }
+    })
+}
+
+fn fibonacci_sums_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "n": { "type": "integer" },
+            "sum": { "type": "string", "description": "F(n+2) − 1" },
+            "sum_of_squares": { "type": "string", "description": "F(n)·F(n+1)" },
+            "alternating_sum": { "type": "string", "description": "(−1)^(n+1)·F(n−1) + 1" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn fibonacci_word(config: &Config) -> Value {
+    json!({
+        "get": {
+            "summary": "The Fibonacci word S(n) = S(n−1)S(n−2), from S(0) = 0 and S(1) = 01",
+            "parameters": [
+                required(query_parameter(
+                    "n",
+                    "Index of the word.",
+                    json!({ "type": "integer", "minimum": 0, "maximum": config.max_n }),
+                )),
+                query_parameter(
+                    "max_len",
+                    "Most characters of the word to return.",
+                    json!({ "type": "integer", "minimum": 0, "maximum": config.max_fib_word_len }),
+                ),
+                format_parameter(),
+            ],
+            "responses": responses("FibonacciWord", true),
+        }
+    })
+}
+
+fn fibonacci_word_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "n": { "type": "integer" },
+            "word": { "type": "string", "description": "The word, cut to max_len characters" },
+            "length": { "type": "string", "description": "Length of the whole word, F(n+2)" },
+            "zeros": { "type": "string", "description": "F(n+1)" },
+            "ones": { "type": "string", "description": "F(n)" },
+            "truncated": { "type": "boolean" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn random_values(config: &Config) -> Value {
+    json!({
+        "get": {
+            "summary": "Cryptographically secure random integers in a range",
+            "parameters": [
+                query_parameter(
+                    "min",
+                    "Smallest value, 0 by default.",
+                    json!({ "type": "string", "pattern": "^-?[0-9]+$" }),
+                ),
+                required(query_parameter(
+                    "max",
+                    "Largest value; the range may span at most the configured number of bytes.",
+                    json!({ "type": "string", "pattern": "^-?[0-9]+$" }),
+                )),
+                query_parameter(
+                    "count",
+                    "Values to draw, 1 by default.",
+                    json!({ "type": "integer", "minimum": 0, "maximum": config.max_random_count }),
+                ),
+                format_parameter(),
+            ],
+            "responses": responses("Random", false),
+        }
+    })
+}
+
+fn random_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "values": { "type": "array", "items": { "type": "string" } },
+            "min": { "type": "string" },
+            "max": { "type": "string" },
+            "count": { "type": "integer" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+// `GET /api/seq/{name}/{n}`, with the names taken from the registry
+// `index` says whether `n` is the last path segment or a query parameter
+fn sequence_term(config: &Config, index: &str) -> Value {
+    let names: Vec<_> = SEQUENCES.iter().map(|sequence| sequence.name).collect();
+    json!({
+        "get": {
+            "summary": "The n-th term of a classic integer sequence",
+            "parameters": [
+                {
+                    "name": "name",
+                    "in": "path",
+                    "required": true,
+                    "description": "The sequence; `GET /api/seq` describes each one.",
+                    "schema": { "type": "string", "enum": names },
+                },
+                {
+                    "name": "n",
+                    "in": index,
+                    "required": true,
+                    "description": "Index of the term, from 0.",
+                    "schema": { "type": "integer", "minimum": 0, "maximum": config.max_seq_n },
+                },
+                format_parameter(),
+            ],
+            "responses": responses("Sequence", true),
+        }
+    })
+}
+
+fn documentation_page() -> Value {
+    json!({
+        "get": {
+            "summary": "Swagger UI rendering this document",
+            "responses": {
+                "200": {
+                    "description": "An HTML page loading Swagger UI from unpkg",
+                    "content": { "text/html": { "schema": { "type": "string" } } },
+                }
+            },
+        }
+    })
+}
+
+fn sequence_list() -> Value {
+    json!({
+        "get": {
+            "summary": "The sequences served under /api/seq",
+            "parameters": [format_parameter()],
+            "responses": responses("SequenceList", true),
+        }
+    })
+}
+
+fn sequence_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "sequence": { "type": "string" },
+            "n": { "type": "integer" },
+            "value": { "type": "string" },
+            "oeis": { "type": "string", "description": "OEIS A-number" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn sequence_list_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "sequences": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "description": { "type": "string" },
+                        "oeis": { "type": "string" },
+                        "url": { "type": "string" },
+                    },
+                },
+            },
+            "count": { "type": "integer" },
+            "timestamp": { "type": "string", "format": "date-time" },
+            "status": { "type": "string", "enum": ["success"] },
+        },
+    })
+}
+
+fn job_responses() -> Value {
+    let error = json!({
+        "description": "Error",
+        "content": { PROBLEM_CONTENT_TYPE: { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+    json!({
+        "202": {
+            "description": "Accepted; the result follows at the callback URL",
+            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Job" } } },
+        },
+        "400": error,
+        "401": error,
+        "405": error,
+        "422": error,
+        "429": error,
+        "503": error,
+    })
+}
+
+fn responses(schema: &str, cacheable: bool) -> Value {
+    let reference = json!({ "$ref": format!("#/components/schemas/{}", schema) });
+    let content: Map<String, Value> = Format::ALL
+        .iter()
+        .map(|format| {
+            let schema = match format {
+                Format::Json | Format::MessagePack => reference.clone(),
+                Format::Text | Format::Csv | Format::Ndjson => json!({ "type": "string" }),
+            };
+            (format.media_type().to_string(), json!({ "schema": schema }))
+        })
+        .collect();
+
+    let error = json!({
+        "description": "Error",
+        "content": { PROBLEM_CONTENT_TYPE: { "schema": { "$ref": "#/components/schemas/Error" } } },
+    });
+    let mut responses = json!({
+        "200": { "description": "Success", "content": content },
+        "400": error,
+        "401": error,
+        "405": error,
+        "422": error,
+        "414": error,
+        "429": error,
+    });
+    // Only the endpoints checking the time budget answer 504
+    if matches!(
+        schema,
+        "Fibonacci" | "FibonacciMap" | "Batch" | "Factorization" | "Collatz" | "Evaluation"
+    ) {
+        responses["504"] = error;
+    }
+    if cacheable {
+        responses["304"] = json!({ "description": "Not modified since the ETag in If-None-Match" });
+    }
+    responses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_follow_the_configuration() {
+        let config = Config {
+            max_n: 5000,
+            max_batch_size: 7,
+            ..Config::default()
+        };
+        let spec = spec(&config);
+
+        let n = &spec["paths"]["/api/{n}"]["get"]["parameters"][0];
+        assert_eq!(n["schema"]["maximum"], 5000);
+        let batch = &spec["paths"]["/api/batch"]["post"]["requestBody"]["content"]["application/json"];
+        assert_eq!(batch["schema"]["properties"]["n"]["maxItems"], 7);
+        assert!(spec.get("security").is_none());
+    }
+
+    #[test]
+    fn documents_every_format_and_error_code() {
+        let spec = spec(&Config::default());
+        let content = &spec["paths"]["/api/range"]["get"]["responses"]["200"]["content"];
+        for format in Format::ALL {
+            assert!(content.get(format.media_type()).is_some(), "{:?}", format);
+        }
+        assert_eq!(
+            spec["components"]["schemas"]["Error"]["properties"]["code"]["enum"],
+            json!(ApiError::CODES)
+        );
+    }
+
+    #[test]
+    fn documents_every_route() {
+        let spec = spec(&Config::default());
+        let mut documented: Vec<&str> = spec["paths"].as_object().unwrap().keys().map(String::as_str).collect();
+        let mut routed = crate::handlers::paths();
+        documented.sort_unstable();
+        routed.sort_unstable();
+        assert_eq!(documented, routed);
+    }
+}
@@ -0,0 +1,432 @@
+//! Optional OpenTelemetry export over OTLP/HTTP, in its JSON encoding.
+//!
+//! With `OTEL_EXPORTER_OTLP_ENDPOINT` set, every request becomes a server span
+//! in its [`TraceContext`](crate::telemetry::TraceContext) trace, with child
+//! spans timing the computations ([`child_span`]) and an `exception` event
+//! for every error it answers ([`record_error`]). The spans, and a snapshot of
+//! the [`metrics`] counters, are posted to `/v1/traces` and `/v1/metrics`
+//! under that endpoint before the response is returned: a lambda may be
+//! frozen the moment it answers, so a batch left for later may never be sent.
+//! Export gives up after [`EXPORT_TIMEOUT`] and its failures are only logged.
+
+use crate::build_info;
+use crate::config::Config;
+use crate::metrics::{self, EndpointSnapshot, LATENCY_BUCKETS, STATUS_CLASSES};
+use crate::middleware;
+use crate::telemetry::{self, TraceContext};
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use vercel_runtime::{Body, Error, Response};
+
+/// Longest the export may hold up a response.
+pub const EXPORT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// OTLP enum values
+const SPAN_KIND_INTERNAL: u8 = 1;
+const SPAN_KIND_SERVER: u8 = 2;
+const STATUS_UNSET: u8 = 0;
+const STATUS_ERROR: u8 = 2;
+const TEMPORALITY_CUMULATIVE: u8 = 2;
+
+/// A finished span waiting to be exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanData {
+    pub name: String,
+    pub span_id: String,
+    /// `None` for a request that started its trace.
+    pub parent_id: Option<String>,
+    pub kind: u8,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Vec<(&'static str, Value)>,
+    pub events: Vec<SpanEvent>,
+    pub error: bool,
+}
+
+/// Something that happened during a span, such as an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanEvent {
+    pub name: &'static str,
+    pub time: SystemTime,
+    pub attributes: Vec<(&'static str, Value)>,
+}
+
+// What the request recorded so far, next to its own span
+#[derive(Default)]
+struct Pending {
+    spans: Vec<SpanData>,
+    events: Vec<SpanEvent>,
+}
+
+tokio::task_local! {
+    static PENDING: RefCell<Pending>;
+}
+
+/// Whether export is configured.
+pub fn enabled() -> bool {
+    Config::get().otlp_endpoint.is_some()
+}
+
+/// Adds an `exception` event for an error response to the current request's
+/// span. Does nothing when export is off or outside a request.
+pub fn record_error(code: &str, message: &str) {
+    let event = SpanEvent {
+        name: "exception",
+        time: SystemTime::now(),
+        attributes: vec![
+            ("exception.type", json!(code)),
+            ("exception.message", json!(message)),
+        ],
+    };
+    let _ = PENDING.try_with(|pending| pending.borrow_mut().events.push(event));
+}
+
+/// Records a child of the current request's span that ran from `start` until
+/// now, e.g. computing F(n). Does nothing when export is off or outside a
+/// request.
+pub fn child_span(name: impl Into<String>, start: SystemTime, attributes: Vec<(&'static str, Value)>) {
+    let Some(trace) = middleware::trace_context() else {
+        return;
+    };
+    let span = SpanData {
+        name: name.into(),
+        span_id: telemetry::random_hex(8),
+        parent_id: Some(trace.span_id),
+        kind: SPAN_KIND_INTERNAL,
+        start,
+        end: SystemTime::now(),
+        attributes,
+        events: Vec::new(),
+        error: false,
+    };
+    let _ = PENDING.try_with(|pending| pending.borrow_mut().spans.push(span));
+}
+
+/// Runs `handler` as the request to `endpoint`, then exports its spans and
+/// the metrics before handing back the response. Without an endpoint
+/// configured this is just `handler.await`.
+pub async fn collect<Fut>(endpoint: &'static str, method: String, path: String, handler: Fut) -> Result<Response<Body>, Error>
+where
+    Fut: Future<Output = Result<Response<Body>, Error>>,
+{
+    if !enabled() {
+        return handler.await;
+    }
+    process_start();
+    let start = SystemTime::now();
+    PENDING
+        .scope(RefCell::new(Pending::default()), async move {
+            let result = handler.await;
+            let Pending { mut spans, mut events } = PENDING.with(|pending| pending.take());
+            let Some(trace) = middleware::trace_context() else {
+                return result;
+            };
+            let status = match &result {
+                Ok(response) => response.status().as_u16(),
+                Err(err) => {
+                    events.push(SpanEvent {
+                        name: "exception",
+                        time: SystemTime::now(),
+                        attributes: vec![("exception.message", json!(err.to_string()))],
+                    });
+                    500
+                }
+            };
+            spans.push(SpanData {
+                name: format!("{} {}", method, endpoint),
+                span_id: trace.span_id.clone(),
+                parent_id: trace.parent_id.clone(),
+                kind: SPAN_KIND_SERVER,
+                start,
+                end: SystemTime::now(),
+                attributes: vec![
+                    ("http.request.method", json!(method)),
+                    ("http.route", json!(endpoint)),
+                    ("url.path", json!(path)),
+                    ("http.response.status_code", json!(status)),
+                ],
+                events,
+                error: status >= 500,
+            });
+            export(&trace, &spans).await;
+            result
+        })
+        .await
+}
+
+async fn export(trace: &TraceContext, spans: &[SpanData]) {
+    let config = Config::get();
+    let Some(endpoint) = config.otlp_endpoint.as_deref() else {
+        return;
+    };
+    let traces = traces_request(&config.otel_service_name, trace, spans);
+    let metrics = metrics_request(&config.otel_service_name, &metrics::snapshot(), process_start(), SystemTime::now());
+    let both = async {
+        tokio::join!(post(endpoint, "traces", traces), post(endpoint, "metrics", metrics));
+    };
+    if tokio::time::timeout(EXPORT_TIMEOUT, both).await.is_err() {
+        tracing::warn!("telemetry export timed out");
+    }
+}
+
+async fn post(endpoint: &str, signal: &str, body: Value) {
+    let mut request = client()
+        .post(format!("{}/v1/{}", endpoint, signal))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    if let Some(headers) = &Config::get().otlp_headers {
+        for (name, value) in parse_headers(&headers.0) {
+            request = request.header(name, value);
+        }
+    }
+    match request.send().await {
+        Ok(response) if response.status().is_success() => {
+            tracing::debug!(signal, "exported telemetry");
+        }
+        Ok(response) => tracing::warn!(signal, status = response.status().as_u16(), "collector rejected telemetry"),
+        Err(err) => tracing::warn!(signal, error = %err, "telemetry export failed"),
+    }
+}
+
+/// `key=value` pairs separated by commas, as in `OTEL_EXPORTER_OTLP_HEADERS`.
+pub fn parse_headers(raw: &str) -> Vec<(String, String)> {
+    raw.split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+/// The `ExportTraceServiceRequest` for `spans` of one trace.
+pub fn traces_request(service: &str, trace: &TraceContext, spans: &[SpanData]) -> Value {
+    let spans: Vec<Value> = spans
+        .iter()
+        .map(|span| {
+            let mut encoded = json!({
+                "traceId": trace.trace_id,
+                "spanId": span.span_id,
+                "name": span.name,
+                "kind": span.kind,
+                "startTimeUnixNano": nanos(span.start),
+                "endTimeUnixNano": nanos(span.end),
+                "attributes": attributes(&span.attributes),
+                "events": span.events.iter().map(|event| json!({
+                    "timeUnixNano": nanos(event.time),
+                    "name": event.name,
+                    "attributes": attributes(&event.attributes),
+                })).collect::<Vec<_>>(),
+                "status": { "code": if span.error { STATUS_ERROR } else { STATUS_UNSET } },
+            });
+            if let Some(parent) = &span.parent_id {
+                encoded["parentSpanId"] = json!(parent);
+            }
+            if let (SPAN_KIND_SERVER, Some(state)) = (span.kind, &trace.state) {
+                encoded["traceState"] = json!(state);
+            }
+            encoded
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": resource(service),
+            "scopeSpans": [{ "scope": scope(), "spans": spans }],
+        }]
+    })
+}
+
+/// The `ExportMetricsServiceRequest` for the cumulative counters in
+/// `snapshot`, counted since `start`.
+pub fn metrics_request(service: &str, snapshot: &[EndpointSnapshot], start: SystemTime, now: SystemTime) -> Value {
+    let (start, now) = (nanos(start), nanos(now));
+    let requests: Vec<Value> = snapshot
+        .iter()
+        .flat_map(|endpoint| {
+            STATUS_CLASSES
+                .iter()
+                .zip(endpoint.by_status_class)
+                .filter(|(_, count)| *count > 0)
+                .map(|(class, count)| {
+                    json!({
+                        "attributes": attributes(&[("endpoint", json!(endpoint.name)), ("status", json!(class))]),
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": now,
+                        "asInt": count.to_string(),
+                    })
+                })
+        })
+        .collect();
+    let durations: Vec<Value> = snapshot
+        .iter()
+        .map(|endpoint| {
+            json!({
+                "attributes": attributes(&[("endpoint", json!(endpoint.name))]),
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "count": endpoint.requests().to_string(),
+                "sum": endpoint.latency_sum_secs,
+                "bucketCounts": endpoint.latency_buckets.iter().map(u64::to_string).collect::<Vec<_>>(),
+                "explicitBounds": LATENCY_BUCKETS,
+            })
+        })
+        .collect();
+    json!({
+        "resourceMetrics": [{
+            "resource": resource(service),
+            "scopeMetrics": [{
+                "scope": scope(),
+                "metrics": [
+                    {
+                        "name": "fib.requests",
+                        "description": "Requests handled, by endpoint and status class.",
+                        "unit": "{request}",
+                        "sum": {
+                            "aggregationTemporality": TEMPORALITY_CUMULATIVE,
+                            "isMonotonic": true,
+                            "dataPoints": requests,
+                        },
+                    },
+                    {
+                        "name": "fib.request.duration",
+                        "description": "Request latency.",
+                        "unit": "s",
+                        "histogram": {
+                            "aggregationTemporality": TEMPORALITY_CUMULATIVE,
+                            "dataPoints": durations,
+                        },
+                    },
+                ],
+            }],
+        }]
+    })
+}
+
+fn resource(service: &str) -> Value {
+    json!({
+        "attributes": attributes(&[
+            ("service.name", json!(service)),
+            ("service.version", json!(build_info::VERSION)),
+            ("telemetry.sdk.language", json!("rust")),
+        ])
+    })
+}
+
+fn scope() -> Value {
+    json!({ "name": env!("CARGO_PKG_NAME"), "version": build_info::VERSION })
+}
+
+// OTLP JSON wraps every value in its type; 64-bit integers travel as strings
+fn attributes(pairs: &[(&'static str, Value)]) -> Vec<Value> {
+    pairs
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::Bool(flag) => json!({ "boolValue": flag }),
+                Value::Number(number) if number.is_f64() => json!({ "doubleValue": number }),
+                Value::Number(number) => json!({ "intValue": number.to_string() }),
+                Value::String(text) => json!({ "stringValue": text }),
+                other => json!({ "stringValue": other.to_string() }),
+            };
+            json!({ "key": key, "value": value })
+        })
+        .collect()
+}
+
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH).map(|since| since.as_nanos()).unwrap_or(0).to_string()
+}
+
+// When this instance started counting, the start of every cumulative series
+fn process_start() -> SystemTime {
+    static START: OnceLock<SystemTime> = OnceLock::new();
+    *START.get_or_init(SystemTime::now)
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(EXPORT_TIMEOUT)
+            .build()
+            .expect("default TLS backend is available")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn encodes_spans() {
+        let trace = TraceContext {
+            trace_id: "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+            span_id: "00f067aa0ba902b7".to_string(),
+            parent_id: None,
+            flags: 1,
+            state: Some("congo=1".to_string()),
+        };
+        let span = SpanData {
+            name: "GET fibonacci".to_string(),
+            span_id: trace.span_id.clone(),
+            parent_id: None,
+            kind: SPAN_KIND_SERVER,
+            start: at(1),
+            end: at(2),
+            attributes: vec![("http.response.status_code", json!(500)), ("url.path", json!("/api/20"))],
+            events: vec![SpanEvent {
+                name: "exception",
+                time: at(2),
+                attributes: vec![("exception.type", json!("internal_error"))],
+            }],
+            error: true,
+        };
+        let body = traces_request("fib", &trace, &[span]);
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(resource["resource"]["attributes"][0]["value"]["stringValue"], "fib");
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(span["startTimeUnixNano"], "1000000000");
+        assert_eq!(span["attributes"][0]["value"]["intValue"], "500");
+        assert_eq!(span["events"][0]["name"], "exception");
+        assert_eq!(span["status"]["code"], STATUS_ERROR);
+        assert_eq!(span["traceState"], "congo=1");
+        assert!(span.get("parentSpanId").is_none());
+    }
+
+    #[test]
+    fn encodes_metrics() {
+        let mut snapshot = EndpointSnapshot {
+            name: "fibonacci",
+            by_status_class: [0, 3, 0, 1, 0],
+            latency_buckets: [0; LATENCY_BUCKETS.len() + 1],
+            latency_sum_secs: 0.5,
+        };
+        snapshot.latency_buckets[2] = 4;
+        let body = metrics_request("fib", &[snapshot], at(1), at(2));
+        let metrics = &body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        let points = metrics[0]["sum"]["dataPoints"].as_array().unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0]["asInt"], "3");
+        let histogram = &metrics[1]["histogram"]["dataPoints"][0];
+        assert_eq!(histogram["count"], "4");
+        assert_eq!(histogram["bucketCounts"][2], "4");
+        assert_eq!(histogram["explicitBounds"].as_array().unwrap().len(), LATENCY_BUCKETS.len());
+    }
+
+    #[test]
+    fn parses_header_pairs() {
+        assert_eq!(
+            parse_headers("x-honeycomb-team=abc, x-dataset = api,broken"),
+            vec![
+                ("x-honeycomb-team".to_string(), "abc".to_string()),
+                ("x-dataset".to_string(), "api".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Bounded concurrency for the endpoints computing many independent values.
+//!
+//! A batch of large indices would otherwise start every computation at once
+//! and make them all finish late; at most `MAX_PARALLELISM` run at a time
+//! instead, so the first ones are done (and can be answered) within the time
+//! budget even when the last ones are not.
+
+use crate::config::Config;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use vercel_runtime::Error;
+
+/// How many tasks [`map`] runs at once; `MAX_PARALLELISM=0` counts as 1.
+pub fn limit() -> usize {
+    usize::try_from(Config::get().max_parallelism).unwrap_or(usize::MAX).max(1)
+}
+
+/// Runs `task` for every item as its own tokio task, at most [`limit`] at a
+/// time, and returns the outputs in the order of `items`.
+pub async fn map<I, F, Fut>(items: I, task: F) -> Result<Vec<Fut::Output>, Error>
+where
+    I: IntoIterator,
+    F: Fn(I::Item) -> Fut,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    let permits = Arc::new(Semaphore::new(limit()));
+    let handles: Vec<_> = items
+        .into_iter()
+        .map(|item| {
+            let permits = Arc::clone(&permits);
+            let work = task(item);
+            tokio::spawn(async move {
+                // The semaphore is never closed, so this always holds a permit
+                let _permit = permits.acquire_owned().await;
+                work.await
+            })
+        })
+        .collect();
+    let mut outputs = Vec::with_capacity(handles.len());
+    for handle in handles {
+        outputs.push(handle.await?);
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn keeps_order_within_the_limit() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let outputs = map(0..20u64, |i| {
+            let (running, peak) = (Arc::clone(&running), Arc::clone(&peak));
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20 - i)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                i * 2
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(outputs, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= limit());
+    }
+}
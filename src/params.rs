@@ -0,0 +1,625 @@
+//! Typed extraction of request parameters.
+//!
+//! A handler declares the parameters it accepts as a struct deriving
+//! `Deserialize` and reads them with [`Params`] from the query string, the
+//! JSON body and the last path segment. A value that does not fit its field
+//! is reported with the parameter's name, the value received and what is
+//! accepted, including the range set with [`Params::bound`]: `parameter 'n'
+//! must be an integer between 0 and 1000, got 'ten'`.
+
+use crate::error::ApiError;
+use crate::query::Query;
+use crate::routing::parse_json_object;
+use num_bigint::{BigInt, BigUint, Sign};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// The parameters of one request, each with every value it was sent.
+#[derive(Debug, Clone, Default)]
+pub struct Params {
+    values: Vec<(String, Raw)>,
+    bounds: Vec<(&'static str, RangeInclusive<i128>)>,
+}
+
+// One received value: text from the query or path, or a JSON body member
+#[derive(Debug, Clone)]
+enum Raw {
+    Text(String),
+    Json(Value),
+}
+
+impl Raw {
+    // The value as it is echoed back in errors
+    fn received(&self) -> String {
+        match self {
+            Raw::Text(text) | Raw::Json(Value::String(text)) => text.clone(),
+            Raw::Json(value) => value.to_string(),
+        }
+    }
+}
+
+impl Params {
+    /// The parameters of a `a=1&b=2` style query string; a key may repeat.
+    pub fn from_query(query: &str) -> Params {
+        let values = Query::parse(query)
+            .iter()
+            .map(|(key, value)| (key.to_string(), Raw::Text(value.to_string())))
+            .collect();
+        Params {
+            values,
+            bounds: Vec::new(),
+        }
+    }
+
+    /// The members of a request body that must be a JSON object. An empty
+    /// body has no parameters.
+    pub fn from_body(body: &[u8]) -> Result<Params, ApiError> {
+        if body.is_empty() {
+            return Ok(Params::default());
+        }
+        let values = parse_json_object(body)?
+            .into_iter()
+            .map(|(key, value)| (key, Raw::Json(value)))
+            .collect();
+        Ok(Params {
+            values,
+            bounds: Vec::new(),
+        })
+    }
+
+    /// Reads `name` from the last segment of `path`, as in `/api/42`, unless
+    /// it was already given. `/api` and `/api/` name nothing.
+    pub fn with_path(mut self, name: &str, path: &str) -> Params {
+        match path.split('/').next_back() {
+            Some("" | "api") | None => {}
+            Some(_) if self.contains(name) => {}
+            Some(segment) => self.values.push((name.to_string(), Raw::Text(segment.to_string()))),
+        }
+        self
+    }
+
+    /// Adds the parameters of `other` that are not given here, so values
+    /// read first take precedence.
+    pub fn merge(mut self, other: Params) -> Params {
+        // Every value of a key comes from the same source
+        let given: Vec<String> = self.values.iter().map(|(key, _)| key.clone()).collect();
+        self.values.extend(other.values.into_iter().filter(|(key, _)| !given.contains(key)));
+        self.bounds.extend(other.bounds);
+        self
+    }
+
+    /// Only accepts integers within `range` for `name`, or for every
+    /// element when it is a list. Integers outside the range are reported as
+    /// out of range, other values as invalid; both errors state the range.
+    pub fn bound(mut self, name: &'static str, range: RangeInclusive<i128>) -> Params {
+        self.bounds.push((name, range));
+        self
+    }
+
+    /// Whether `name` was given at all.
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.iter().any(|(key, _)| key == name)
+    }
+
+    /// Reads the parameters into `T`. A missing field that is not an
+    /// `Option` is reported as a missing parameter.
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, ApiError> {
+        T::deserialize(Request(self)).map_err(|rejection| match rejection {
+            Rejection::Api(err) => err,
+            Rejection::Expected(reason) => ApiError::MalformedBody { reason },
+        })
+    }
+
+    fn values_of(&self, name: &str) -> Vec<Raw> {
+        self.values
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn bound_of(&self, name: &str) -> Option<RangeInclusive<i128>> {
+        self.bounds.iter().find(|(key, _)| *key == name).map(|(_, range)| range.clone())
+    }
+}
+
+/// A non-negative decimal integer of any size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Natural(pub BigUint);
+
+/// A decimal integer of any size and sign.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Integer(pub BigInt);
+
+impl<'de> Deserialize<'de> for Natural {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Natural, D::Error> {
+        let value = deserializer.deserialize_any(Decimal { signed: false })?;
+        Ok(Natural(value.into_parts().1))
+    }
+}
+
+impl<'de> Deserialize<'de> for Integer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Integer, D::Error> {
+        deserializer.deserialize_any(Decimal { signed: true }).map(Integer)
+    }
+}
+
+// Reads decimal digits, with a leading '-' when `signed`; `BigInt::from_str`
+// alone would also accept a '+' and underscores
+struct Decimal {
+    signed: bool,
+}
+
+impl Visitor<'_> for Decimal {
+    type Value = BigInt;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.signed { "a decimal integer" } else { "a non-negative decimal integer" })
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<BigInt, E> {
+        let digits = match value.strip_prefix('-') {
+            Some(digits) if self.signed => digits,
+            _ => value,
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(E::invalid_value(Unexpected::Str(value), &self));
+        }
+        let magnitude = digits.parse::<BigUint>().expect("checked to be decimal");
+        let sign = if digits.len() < value.len() { Sign::Minus } else { Sign::Plus };
+        Ok(BigInt::from_biguint(sign, magnitude))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<BigInt, E> {
+        Ok(BigInt::from(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<BigInt, E> {
+        if value < 0 && !self.signed {
+            return Err(E::invalid_value(Unexpected::Signed(value), &self));
+        }
+        Ok(BigInt::from(value))
+    }
+}
+
+// Why a value was not accepted: a finished error, or what the field expects,
+// which is named once the parameter it belongs to is known
+#[derive(Debug)]
+enum Rejection {
+    Api(ApiError),
+    Expected(String),
+}
+
+impl Rejection {
+    fn named(self, name: &'static str, received: String) -> Rejection {
+        match self {
+            Rejection::Expected(expected) => Rejection::Api(ApiError::InvalidParameter {
+                name,
+                received,
+                expected: expected.into(),
+            }),
+            api => api,
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Api(err) => err.fmt(f),
+            Rejection::Expected(expected) => write!(f, "expected {}", expected),
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+impl de::Error for Rejection {
+    fn custom<T: fmt::Display>(msg: T) -> Rejection {
+        Rejection::Expected(msg.to_string())
+    }
+
+    fn invalid_type(_: de::Unexpected, expected: &dyn de::Expected) -> Rejection {
+        Rejection::Expected(expected.to_string())
+    }
+
+    fn invalid_value(_: de::Unexpected, expected: &dyn de::Expected) -> Rejection {
+        Rejection::Expected(expected.to_string())
+    }
+
+    fn unknown_variant(_: &str, expected: &'static [&'static str]) -> Rejection {
+        Rejection::Expected(one_of(expected))
+    }
+
+    fn missing_field(field: &'static str) -> Rejection {
+        Rejection::Api(ApiError::MissingParameter { name: field })
+    }
+}
+
+// "one of a, b or c"
+fn one_of(names: &[&str]) -> String {
+    match names {
+        [] => "nothing".to_string(),
+        [only] => only.to_string(),
+        [rest @ .., last] => format!("one of {} or {}", rest.join(", "), last),
+    }
+}
+
+// The whole request, read as a struct whose fields are the parameters
+struct Request<'a>(&'a Params);
+
+impl<'de> de::Deserializer<'de> for Request<'_> {
+    type Error = Rejection;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Rejection> {
+        Err(Rejection::Expected("a struct of parameters".to_string()))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Rejection> {
+        visitor.visit_map(Fields {
+            params: self.0,
+            fields: fields.iter(),
+            current: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+// The declared fields that were given, in declaration order
+struct Fields<'a> {
+    params: &'a Params,
+    fields: std::slice::Iter<'static, &'static str>,
+    current: Option<&'static str>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'_> {
+    type Error = Rejection;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Rejection> {
+        for &field in self.fields.by_ref() {
+            if self.params.contains(field) {
+                self.current = Some(field);
+                return seed.deserialize(field.into_deserializer()).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Rejection> {
+        let name = self.current.take().expect("a value is read after its key");
+        let values = self.params.values_of(name);
+        let received = values.iter().map(Raw::received).collect::<Vec<_>>().join(",");
+        let value = Parameter {
+            name,
+            values,
+            bound: self.params.bound_of(name),
+        };
+        seed.deserialize(value).map_err(|rejection| rejection.named(name, received))
+    }
+}
+
+// Every value of one parameter; scalar fields read the first
+struct Parameter {
+    name: &'static str,
+    values: Vec<Raw>,
+    bound: Option<RangeInclusive<i128>>,
+}
+
+impl Parameter {
+    fn first(&self) -> &Raw {
+        &self.values[0]
+    }
+
+    fn invalid(&self, expected: impl Into<String>) -> Rejection {
+        Rejection::Api(ApiError::InvalidParameter {
+            name: self.name,
+            received: self.first().received(),
+            expected: expected.into().into(),
+        })
+    }
+
+    // Reads an integer that the field's type holds between `min` and `max`
+    fn integer<'de, V: Visitor<'de>>(self, visitor: V, min: i128, max: i128) -> Result<V::Value, Rejection> {
+        let (low, high) = match &self.bound {
+            Some(bound) => (*bound.start().max(&min), *bound.end().min(&max)),
+            None => (min, max),
+        };
+        let expected = if self.bound.is_some() || max < i128::from(i64::MAX) {
+            format!("an integer between {} and {}", low, high)
+        } else if min == 0 {
+            "a non-negative integer".to_string()
+        } else {
+            "an integer".to_string()
+        };
+        let value = match self.first() {
+            Raw::Text(text) => text.parse::<i128>().ok(),
+            Raw::Json(Value::Number(number)) => number
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| number.as_u64().map(i128::from)),
+            Raw::Json(_) => None,
+        };
+        match value {
+            Some(value) if !(low..=high).contains(&value) && self.bound.is_some() => {
+                Err(Rejection::Api(ApiError::NotInRange {
+                    name: self.name,
+                    received: value.to_string(),
+                    min: low,
+                    max: high,
+                }))
+            }
+            Some(value) if (low..=high).contains(&value) => match u64::try_from(value) {
+                Ok(value) => visitor.visit_u64(value),
+                Err(_) => visitor.visit_i64(value as i64),
+            },
+            _ => Err(self.invalid(expected)),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Parameter {
+    type Error = Rejection;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        match self.first() {
+            Raw::Text(text) => visitor.visit_string(text.clone()),
+            Raw::Json(Value::Null) => visitor.visit_unit(),
+            Raw::Json(Value::Bool(value)) => visitor.visit_bool(*value),
+            Raw::Json(Value::Number(number)) => match (number.as_u64(), number.as_i64()) {
+                (Some(value), _) => visitor.visit_u64(value),
+                (None, Some(value)) => visitor.visit_i64(value),
+                (None, None) => visitor.visit_f64(number.as_f64().unwrap_or(f64::NAN)),
+            },
+            Raw::Json(Value::String(text)) => visitor.visit_string(text.clone()),
+            Raw::Json(Value::Array(_)) => self.deserialize_seq(visitor),
+            Raw::Json(Value::Object(_)) => Err(self.invalid("a number, string, boolean or list")),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        match self.first() {
+            Raw::Text(text) => match text.as_str() {
+                "" | "true" | "1" => visitor.visit_bool(true),
+                "false" | "0" => visitor.visit_bool(false),
+                _ => Err(self.invalid("true or false")),
+            },
+            Raw::Json(Value::Bool(value)) => visitor.visit_bool(*value),
+            Raw::Json(_) => Err(self.invalid("true or false")),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.integer(visitor, i8::MIN.into(), i8::MAX.into())
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.integer(visitor, i16::MIN.into(), i16::MAX.into())
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.integer(visitor, i32::MIN.into(), i32::MAX.into())
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.integer(visitor, i64::MIN.into(), i64::MAX.into())
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.integer(visitor, 0, u8::MAX.into())
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.integer(visitor, 0, u16::MAX.into())
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.integer(visitor, 0, u32::MAX.into())
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.integer(visitor, 0, u64::MAX.into())
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        let value = match self.first() {
+            Raw::Text(text) => text.parse::<f64>().ok().filter(|value| value.is_finite()),
+            Raw::Json(Value::Number(number)) => number.as_f64(),
+            Raw::Json(_) => None,
+        };
+        match value {
+            Some(value) => visitor.visit_f64(value),
+            None => Err(self.invalid("a number")),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        match self.first() {
+            Raw::Text(text) | Raw::Json(Value::String(text)) => visitor.visit_string(text.clone()),
+            Raw::Json(_) => Err(self.invalid("a string")),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        match self.first() {
+            Raw::Json(Value::Null) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Rejection> {
+        let mut items = Vec::new();
+        for value in &self.values {
+            match value {
+                Raw::Text(text) => items.extend(text.split(',').map(|item| Raw::Text(item.trim().to_string()))),
+                Raw::Json(Value::Array(elements)) => items.extend(elements.iter().cloned().map(Raw::Json)),
+                Raw::Json(_) => return Err(self.invalid("a list")),
+            }
+        }
+        visitor.visit_seq(Items {
+            name: self.name,
+            items: items.into_iter(),
+            bound: self.bound,
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Rejection> {
+        match self.first() {
+            Raw::Text(text) | Raw::Json(Value::String(text)) => visitor.visit_enum(text.clone().into_deserializer()),
+            Raw::Json(_) => Err(self.invalid(one_of(variants))),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, Rejection> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char bytes byte_buf unit unit_struct tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+// The elements of a list parameter, each read like a parameter of its own
+struct Items {
+    name: &'static str,
+    items: std::vec::IntoIter<Raw>,
+    bound: Option<RangeInclusive<i128>>,
+}
+
+impl<'de> SeqAccess<'de> for Items {
+    type Error = Rejection;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Rejection> {
+        let Some(item) = self.items.next() else {
+            return Ok(None);
+        };
+        let received = item.received();
+        let element = Parameter {
+            name: self.name,
+            values: vec![item],
+            bound: self.bound.clone(),
+        };
+        seed.deserialize(element).map(Some).map_err(|rejection| rejection.named(self.name, received))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Index {
+        n: u64,
+        strict: Option<bool>,
+        base: Option<u32>,
+    }
+
+    #[test]
+    fn reports_the_parameter_value_and_range() {
+        let params = Params::from_query("n=ten").bound("n", 0..=1000);
+        let err = params.parse::<Index>().unwrap_err();
+        assert_eq!(err.to_string(), "parameter 'n' must be an integer between 0 and 1000, got 'ten'");
+        assert_eq!(err.status(), vercel_runtime::StatusCode::BAD_REQUEST);
+
+        let err = Params::from_query("n=1001").bound("n", 0..=1000).parse::<Index>().unwrap_err();
+        assert_eq!(err.status(), vercel_runtime::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(err.to_string(), "parameter 'n' must be an integer between 0 and 1000, got 1001");
+        let err = Params::from_query("n=-1").bound("n", 0..=1000).parse::<Index>().unwrap_err();
+        assert_eq!(err.to_string(), "parameter 'n' must be an integer between 0 and 1000, got -1");
+
+        let err = Params::from_query("n=5&strict=yes").parse::<Index>().unwrap_err();
+        assert_eq!(err.to_string(), "parameter 'strict' must be true or false, got 'yes'");
+        let err = Params::from_query("n=x").parse::<Index>().unwrap_err();
+        assert_eq!(err.to_string(), "parameter 'n' must be a non-negative integer, got 'x'");
+        let err = Params::from_query("strict").parse::<Index>().unwrap_err();
+        assert_eq!(err, ApiError::MissingParameter { name: "n" });
+    }
+
+    #[test]
+    fn reads_query_path_and_body() {
+        let params = Params::from_query("strict&base=16").with_path("n", "/api/42");
+        let expected = Index {
+            n: 42,
+            strict: Some(true),
+            base: Some(16),
+        };
+        assert_eq!(params.parse::<Index>(), Ok(expected));
+        assert_eq!(Params::from_query("n=7").with_path("n", "/api/42").parse::<Index>().unwrap().n, 7);
+        assert!(Params::from_query("").with_path("n", "/api/").parse::<Index>().is_err());
+
+        let body = Params::from_body(br#"{"n": 250, "base": null}"#).unwrap();
+        let params = body.merge(Params::from_query("n=3&strict=false"));
+        let expected = Index {
+            n: 250,
+            strict: Some(false),
+            base: None,
+        };
+        assert_eq!(params.parse::<Index>(), Ok(expected));
+        let err = Params::from_body(br#"{"n": "250"}"#).unwrap().parse::<Index>().unwrap_err();
+        assert_eq!(err.to_string(), "parameter 'n' must be a non-negative integer, got '250'");
+        assert!(Params::from_body(b"[1]").is_err());
+    }
+
+    #[test]
+    fn reads_lists() {
+        #[derive(Debug, Deserialize)]
+        struct Many {
+            n: Vec<i64>,
+        }
+        let params = Params::from_query("n=5,-3&n=8");
+        assert_eq!(params.parse::<Many>().unwrap().n, [5, -3, 8]);
+        let params = Params::from_body(br#"{"n": [1, 2]}"#).unwrap();
+        assert_eq!(params.parse::<Many>().unwrap().n, [1, 2]);
+        let err = Params::from_query("n=5,x").bound("n", -10..=10).parse::<Many>().unwrap_err();
+        assert_eq!(err.to_string(), "parameter 'n' must be an integer between -10 and 10, got 'x'");
+    }
+
+    #[test]
+    fn reads_integers_of_any_size() {
+        #[derive(Debug, Deserialize)]
+        struct Big {
+            x: Natural,
+            a: Option<Vec<Integer>>,
+        }
+        let big = Params::from_query("x=123456789012345678901234567890&a=-123456789012345678901,4&a=7")
+            .parse::<Big>()
+            .unwrap();
+        assert_eq!(big.x.0.to_string(), "123456789012345678901234567890");
+        let a: Vec<String> = big.a.unwrap().iter().map(|Integer(value)| value.to_string()).collect();
+        assert_eq!(a, ["-123456789012345678901", "4", "7"]);
+        assert_eq!(Params::from_body(br#"{"x": 5}"#).unwrap().parse::<Big>().unwrap().x.0, BigUint::from(5u8));
+
+        let err = Params::from_query("x=%2B5").parse::<Big>().unwrap_err();
+        assert_eq!(err.to_string(), "parameter 'x' must be a non-negative decimal integer, got '+5'");
+        assert!(Params::from_query("x=").parse::<Big>().is_err());
+        assert!(Params::from_query("x=-5").parse::<Big>().is_err());
+        assert!(Params::from_query("x=1&a=5e3").parse::<Big>().is_err());
+    }
+}
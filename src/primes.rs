@@ -0,0 +1,368 @@
+//! Prime numbers: the n-th prime, primality testing and factorization.
+//!
+//! The n-th prime comes from a segmented sieve of Eratosthenes run up to an
+//! upper bound on p(n), so memory stays at one cache-sized segment plus the
+//! primes up to √p(n). Primality is decided by Miller–Rabin with the first
+//! thirteen primes as bases, which is exact below
+//! [`DETERMINISTIC_BELOW`] and a probable-prime test above it. Factorization
+//! divides out the primes below [`TRIAL_DIVISION_LIMIT`] and splits what is
+//! left with Pollard's rho.
+
+use crate::budget::Deadline;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+use std::fmt;
+
+/// Numbers in one sieve segment, sized to stay in the L1 cache.
+const SEGMENT: usize = 1 << 15;
+
+/// Miller–Rabin bases. Together they admit no strong pseudoprime below
+/// [`DETERMINISTIC_BELOW`] (Sorenson and Webster, 2015).
+const BASES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// Bound below which [`primality`] never answers `ProbablePrime`, about 2^81.
+pub const DETERMINISTIC_BELOW: u128 = 3_317_044_064_679_887_385_961_981;
+
+/// Primes below this are found by trial division before Pollard's rho runs.
+pub const TRIAL_DIVISION_LIMIT: u64 = 10_000;
+
+/// Rho steps between the gcds in Brent's variant.
+const RHO_BATCH: u64 = 128;
+
+/// The outcome of a primality test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primality {
+    Prime,
+    /// Passed every round, but is too large for the rounds to be a proof.
+    /// A composite passes each round with probability at most 1/4.
+    ProbablePrime,
+    Composite,
+    /// 0 and 1, which are neither prime nor composite.
+    Neither,
+}
+
+impl Primality {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Primality::Prime => "prime",
+            Primality::ProbablePrime => "probable_prime",
+            Primality::Composite => "composite",
+            Primality::Neither => "neither",
+        }
+    }
+
+    /// Whether the number is (probably) prime.
+    pub fn is_prime(self) -> bool {
+        matches!(self, Primality::Prime | Primality::ProbablePrime)
+    }
+}
+
+/// The `n`-th prime, counting 2 as the first. `None` for `n = 0`.
+pub fn nth_prime(n: u64) -> Option<u64> {
+    if n == 0 {
+        return None;
+    }
+    let limit = upper_bound(n);
+    let base = small_primes(limit.isqrt());
+    let mut composite = vec![false; SEGMENT];
+    let mut count = 0;
+    let mut low = 2;
+    while low <= limit {
+        let high = (low + SEGMENT as u64 - 1).min(limit);
+        composite.fill(false);
+        for &p in base.iter().take_while(|&&p| p * p <= high) {
+            let mut multiple = (p * p).max(low.div_ceil(p) * p);
+            while multiple <= high {
+                composite[(multiple - low) as usize] = true;
+                multiple += p;
+            }
+        }
+        for (offset, &crossed) in composite.iter().take((high - low + 1) as usize).enumerate() {
+            if !crossed {
+                count += 1;
+                if count == n {
+                    return Some(low + offset as u64);
+                }
+            }
+        }
+        low = high + 1;
+    }
+    unreachable!("p({}) is below {}", n, limit)
+}
+
+// Rosser's bound p(n) < n(ln n + ln ln n), valid from n = 6
+fn upper_bound(n: u64) -> u64 {
+    if n < 6 {
+        return 13;
+    }
+    let n = n as f64;
+    (n * (n.ln() + n.ln().ln())).ceil() as u64
+}
+
+// The primes up to `limit`, by a plain sieve
+fn small_primes(limit: u64) -> Vec<u64> {
+    let limit = limit as usize;
+    let mut composite = vec![false; limit + 1];
+    let mut primes = Vec::new();
+    for i in 2..=limit {
+        if !composite[i] {
+            primes.push(i as u64);
+            for multiple in (i * i..=limit).step_by(i) {
+                composite[multiple] = true;
+            }
+        }
+    }
+    primes
+}
+
+/// Tests `x` for primality.
+pub fn primality(x: &BigUint) -> Primality {
+    if x < &BigUint::from(2u32) {
+        return Primality::Neither;
+    }
+    for &p in &BASES {
+        if *x == BigUint::from(p) {
+            return Primality::Prime;
+        }
+        if (x % p).is_zero() {
+            return Primality::Composite;
+        }
+    }
+    if BASES.iter().any(|&base| !strong_probable_prime(x, base)) {
+        return Primality::Composite;
+    }
+    match x.to_u128() {
+        Some(x) if x < DETERMINISTIC_BELOW => Primality::Prime,
+        _ => Primality::ProbablePrime,
+    }
+}
+
+// One Miller–Rabin round: whether `x` (odd, above `base`) is a strong
+// probable prime to `base`
+fn strong_probable_prime(x: &BigUint, base: u32) -> bool {
+    let one = BigUint::one();
+    let x_minus_one = x - 1u32;
+    let s = x_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &x_minus_one >> s;
+    let mut y = BigUint::from(base).modpow(&d, x);
+    if y == one || y == x_minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        y = &y * &y % x;
+        if y == x_minus_one {
+            return true;
+        }
+        if y == one {
+            return false;
+        }
+    }
+    false
+}
+
+/// A prime factor and its exponent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Factor {
+    pub prime: BigUint,
+    pub exponent: u32,
+    /// False when `prime` is only a probable prime.
+    pub certain: bool,
+}
+
+/// A factorization abandoned at the deadline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unfactored {
+    /// The prime factors found so far.
+    pub found: Vec<Factor>,
+    /// The composite cofactor still to split.
+    pub remaining: BigUint,
+}
+
+impl fmt::Display for Unfactored {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.found.is_empty() {
+            write!(f, "no factors found")?;
+        } else {
+            write!(f, "found {}", format_factors(&self.found))?;
+        }
+        write!(f, ", a {}-digit composite remains", self.remaining.to_string().len())
+    }
+}
+
+/// Writes factors as `2^3 × 5`.
+pub fn format_factors(factors: &[Factor]) -> String {
+    let terms: Vec<_> = factors
+        .iter()
+        .map(|factor| match factor.exponent {
+            1 => factor.prime.to_string(),
+            exponent => format!("{}^{}", factor.prime, exponent),
+        })
+        .collect();
+    terms.join(" × ")
+}
+
+/// The prime factorization of `x` (at least 1) in ascending order, or how
+/// far it got once `deadline` passed.
+pub fn factorize(x: &BigUint, deadline: &Deadline) -> Result<Vec<Factor>, Unfactored> {
+    let mut primes: Vec<(BigUint, bool)> = Vec::new();
+    let mut rest = x.clone();
+    for p in small_primes(TRIAL_DIVISION_LIMIT) {
+        if rest.is_one() {
+            break;
+        }
+        while (&rest % p).is_zero() {
+            rest /= p;
+            primes.push((BigUint::from(p), true));
+        }
+    }
+
+    let mut composites = Vec::new();
+    if !rest.is_one() {
+        composites.push(rest);
+    }
+    while let Some(n) = composites.pop() {
+        match primality(&n) {
+            Primality::Prime => primes.push((n, true)),
+            Primality::ProbablePrime => primes.push((n, false)),
+            Primality::Neither => unreachable!("only factors above 1 are tested"),
+            Primality::Composite => match pollard_rho(&n, deadline) {
+                Some(divisor) => {
+                    composites.push(&n / &divisor);
+                    composites.push(divisor);
+                }
+                None => {
+                    let remaining = composites.iter().fold(n, |product, other| product * other);
+                    return Err(Unfactored {
+                        found: group(primes),
+                        remaining,
+                    });
+                }
+            },
+        }
+    }
+    Ok(group(primes))
+}
+
+// Sorts the primes and counts repeats
+fn group(mut primes: Vec<(BigUint, bool)>) -> Vec<Factor> {
+    primes.sort();
+    let mut factors: Vec<Factor> = Vec::new();
+    for (prime, certain) in primes {
+        match factors.last_mut() {
+            Some(last) if last.prime == prime => last.exponent += 1,
+            _ => factors.push(Factor {
+                prime,
+                exponent: 1,
+                certain,
+            }),
+        }
+    }
+    factors
+}
+
+// A non-trivial divisor of the odd composite `n`, by Brent's variant of
+// Pollard's rho; `None` once `deadline` passes
+fn pollard_rho(n: &BigUint, deadline: &Deadline) -> Option<BigUint> {
+    let one = BigUint::one();
+    let distance = |a: &BigUint, b: &BigUint| if a > b { a - b } else { b - a };
+    for c in 1u32.. {
+        let step = |y: &BigUint| (y * y + c) % n;
+        let mut y = BigUint::from(2u32);
+        let mut x = y.clone();
+        let mut saved = y.clone();
+        let mut product = one.clone();
+        let mut divisor = one.clone();
+        let mut cycle = 1;
+        while divisor.is_one() {
+            x = y.clone();
+            for i in 0..cycle {
+                if i % RHO_BATCH == 0 && deadline.expired() {
+                    return None;
+                }
+                y = step(&y);
+            }
+            let mut done = 0;
+            while done < cycle && divisor.is_one() {
+                if deadline.expired() {
+                    return None;
+                }
+                saved = y.clone();
+                for _ in 0..RHO_BATCH.min(cycle - done) {
+                    y = step(&y);
+                    product = product * distance(&x, &y) % n;
+                }
+                divisor = product.gcd(n);
+                done += RHO_BATCH;
+            }
+            cycle *= 2;
+        }
+        // The batch overshot a factor; retrace it one step at a time
+        if divisor == *n {
+            loop {
+                saved = step(&saved);
+                divisor = distance(&x, &saved).gcd(n);
+                if !divisor.is_one() {
+                    break;
+                }
+            }
+        }
+        if divisor != *n {
+            return Some(divisor);
+        }
+    }
+    unreachable!("some polynomial splits a composite")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nth_primes() {
+        assert_eq!(nth_prime(0), None);
+        let first: Vec<_> = (1..=10).map(|n| nth_prime(n).unwrap()).collect();
+        assert_eq!(first, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert_eq!(nth_prime(1000), Some(7919));
+        assert_eq!(nth_prime(100_000), Some(1_299_709));
+    }
+
+    #[test]
+    fn primality_of_small_and_large_numbers() {
+        let test = |x: u128| primality(&BigUint::from(x));
+        assert_eq!(test(0), Primality::Neither);
+        assert_eq!(test(1), Primality::Neither);
+        assert!(!test(1).is_prime());
+        assert_eq!(test(1).as_str(), "neither");
+        assert_eq!(test(2), Primality::Prime);
+        assert_eq!(test(41), Primality::Prime);
+        assert_eq!(test(1_000_000_007), Primality::Prime);
+        // A Carmichael number and a strong pseudoprime to bases 2 through 11
+        assert_eq!(test(561), Primality::Composite);
+        assert_eq!(test(2_152_302_898_747), Primality::Composite);
+        assert_eq!(test(18_446_744_073_709_551_557), Primality::Prime);
+        assert_eq!(test(u128::MAX), Primality::Composite);
+        // 2^127 − 1 is prime, but beyond the deterministic range
+        assert_eq!(test((1 << 127) - 1), Primality::ProbablePrime);
+    }
+
+    #[test]
+    fn factorizations() {
+        let factor = |x: u128| format_factors(&factorize(&BigUint::from(x), &Deadline::none()).unwrap());
+        assert_eq!(factor(1), "");
+        assert_eq!(factor(360), "2^3 × 3^2 × 5");
+        assert_eq!(factor(1_000_000_007), "1000000007");
+        // Both factors are beyond trial division
+        assert_eq!(factor(1_000_000_007 * 998_244_353), "998244353 × 1000000007");
+        assert_eq!(factor(10_007u128.pow(3)), "10007^3");
+        assert_eq!(factor(u64::MAX as u128), "3 × 5 × 17 × 257 × 641 × 65537 × 6700417");
+    }
+
+    #[test]
+    fn factorization_stops_at_the_deadline() {
+        let x = BigUint::from(1_000_000_007u64 * 998_244_353) * 4u32;
+        let unfactored = factorize(&x, &Deadline::after(std::time::Duration::ZERO)).unwrap_err();
+        assert_eq!(unfactored.remaining, BigUint::from(1_000_000_007u64 * 998_244_353));
+        assert_eq!(unfactored.to_string(), "found 2^2, a 18-digit composite remains");
+    }
+}
@@ -0,0 +1,78 @@
+//! Repeated-run timing of the Fibonacci computation for benchmarking.
+
+use crate::fib::calculate_fibonacci;
+use serde_json::json;
+use std::time::Instant;
+
+/// Timing summary (in nanoseconds) collected over repeated computations
+pub struct ProfileStats {
+    pub n: u64,
+    pub runs: usize,
+    pub min_ns: u128,
+    pub median_ns: u128,
+    pub p95_ns: u128,
+    pub max_ns: u128,
+}
+
+impl ProfileStats {
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "n": self.n,
+            "runs": self.runs,
+            "min_ns": self.min_ns as u64,
+            "median_ns": self.median_ns as u64,
+            "p95_ns": self.p95_ns as u64,
+            "max_ns": self.max_ns as u64
+        })
+    }
+}
+
+pub fn profile_fibonacci(n: u64, runs: usize) -> ProfileStats {
+    let mut timings: Vec<u128> = (0..runs.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            let result = calculate_fibonacci(n);
+            let elapsed = start.elapsed().as_nanos();
+            drop(result);
+            elapsed
+        })
+        .collect();
+    timings.sort_unstable();
+
+    ProfileStats {
+        n,
+        runs: timings.len(),
+        min_ns: timings[0],
+        median_ns: percentile(&timings, 50),
+        p95_ns: percentile(&timings, 95),
+        max_ns: timings[timings.len() - 1],
+    }
+}
+
+// Nearest-rank percentile over an already sorted, non-empty slice
+fn percentile(sorted: &[u128], pct: usize) -> u128 {
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_percentiles_are_present_and_ordered() {
+        let profile = profile_fibonacci(500, 25).to_json();
+
+        let field = |name: &str| {
+            profile[name]
+                .as_u64()
+                .unwrap_or_else(|| panic!("missing profile field {}", name))
+        };
+        let (min, median, p95, max) = (field("min_ns"), field("median_ns"), field("p95_ns"), field("max_ns"));
+
+        assert_eq!(profile["runs"], 25);
+        assert!(min <= median);
+        assert!(median <= p95);
+        assert!(p95 <= max);
+    }
+}
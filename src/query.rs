@@ -0,0 +1,118 @@
+//! Parsing of `application/x-www-form-urlencoded` query strings.
+//!
+//! Keys and values are percent-decoded and `+` stands for a space, so
+//! `?n=5%2C10` and `?n=5,10` read the same. A key may repeat; lookups see the
+//! values in the order they were sent.
+
+use std::borrow::Cow;
+
+/// A decoded query string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pairs: Vec<(String, String)>,
+}
+
+impl Query {
+    /// Parses `raw`, the part of the URI after `?`. Empty pairs are skipped
+    /// and a key without `=` gets an empty value.
+    pub fn parse(raw: &str) -> Query {
+        let pairs = raw
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(key).into_owned(), decode(value).into_owned())
+            })
+            .collect();
+        Query { pairs }
+    }
+
+    /// The first value of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every value of `key`, in order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every value of `key`, with comma-separated lists such as `5,10,200`
+    /// split into their items.
+    pub fn get_list<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.get_all(key).flat_map(|value| value.split(','))
+    }
+
+    /// Every key and value, in the order they were sent.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Whether `key` appears at all, with or without a value.
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+/// Percent-decodes one key or value, reading `+` as a space. A `%` not
+/// followed by two hex digits is kept as is, and bytes that do not form
+/// UTF-8 are replaced.
+pub fn decode(component: &str) -> Cow<'_, str> {
+    if !component.contains(['%', '+']) {
+        return Cow::Borrowed(component);
+    }
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match component.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    Cow::Owned(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_keys_and_values() {
+        let query = Query::parse("n=5%2C10&format=text%2Fcsv&q=a+b&debug&=x&&bad=%zz");
+        assert_eq!(query.get("n"), Some("5,10"));
+        assert_eq!(query.get("format"), Some("text/csv"));
+        assert_eq!(query.get("q"), Some("a b"));
+        assert_eq!(query.get("debug"), Some(""));
+        assert_eq!(query.get("bad"), Some("%zz"));
+        assert_eq!(query.get("missing"), None);
+    }
+
+    #[test]
+    fn repeated_keys_and_lists() {
+        let query = Query::parse("n=5&n=10,200&m=1");
+        assert_eq!(query.get("n"), Some("5"));
+        assert_eq!(query.get_all("n").collect::<Vec<_>>(), ["5", "10,200"]);
+        assert_eq!(query.get_list("n").collect::<Vec<_>>(), ["5", "10", "200"]);
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        assert_eq!(decode("caf%C3%A9"), "café");
+        assert_eq!(decode("%FF"), "\u{FFFD}");
+    }
+}
@@ -0,0 +1,110 @@
+//! Encoding of results in bases other than ten (`?base=`), and decoding of
+//! values written in them.
+
+use crate::error::ApiError;
+use num_bigint::BigUint;
+use std::ops::RangeInclusive;
+
+/// Smallest and largest supported bases.
+pub const MIN_BASE: u32 = 2;
+pub const MAX_BASE: u32 = 62;
+
+/// The supported bases, as a bound for [`crate::params::Params::bound`].
+pub const BASES: RangeInclusive<i128> = MIN_BASE as i128..=MAX_BASE as i128;
+
+// Digit alphabet for bases above 36, following GMP: 0-9, then A-Z, then a-z
+const DIGITS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Writes `value` in `base`. Bases up to 36 use lowercase letters like
+/// most languages' radix conversions; larger ones use the GMP alphabet
+/// `0-9A-Za-z`.
+pub fn encode(value: &BigUint, base: u32) -> String {
+    debug_assert!((MIN_BASE..=MAX_BASE).contains(&base));
+    if base <= 36 {
+        return value.to_str_radix(base);
+    }
+    value
+        .to_radix_be(base)
+        .into_iter()
+        .map(|digit| char::from(DIGITS[usize::from(digit)]))
+        .collect()
+}
+
+/// A character that is not a digit of the base being decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidDigit {
+    pub digit: char,
+    /// Character offset in the text.
+    pub position: usize,
+}
+
+/// Reads `text`, a string of digits in `base`, with the same
+/// alphabets as [`encode`]; up to base 36, letters may be either case.
+pub fn decode(text: &str, base: u32) -> Result<BigUint, InvalidDigit> {
+    debug_assert!((MIN_BASE..=MAX_BASE).contains(&base));
+    let digits = text
+        .chars()
+        .enumerate()
+        .map(|(position, digit)| {
+            let value = match digit {
+                '0'..='9' => Some(digit as u32 - '0' as u32),
+                'A'..='Z' => Some(digit as u32 - 'A' as u32 + 10),
+                'a'..='z' if base <= 36 => Some(digit as u32 - 'a' as u32 + 10),
+                'a'..='z' => Some(digit as u32 - 'a' as u32 + 36),
+                _ => None,
+            };
+            value
+                .filter(|&value| value < base)
+                .map(|value| value as u8)
+                .ok_or(InvalidDigit { digit, position })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(BigUint::from_radix_be(&digits, base).expect("every digit is below the base"))
+}
+
+/// Checks that `base` is between [`MIN_BASE`] and [`MAX_BASE`].
+pub fn validate(base: u64) -> Result<u32, ApiError> {
+    u32::try_from(base)
+        .ok()
+        .filter(|base| (MIN_BASE..=MAX_BASE).contains(base))
+        .ok_or_else(|| ApiError::InvalidParameter {
+            name: "base",
+            received: base.to_string(),
+            expected: "an integer between 2 and 62".into(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_in_every_base() {
+        let value = BigUint::from(6765u32); // F(20)
+        assert_eq!(encode(&value, 2), "1101001101101");
+        assert_eq!(encode(&value, 16), "1a6d");
+        assert_eq!(encode(&value, 36), "57x");
+        assert_eq!(encode(&value, 62), "1l7"); // 1·62² + 47·62 + 7
+        assert_eq!(encode(&BigUint::ZERO, 62), "0");
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let value = BigUint::from(6765u32);
+        for base in MIN_BASE..=MAX_BASE {
+            assert_eq!(decode(&encode(&value, base), base), Ok(value.clone()), "base {}", base);
+        }
+        assert_eq!(decode("1A6D", 16), Ok(value.clone()));
+        assert_eq!(decode("1g", 16), Err(InvalidDigit { digit: 'g', position: 1 }));
+        assert_eq!(decode("102", 2), Err(InvalidDigit { digit: '2', position: 2 }));
+        assert_eq!(decode("-1", 10), Err(InvalidDigit { digit: '-', position: 0 }));
+    }
+
+    #[test]
+    fn rejects_unsupported_bases() {
+        assert_eq!(validate(16), Ok(16));
+        assert!(validate(1).is_err());
+        assert!(validate(63).is_err());
+        assert!(validate(u64::from(u32::MAX) + 2).is_err());
+    }
+}
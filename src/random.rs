@@ -0,0 +1,65 @@
+//! Uniform random integers of any size from the operating system's CSPRNG.
+//!
+//! Every value is drawn from `getrandom`, never from a seeded generator, so
+//! the output is fit for tokens and keys. Values in a range are produced by
+//! rejection sampling: draw just enough bits to cover the range and try again
+//! if the draw lands past its end, which keeps the distribution exactly
+//! uniform at the cost of fewer than two draws on average.
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+
+/// A uniform integer in `0..bound`; `bound` must be positive.
+pub fn below(bound: &BigUint) -> Result<BigUint, getrandom::Error> {
+    debug_assert!(!bound.is_zero());
+    let largest = bound - 1u32;
+    let bits = largest.bits();
+    if bits == 0 {
+        return Ok(BigUint::zero());
+    }
+    let mut bytes = vec![0u8; bits.div_ceil(8) as usize];
+    // Bits above the top bit of `largest` are cleared in the leading byte
+    let mask = 0xff >> (bytes.len() as u64 * 8 - bits);
+    loop {
+        getrandom::fill(&mut bytes)?;
+        bytes[0] &= mask;
+        let candidate = BigUint::from_bytes_be(&bytes);
+        if candidate <= largest {
+            return Ok(candidate);
+        }
+    }
+}
+
+/// A uniform integer in `min..=max`; `min` must not exceed `max`.
+pub fn between(min: &BigInt, max: &BigInt) -> Result<BigInt, getrandom::Error> {
+    debug_assert!(min <= max);
+    let span = (max - min + BigInt::one()).to_biguint().expect("min <= max");
+    Ok(min + BigInt::from(below(&span)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stays_within_the_range() {
+        let (min, max) = (BigInt::from(-3), BigInt::from(3));
+        let mut seen = [false; 7];
+        for _ in 0..500 {
+            let value = between(&min, &max).unwrap();
+            assert!(value >= min && value <= max);
+            seen[usize::try_from(value + 3).unwrap()] = true;
+        }
+        assert!(seen.iter().all(|&seen| seen), "every value turns up in 500 draws");
+        assert_eq!(between(&BigInt::from(5), &BigInt::from(5)).unwrap(), BigInt::from(5));
+    }
+
+    #[test]
+    fn draws_beyond_machine_words() {
+        let bound = BigUint::one() << 1000u32;
+        let values: Vec<_> = (0..4).map(|_| below(&bound).unwrap()).collect();
+        assert!(values.iter().all(|value| value < &bound));
+        assert!(values.iter().any(|value| value.bits() > 900));
+        assert_ne!(values[0], values[1]);
+    }
+}
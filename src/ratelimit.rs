@@ -0,0 +1,200 @@
+//! Token-bucket rate limiting keyed by API key or client IP.
+//!
+//! Buckets live in process memory, so each warm instance enforces its own
+//! limit; a deployment with several concurrent instances admits proportionally
+//! more traffic overall.
+
+use crate::auth;
+use crate::config::Config;
+use http::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use vercel_runtime::Request;
+
+// Upper bound on tracked clients; the least recently seen are forgotten first
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Outcome of a rate-limit check, used to populate the response headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Requests admitted per minute.
+    pub limit: u64,
+    /// Whole tokens left in the bucket after this request.
+    pub remaining: u64,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until the next request would be admitted (0 when allowed).
+    pub retry_after_secs: u64,
+}
+
+impl Decision {
+    /// Adds `X-RateLimit-*` (and `Retry-After` when rejected) to `headers`.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert("X-RateLimit-Limit", HeaderValue::from(self.limit));
+        headers.insert("X-RateLimit-Remaining", HeaderValue::from(self.remaining));
+        headers.insert("X-RateLimit-Reset", HeaderValue::from(self.reset_secs));
+        if !self.allowed {
+            headers.insert(RETRY_AFTER, HeaderValue::from(self.retry_after_secs));
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// A set of token buckets sharing one refill rate and burst size.
+pub struct RateLimiter {
+    per_minute: u64,
+    burst: u64,
+    buckets: Mutex<LruCache<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u64, burst: u64) -> Self {
+        RateLimiter {
+            per_minute,
+            burst: burst.max(1),
+            buckets: Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_TRACKED_CLIENTS).expect("non-zero capacity"),
+            )),
+        }
+    }
+
+    /// Takes one token from `key`'s bucket if available.
+    pub fn check(&self, key: &str) -> Decision {
+        self.check_at(key, Instant::now())
+    }
+
+    /// Like [`RateLimiter::check`], with an explicit clock for testing.
+    pub fn check_at(&self, key: &str, now: Instant) -> Decision {
+        let rate = self.per_minute as f64 / 60.0; // tokens per second
+        let capacity = self.burst as f64;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bucket = buckets.get_or_insert_mut(key.to_string(), || Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        let seconds_for = |tokens: f64| {
+            if tokens <= 0.0 {
+                0
+            } else if rate > 0.0 {
+                (tokens / rate).ceil() as u64
+            } else {
+                u64::MAX
+            }
+        };
+
+        Decision {
+            allowed,
+            limit: self.per_minute,
+            remaining: bucket.tokens.floor() as u64,
+            reset_secs: seconds_for(capacity - bucket.tokens),
+            retry_after_secs: if allowed { 0 } else { seconds_for(1.0 - bucket.tokens) },
+        }
+    }
+}
+
+/// Checks `req` against the instance-wide limiter.
+///
+/// Returns `None` when rate limiting is disabled (`RATE_LIMIT_PER_MINUTE=0`).
+pub fn check(req: &Request) -> Option<Decision> {
+    static LIMITER: OnceLock<Option<RateLimiter>> = OnceLock::new();
+    let limiter = LIMITER
+        .get_or_init(|| {
+            let config = Config::get();
+            (config.rate_limit_per_minute > 0)
+                .then(|| RateLimiter::new(config.rate_limit_per_minute, config.rate_limit_burst))
+        })
+        .as_ref()?;
+    Some(limiter.check(&client_key(req)))
+}
+
+/// Identifies the caller: the API key [`auth::authenticate`] accepted,
+/// otherwise the first address in `x-forwarded-for`. A key that is not
+/// configured counts for nothing, so inventing one gets no fresh bucket.
+pub fn client_key(req: &Request) -> String {
+    if let Some(key) = auth::authenticated_key(req) {
+        return format!("key:{}", key);
+    }
+
+    let forwarded = req.headers().get("x-forwarded-for").and_then(|v| v.to_str().ok());
+    match forwarded.and_then(|value| value.split(',').next()) {
+        Some(ip) => format!("ip:{}", ip.trim()),
+        None => "anonymous".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use vercel_runtime::Body;
+
+    #[test]
+    fn burst_then_reject_then_refill() {
+        let limiter = RateLimiter::new(60, 2);
+        let start = Instant::now();
+
+        assert!(limiter.check_at("a", start).allowed);
+        let second = limiter.check_at("a", start);
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+
+        let rejected = limiter.check_at("a", start);
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.retry_after_secs, 1);
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at("b", start).allowed);
+
+        // One token per second at 60/minute
+        assert!(limiter.check_at("a", start + Duration::from_secs(1)).allowed);
+    }
+
+    fn req(headers: &[(&str, &str)]) -> Request {
+        let mut builder = http::Request::builder();
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(Body::Empty).unwrap()
+    }
+
+    #[test]
+    fn keys_prefer_authenticated_key_over_ip() {
+        assert_eq!(client_key(&req(&[])), "anonymous");
+        assert_eq!(client_key(&req(&[("x-forwarded-for", "1.2.3.4, 10.0.0.1")])), "ip:1.2.3.4");
+
+        let mut authenticated = req(&[("x-forwarded-for", "1.2.3.4"), ("x-api-key", "k1")]);
+        authenticated.extensions_mut().insert(auth::Authenticated("k1".to_string()));
+        assert_eq!(client_key(&authenticated), "key:k1");
+    }
+
+    #[test]
+    fn unknown_keys_share_the_address_bucket() {
+        let limiter = RateLimiter::new(60, 1);
+        let start = Instant::now();
+        let first = client_key(&req(&[("x-forwarded-for", "1.2.3.4"), ("x-api-key", "random-1")]));
+        let second = client_key(&req(&[("x-forwarded-for", "1.2.3.4"), ("authorization", "Bearer random-2")]));
+
+        assert_eq!(first, "ip:1.2.3.4");
+        assert_eq!(first, second);
+        assert!(limiter.check_at(&first, start).allowed);
+        assert!(!limiter.check_at(&second, start).allowed);
+    }
+}
@@ -0,0 +1,333 @@
+//! Response construction shared by every endpoint.
+
+use crate::error::ApiError;
+use crate::format;
+use crate::middleware;
+use crate::negotiation::Format;
+use crate::params::Params;
+use crate::version::{self, ApiVersion};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt::Write;
+use vercel_runtime::{Body, Error, Response, StatusCode};
+
+/// Longest value written on a single NDJSON line; longer ones are split.
+pub const NDJSON_CHUNK_LEN: usize = 4096;
+
+/// A successful result in a form every output format can render.
+pub struct Payload {
+    /// The full JSON document, also used for MessagePack.
+    pub json: Value,
+    /// `(n, value)` pairs used by the text, CSV and NDJSON representations. Indices
+    /// are strings so that endpoints taking arbitrarily large `n` can use them.
+    pub rows: Vec<(String, String)>,
+    /// CSV header of the value column, e.g. `fibonacci`.
+    pub label: &'static str,
+    /// How the result was computed, added to the document as `meta`.
+    pub meta: Meta,
+}
+
+/// Cost metadata reported as a `meta` object in JSON and MessagePack
+/// responses, next to the request's elapsed time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Meta {
+    /// The implementation that produced the result, e.g. `fast-doubling`.
+    pub algorithm: &'static str,
+    /// `hit`, `miss` or `partial` for results read through the in-process
+    /// cache, `None` for endpoints that do not use it.
+    pub cache: Option<&'static str>,
+    /// Digits in the result; `None` counts the digits of the row values.
+    pub digits: Option<u64>,
+    /// `table` for values read from the embedded [`table`](crate::table),
+    /// `computed` otherwise; `None` leaves it out, for endpoints that never
+    /// use the table.
+    pub source: Option<&'static str>,
+}
+
+impl Meta {
+    pub fn new(algorithm: &'static str) -> Self {
+        Meta {
+            algorithm,
+            cache: None,
+            digits: None,
+            source: None,
+        }
+    }
+
+    pub fn with_cache(self, cache: &'static str) -> Self {
+        Meta {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    pub fn with_digits(self, digits: u64) -> Self {
+        Meta {
+            digits: Some(digits),
+            ..self
+        }
+    }
+
+    pub fn with_source(self, source: &'static str) -> Self {
+        Meta {
+            source: Some(source),
+            ..self
+        }
+    }
+}
+
+/// How JSON documents are trimmed and laid out, chosen per request with
+/// `?fields=fibonacci,n` (keep only those top-level keys) and `?pretty=true`
+/// (indent the JSON). Fields also apply to MessagePack; the other formats are
+/// unaffected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shape {
+    /// Top-level keys to keep, or `None` for the whole document.
+    pub fields: Option<Vec<String>>,
+    /// Whether JSON is indented rather than compact.
+    pub pretty: bool,
+}
+
+tokio::task_local! {
+    static SHAPE: Shape;
+}
+
+impl Shape {
+    /// Reads `fields` and `pretty` from a query string.
+    pub fn from_query(query: &str) -> Result<Shape, ApiError> {
+        #[derive(Deserialize)]
+        struct Received {
+            fields: Option<Vec<String>>,
+            #[serde(default)]
+            pretty: bool,
+        }
+
+        let Received { fields, pretty } = Params::from_query(query).parse()?;
+        let fields = fields.map(|fields| fields.into_iter().filter(|field| !field.is_empty()).collect());
+        Ok(Shape { fields, pretty })
+    }
+
+    /// The shape of the request being served, the full compact document
+    /// outside a request.
+    pub fn current() -> Shape {
+        SHAPE.try_with(Shape::clone).unwrap_or_default()
+    }
+
+    /// Runs `future` with `self` as the [`current`](Self::current) shape.
+    pub async fn scope<F: std::future::Future>(self, future: F) -> F::Output {
+        SHAPE.scope(self, future).await
+    }
+
+    /// Identifies the shape in cache keys; empty for the default.
+    pub fn key(&self) -> String {
+        let mut key = match &self.fields {
+            Some(fields) => format!("fields={}", fields.join(",")),
+            None => String::new(),
+        };
+        if self.pretty {
+            key.push_str(";pretty");
+        }
+        key
+    }
+
+    // Drops the keys not asked for; documents that are not objects are kept
+    fn trim(&self, body: &mut Value) {
+        if let (Some(fields), Some(object)) = (&self.fields, body.as_object_mut()) {
+            object.retain(|key, _| fields.iter().any(|field| field == key));
+        }
+    }
+
+    fn to_json(&self, body: &Value) -> String {
+        if self.pretty {
+            serde_json::to_string_pretty(body).unwrap_or_else(|_| body.to_string())
+        } else {
+            body.to_string()
+        }
+    }
+}
+
+impl Payload {
+    // `formatted` maps each row whose value is a decimal integer to its grouped
+    // form, next to the `locale` used
+    fn add_grouped(&mut self, locale: &format::Locale) {
+        let formatted: serde_json::Map<String, Value> = self
+            .rows
+            .iter()
+            .filter_map(|(n, value)| Some((n.clone(), Value::String(locale.group(value)?))))
+            .collect();
+        if !formatted.is_empty() {
+            self.json["formatted"] = Value::Object(formatted);
+            self.json["locale"] = json!(locale.tag);
+        }
+    }
+
+    // The `meta` object; digits in bases above 10 include letters
+    fn meta_json(&self) -> Value {
+        let digits = self.meta.digits.unwrap_or_else(|| {
+            self.rows
+                .iter()
+                .map(|(_, value)| value.bytes().filter(u8::is_ascii_alphanumeric).count() as u64)
+                .sum()
+        });
+        let mut meta = json!({
+            "elapsed_us": middleware::request_elapsed().map(|elapsed| elapsed.as_micros() as u64),
+            "digits": digits,
+            "algorithm": self.meta.algorithm,
+            "cache": self.meta.cache,
+        });
+        if let Some(source) = self.meta.source {
+            meta["source"] = json!(source);
+        }
+        meta
+    }
+}
+
+/// Builds a JSON response, shaped like [`render`]'s.
+pub fn json(status: StatusCode, body: &Value) -> Result<Response<Body>, Error> {
+    let shape = Shape::current();
+    let body = match shape.fields {
+        Some(_) => {
+            let mut body = body.clone();
+            shape.trim(&mut body);
+            shape.to_json(&body)
+        }
+        None => shape.to_json(body),
+    };
+    send(status, Format::Json.content_type(), body.into())
+}
+
+/// Builds an HTML response, used for documentation pages.
+pub fn html(status: StatusCode, body: String) -> Result<Response<Body>, Error> {
+    text(status, "text/html; charset=utf-8", body)
+}
+
+/// Builds a textual response with an explicit content type.
+pub fn text(status: StatusCode, content_type: &str, body: String) -> Result<Response<Body>, Error> {
+    send(status, content_type, body.into())
+}
+
+/// Renders `payload` in the negotiated `format`. JSON and MessagePack
+/// documents get a `meta` object, see [`Meta`], are reshaped for the
+/// request's [`ApiVersion`], gain the grouped row values when the request
+/// asked for them (see [`format`]), and are then trimmed and laid out by its
+/// [`Shape`].
+pub fn render(status: StatusCode, format: Format, mut payload: Payload) -> Result<Response<Body>, Error> {
+    let shape = Shape::current();
+    if matches!(format, Format::Json | Format::MessagePack) && payload.json.is_object() {
+        payload.json["meta"] = payload.meta_json();
+        if ApiVersion::current() == ApiVersion::V2 {
+            version::clean(&mut payload.json);
+        }
+        if let Some(locale) = format::current() {
+            payload.add_grouped(locale);
+        }
+        shape.trim(&mut payload.json);
+    }
+    let body: Body = match format {
+        Format::Json => shape.to_json(&payload.json).into(),
+        Format::Text => payload
+            .rows
+            .iter()
+            .map(|(_, value)| format!("{}\n", value))
+            .collect::<String>()
+            .into(),
+        Format::Csv => std::iter::once(format!("n,{}\n", payload.label))
+            .chain(payload.rows.iter().map(|(n, value)| format!("{},{}\n", n, value)))
+            .collect::<String>()
+            .into(),
+        Format::MessagePack => Body::Binary(rmp_serde::to_vec_named(&payload.json)?),
+        Format::Ndjson => ndjson(payload.rows.iter().cloned(), payload.label).into(),
+    };
+    send(status, format.content_type(), body)
+}
+
+/// Writes `rows` as newline-delimited JSON, one `{"n": .., "<label>": ..}`
+/// object per row.
+///
+/// Rows are consumed one at a time, so a caller can pass a lazy iterator and
+/// never hold the whole result next to its serialization. A value longer than
+/// [`NDJSON_CHUNK_LEN`] is split over consecutive lines that also carry
+/// `chunk` (from 0) and `chunks`; concatenating them gives the value back.
+pub fn ndjson(rows: impl IntoIterator<Item = (String, String)>, label: &str) -> String {
+    let mut out = String::new();
+    for (n, value) in rows {
+        // Indices are numbers whenever they fit, like in the JSON documents
+        let n = n
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| n.parse::<u64>().map(Value::from))
+            .unwrap_or(Value::String(n));
+        if value.len() <= NDJSON_CHUNK_LEN || !value.is_ascii() {
+            let _ = writeln!(out, "{}", json!({ "n": n, label: value }));
+            continue;
+        }
+        let chunks = value.len().div_ceil(NDJSON_CHUNK_LEN);
+        for (chunk, part) in value.as_bytes().chunks(NDJSON_CHUNK_LEN).enumerate() {
+            // ASCII was checked above, so every chunk is valid UTF-8
+            let part = std::str::from_utf8(part).unwrap_or_default();
+            let line = json!({ "n": n, "chunk": chunk, "chunks": chunks, label: part });
+            let _ = writeln!(out, "{}", line);
+        }
+    }
+    out
+}
+
+/// Builds a response without a body, e.g. `304 Not Modified`.
+pub fn empty(status: StatusCode) -> Result<Response<Body>, Error> {
+    Ok(headers(Response::builder().status(status)).body(Body::Empty)?)
+}
+
+/// Builds the headers of a `format` response without its body, for HEAD
+/// requests that should not pay for the computation.
+pub fn head(status: StatusCode, format: Format) -> Result<Response<Body>, Error> {
+    send(status, format.content_type(), Body::Empty)
+}
+
+fn send(status: StatusCode, content_type: &str, body: Body) -> Result<Response<Body>, Error> {
+    Ok(headers(Response::builder().status(status))
+        .header("Content-Type", content_type)
+        .body(body)?)
+}
+
+// Headers shared by every response. CORS headers depend on the request's
+// Origin and are added by the handler wrapper, see `cors`
+fn headers(builder: http::response::Builder) -> http::response::Builder {
+    builder.header("Vary", "Accept, Accept-Language")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ndjson_lines_and_chunks() {
+        let rows = vec![("10".to_string(), "55".to_string()), ("x".to_string(), "1".to_string())];
+        assert_eq!(
+            ndjson(rows, "fibonacci"),
+            "{\"fibonacci\":\"55\",\"n\":10}\n{\"fibonacci\":\"1\",\"n\":\"x\"}\n"
+        );
+
+        let long = "7".repeat(NDJSON_CHUNK_LEN * 2 + 1);
+        let text = ndjson(vec![("1".to_string(), long.clone())], "value");
+        let lines: Vec<Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line["chunks"] == 3));
+        let joined: String = lines.iter().map(|line| line["value"].as_str().unwrap()).collect();
+        assert_eq!(joined, long);
+    }
+
+    #[test]
+    fn shape_trims_and_indents() {
+        let shape = Shape::from_query("fields=fibonacci,%20n,&pretty").unwrap();
+        assert_eq!(shape.fields, Some(vec!["fibonacci".to_string(), "n".to_string()]));
+        assert!(shape.pretty);
+        assert_eq!(shape.key(), "fields=fibonacci,n;pretty");
+        assert_eq!(Shape::from_query("n=10"), Ok(Shape::default()));
+        assert!(Shape::from_query("pretty=maybe").is_err());
+
+        let mut body = json!({ "n": 10, "fibonacci": "55", "status": "success" });
+        shape.trim(&mut body);
+        assert_eq!(body, json!({ "n": 10, "fibonacci": "55" }));
+        assert_eq!(shape.to_json(&body), "{\n  \"fibonacci\": \"55\",\n  \"n\": 10\n}");
+    }
+}
@@ -0,0 +1,170 @@
+//! Path-based dispatch for serving every endpoint from a single function.
+//!
+//! Routes are patterns such as `/api/lucas/{n}` whose `{..}` segments match
+//! any single segment. Handlers still read their parameters from the request
+//! themselves, so a routed request behaves exactly like one sent to the
+//! endpoint's own function.
+
+use http::Uri;
+use std::future::Future;
+use std::pin::Pin;
+use vercel_runtime::{Body, Error, Request, Response};
+
+/// The future returned by a routed handler.
+pub type HandlerFuture = Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send>>;
+
+type Handler = Box<dyn Fn(Request) -> HandlerFuture + Send + Sync>;
+
+/// Query parameter carrying the original path when a catch-all rewrite sends
+/// the request to the router's own URL.
+pub const ROUTE_PARAM: &str = "route";
+
+/// An ordered table of routes; the first matching pattern wins.
+pub struct Router {
+    routes: Vec<(&'static str, Vec<Segment>, Handler)>,
+    fallback: Handler,
+}
+
+enum Segment {
+    Literal(&'static str),
+    Param,
+}
+
+impl Router {
+    /// Creates a router that sends unmatched paths to `fallback`.
+    pub fn new<F, Fut>(fallback: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Response<Body>, Error>> + Send + 'static,
+    {
+        Router {
+            routes: Vec::new(),
+            fallback: boxed(fallback),
+        }
+    }
+
+    /// Adds a route. More specific patterns must be added before ones like
+    /// `/api/{n}` that would otherwise shadow them.
+    pub fn route<F, Fut>(mut self, pattern: &'static str, handler: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Response<Body>, Error>> + Send + 'static,
+    {
+        let segments = segments(pattern)
+            .map(|segment| {
+                if segment.starts_with('{') && segment.ends_with('}') {
+                    Segment::Param
+                } else {
+                    Segment::Literal(segment)
+                }
+            })
+            .collect();
+        self.routes.push((pattern, segments, boxed(handler)));
+        self
+    }
+
+    /// The patterns of the routes, in the order they are matched.
+    pub fn patterns(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.routes.iter().map(|(pattern, _, _)| *pattern)
+    }
+
+    /// Index of the route matching `path`, if any.
+    fn find(&self, path: &str) -> Option<usize> {
+        let parts: Vec<&str> = segments(path).collect();
+        self.routes.iter().position(|(_, pattern, _)| {
+            pattern.len() == parts.len()
+                && pattern.iter().zip(&parts).all(|(segment, part)| match segment {
+                    Segment::Literal(literal) => literal == part,
+                    Segment::Param => true,
+                })
+        })
+    }
+
+    /// Dispatches `req` to the handler of the first matching route.
+    pub async fn handle(&self, mut req: Request) -> Result<Response<Body>, Error> {
+        restore_path(&mut req);
+        let handler = match self.find(req.uri().path()) {
+            Some(index) => &self.routes[index].2,
+            None => {
+                tracing::debug!(path = req.uri().path(), "no route matched");
+                &self.fallback
+            }
+        };
+        handler(req).await
+    }
+}
+
+fn boxed<F, Fut>(handler: F) -> Handler
+where
+    F: Fn(Request) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<Body>, Error>> + Send + 'static,
+{
+    Box::new(move |req| Box::pin(handler(req)))
+}
+
+// Empty segments are skipped, so `/api/` and `/api` are the same path
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Puts back the path a catch-all rewrite moved into `?route=`. Requests
+/// that were not rewritten are left alone, so this may run more than once.
+pub fn restore_path(req: &mut Request) {
+    if let Some(uri) = original_uri(req.uri()) {
+        *req.uri_mut() = uri;
+    }
+}
+
+// Rebuilds `/api/<route>?<rest>` from a rewritten `...?route=<route>&<rest>`
+fn original_uri(uri: &Uri) -> Option<Uri> {
+    let query = uri.query()?;
+    let mut route = None;
+    let mut rest = Vec::new();
+    for pair in query.split('&') {
+        match pair.split_once('=') {
+            Some((ROUTE_PARAM, value)) => route = Some(value),
+            _ => rest.push(pair),
+        }
+    }
+    let route = route?.replace("%2F", "/").replace("%2f", "/");
+    let mut rebuilt = format!("/api/{}", route.trim_start_matches('/'));
+    if !rest.is_empty() {
+        rebuilt.push('?');
+        rebuilt.push_str(&rest.join("&"));
+    }
+    rebuilt.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vercel_runtime::StatusCode;
+
+    async fn respond(status: StatusCode) -> Result<Response<Body>, Error> {
+        Ok(Response::builder().status(status).body(Body::Empty)?)
+    }
+
+    #[test]
+    fn matches_in_order() {
+        let router = Router::new(|_| respond(StatusCode::NOT_FOUND))
+            .route("/api/range", |_| respond(StatusCode::OK))
+            .route("/api/lucas/{n}", |_| respond(StatusCode::OK))
+            .route("/api/{n}", |_| respond(StatusCode::OK));
+        assert_eq!(router.find("/api/range"), Some(0));
+        assert_eq!(router.find("/api/range/"), Some(0));
+        assert_eq!(router.find("/api/lucas/12"), Some(1));
+        assert_eq!(router.find("/api/12"), Some(2));
+        assert_eq!(router.find("/api/lucas"), Some(2));
+        assert_eq!(router.find("/api/a/b"), None);
+        assert_eq!(router.find("/other"), None);
+    }
+
+    #[test]
+    fn restores_rewritten_paths() {
+        let uri: Uri = "/api/router?route=fib%2F20&format=csv".parse().unwrap();
+        assert_eq!(original_uri(&uri).unwrap(), "/api/fib/20?format=csv");
+        let uri: Uri = "/api/router?route=health".parse().unwrap();
+        assert_eq!(original_uri(&uri).unwrap(), "/api/health");
+        assert!(original_uri(&"/api/range?from=1".parse().unwrap()).is_none());
+    }
+}
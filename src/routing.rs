@@ -0,0 +1,76 @@
+//! Lookup of the fields of JSON request bodies; query, path and typed
+//! parameters are read with [`crate::params`].
+
+use crate::error::ApiError;
+use serde_json::{Map, Value};
+
+/// Parses a request body that must be a JSON object.
+pub fn parse_json_object(body: &[u8]) -> Result<Map<String, Value>, ApiError> {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(other) => Err(ApiError::MalformedBody {
+            reason: format!("expected a JSON object, got {}", json_type_name(&other)),
+        }),
+        Err(e) => Err(ApiError::MalformedBody {
+            reason: e.to_string(),
+        }),
+    }
+}
+
+/// Reads an optional non-negative integer field from a JSON object.
+pub fn json_u64(object: &Map<String, Value>, key: &'static str) -> Result<Option<u64>, ApiError> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value.as_u64().map(Some).ok_or_else(|| ApiError::InvalidParameter {
+            name: key,
+            received: value.to_string(),
+            expected: "a non-negative integer".into(),
+        }),
+    }
+}
+
+/// Reads an optional string field from a JSON object.
+pub fn json_str<'a>(object: &'a Map<String, Value>, key: &'static str) -> Result<Option<&'a str>, ApiError> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value.as_str().map(Some).ok_or_else(|| ApiError::InvalidParameter {
+            name: key,
+            received: value.to_string(),
+            expected: "a string".into(),
+        }),
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_body_fields() {
+        let body = parse_json_object(br#"{"n": 250, "expr": "F(10)"}"#).unwrap();
+        assert_eq!(json_u64(&body, "n"), Ok(Some(250)));
+        assert_eq!(json_str(&body, "expr"), Ok(Some("F(10)")));
+        assert_eq!(json_u64(&body, "profile_runs"), Ok(None));
+
+        let body = parse_json_object(br#"{"n": "250", "expr": 5}"#).unwrap();
+        assert!(json_u64(&body, "n").is_err());
+        assert!(json_str(&body, "expr").is_err());
+    }
+
+    #[test]
+    fn malformed_json_body() {
+        assert!(parse_json_object(b"{\"n\": ").is_err());
+        assert!(parse_json_object(b"[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! Security headers attached to every response.
+//!
+//! The API serves data, not pages, so the defaults forbid sniffing, framing,
+//! referrers and loading anything at all. Each header can be changed, or turned
+//! off with `off`, through `SECURITY_*` environment variables. A handler that
+//! needs a looser `Content-Security-Policy`, like the Swagger UI page, sets its
+//! own and keeps it.
+
+use http::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY,
+    STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+};
+
+/// Value that leaves a header out.
+pub const OFF: &str = "off";
+
+/// The security header values; [`OFF`] omits one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityHeaders {
+    pub content_type_options: String,
+    pub referrer_policy: String,
+    pub strict_transport_security: String,
+    pub content_security_policy: String,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        SecurityHeaders {
+            content_type_options: "nosniff".to_string(),
+            referrer_policy: "no-referrer".to_string(),
+            strict_transport_security: "max-age=63072000; includeSubDomains".to_string(),
+            content_security_policy: "default-src 'none'; frame-ancestors 'none'".to_string(),
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// Adds the headers to `headers`, keeping a `Content-Security-Policy` the
+    /// handler already chose.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        set(headers, X_CONTENT_TYPE_OPTIONS, &self.content_type_options);
+        set(headers, REFERRER_POLICY, &self.referrer_policy);
+        set(headers, STRICT_TRANSPORT_SECURITY, &self.strict_transport_security);
+        if !headers.contains_key(CONTENT_SECURITY_POLICY) {
+            set(headers, CONTENT_SECURITY_POLICY, &self.content_security_policy);
+        }
+    }
+}
+
+fn set(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if value.eq_ignore_ascii_case(OFF) {
+        return;
+    }
+    match HeaderValue::from_str(value) {
+        Ok(value) => {
+            headers.insert(name, value);
+        }
+        Err(_) => tracing::warn!(header = %name, value, "skipping invalid security header"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_defaults_and_keeps_handler_policy() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_SECURITY_POLICY, HeaderValue::from_static("default-src 'self'"));
+        let policy = SecurityHeaders {
+            referrer_policy: "Off".to_string(),
+            ..SecurityHeaders::default()
+        };
+        policy.apply(&mut headers);
+        assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[CONTENT_SECURITY_POLICY], "default-src 'self'");
+        assert!(headers.contains_key(STRICT_TRANSPORT_SECURITY));
+        assert!(!headers.contains_key(REFERRER_POLICY));
+    }
+}
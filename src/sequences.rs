@@ -0,0 +1,100 @@
+//! Classic integer sequences served by `/api/seq/{name}/{n}`.
+//!
+//! Every sequence is a function from the index to the term plus an entry
+//! in [`SEQUENCES`]; the endpoint, its documentation and the listing at
+//! `/api/seq` all read the registry, so nothing else needs to change to add
+//! one.
+
+use crate::combinatorics;
+use crate::lucas::LucasParams;
+use num_bigint::BigUint;
+
+/// An integer sequence and how to compute its terms.
+#[derive(Debug)]
+pub struct Sequence {
+    /// The path segment selecting the sequence.
+    pub name: &'static str,
+    /// Definition, starting from index 0.
+    pub description: &'static str,
+    /// A-number in the On-Line Encyclopedia of Integer Sequences.
+    pub oeis: &'static str,
+    /// How terms are computed, reported in the response metadata.
+    pub algorithm: &'static str,
+    /// The term at an index.
+    pub term: fn(u64) -> BigUint,
+}
+
+/// Every sequence served, by name.
+pub static SEQUENCES: &[Sequence] = &[
+    Sequence {
+        name: "catalan",
+        description: "C(n) = (2n choose n) / (n + 1): 1, 1, 2, 5, 14, 42, ...",
+        oeis: "A000108",
+        algorithm: "binomial",
+        term: catalan,
+    },
+    Sequence {
+        name: "factorial",
+        description: "n! = 1 · 2 ⋯ n: 1, 1, 2, 6, 24, 120, ...",
+        oeis: "A000142",
+        algorithm: "binary-splitting",
+        term: combinatorics::factorial,
+    },
+    Sequence {
+        name: "pell",
+        description: "P(n) = 2P(n − 1) + P(n − 2) from 0, 1: 0, 1, 2, 5, 12, 29, ...",
+        oeis: "A000129",
+        algorithm: "lucas-doubling",
+        term: pell,
+    },
+    Sequence {
+        name: "triangular",
+        description: "T(n) = n(n + 1) / 2: 0, 1, 3, 6, 10, 15, ...",
+        oeis: "A000217",
+        algorithm: "closed-form",
+        term: triangular,
+    },
+];
+
+/// The sequence called `name`.
+pub fn find(name: &str) -> Option<&'static Sequence> {
+    SEQUENCES.iter().find(|sequence| sequence.name == name)
+}
+
+fn catalan(n: u64) -> BigUint {
+    combinatorics::choose(2 * n, n) / (n + 1)
+}
+
+// U_n(2, −1), so it shares the Lucas sequence doubling
+fn pell(n: u64) -> BigUint {
+    let (u, _) = LucasParams { p: 2, q: -1 }.terms(n);
+    u.into_parts().1
+}
+
+fn triangular(n: u64) -> BigUint {
+    BigUint::from(n) * (n + 1) / 2u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first(name: &str, count: u64) -> Vec<String> {
+        let sequence = find(name).unwrap();
+        (0..count).map(|n| (sequence.term)(n).to_string()).collect()
+    }
+
+    #[test]
+    fn first_terms_match_the_oeis() {
+        assert_eq!(first("catalan", 8), ["1", "1", "2", "5", "14", "42", "132", "429"]);
+        assert_eq!(first("factorial", 6), ["1", "1", "2", "6", "24", "120"]);
+        assert_eq!(first("pell", 8), ["0", "1", "2", "5", "12", "29", "70", "169"]);
+        assert_eq!(first("triangular", 6), ["0", "1", "3", "6", "10", "15"]);
+        assert!(find("primes").is_none());
+    }
+
+    #[test]
+    fn registry_is_sorted_by_name() {
+        assert!(SEQUENCES.windows(2).all(|pair| pair[0].name < pair[1].name));
+    }
+}
@@ -0,0 +1,210 @@
+//! Response bodies delivered in pieces as they are produced.
+//!
+//! Vercel's Rust runtime sends a function's response in one piece, so there
+//! a streamed body is produced in full and then sent. A transport that can
+//! send a body in pieces, like the dev server, serves the request inside
+//! [`incremental`]; the handler then answers with an empty body carrying a
+//! [`Streamed`] extension, and the transport forwards every chunk from it
+//! as soon as the producer hands it over.
+//!
+//! Either way the chunks are produced within the request's [`Deadline`]. A
+//! body produced in full answers `504` once it passes; one already being sent
+//! ends with an error chunk instead, see [`error_chunk`].
+
+use crate::budget::{self, Deadline};
+use crate::error::ApiError;
+use serde_json::json;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use vercel_runtime::{Body, Error, Response, StatusCode};
+
+/// The content type of server-sent events.
+pub const EVENT_STREAM: &str = "text/event-stream";
+
+// Chunks produced ahead of the client before the producer waits
+const CHANNEL_CAPACITY: usize = 16;
+
+/// The chunks of a body being produced, left in the response's extensions
+/// for the transport to forward.
+#[derive(Debug, Clone)]
+pub struct Streamed(Arc<Mutex<Option<mpsc::Receiver<String>>>>);
+
+impl Streamed {
+    /// The chunks; `None` once a transport took them.
+    pub fn take(&self) -> Option<mpsc::Receiver<String>> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take()
+    }
+}
+
+tokio::task_local! {
+    static INCREMENTAL: ();
+}
+
+/// Serves `future` on a transport that forwards [`Streamed`] bodies.
+pub async fn incremental<F: Future>(future: F) -> F::Output {
+    INCREMENTAL.scope((), future).await
+}
+
+/// Whether the current request is served inside [`incremental`].
+pub fn is_incremental() -> bool {
+    INCREMENTAL.try_with(|_| ()).is_ok()
+}
+
+/// A `200` response of `content_type` whose body is `chunks` in order,
+/// produced on the blocking pool until `deadline` passes. `expired` names
+/// the error for a deadline passed after that many chunks.
+///
+/// Inside [`incremental`] the chunks are produced while the transport sends
+/// them, and production stops when the client goes away; a deadline passing
+/// ends the body with [`error_chunk`]. Otherwise they are joined into the
+/// body, and a deadline passing answers the error instead.
+pub async fn respond<I, E>(
+    content_type: &'static str,
+    chunks: I,
+    deadline: Deadline,
+    expired: E,
+) -> Result<Response<Body>, Error>
+where
+    I: IntoIterator<Item = String>,
+    I::IntoIter: Send + 'static,
+    E: FnOnce(u64) -> ApiError + Send + 'static,
+{
+    let builder = Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", content_type)
+        .header("Cache-Control", "no-cache")
+        .header("Vary", "Accept, Accept-Language");
+    let mut chunks = chunks.into_iter();
+    if !is_incremental() {
+        let produced = Arc::new(AtomicU64::new(0));
+        let counted = Arc::clone(&produced);
+        let body = budget::run_blocking(deadline, move || {
+            let mut body = String::new();
+            while !deadline.expired() {
+                let Some(chunk) = chunks.next() else {
+                    return Some(body);
+                };
+                body.push_str(&chunk);
+                counted.fetch_add(1, Ordering::Relaxed);
+            }
+            None
+        });
+        if let Some(body) = body.await?.flatten() {
+            return Ok(builder.body(body.into())?);
+        }
+        let err = expired(produced.load(Ordering::Relaxed));
+        tracing::warn!(error = %err, "stream abandoned");
+        return err.into_response();
+    }
+    let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+    tokio::task::spawn_blocking(move || {
+        let mut sent = 0;
+        // Nobody reads the chunks once the client went away, so stop making them
+        while !sender.is_closed() {
+            if deadline.expired() {
+                let err = expired(sent);
+                tracing::warn!(error = %err, "stream abandoned");
+                let _ = sender.blocking_send(error_chunk(content_type, &err));
+                return;
+            }
+            let Some(chunk) = chunks.next() else {
+                return;
+            };
+            if sender.blocking_send(chunk).is_err() {
+                return;
+            }
+            sent += 1;
+        }
+    });
+    Ok(builder
+        .extension(Streamed(Arc::new(Mutex::new(Some(receiver)))))
+        .body(Body::Empty)?)
+}
+
+/// The last chunk of a body of `content_type` whose production failed with
+/// `err`: an `error` event for server-sent events, otherwise an NDJSON line
+/// with an `error` member like a failed batch entry.
+pub fn error_chunk(content_type: &str, err: &ApiError) -> String {
+    let error = json!({ "code": err.code(), "status": err.status().as_u16(), "detail": err.to_string() });
+    if content_type == EVENT_STREAM {
+        event(None, "error", &error.to_string())
+    } else {
+        format!("{}\n", json!({ "error": error }))
+    }
+}
+
+/// One server-sent event of type `event` with a `data` line, and an `id`
+/// when given.
+pub fn event(id: Option<u64>, event: &str, data: &str) -> String {
+    match id {
+        Some(id) => format!("id: {}\nevent: {}\ndata: {}\n\n", id, event, data),
+        None => format!("event: {}\ndata: {}\n\n", event, data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn expired(done: u64) -> ApiError {
+        Deadline::none().abandoned("the test", format!("{} chunks", done), Vec::new())
+    }
+
+    async fn received(response: Response<Body>) -> Vec<String> {
+        assert!(response.body().is_empty());
+        let mut receiver = response.extensions().get::<Streamed>().unwrap().take().unwrap();
+        let mut received = Vec::new();
+        while let Some(chunk) = receiver.recv().await {
+            received.push(chunk);
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn hands_chunks_to_incremental_transports() {
+        let chunks = || (1..=3).map(|i| format!("{}\n", i));
+        let response = respond("application/x-ndjson", chunks(), Deadline::none(), expired).await.unwrap();
+        assert_eq!(response.body(), &Body::from("1\n2\n3\n"));
+
+        let response = incremental(respond("application/x-ndjson", chunks(), Deadline::none(), expired)).await;
+        assert_eq!(received(response.unwrap()).await, ["1\n", "2\n", "3\n"]);
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_deadline() {
+        let passed = Deadline::after(Duration::ZERO);
+        let chunks = || (1..=3).map(|i| format!("{}\n", i));
+        let response = respond("application/x-ndjson", chunks(), passed, expired).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let response = incremental(respond(EVENT_STREAM, chunks(), passed, expired)).await;
+        let received = received(response.unwrap()).await;
+        assert_eq!(received.len(), 1);
+        assert!(received[0].starts_with("event: error\ndata: {\"code\":\"deadline_exceeded\""), "{}", received[0]);
+
+        let line = error_chunk("application/x-ndjson", &expired(2));
+        let line: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(line["error"]["status"], 504);
+    }
+
+    #[tokio::test]
+    async fn stops_producing_once_the_client_is_gone() {
+        let produced = Arc::new(AtomicU64::new(0));
+        let counted = Arc::clone(&produced);
+        let endless = (0u64..).map(move |i| {
+            counted.fetch_add(1, Ordering::Relaxed);
+            format!("{}\n", i)
+        });
+        let response = incremental(respond("application/x-ndjson", endless, Deadline::none(), expired)).await;
+        drop(response.unwrap().extensions().get::<Streamed>().unwrap().take());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let stopped_at = produced.load(Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(produced.load(Ordering::Relaxed), stopped_at);
+        assert!(stopped_at <= CHANNEL_CAPACITY as u64 + 1);
+    }
+}
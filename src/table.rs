@@ -0,0 +1,30 @@
+//! F(0) to F([`MAX_N`]) in decimal, generated by `build.rs` and embedded in
+//! the binary.
+//!
+//! Most requests ask for small indices, so the single-value endpoint answers
+//! those with a lookup instead of computing anything or allocating a
+//! `BigUint`, and reports `"source": "table"` in its `meta`.
+
+/// The largest index in the table.
+pub const MAX_N: u64 = 1000;
+
+static TABLE: [&str; MAX_N as usize + 1] = include!(concat!(env!("OUT_DIR"), "/fib_table.rs"));
+
+/// F(n) in decimal, or `None` above [`MAX_N`].
+pub fn get(n: u64) -> Option<&'static str> {
+    TABLE.get(usize::try_from(n).ok()?).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fib::calculate_fibonacci;
+
+    #[test]
+    fn matches_computed_values() {
+        for n in [0, 1, 2, 10, 93, 94, 500, MAX_N] {
+            assert_eq!(get(n), Some(calculate_fibonacci(n).to_string().as_str()), "F({})", n);
+        }
+        assert_eq!(get(MAX_N + 1), None);
+    }
+}
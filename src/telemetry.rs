@@ -0,0 +1,200 @@
+//! Structured logging with `tracing`.
+//!
+//! Logs are emitted as one JSON object per line so the Vercel dashboard can
+//! filter them by field. The level defaults to `info` and can be overridden
+//! with the usual `RUST_LOG` directives (e.g. `RUST_LOG=debug`).
+//!
+//! Requests also join the caller's distributed trace: an incoming W3C
+//! `traceparent` header is continued with a new span ID ([`TraceContext`]),
+//! and a new trace is started when there is none.
+
+use crate::signing;
+use http::header::HeaderValue;
+use http::HeaderMap;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing_subscriber::EnvFilter;
+use vercel_runtime::Request;
+
+/// Installs the JSON subscriber. Safe to call more than once.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let _ = tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(filter)
+        .with_current_span(true)
+        .with_span_list(false)
+        .try_init();
+}
+
+/// Request and response header carrying the trace and parent span IDs.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Vendor-specific trace data, passed on unchanged.
+pub const TRACESTATE: &str = "tracestate";
+
+/// This request's place in a W3C Trace Context trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 32 hex digits shared by every span of the trace.
+    pub trace_id: String,
+    /// 16 hex digits identifying this request's span.
+    pub span_id: String,
+    /// The caller's span, `None` when this request started the trace.
+    pub parent_id: Option<String>,
+    /// Trace flags; bit 0 means the caller sampled the trace.
+    pub flags: u8,
+    /// The caller's `tracestate`, kept only with a valid `traceparent`.
+    pub state: Option<String>,
+}
+
+impl TraceContext {
+    /// Continues the trace in `headers`, or starts a new sampled one when
+    /// `traceparent` is missing or invalid.
+    pub fn from_headers(headers: &HeaderMap) -> TraceContext {
+        let header = |name| headers.get(name).and_then(|value: &HeaderValue| value.to_str().ok());
+        match header(TRACEPARENT).and_then(parse_traceparent) {
+            Some((trace_id, parent_id, flags)) => TraceContext {
+                trace_id,
+                span_id: random_hex(8),
+                parent_id: Some(parent_id),
+                flags,
+                state: header(TRACESTATE).map(str::trim).filter(|state| !state.is_empty()).map(String::from),
+            },
+            None => TraceContext {
+                trace_id: random_hex(16),
+                span_id: random_hex(8),
+                parent_id: None,
+                flags: 1,
+                state: None,
+            },
+        }
+    }
+
+    /// The `traceparent` naming this request's span, sent back to the caller
+    /// and on to anything this request calls.
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, self.flags)
+    }
+
+    /// Adds `traceparent` and, when the caller sent one, `tracestate`.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Ok(value) = HeaderValue::from_str(&self.traceparent()) {
+            headers.insert(TRACEPARENT, value);
+        }
+        if let Some(value) = self.state.as_deref().and_then(|state| HeaderValue::from_str(state).ok()) {
+            headers.insert(TRACESTATE, value);
+        }
+    }
+}
+
+// `version-traceid-parentid-flags`. Versions after 00 may append fields, which
+// are ignored; all-zero IDs and version ff are invalid
+fn parse_traceparent(value: &str) -> Option<(String, String, u8)> {
+    let value = value.trim();
+    let mut fields = value.split('-');
+    let version = fields.next()?;
+    let trace_id = fields.next()?;
+    let parent_id = fields.next()?;
+    let flags = fields.next()?;
+    let rest = fields.next();
+    let hex = |field: &str, len: usize| {
+        field.len() == len && field.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    };
+    let valid = hex(version, 2)
+        && version != "ff"
+        && (version != "00" || rest.is_none())
+        && hex(trace_id, 32)
+        && hex(parent_id, 16)
+        && hex(flags, 2)
+        && trace_id.bytes().any(|b| b != b'0')
+        && parent_id.bytes().any(|b| b != b'0');
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+    valid.then(|| (trace_id.to_string(), parent_id.to_string(), flags))
+}
+
+/// `len` random bytes in hex, as used for trace and span IDs: from the OS
+/// generator or, should that fail, a hash of the time and a counter.
+pub fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0; len];
+    if getrandom::fill(&mut bytes).is_err() || bytes.iter().all(|&b| b == 0) {
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or(0);
+        let mut hasher = Sha256::new();
+        hasher.update(nanos.to_le_bytes());
+        hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+        bytes.copy_from_slice(&hasher.finalize()[..len]);
+    }
+    signing::hex(&bytes)
+}
+
+/// Returns the ID used to correlate a request's log lines: Vercel's
+/// `x-vercel-id` when present, otherwise a locally generated one.
+pub fn request_id(req: &Request) -> String {
+    if let Some(id) = req.headers().get("x-vercel-id").and_then(|v| v.to_str().ok()) {
+        return id.to_string();
+    }
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0);
+    format!("{:x}-{:x}", nanos, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Records the index `n` a request asks for on its span.
+pub fn record_n<N: tracing::Value>(n: N) {
+    tracing::Span::current().record("n", n);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(traceparent: &str, tracestate: Option<&str>) -> TraceContext {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT, HeaderValue::from_str(traceparent).unwrap());
+        if let Some(state) = tracestate {
+            headers.insert(TRACESTATE, HeaderValue::from_str(state).unwrap());
+        }
+        TraceContext::from_headers(&headers)
+    }
+
+    #[test]
+    fn continues_a_valid_traceparent() {
+        let trace = context("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01", Some("congo=t61rcWkgMzE"));
+        assert_eq!(trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(trace.parent_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_ne!(trace.span_id, "00f067aa0ba902b7");
+        assert_eq!(trace.state.as_deref(), Some("congo=t61rcWkgMzE"));
+        assert!(trace.traceparent().starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(trace.traceparent().ends_with("-01"));
+
+        // Later versions may carry more fields
+        let trace = context("cc-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra", None);
+        assert_eq!(trace.flags, 0);
+    }
+
+    #[test]
+    fn restarts_invalid_traces() {
+        for invalid in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "garbage",
+        ] {
+            let trace = context(invalid, Some("congo=1"));
+            assert_eq!(trace.parent_id, None, "{}", invalid);
+            assert_eq!(trace.state, None);
+            assert_eq!(trace.trace_id.len(), 32);
+            assert_eq!(trace.span_id.len(), 16);
+        }
+    }
+}
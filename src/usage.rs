@@ -0,0 +1,267 @@
+//! Per-key usage accounting and quotas.
+//!
+//! With `API_KEYS` set, every request is counted against the key it presents:
+//! once as a request and again in compute units, one per request plus the
+//! [`Work`] it does: one per [`N_PER_UNIT`] of the indices it computes in
+//! full, so F(1000000) costs far more than F(10), and a range as much as its
+//! indices together. Counts are kept per UTC day and month in the persistent
+//! [`store`] when one is configured, in process memory otherwise. The
+//! `QUOTA_*` limits on them answer `429` once a key has used its share, and
+//! `GET /api/usage` shows a key what it has used so far.
+
+use crate::auth;
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::{signing, store};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::cell::Cell;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, OnceLock};
+use vercel_runtime::Request;
+
+/// Indices covered by one compute unit.
+pub const N_PER_UNIT: u64 = 1000;
+
+// Counters kept in process memory
+const CACHE_CAPACITY: usize = 4096;
+
+/// Limits on what one key may use; 0 leaves a limit off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quotas {
+    pub daily_requests: u64,
+    pub monthly_requests: u64,
+    pub daily_units: u64,
+    pub monthly_units: u64,
+}
+
+impl Quotas {
+    /// Whether no limit is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Quotas::default()
+    }
+
+    /// The request and unit limits for `period`.
+    pub fn limits(&self, period: Period) -> Usage {
+        match period {
+            Period::Day => Usage {
+                requests: self.daily_requests,
+                units: self.daily_units,
+            },
+            Period::Month => Usage {
+                requests: self.monthly_requests,
+                units: self.monthly_units,
+            },
+        }
+    }
+}
+
+/// A span of time usage is counted over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Month,
+}
+
+impl Period {
+    pub const ALL: [Period; 2] = [Period::Day, Period::Month];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Period::Day => "daily",
+            Period::Month => "monthly",
+        }
+    }
+
+    /// The period containing `now`, e.g. `2026-10-14` or `2026-10`.
+    pub fn label(self, now: DateTime<Utc>) -> String {
+        match self {
+            Period::Day => now.format("%Y-%m-%d").to_string(),
+            Period::Month => now.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// When the period containing `now` ends and its counts start over.
+    pub fn resets_at(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.date_naive();
+        let next = match self {
+            Period::Day => today.succ_opt(),
+            Period::Month if today.month() == 12 => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+            Period::Month => NaiveDate::from_ymd_opt(today.year(), today.month() + 1, 1),
+        };
+        next.and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|midnight| midnight.and_utc())
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+/// What a key used, or may use, in one period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub requests: u64,
+    pub units: u64,
+}
+
+/// What a request computes, priced in compute units beyond the base one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Work {
+    /// Values in full at indices adding up to this: one unit per
+    /// [`N_PER_UNIT`] of the sum.
+    Indices(u128),
+    /// One unit, for work that costs about the same at any index: a value
+    /// modulo m, its last digits or an estimate.
+    Flat,
+}
+
+impl Work {
+    /// A value in full at `n`.
+    pub fn index(n: impl Into<i128>) -> Work {
+        Work::Indices(n.into().unsigned_abs())
+    }
+
+    /// Values in full at each of `indices`, such as the terms of a range.
+    pub fn indices<N: Into<i128>>(indices: impl IntoIterator<Item = N>) -> Work {
+        let sum = indices
+            .into_iter()
+            .fold(0u128, |sum, n| sum.saturating_add(n.into().unsigned_abs()));
+        Work::Indices(sum)
+    }
+
+    /// Compute units beyond the base one.
+    pub fn units(self) -> u64 {
+        match self {
+            Work::Indices(sum) => u64::try_from(sum / u128::from(N_PER_UNIT)).unwrap_or(u64::MAX),
+            Work::Flat => 1,
+        }
+    }
+}
+
+tokio::task_local! {
+    static CHARGED: Cell<u64>;
+}
+
+/// Charges the current request for `work`. Every handler that computes
+/// something calls this once it knows what; it does nothing outside a
+/// metered request.
+pub fn charge(work: Work) {
+    let _ = CHARGED.try_with(|charged| charged.set(charged.get().saturating_add(work.units())));
+}
+
+/// Where the request's usage is counted: its API key hashed, so the store
+/// never sees it. `None` unless [`auth::authenticate`] accepted a key.
+pub fn account(req: &Request) -> Option<String> {
+    let key = auth::authenticated_key(req)?;
+    Some(format!("usage:{}", signing::hex(&Sha256::digest(key.as_bytes()))))
+}
+
+/// What `account` used in the period containing `now`.
+pub async fn read(account: &str, period: Period, now: DateTime<Utc>) -> Usage {
+    Usage {
+        requests: add(&slot(account, period, now, "requests"), 0, ttl(period, now)).await,
+        units: add(&slot(account, period, now, "units"), 0, ttl(period, now)).await,
+    }
+}
+
+/// Rejects the request when `account` has used up a quota.
+pub async fn check(account: &str, now: DateTime<Utc>) -> Result<(), ApiError> {
+    let quotas = Config::get().quotas;
+    if quotas.is_empty() {
+        return Ok(());
+    }
+    for period in Period::ALL {
+        let limits = quotas.limits(period);
+        let used = read(account, period, now).await;
+        let exceeded = [("requests", used.requests, limits.requests), ("units", used.units, limits.units)]
+            .into_iter()
+            .find(|&(_, used, limit)| limit > 0 && used >= limit);
+        if let Some((unit, used, limit)) = exceeded {
+            return Err(ApiError::QuotaExceeded {
+                period: period.name(),
+                unit,
+                used,
+                limit,
+                retry_after_secs: (period.resets_at(now) - now).num_seconds().max(1) as u64,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Runs `future` as a request by `account`, then counts it with the units
+/// [`charge`]d while it ran.
+pub async fn metered<F: Future>(account: &str, future: F) -> F::Output {
+    let (output, charged) = CHARGED
+        .scope(Cell::new(0), async {
+            let output = future.await;
+            (output, CHARGED.with(Cell::get))
+        })
+        .await;
+    let now = Utc::now();
+    for period in Period::ALL {
+        let ttl = ttl(period, now);
+        add(&slot(account, period, now, "requests"), 1, ttl).await;
+        add(&slot(account, period, now, "units"), charged.saturating_add(1), ttl).await;
+    }
+    output
+}
+
+fn slot(account: &str, period: Period, now: DateTime<Utc>, counter: &str) -> String {
+    format!("{}:{}:{}", account, period.label(now), counter)
+}
+
+// Counters outlive their period by a day, so a late read still finds them
+fn ttl(period: Period, now: DateTime<Utc>) -> u64 {
+    (period.resets_at(now) - now + Duration::days(1)).num_seconds().max(1) as u64
+}
+
+// Adds `by` to a counter, in the store when it answers and in memory otherwise
+async fn add(slot: &str, by: u64, ttl_secs: u64) -> u64 {
+    let stored = store::increment(slot, by, ttl_secs).await;
+    let mut counters = counters().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let counter = counters.get_or_insert_mut(slot.to_string(), || 0);
+    *counter = stored.unwrap_or_else(|| counter.saturating_add(by));
+    *counter
+}
+
+fn counters() -> &'static Mutex<LruCache<String, u64>> {
+    static COUNTERS: OnceLock<Mutex<LruCache<String, u64>>> = OnceLock::new();
+    COUNTERS.get_or_init(|| Mutex::new(LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap_or(NonZeroUsize::MIN))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn periods_reset_at_utc_boundaries() {
+        let now = Utc.with_ymd_and_hms(2026, 12, 31, 18, 30, 0).unwrap();
+        assert_eq!(Period::Day.label(now), "2026-12-31");
+        assert_eq!(Period::Month.label(now), "2026-12");
+        assert_eq!(Period::Day.resets_at(now), Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(Period::Month.resets_at(now), Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap());
+        let mid = Utc.with_ymd_and_hms(2026, 2, 10, 0, 0, 0).unwrap();
+        assert_eq!(Period::Month.resets_at(mid), Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn units_scale_with_the_work() {
+        assert_eq!(Work::index(999).units(), 0);
+        assert_eq!(Work::index(-1_000_000i64).units(), 1000);
+        assert_eq!(Work::Indices(u128::MAX).units(), u64::MAX);
+        assert_eq!(Work::indices([600u64, 700]).units(), 1);
+        assert_eq!(Work::indices(1..=2000u64).units(), 2001);
+        assert_eq!(Work::Flat.units(), 1);
+    }
+
+    #[tokio::test]
+    async fn counts_requests_and_charged_units() {
+        let now = Utc::now();
+        metered("usage:test", async { charge(Work::index(2500)) }).await;
+        metered("usage:test", async {}).await;
+        let used = read("usage:test", Period::Month, now).await;
+        assert_eq!(used, Usage { requests: 2, units: 4 });
+    }
+}
@@ -0,0 +1,132 @@
+//! API versions, selected with a `/api/v1/...` or `/api/v2/...` path prefix.
+//!
+//! Paths without a prefix are version 1, the original response shape, so
+//! existing consumers keep working. Version 2 trims the computed results
+//! (see [`clean`]); errors are the same problem documents in both. The
+//! version served is echoed in the [`HEADER`] response header.
+
+use http::Uri;
+use serde_json::Value;
+use vercel_runtime::Request;
+
+/// Response header naming the version that served the request.
+pub const HEADER: &str = "x-api-version";
+
+/// A response schema version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// The original shape, with `status`, `usage` and the `debug` block.
+    #[default]
+    V1,
+    /// The cleaned-up shape.
+    V2,
+}
+
+tokio::task_local! {
+    static CURRENT: ApiVersion;
+}
+
+impl ApiVersion {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+
+    fn from_segment(segment: &str) -> Option<ApiVersion> {
+        match segment {
+            "v1" => Some(ApiVersion::V1),
+            "v2" => Some(ApiVersion::V2),
+            _ => None,
+        }
+    }
+
+    /// The version `req` was routed as, V1 unless [`select`] chose another.
+    pub fn of(req: &Request) -> ApiVersion {
+        req.extensions().get::<ApiVersion>().copied().unwrap_or_default()
+    }
+
+    /// The version of the request being served, V1 outside a request.
+    pub fn current() -> ApiVersion {
+        CURRENT.try_with(|version| *version).unwrap_or_default()
+    }
+
+    /// Runs `future` with `self` as the [`current`](Self::current) version.
+    pub async fn scope<F: std::future::Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+}
+
+/// Strips a version prefix from the request path, so `/api/v2/20` is routed
+/// as `/api/20`, and records the version on the request.
+pub fn select(req: &mut Request) {
+    if let Some((version, uri)) = strip(req.uri()) {
+        *req.uri_mut() = uri;
+        req.extensions_mut().insert(version);
+    }
+}
+
+// `/api/<version>/<rest>?<query>` without the version segment
+fn strip(uri: &Uri) -> Option<(ApiVersion, Uri)> {
+    let rest = uri.path().strip_prefix("/api/")?;
+    let (segment, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    let version = ApiVersion::from_segment(segment)?;
+    let mut path = format!("/api/{}", rest).trim_end_matches('/').to_string();
+    if let Some(query) = uri.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+    Some((version, path.parse().ok()?))
+}
+
+/// Reshapes a version 1 result document for version 2: the redundant
+/// `status` and `usage` fields and the `debug` block are dropped, and
+/// `timestamp` moves into `meta`.
+pub fn clean(document: &mut Value) {
+    let Some(fields) = document.as_object_mut() else {
+        return;
+    };
+    fields.remove("status");
+    fields.remove("usage");
+    fields.remove("debug");
+    if let Some(timestamp) = fields.remove("timestamp") {
+        if let Some(meta) = fields.get_mut("meta").and_then(Value::as_object_mut) {
+            meta.insert("timestamp".to_string(), timestamp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn strips_the_version_segment() {
+        let (version, uri) = strip(&"/api/v2/20?base=16".parse().unwrap()).unwrap();
+        assert_eq!(version, ApiVersion::V2);
+        assert_eq!(uri, "/api/20?base=16");
+        let (version, uri) = strip(&"/api/v1".parse().unwrap()).unwrap();
+        assert_eq!((version, uri.path()), (ApiVersion::V1, "/api"));
+        assert!(strip(&"/api/20".parse().unwrap()).is_none());
+        assert!(strip(&"/api/v3/20".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn v2_drops_the_redundant_fields() {
+        let mut document = json!({
+            "fibonacci": "55",
+            "status": "success",
+            "usage": "...",
+            "debug": {},
+            "timestamp": "2024-01-15T10:30:00Z",
+            "meta": { "digits": 2 },
+        });
+        clean(&mut document);
+        assert_eq!(
+            document,
+            json!({ "fibonacci": "55", "meta": { "digits": 2, "timestamp": "2024-01-15T10:30:00Z" } })
+        );
+    }
+}
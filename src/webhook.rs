@@ -0,0 +1,225 @@
+//! Signed delivery of job results to client callback URLs.
+//!
+//! Every delivery is a JSON `POST` carrying `X-Fib-Job-Id`,
+//! `X-Fib-Timestamp` and an `X-Fib-Signature` header produced by
+//! [`signing::header`], so the receiver can check it came from this API.
+//! Connection errors and 5xx replies are retried a few times with backoff;
+//! any other reply ends the delivery.
+//!
+//! Callbacks only ever reach public addresses: a loopback, private,
+//! link-local or unspecified host is refused when the job is accepted, and
+//! again whenever its name is resolved for a delivery, so a name that
+//! starts pointing inward afterwards gets nowhere either.
+
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::signing::{self, Secret};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// Header carrying the job ID.
+pub const JOB_ID_HEADER: &str = "x-fib-job-id";
+/// Header carrying the Unix timestamp the signature covers.
+pub const TIMESTAMP_HEADER: &str = "x-fib-timestamp";
+/// Header carrying `t=<timestamp>,v1=<hex HMAC-SHA256>`.
+pub const SIGNATURE_HEADER: &str = "x-fib-signature";
+
+const ATTEMPTS: u32 = 3;
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Parses a callback URL. It must be `https`, unless `WEBHOOK_ALLOW_HTTP`
+/// is set for local testing, and a host given as an address must be public.
+pub fn parse_callback(raw: &str) -> Result<Url, ApiError> {
+    let url = Url::parse(raw).map_err(|_| invalid_callback(raw))?;
+    let allowed = match url.scheme() {
+        "https" => true,
+        "http" => Config::get().webhook_allow_http,
+        _ => false,
+    };
+    let public = match url.host_str() {
+        Some(host) => address(host).is_none_or(is_public),
+        None => false,
+    };
+    if !allowed || !public {
+        return Err(invalid_callback(raw));
+    }
+    Ok(url)
+}
+
+/// Rejects a callback whose host name resolves to anything but public
+/// addresses, see [`parse_callback`] for hosts given as addresses.
+pub async fn check_callback(url: &Url) -> Result<(), ApiError> {
+    let Some(host) = url.host_str().filter(|host| address(host).is_none()) else {
+        return Ok(());
+    };
+    if let Err(err) = public_addrs(host).await {
+        tracing::info!(host, error = %err, "callback host refused");
+        return Err(invalid_callback(url.as_str()));
+    }
+    Ok(())
+}
+
+// The address a URL host names literally, IPv6 ones in brackets
+fn address(host: &str) -> Option<IpAddr> {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+        .parse()
+        .ok()
+}
+
+fn invalid_callback(raw: &str) -> ApiError {
+    ApiError::InvalidParameter {
+        name: "callback_url",
+        received: raw.to_string(),
+        expected: "an absolute https URL of a public host".into(),
+    }
+}
+
+/// Whether `ip` may be reached from outside its own network: not loopback,
+/// private, link-local, unspecified, broadcast, multicast or documentation.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            // 0.0.0.0/8 is "this network" and 100.64.0.0/10 carrier-grade NAT
+            let reserved = a == 0 || (a == 100 && (64..128).contains(&b));
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || reserved)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public(IpAddr::V4(mapped)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+// The public addresses `host` resolves to, or an error when there are none
+async fn public_addrs(host: &str) -> io::Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+        .await?
+        .filter(|addr| is_public(addr.ip()))
+        .collect();
+    if addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "no public address"));
+    }
+    Ok(addrs)
+}
+
+// Resolves names for the callback client, to public addresses only
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = public_addrs(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Posts `body` to `url`, signed with `secret`. Returns the final HTTP
+/// status, or `None` when every attempt failed to get a reply.
+pub async fn deliver(url: &Url, job_id: &str, body: Vec<u8>, secret: &Secret) -> Option<u16> {
+    let mut backoff = FIRST_BACKOFF;
+    for attempt in 1..=ATTEMPTS {
+        // Re-signed per attempt so the timestamp reflects the actual send
+        let timestamp = chrono::Utc::now().timestamp();
+        let request = client()
+            .post(url.clone())
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(JOB_ID_HEADER, job_id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, signing::header(secret, timestamp, &body))
+            .body(body.clone());
+        match request.send().await {
+            Ok(response) if !response.status().is_server_error() => {
+                let status = response.status().as_u16();
+                tracing::info!(job_id, status, attempt, "callback delivered");
+                return Some(status);
+            }
+            Ok(response) if attempt == ATTEMPTS => {
+                let status = response.status().as_u16();
+                tracing::warn!(job_id, status, attempt, "callback failed, giving up");
+                return Some(status);
+            }
+            Ok(response) => {
+                tracing::warn!(job_id, status = response.status().as_u16(), attempt, "callback failed");
+            }
+            Err(err) => tracing::warn!(job_id, error = %err, attempt, "callback unreachable"),
+        }
+        if attempt < ATTEMPTS {
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+    None
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            // A callback must answer itself rather than send us elsewhere
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .expect("default TLS backend is available")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callbacks_must_be_https() {
+        assert!(parse_callback("https://example.com/hook").is_ok());
+        assert!(parse_callback("http://example.com/hook").is_err());
+        assert!(parse_callback("ftp://example.com/hook").is_err());
+        assert!(parse_callback("/relative").is_err());
+    }
+
+    #[test]
+    fn callbacks_must_be_public() {
+        assert!(parse_callback("https://93.184.215.14/hook").is_ok());
+        for private in [
+            "https://127.0.0.1/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://10.1.2.3/hook",
+            "https://192.168.0.1/hook",
+            "https://0.0.0.0/hook",
+            "https://100.64.0.1/hook",
+            "https://[::1]/hook",
+            "https://[fd00::1]/hook",
+            "https://[fe80::1]/hook",
+            "https://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(parse_callback(private).is_err(), "{}", private);
+        }
+    }
+
+    #[tokio::test]
+    async fn names_of_private_hosts_are_refused() {
+        let url = parse_callback("https://localhost/hook").unwrap();
+        assert!(check_callback(&url).await.is_err());
+    }
+}
@@ -0,0 +1,638 @@
+//! End-to-end tests calling the handlers with constructed requests.
+
+mod common;
+
+use common::{header, json, text, TestRequest};
+use my_rust_vercel_api::handlers;
+use my_rust_vercel_api::streaming::{self, Streamed};
+use my_rust_vercel_api::usage::{self, Period};
+use serde_json::json;
+use vercel_runtime::StatusCode;
+
+#[tokio::test]
+async fn computes_a_single_value() {
+    let response = handlers::fibonacci(TestRequest::get("/api/20").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json(&response);
+    assert_eq!(body["fibonacci"], "6765");
+    assert_eq!(body["n"], 20);
+    assert_eq!(body["status"], "success");
+}
+
+// The compute units charged for serving `path` as the request of `account`
+async fn units_for(account: &str, path: &str) -> u64 {
+    let now = chrono::Utc::now();
+    let response = usage::metered(account, handlers::router(TestRequest::get(path).build())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK, "{}", path);
+    usage::read(account, Period::Day, now).await.units
+}
+
+#[tokio::test]
+async fn charges_units_for_the_work_done() {
+    // One unit per 1000 of the indices computed, plus the base one
+    assert_eq!(units_for("usage:api-single", "/api/900").await, 1);
+    assert_eq!(units_for("usage:api-many", "/api?n=600,700,-900").await, 3);
+    // 500 + 501 + ... + 999 = 374750
+    assert_eq!(units_for("usage:api-range", "/api/range?from=500&to=999").await, 375);
+    // Residues and estimates cost one unit at any index
+    assert_eq!(units_for("usage:api-last-digits", "/api/4000000000000000?last_digits=5").await, 2);
+    assert_eq!(units_for("usage:api-binet", "/api/4000000000000000?algo=binet").await, 2);
+}
+
+#[tokio::test]
+async fn negative_and_based_values() {
+    let response = handlers::fibonacci(TestRequest::get("/api/-8").build()).await.unwrap();
+    assert_eq!(json(&response)["fibonacci"], "-21");
+
+    let response = handlers::fibonacci(TestRequest::get("/api/20?base=16").build()).await.unwrap();
+    assert_eq!(json(&response)["fibonacci"], "1a6d");
+}
+
+#[tokio::test]
+async fn answers_several_indices() {
+    let request = TestRequest::get("/api?n=5%2C10&n=-8").build();
+    let body = json(&handlers::fibonacci(request).await.unwrap());
+    assert_eq!(body["results"], json!({ "5": "5", "10": "55", "-8": "-21" }));
+    assert_eq!(body["count"], 3);
+
+    let request = TestRequest::get("/api?n=5,10&algo=matrix").build();
+    let response = handlers::fibonacci(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn reads_post_bodies() {
+    let request = TestRequest::post("/api").json(&json!({ "n": 30 })).build();
+    let response = handlers::fibonacci(request).await.unwrap();
+    assert_eq!(json(&response)["fibonacci"], "832040");
+
+    let request = TestRequest::post("/api/batch").json(&json!({ "n": [1, 2, 10] })).build();
+    let body = json(&handlers::batch(request).await.unwrap());
+    assert_eq!(body["count"], 3);
+}
+
+#[tokio::test]
+async fn reports_batch_errors_per_entry() {
+    let request = TestRequest::post("/api/batch").json(&json!({ "n": [10, 5000, "x", 20] })).build();
+    let response = handlers::batch(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json(&response);
+    assert_eq!(body["failed"], 2);
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results[0]["fibonacci"], "55");
+    assert_eq!(results[1]["n"], 5000);
+    assert_eq!(results[1]["error"]["code"], "out_of_range");
+    assert_eq!(results[2]["n"], "x");
+    assert_eq!(results[2]["error"]["code"], "invalid_parameter");
+    assert_eq!(results[3]["fibonacci"], "6765");
+
+    let body = json(&handlers::range(TestRequest::get("/api/range?from=0&to=499").build()).await.unwrap());
+    let sequence = body["sequence"].as_array().unwrap();
+    assert_eq!(sequence.len(), 500);
+    // Across the boundaries of the runs computed in parallel
+    assert_eq!(sequence[130], "659034621587630041982498215");
+    assert_eq!(
+        sequence[499],
+        "86168291600238450732788312165664788095941068326060883324529903470149056115823592713458328176574447204501"
+    );
+}
+
+#[tokio::test]
+async fn reports_structured_errors() {
+    let response = handlers::fibonacci(TestRequest::get("/api/abc").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(header(&response, "content-type"), Some("application/problem+json"));
+    let problem = json(&response);
+    assert_eq!(problem["code"], "invalid_parameter");
+    assert_eq!(problem["type"], "urn:fibonacci-api:problem:invalid_parameter");
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["instance"], "/api/abc");
+
+    let response = handlers::fibonacci(TestRequest::get("/api/100000").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json(&response)["code"], "out_of_range");
+
+    let response = handlers::range(TestRequest::new("DELETE", "/api/range").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(header(&response, "allow"), Some("GET, HEAD, OPTIONS"));
+
+    let response = handlers::router(TestRequest::get("/api/fib/1/extra").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(json(&response)["code"], "not_found");
+
+    // Jobs stay off until WEBHOOK_SECRET is configured
+    let request = TestRequest::post("/api/jobs")
+        .json(&json!({ "n": 10, "callback_url": "https://example.com/hook" }))
+        .build();
+    let response = handlers::jobs(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json(&response)["code"], "unavailable");
+}
+
+#[tokio::test]
+async fn negotiates_formats() {
+    let request = TestRequest::get("/api/range?from=1&to=3").header("accept", "text/csv").build();
+    let response = handlers::range(request).await.unwrap();
+    assert_eq!(header(&response, "content-type"), Some("text/csv; charset=utf-8"));
+    assert_eq!(text(&response), "n,fibonacci\n1,1\n2,1\n3,2\n");
+
+    let request = TestRequest::get("/api/range?from=4&to=5&format=ndjson").build();
+    let response = handlers::range(request).await.unwrap();
+    assert_eq!(text(&response), "{\"fibonacci\":\"3\",\"n\":4}\n{\"fibonacci\":\"5\",\"n\":5}\n");
+
+    // A transport sending bodies in pieces gets one line per value
+    let request = TestRequest::get("/api/range?from=4&to=5&format=ndjson").build();
+    let response = streaming::incremental(handlers::range(request)).await.unwrap();
+    let mut lines = response.extensions().get::<Streamed>().unwrap().take().unwrap();
+    assert_eq!(lines.recv().await.as_deref(), Some("{\"fibonacci\":\"3\",\"n\":4}\n"));
+    assert_eq!(lines.recv().await.as_deref(), Some("{\"fibonacci\":\"5\",\"n\":5}\n"));
+    assert_eq!(lines.recv().await, None);
+}
+
+#[tokio::test]
+async fn revalidates_with_etags() {
+    let response = handlers::fibonacci(TestRequest::get("/api/42").build()).await.unwrap();
+    let etag = header(&response, "etag").expect("cacheable response").to_string();
+
+    let request = TestRequest::get("/api/42").header("if-none-match", &etag).build();
+    let response = handlers::fibonacci(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert!(text(&response).is_empty());
+}
+
+#[tokio::test]
+async fn routes_to_every_endpoint() {
+    let response = handlers::router(TestRequest::get("/api/fib/12").build()).await.unwrap();
+    assert_eq!(json(&response)["fibonacci"], "144");
+
+    let response = handlers::router(TestRequest::get("/api/router?route=health").build())
+        .await
+        .unwrap();
+    assert_eq!(json(&response)["status"], "ok");
+
+    let response = handlers::router(TestRequest::get("/api/lucas/10").build()).await.unwrap();
+    assert_eq!(json(&response)["lucas"], "123");
+}
+
+#[tokio::test]
+async fn answers_prime_questions() {
+    let response = handlers::router(TestRequest::get("/api/prime/1000").build()).await.unwrap();
+    assert_eq!(json(&response)["prime"], "7919");
+
+    let response = handlers::router(TestRequest::get("/api/prime/0").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = handlers::is_prime(TestRequest::get("/api/is-prime?x=561").build()).await.unwrap();
+    let body = json(&response);
+    assert_eq!(body["is_prime"], false);
+    assert_eq!(body["primality"], "composite");
+    let response = handlers::is_prime(TestRequest::get("/api/is-prime?x=1").build()).await.unwrap();
+    assert_eq!(json(&response)["primality"], "neither");
+
+    let response = handlers::factorize(TestRequest::get("/api/factorize?x=360").build()).await.unwrap();
+    let body = json(&response);
+    assert_eq!(body["factorization"], "2^3 × 3^2 × 5");
+    assert_eq!(body["factors"][0], json!({ "prime": "2", "exponent": 3 }));
+
+    let response = handlers::factorize(TestRequest::get("/api/factorize?x=0").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn computes_factorials_and_binomials() {
+    let response = handlers::router(TestRequest::get("/api/factorial/20").build()).await.unwrap();
+    assert_eq!(json(&response)["factorial"], "2432902008176640000");
+
+    let response = handlers::choose(TestRequest::get("/api/choose?n=10&k=3").build()).await.unwrap();
+    assert_eq!(json(&response)["binomial"], "120");
+
+    let response = handlers::choose(TestRequest::get("/api/choose?n=10").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn computes_gcds() {
+    let response = handlers::gcd(TestRequest::get("/api/gcd?a=240&b=46").build()).await.unwrap();
+    let body = json(&response);
+    assert_eq!(body["gcd"], "2");
+    assert_eq!(body["lcm"], "5520");
+    assert_eq!(body["bezout"], json!(["-9", "47"]));
+
+    let response = handlers::gcd(TestRequest::get("/api/gcd?a=12,18,30").build()).await.unwrap();
+    assert_eq!(json(&response)["gcd"], "6");
+
+    let response = handlers::gcd(TestRequest::get("/api/gcd?a=12").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn follows_collatz_trajectories() {
+    let response = handlers::router(TestRequest::get("/api/collatz/27").build()).await.unwrap();
+    let body = json(&response);
+    assert_eq!(body["stopping_time"], 111);
+    assert_eq!(body["max_value"], "9232");
+    assert!(body.get("sequence").is_none());
+
+    let request = TestRequest::get("/api/collatz/6?include_sequence=true&max_length=3").build();
+    let body = json(&handlers::collatz(request).await.unwrap());
+    assert_eq!(body["sequence"], json!(["6", "3", "10"]));
+    assert_eq!(body["sequence_truncated"], true);
+
+    let response = handlers::collatz(TestRequest::get("/api/collatz/0").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn converts_between_bases() {
+    let request = TestRequest::get("/api/convert?value=1A6D&from=16&to=2").build();
+    let body = json(&handlers::router(request).await.unwrap());
+    assert_eq!(body["value"], "1a6d");
+    assert_eq!(body["result"], "1101001101101");
+
+    let request = TestRequest::get("/api/convert?value=-6765&to=62").build();
+    assert_eq!(json(&handlers::convert(request).await.unwrap())["result"], "-1l7");
+
+    let request = TestRequest::get("/api/convert?value=1g&from=16&to=10").build();
+    let response = handlers::convert(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json(&response)["received"], "g");
+}
+
+#[tokio::test]
+async fn evaluates_expressions() {
+    let request = TestRequest::post("/api/eval")
+        .json(&json!({ "expression": "fib(100) * 3 + factorial(20)" }))
+        .build();
+    let body = json(&handlers::router(request).await.unwrap());
+    assert_eq!(body["result"], "1065107446545962385225");
+    assert_eq!(body["digits"], 22);
+
+    let request = TestRequest::post("/api/eval").json(&json!({ "expression": "1 / (2 - 2)" })).build();
+    let response = handlers::eval(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let problem = json(&response);
+    assert_eq!(problem["code"], "invalid_expression");
+    assert_eq!(problem["position"], 2);
+
+    let request = TestRequest::post("/api/eval").json(&json!({ "expression": "fib(100000)" })).build();
+    let response = handlers::eval(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn expands_continued_fractions() {
+    let request = TestRequest::get("/api/continued-fraction?x=sqrt(2)&terms=4").build();
+    let body = json(&handlers::router(request).await.unwrap());
+    assert_eq!(body["terms"], json!(["1", "2", "2", "2"]));
+    assert_eq!(body["convergents"][3], "17/12");
+    assert_eq!(body["period"], json!({ "start": 1, "length": 1 }));
+
+    let request = TestRequest::get("/api/continued-fraction?x=415/93").build();
+    let body = json(&handlers::continued_fraction(request).await.unwrap());
+    assert_eq!(body["notation"], "[4; 2, 6, 7]");
+    assert_eq!(body["complete"], true);
+
+    let request = TestRequest::get("/api/continued-fraction?x=pi").build();
+    let response = handlers::continued_fraction(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn sums_fibonacci_numbers() {
+    let response = handlers::router(TestRequest::get("/api/fib-sum?n=10").build()).await.unwrap();
+    let body = json(&response);
+    assert_eq!(body["sum"], "143");
+    assert_eq!(body["sum_of_squares"], "4895");
+    assert_eq!(body["alternating_sum"], "-33");
+
+    let response = handlers::fib_sum(TestRequest::get("/api/fib-sum").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn builds_fibonacci_words() {
+    let response = handlers::router(TestRequest::get("/api/fib-word?n=5").build()).await.unwrap();
+    let body = json(&response);
+    assert_eq!(body["word"], "0100101001001");
+    assert_eq!(body["truncated"], false);
+
+    let body = json(&handlers::fib_word(TestRequest::get("/api/fib-word?n=100&max_len=4").build()).await.unwrap());
+    assert_eq!(body["word"], "0100");
+    assert_eq!(body["length"], "927372692193078999176");
+    assert_eq!(body["truncated"], true);
+}
+
+#[tokio::test]
+async fn trims_and_indents_json() {
+    let response = handlers::router(TestRequest::get("/api/20?fields=fibonacci,n&pretty=true").build()).await.unwrap();
+    assert_eq!(text(&response), "{\n  \"fibonacci\": \"6765\",\n  \"n\": 20\n}");
+
+    let full = handlers::router(TestRequest::get("/api/20").build()).await.unwrap();
+    assert_ne!(header(&full, "etag"), header(&response, "etag"));
+}
+
+#[tokio::test]
+async fn groups_digits_for_the_locale() {
+    let request = TestRequest::get("/api/30").header("accept-language", "de-DE, en;q=0.5").build();
+    let body = json(&handlers::router(request).await.unwrap());
+    assert_eq!(body["fibonacci"], "832040");
+    assert_eq!(body["formatted"], json!({ "30": "832.040" }));
+    assert_eq!(body["locale"], "de");
+
+    let body = json(&handlers::router(TestRequest::get("/api/30?grouped=true").build()).await.unwrap());
+    assert_eq!(body["formatted"]["30"], "832,040");
+
+    let response = handlers::router(TestRequest::get("/api/30?grouped=maybe").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn sends_security_headers() {
+    let response = handlers::router(TestRequest::get("/api/nope").build()).await.unwrap();
+    assert_eq!(header(&response, "x-content-type-options"), Some("nosniff"));
+    assert_eq!(header(&response, "referrer-policy"), Some("no-referrer"));
+    assert!(header(&response, "strict-transport-security").is_some());
+    assert_eq!(header(&response, "content-security-policy"), Some("default-src 'none'; frame-ancestors 'none'"));
+
+    let docs = handlers::router(TestRequest::get("/api/docs").build()).await.unwrap();
+    assert!(header(&docs, "content-security-policy").unwrap().contains("https://unpkg.com"));
+}
+
+#[tokio::test]
+async fn rejects_oversized_requests() {
+    let query = format!("/api?n={}", "1,".repeat(10_000));
+    let response = handlers::router(TestRequest::get(&query).build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::URI_TOO_LONG);
+    assert_eq!(json(&response)["code"], "uri_too_long");
+
+    let body = format!("{{\"n\": [{}1]}}", "1, ".repeat(30_000));
+    let response = handlers::router(TestRequest::post("/api/batch").body(&body).build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(json(&response)["max"], 65_536);
+}
+
+#[tokio::test]
+async fn replays_idempotent_posts() {
+    let submit = |body: &str| {
+        TestRequest::post("/api/batch")
+            .header("idempotency-key", "retry-test-1")
+            .header("x-forwarded-for", "203.0.113.9")
+            .body(body)
+            .build()
+    };
+    let first = handlers::router(submit("{\"n\": [10, 20]}")).await.unwrap();
+    assert_eq!(header(&first, "idempotent-replayed"), None);
+    let second = handlers::router(submit("{\"n\": [10, 20]}")).await.unwrap();
+    assert_eq!(header(&second, "idempotent-replayed"), Some("true"));
+    assert_eq!(text(&second), text(&first));
+
+    let conflict = handlers::router(submit("{\"n\": [30]}")).await.unwrap();
+    assert_eq!(conflict.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json(&conflict)["code"], "idempotency_key_reused");
+}
+
+#[tokio::test]
+async fn verification_needs_a_signing_secret() {
+    let request = TestRequest::post("/api/verify")
+        .json(&json!({ "body": { "n": 1 }, "signature": "t=1,v1=00" }))
+        .build();
+    let response = handlers::router(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(header(&response, "x-signature"), None);
+}
+
+#[tokio::test]
+async fn propagates_trace_context() {
+    let request = TestRequest::get("/api/20")
+        .header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+        .header("tracestate", "congo=t61rcWkgMzE")
+        .build();
+    let response = handlers::router(request).await.unwrap();
+    let traceparent = header(&response, "traceparent").unwrap();
+    assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+    assert!(!traceparent.contains("00f067aa0ba902b7"));
+    assert_eq!(header(&response, "tracestate"), Some("congo=t61rcWkgMzE"));
+
+    let response = handlers::router(TestRequest::get("/api/20").build()).await.unwrap();
+    assert_eq!(header(&response, "traceparent").map(str::len), Some(55));
+}
+
+#[tokio::test]
+async fn usage_needs_api_keys() {
+    let response = handlers::router(TestRequest::get("/api/usage").header("x-api-key", "k1").build())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json(&response)["code"], "unavailable");
+}
+
+#[tokio::test]
+async fn answers_graphql_queries() {
+    let query = json!({
+        "query": "query ($n: Int!) { fib: fibonacci(n: $n) { value } big: fibonacci(n: 5000) { value } isPrime(x: 97) }",
+        "variables": { "n": 10 },
+    });
+    let response = handlers::router(TestRequest::post("/api/graphql").json(&query).build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json(&response);
+    assert_eq!(body["data"], json!({ "fib": { "value": "55" }, "big": null, "isPrime": true }));
+    assert_eq!(body["errors"][0]["path"], json!(["big"]));
+
+    let query = json!({ "query": "{ fibonacci(n: 1) }" });
+    let response = handlers::router(TestRequest::post("/api/graphql").json(&query).build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(json(&response).get("data").is_none());
+
+    let response = handlers::router(TestRequest::get("/api/graphql").build()).await.unwrap();
+    assert!(text(&response).contains("isPrime(x: BigInt!): Boolean"));
+}
+
+#[tokio::test]
+async fn streams_sequence_elements() {
+    let response = handlers::router(TestRequest::get("/api/stream?from=10&to=12").build()).await.unwrap();
+    assert_eq!(header(&response, "content-type"), Some("application/x-ndjson"));
+    let lines: Vec<serde_json::Value> = text(&response).lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[2], json!({ "n": 12, "fibonacci": "144" }));
+
+    let request = TestRequest::get("/api/stream?from=10&to=11").header("accept", "text/event-stream").build();
+    let response = handlers::router(request).await.unwrap();
+    assert_eq!(header(&response, "content-type"), Some("text/event-stream"));
+    let body = text(&response);
+    assert!(body.starts_with("id: 10\nevent: fibonacci\ndata: {\"fibonacci\":\"55\",\"n\":10}\n\n"), "{}", body);
+    assert!(body.ends_with("event: end\ndata: {\"count\":2,\"from\":10,\"to\":11}\n\n"), "{}", body);
+}
+
+#[tokio::test]
+async fn reports_digit_statistics() {
+    let response = handlers::router(TestRequest::get("/api/fib-digits/-30?stats=true").build()).await.unwrap();
+    let body = json(&response);
+    assert_eq!(body["histogram"], json!([2, 0, 1, 1, 1, 0, 0, 0, 1, 0]));
+    assert_eq!(body["digit_sum"], 17);
+    assert_eq!(body["digital_root"], 8);
+    assert!(body.get("value").is_none());
+
+    let response = handlers::router(TestRequest::get("/api/fib-digits/30").build()).await.unwrap();
+    assert_eq!(json(&response)["value"], "832040");
+}
+
+#[tokio::test]
+async fn names_the_accepted_range_of_invalid_parameters() {
+    let response = handlers::router(TestRequest::get("/api/lucas/ten").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json(&response);
+    assert_eq!(body["detail"], "parameter 'n' must be an integer between 0 and 1000, got 'ten'");
+    assert_eq!(body["parameter"], "n");
+    assert_eq!(body["received"], "ten");
+    assert_eq!(body["expected"], "an integer between 0 and 1000");
+
+    let request = TestRequest::post("/api").json(&json!({ "n": 10, "strict": "yes" })).build();
+    let response = handlers::router(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json(&response)["detail"], "parameter 'strict' must be true or false, got 'yes'");
+    let response = handlers::router(TestRequest::get("/api/10?format=xml").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json(&response);
+    assert_eq!(body["detail"], "parameter 'format' must be one of json, text, csv, msgpack or ndjson, got 'xml'");
+    assert_eq!(body["parameter"], "format");
+    assert_eq!(body["received"], "xml");
+
+    let response = handlers::router(TestRequest::get("/api?n=ten").build()).await.unwrap();
+    assert_eq!(json(&response)["detail"], "parameter 'n' must be an integer between -1000 and 1000, got 'ten'");
+    let response = handlers::router(TestRequest::get("/api/-5000").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json(&response)["detail"], "parameter 'n' must be an integer between -1000 and 1000, got -5000");
+    // Approximations are not limited
+    let response = handlers::router(TestRequest::get("/api/5000?algo=binet").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = handlers::router(TestRequest::get("/api/prime/0").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = json(&response);
+    assert_eq!(body["detail"], "parameter 'n' must be an integer between 1 and 10000000, got 0");
+    assert_eq!((body["min"].clone(), body["max"].clone()), (json!(1), json!(10000000)));
+}
+
+#[tokio::test]
+async fn benchmarks_the_instance() {
+    let response = handlers::router(TestRequest::get("/api/benchmark").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(header(&response, "cache-control"), Some("no-store"));
+    let body = json(&response);
+    let tasks = body["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 5);
+    assert_eq!(tasks[1]["name"], "fibonacci-100k");
+    assert_eq!(tasks[2]["result_bytes"], 20899);
+    let elapsed: u64 = tasks.iter().map(|task| task["elapsed_ns"].as_u64().unwrap()).sum();
+    assert_eq!(body["total_ns"], elapsed);
+    assert!(body["instance"]["run"].as_u64().unwrap() >= 1);
+}
+
+#[tokio::test]
+async fn draws_random_values() {
+    let response = handlers::router(TestRequest::get("/api/random?min=-2&max=2&count=50").build()).await.unwrap();
+    assert_eq!(header(&response, "cache-control"), Some("no-store"));
+    let body = json(&response);
+    let values = body["values"].as_array().unwrap();
+    assert_eq!(values.len(), 50);
+    assert!(values.iter().all(|value| (-2..=2).contains(&value.as_str().unwrap().parse::<i32>().unwrap())));
+
+    let response = handlers::random(TestRequest::get("/api/random?min=5&max=1").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn serves_registered_sequences() {
+    let response = handlers::router(TestRequest::get("/api/seq/catalan/10").build()).await.unwrap();
+    let body = json(&response);
+    assert_eq!(body["value"], "16796");
+    assert_eq!(body["oeis"], "A000108");
+
+    let response = handlers::router(TestRequest::get("/api/seq/pell?n=6").build()).await.unwrap();
+    assert_eq!(json(&response)["value"], "70");
+
+    let response = handlers::router(TestRequest::get("/api/seq").build()).await.unwrap();
+    assert_eq!(json(&response)["count"], 4);
+
+    let response = handlers::router(TestRequest::get("/api/seq/nope/3").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn versions_reshape_results() {
+    let response = handlers::router(TestRequest::get("/api/v1/20?debug=true").build()).await.unwrap();
+    assert_eq!(header(&response, "x-api-version"), Some("v1"));
+    let v1 = json(&response);
+    assert_eq!(v1["status"], "success");
+    assert!(v1["debug"].is_object());
+
+    let response = handlers::router(TestRequest::get("/api/router?route=v2/20&debug=true").build())
+        .await
+        .unwrap();
+    assert_eq!(header(&response, "x-api-version"), Some("v2"));
+    let v2 = json(&response);
+    assert_eq!(v2["fibonacci"], "6765");
+    assert!(v2.get("status").is_none() && v2.get("debug").is_none());
+    assert!(v2["meta"]["timestamp"].is_string());
+
+    let response = handlers::fibonacci(TestRequest::get("/api/20").build()).await.unwrap();
+    assert_eq!(header(&response, "x-api-version"), Some("v1"));
+}
+
+#[tokio::test]
+async fn answers_preflights_and_head() {
+    let request = TestRequest::new("OPTIONS", "/api/20")
+        .header("origin", "https://example.test")
+        .build();
+    let response = handlers::fibonacci(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(header(&response, "access-control-allow-origin").is_some());
+
+    let response = handlers::fibonacci(TestRequest::new("HEAD", "/api/20").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(text(&response).is_empty());
+}
+
+#[tokio::test]
+async fn selects_algorithms() {
+    for algo in ["iterative", "fast-doubling", "matrix", "binet"] {
+        let uri = format!("/api/50?algo={}", algo);
+        let body = json(&handlers::fibonacci(TestRequest::get(&uri).build()).await.unwrap());
+        assert_eq!(body["fibonacci"], "12586269025", "{}", algo);
+        assert_eq!(body["algorithm"], algo);
+        assert!(body["elapsed_ns"].is_u64());
+    }
+
+    let body = json(&handlers::fibonacci(TestRequest::get("/api/5000?algo=binet").build()).await.unwrap());
+    assert_eq!(body["approximate"], true);
+    assert!(body["relative_error"].as_f64().unwrap() < 1e-10);
+
+    // Past this index the error estimate would exceed the value itself
+    let response = handlers::fibonacci(TestRequest::get("/api/4679435742058786?algo=binet").build()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = json(&handlers::fibonacci(TestRequest::get("/api/4679435742058785?algo=binet").build()).await.unwrap());
+    assert_eq!(body["fibonacci"], "2e977944233434273");
+    assert_eq!(body["meta"]["digits"], 977944233434274u64);
+}
+
+#[tokio::test]
+async fn reports_cost_metadata() {
+    // An explicit base skips the embedded table, so the value goes through the cache
+    let body = json(&handlers::fibonacci(TestRequest::get("/api/777?base=10").build()).await.unwrap());
+    let meta = &body["meta"];
+    assert_eq!(meta["algorithm"], "fast-doubling");
+    assert_eq!(meta["digits"], 163);
+    assert_eq!(meta["source"], "computed");
+    assert!(meta["elapsed_us"].is_u64());
+    let body = json(&handlers::fibonacci(TestRequest::get("/api/777?base=10").build()).await.unwrap());
+    assert_eq!(body["meta"]["cache"], "hit");
+
+    let body = json(&handlers::fibonacci(TestRequest::get("/api/777").build()).await.unwrap());
+    assert_eq!(body["meta"]["source"], "table");
+    assert_eq!(body["meta"]["digits"], 163);
+    assert_eq!(body["meta"]["cache"], serde_json::Value::Null);
+
+    let body = json(&handlers::range(TestRequest::get("/api/range?from=10&to=12").build()).await.unwrap());
+    assert_eq!(body["meta"]["digits"], 2 + 2 + 3);
+    assert_eq!(body["meta"]["cache"], serde_json::Value::Null);
+}
@@ -0,0 +1,84 @@
+//! Helpers shared by the integration tests: a request builder and response
+//! accessors, so handlers can be exercised without deploying them.
+
+#![allow(dead_code)]
+
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use vercel_runtime::{Body, Request, Response};
+
+/// Builds a `vercel_runtime::Request` for calling a handler directly.
+///
+/// Every request gets its own `x-forwarded-for` address unless one is set
+/// explicitly, so tests do not share (and exhaust) a rate-limit bucket.
+pub struct TestRequest {
+    builder: http::request::Builder,
+    body: Body,
+    forwarded: bool,
+}
+
+impl TestRequest {
+    pub fn get(uri: &str) -> Self {
+        Self::new("GET", uri)
+    }
+
+    pub fn post(uri: &str) -> Self {
+        Self::new("POST", uri)
+    }
+
+    pub fn new(method: &str, uri: &str) -> Self {
+        TestRequest {
+            builder: http::Request::builder().method(method).uri(uri),
+            body: Body::Empty,
+            forwarded: false,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.forwarded |= name.eq_ignore_ascii_case("x-forwarded-for");
+        self.builder = self.builder.header(name, value);
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = Body::Text(body.to_string());
+        self
+    }
+
+    pub fn json(self, body: &Value) -> Self {
+        self.header("content-type", "application/json")
+            .body(&body.to_string())
+    }
+
+    pub fn build(self) -> Request {
+        static CLIENTS: AtomicU64 = AtomicU64::new(1);
+        let mut builder = self.builder;
+        if !self.forwarded {
+            let client = CLIENTS.fetch_add(1, Ordering::Relaxed);
+            builder = builder.header(
+                "x-forwarded-for",
+                format!("10.{}.{}.{}", client >> 16 & 255, client >> 8 & 255, client & 255),
+            );
+        }
+        builder.body(self.body).expect("valid test request")
+    }
+}
+
+/// The body as text; binary bodies must be UTF-8.
+pub fn text(response: &Response<Body>) -> String {
+    match response.body() {
+        Body::Empty => String::new(),
+        Body::Text(text) => text.clone(),
+        Body::Binary(bytes) => String::from_utf8(bytes.clone()).expect("UTF-8 body"),
+    }
+}
+
+/// The body parsed as JSON.
+pub fn json(response: &Response<Body>) -> Value {
+    serde_json::from_str(&text(response)).expect("JSON body")
+}
+
+/// The value of header `name`, if present.
+pub fn header<'a>(response: &'a Response<Body>, name: &str) -> Option<&'a str> {
+    response.headers().get(name).and_then(|value| value.to_str().ok())
+}
@@ -0,0 +1,74 @@
+//! Property tests for the number theory behind the endpoints.
+
+use my_rust_vercel_api::combinatorics::{choose, factorial};
+use my_rust_vercel_api::fib::{self, calculate_fibonacci as f};
+use my_rust_vercel_api::radix;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn recurrence_holds(n in 0u64..2000) {
+        prop_assert_eq!(f(n + 2), f(n + 1) + f(n));
+    }
+
+    #[test]
+    fn algorithms_agree(n in 0u64..3000) {
+        prop_assert_eq!(fib::fast_doubling(n), fib::iterative(n));
+    }
+
+    #[test]
+    fn addition_formula(m in 1u64..500, n in 0u64..500) {
+        // F(m + n) = F(m)·F(n + 1) + F(m − 1)·F(n)
+        let expected = f(m) * f(n + 1) + f(m - 1) * f(n);
+        prop_assert_eq!(f(m + n), expected);
+    }
+
+    #[test]
+    fn modular_matches_the_full_value(n in 0u64..3000, m in 1u64..1_000_000_007) {
+        let expected = f(n) % BigUint::from(m);
+        prop_assert_eq!(BigUint::from(fib::modular(&BigUint::from(n), m)), expected);
+    }
+
+    #[test]
+    fn negafibonacci_sign(n in 1i64..300) {
+        let positive = fib::negafibonacci(n);
+        let negative = fib::negafibonacci(-n);
+        if n % 2 == 0 {
+            prop_assert_eq!(negative, -positive);
+        } else {
+            prop_assert_eq!(negative, positive);
+        }
+    }
+
+    #[test]
+    fn zeckendorf_terms_sum_back(x in 1u64..u64::MAX) {
+        let x = BigUint::from(x);
+        let terms = fib::zeckendorf(&x);
+        let sum = terms.iter().fold(BigUint::zero(), |sum, (_, value)| sum + value);
+        prop_assert_eq!(sum, x);
+        // No two consecutive indices
+        prop_assert!(terms.windows(2).all(|pair| pair[0].0 > pair[1].0 + 1));
+    }
+
+    // F(1) = F(2) = 1 and index_of returns the smaller index, so start past both
+    #[test]
+    fn every_fibonacci_number_is_recognized(n in 3u64..1000) {
+        prop_assert_eq!(fib::index_of(&f(n)), Some(n));
+        prop_assert!(!fib::is_fibonacci(&(f(n + 3) - 1u32)));
+    }
+
+    #[test]
+    fn pascals_rule(n in 1u64..300, k in 1u64..300) {
+        prop_assert_eq!(choose(n, k), choose(n - 1, k - 1) + choose(n - 1, k));
+        prop_assert_eq!(choose(n, k.min(n)) * factorial(k.min(n)) * factorial(n - k.min(n)), factorial(n));
+    }
+
+    #[test]
+    fn radix_round_trips(value in any::<u128>(), base in 2u32..=36) {
+        let value = BigUint::from(value);
+        let encoded = radix::encode(&value, base);
+        prop_assert_eq!(BigUint::parse_bytes(encoded.as_bytes(), base), Some(value));
+    }
+}